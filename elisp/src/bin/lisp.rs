@@ -10,8 +10,6 @@ extern crate env_logger;
 use elisp::lisp;
 use std::env;
 use std::error::Error;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 
 use elisp::print_error;
 
@@ -32,17 +30,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     } else {
         let filename = &args[1];
-        let mut program: Vec<String> = Vec::new();
+        let program = std::fs::read_to_string(filename)?;
         let env = lisp::Environment::new();
 
-        for result in BufReader::new(File::open(filename)?).lines() {
-            let l = result?;
-            if l.starts_with(';') {
-                continue;
-            }
-            program.push(l);
-        }
-        match lisp::do_core_logic(&program.join(" "), &env) {
+        match lisp::do_core_logic_file(&program, filename, &env) {
             Ok(r) => println!("{}", r),
             Err(e) => print_error!(e),
         }
@@ -10,12 +10,19 @@ use log::{debug, error, info, warn};
 use crate::lisp::BasicBuiltIn;
 
 use crate::boolean;
+use crate::bytevector;
 use crate::chars;
+use crate::exception;
+use crate::format;
 use crate::hashtable;
 use crate::io;
 use crate::list;
+use crate::macros;
 use crate::math;
 use crate::operation;
+use crate::parameter;
+use crate::record;
+use crate::srfi1;
 use crate::strings;
 use crate::syntax;
 use crate::util;
@@ -47,6 +54,18 @@ where
     util::create_function(b);
 
     hashtable::create_function(b);
+
+    macros::create_function(b);
+
+    exception::create_function(b);
+
+    record::create_function(b);
+
+    bytevector::create_function(b);
+
+    srfi1::create_function(b);
+    format::create_function(b);
+    parameter::create_function(b);
 }
 #[cfg(test)]
 mod tests {
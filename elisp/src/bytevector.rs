@@ -0,0 +1,652 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+
+use std::convert::TryFrom;
+
+use num_bigint::BigInt;
+
+use crate::create_error;
+use crate::create_error_value;
+use crate::mut_obj;
+use crate::reference_obj;
+
+use crate::buildin::BuildInTable;
+use crate::lisp::eval;
+use crate::lisp::{BytesRc, Environment, Expression, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::number::Number;
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("bytevector", bytevector);
+    b.regist("make-bytevector", make_bytevector);
+    b.regist("bytevector-length", bytevector_length);
+    b.regist("bytevector-u8-ref", bytevector_u8_ref);
+    b.regist("bytevector-u8-set!", bytevector_u8_set);
+    b.regist("bytevector-copy", bytevector_copy);
+    b.regist("bytevector-copy!", bytevector_copy_effect);
+    b.regist("bytevector-append", bytevector_append);
+    b.regist("bytevector-fill!", bytevector_fill);
+    b.regist("utf8->string", utf8_string);
+    b.regist("string->utf8", string_utf8);
+
+    b.regist("bytevector-s8-ref", |exp, env| int_ref(exp, env, 1, true));
+    b.regist("bytevector-s8-set!", |exp, env| int_set(exp, env, 1, true));
+    b.regist("bytevector-u16-ref", |exp, env| int_ref(exp, env, 2, false));
+    b.regist("bytevector-s16-ref", |exp, env| int_ref(exp, env, 2, true));
+    b.regist("bytevector-u16-set!", |exp, env| {
+        int_set(exp, env, 2, false)
+    });
+    b.regist("bytevector-s16-set!", |exp, env| int_set(exp, env, 2, true));
+    b.regist("bytevector-u32-ref", |exp, env| int_ref(exp, env, 4, false));
+    b.regist("bytevector-s32-ref", |exp, env| int_ref(exp, env, 4, true));
+    b.regist("bytevector-u32-set!", |exp, env| {
+        int_set(exp, env, 4, false)
+    });
+    b.regist("bytevector-s32-set!", |exp, env| int_set(exp, env, 4, true));
+    b.regist("bytevector-u64-ref", |exp, env| int_ref(exp, env, 8, false));
+    b.regist("bytevector-s64-ref", |exp, env| int_ref(exp, env, 8, true));
+    b.regist("bytevector-u64-set!", |exp, env| {
+        int_set(exp, env, 8, false)
+    });
+    b.regist("bytevector-s64-set!", |exp, env| int_set(exp, env, 8, true));
+    b.regist("bytevector-ieee-single-ref", |exp, env| {
+        float_ref(exp, env, 4)
+    });
+    b.regist("bytevector-ieee-double-ref", |exp, env| {
+        float_ref(exp, env, 8)
+    });
+    b.regist("bytevector-ieee-single-set!", |exp, env| {
+        float_set(exp, env, 4)
+    });
+    b.regist("bytevector-ieee-double-set!", |exp, env| {
+        float_set(exp, env, 8)
+    });
+}
+pub(crate) fn to_byte(v: Expression) -> Result<u8, Error> {
+    match v {
+        Expression::Integer(i) => match u8::try_from(i) {
+            Ok(b) => Ok(b),
+            Err(_) => Err(create_error_value!(ErrCode::E1021, i)),
+        },
+        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+}
+pub(crate) fn to_bytevector(v: Expression) -> Result<BytesRc, Error> {
+    match v {
+        Expression::ByteVector(b) => Ok(b),
+        e => Err(create_error_value!(ErrCode::E1035, e)),
+    }
+}
+fn to_index(v: Expression) -> Result<usize, Error> {
+    match v {
+        Expression::Integer(i) if i >= 0 => Ok(i as usize),
+        Expression::Integer(i) => Err(create_error_value!(ErrCode::E1021, i)),
+        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+}
+// The optional start and end, ex. (bytevector-copy bv 1 3)
+pub(crate) fn range_arg(
+    exp: &[Expression],
+    i: usize,
+    env: &Environment,
+    len: usize,
+) -> Result<(usize, usize), Error> {
+    let mut range = [0, len];
+    for (j, e) in exp.iter().skip(i).take(2).enumerate() {
+        range[j] = to_index(eval(e, env)?)?;
+    }
+    let [start, end] = range;
+    if start > end || end > len {
+        return Err(create_error_value!(ErrCode::E1021, end));
+    }
+    Ok((start, end))
+}
+fn bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let mut v = Vec::new();
+    for e in &exp[1..] {
+        v.push(to_byte(eval(e, env)?)?);
+    }
+    Ok(Environment::create_bytevector(v))
+}
+fn make_bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let k = to_index(eval(&exp[1], env)?)?;
+    let b = match exp.get(2) {
+        Some(e) => to_byte(eval(e, env)?)?,
+        None => 0,
+    };
+    Ok(Environment::create_bytevector(vec![b; k]))
+}
+fn bytevector_length(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let n = reference_obj!(v).len();
+    Ok(Expression::Integer(n as _))
+}
+fn bytevector_u8_ref(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let b = reference_obj!(v).get(k).copied();
+    match b {
+        Some(b) => Ok(Expression::Integer(b as _)),
+        None => Err(create_error!(ErrCode::E1011)),
+    }
+}
+fn bytevector_u8_set(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let b = to_byte(eval(&exp[3], env)?)?;
+    match mut_obj!(v).get_mut(k) {
+        Some(e) => *e = b,
+        None => return Err(create_error!(ErrCode::E1011)),
+    }
+    Ok(Expression::Nil())
+}
+fn bytevector_copy(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let v = reference_obj!(v).clone();
+    let (start, end) = range_arg(exp, 2, env, v.len())?;
+    Ok(Environment::create_bytevector(v[start..end].to_vec()))
+}
+// ex. (bytevector-copy! to at from start end)
+fn bytevector_copy_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 4 || exp.len() > 6 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let to = to_bytevector(eval(&exp[1], env)?)?;
+    let at = to_index(eval(&exp[2], env)?)?;
+    let from = to_bytevector(eval(&exp[3], env)?)?;
+    // The source is copied, so that (bytevector-copy! bv 1 bv) is safe.
+    let from = reference_obj!(from).clone();
+    let (start, end) = range_arg(exp, 4, env, from.len())?;
+
+    let mut to = mut_obj!(to);
+    if at + (end - start) > to.len() {
+        return Err(create_error_value!(ErrCode::E1021, at));
+    }
+    to[at..at + (end - start)].copy_from_slice(&from[start..end]);
+    Ok(Expression::Nil())
+}
+fn bytevector_append(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let mut v = Vec::new();
+    for e in &exp[1..] {
+        let b = to_bytevector(eval(e, env)?)?;
+        v.extend_from_slice(&reference_obj!(b));
+    }
+    Ok(Environment::create_bytevector(v))
+}
+fn bytevector_fill(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 3 || exp.len() > 5 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let b = to_byte(eval(&exp[2], env)?)?;
+    let len = reference_obj!(v).len();
+    let (start, end) = range_arg(exp, 3, env, len)?;
+    mut_obj!(v)[start..end].fill(b);
+    Ok(Expression::Nil())
+}
+fn utf8_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let v = reference_obj!(v).clone();
+    let (start, end) = range_arg(exp, 2, env, v.len())?;
+    match String::from_utf8(v[start..end].to_vec()) {
+        Ok(s) => Ok(Environment::create_string(s)),
+        Err(e) => Err(create_error_value!(ErrCode::E1015, e)),
+    }
+}
+fn string_utf8(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let s = match eval(&exp[1], env)? {
+        Expression::String(s) => s,
+        e => return Err(create_error_value!(ErrCode::E1015, e)),
+    };
+    let (start, end) = range_arg(exp, 2, env, s.chars().count())?;
+    let s: String = s.chars().skip(start).take(end - start).collect();
+    Ok(Environment::create_bytevector(s.into_bytes()))
+}
+// The optional endianness, the default is (native-endian).
+// ex. 'big, 'little, 'big-endian, 'little-endian
+fn big_endian(exp: &[Expression], i: usize, env: &Environment) -> Result<bool, Error> {
+    let e = match exp.get(i) {
+        Some(e) => eval(e, env)?,
+        None => return Ok(cfg!(target_endian = "big")),
+    };
+    match &e {
+        Expression::Symbol(s) if s == "big" || s == "big-endian" => Ok(true),
+        Expression::Symbol(s) if s == "little" || s == "little-endian" => Ok(false),
+        Expression::Symbol(_) => Err(create_error_value!(ErrCode::E1021, e)),
+        _ => Err(create_error_value!(ErrCode::E1004, e)),
+    }
+}
+// The bytes at k..k+n, they are returned in the big endian order.
+fn get_bytes(v: &BytesRc, k: usize, n: usize, big: bool) -> Result<Vec<u8>, Error> {
+    let v = reference_obj!(v);
+    if k + n > v.len() {
+        return Err(create_error_value!(ErrCode::E1011, k));
+    }
+    let mut b = v[k..k + n].to_vec();
+    if !big {
+        b.reverse();
+    }
+    Ok(b)
+}
+// The bytes in the big endian order are stored at k..k+n.
+fn set_bytes(v: &BytesRc, k: usize, mut b: Vec<u8>, big: bool) -> Result<(), Error> {
+    let mut v = mut_obj!(v);
+    if k + b.len() > v.len() {
+        return Err(create_error_value!(ErrCode::E1011, k));
+    }
+    if !big {
+        b.reverse();
+    }
+    v[k..k + b.len()].copy_from_slice(&b);
+    Ok(())
+}
+// ex. (bytevector-u16-ref bv 0 'big)
+fn int_ref(exp: &[Expression], env: &Environment, n: usize, signed: bool) -> ResultExpression {
+    if exp.len() != 3 && exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let big = big_endian(exp, 3, env)?;
+
+    let b = get_bytes(&v, k, n, big)?;
+    let i = if signed {
+        BigInt::from_signed_bytes_be(&b)
+    } else {
+        BigInt::from_bytes_be(num_bigint::Sign::Plus, &b)
+    };
+    Ok(Number::from_big(i).to_expression())
+}
+// ex. (bytevector-u16-set! bv 0 #xffff 'big)
+fn int_set(exp: &[Expression], env: &Environment, n: usize, signed: bool) -> ResultExpression {
+    if exp.len() != 4 && exp.len() != 5 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let i = match eval(&exp[3], env)? {
+        Expression::Integer(i) => BigInt::from(i),
+        Expression::BigInt(i) => *i,
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
+    };
+    let big = big_endian(exp, 4, env)?;
+
+    let bits = 8 * n as u32;
+    let (min, max) = if signed {
+        (
+            -(BigInt::from(1) << (bits - 1)),
+            (BigInt::from(1) << (bits - 1)) - 1,
+        )
+    } else {
+        (BigInt::from(0), (BigInt::from(1) << bits) - 1)
+    };
+    if i < min || i > max {
+        return Err(create_error_value!(ErrCode::E1021, i));
+    }
+    // The two's complement of n bytes.
+    let mut b = i.to_signed_bytes_be();
+    let pad = if i < BigInt::from(0) { 0xff } else { 0 };
+    while b.len() < n {
+        b.insert(0, pad);
+    }
+    set_bytes(&v, k, b[b.len() - n..].to_vec(), big)?;
+    Ok(Expression::Nil())
+}
+// ex. (bytevector-ieee-double-ref bv 0 'little)
+fn float_ref(exp: &[Expression], env: &Environment, n: usize) -> ResultExpression {
+    if exp.len() != 3 && exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let big = big_endian(exp, 3, env)?;
+
+    let b = get_bytes(&v, k, n, big)?;
+    let mut buf = [0; 8];
+    buf[..n].copy_from_slice(&b);
+    let f = if n == 4 {
+        f32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64
+    } else {
+        f64::from_be_bytes(buf)
+    };
+    Ok(Expression::Float(f))
+}
+fn float_set(exp: &[Expression], env: &Environment, n: usize) -> ResultExpression {
+    if exp.len() != 4 && exp.len() != 5 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = to_bytevector(eval(&exp[1], env)?)?;
+    let k = to_index(eval(&exp[2], env)?)?;
+    let f = match eval(&exp[3], env)? {
+        Expression::Complex(a, b) => {
+            return Err(create_error_value!(
+                ErrCode::E1031,
+                Expression::Complex(a, b)
+            ))
+        }
+        e => Expression::to_number(&e)?.to_f64(),
+    };
+    let big = big_endian(exp, 4, env)?;
+
+    let b = if n == 4 {
+        (f as f32).to_be_bytes().to_vec()
+    } else {
+        f.to_be_bytes().to_vec()
+    };
+    set_bytes(&v, k, b, big)?;
+    Ok(Expression::Nil())
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn bytevector() {
+        assert_eq!(do_lisp("(bytevector)"), "#u8()");
+        assert_eq!(do_lisp("(bytevector 1 3 5 255)"), "#u8(1 3 5 255)");
+        assert_eq!(do_lisp("#u8(1 #xff)"), "#u8(1 255)");
+        assert_eq!(do_lisp("(bytevector? #u8())"), "#t");
+        assert_eq!(do_lisp("(bytevector? #(1))"), "#f");
+        assert_eq!(do_lisp("(equal? #u8(1 2) (bytevector 1 2))"), "#t");
+        assert_eq!(do_lisp("(equal? #u8(1 2) #u8(1))"), "#f");
+        assert_eq!(do_lisp("(eqv? #u8(1 2) #u8(1 2))"), "#f");
+    }
+    #[test]
+    fn make_bytevector() {
+        assert_eq!(do_lisp("(make-bytevector 2 12)"), "#u8(12 12)");
+        assert_eq!(do_lisp("(make-bytevector 3)"), "#u8(0 0 0)");
+        assert_eq!(do_lisp("(make-bytevector 0)"), "#u8()");
+    }
+    #[test]
+    fn bytevector_length() {
+        assert_eq!(do_lisp("(bytevector-length #u8())"), "0");
+        assert_eq!(do_lisp("(bytevector-length (make-bytevector 10))"), "10");
+    }
+    #[test]
+    fn bytevector_u8() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define bv (bytevector 1 2 3 4))", &env);
+        assert_eq!(do_lisp_env("(bytevector-u8-ref bv 1)", &env), "2");
+        assert_eq!(do_lisp_env("(bytevector-u8-set! bv 1 255)", &env), "nil");
+        assert_eq!(do_lisp_env("bv", &env), "#u8(1 255 3 4)");
+        assert_eq!(do_lisp_env("(bytevector-s8-ref bv 1)", &env), "-1");
+        assert_eq!(do_lisp_env("(bytevector-s8-set! bv 0 -128)", &env), "nil");
+        assert_eq!(do_lisp_env("(bytevector-u8-ref bv 0)", &env), "128");
+    }
+    #[test]
+    fn bytevector_copy() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a #u8(1 2 3 4 5))", &env);
+        assert_eq!(do_lisp_env("(bytevector-copy a)", &env), "#u8(1 2 3 4 5)");
+        assert_eq!(do_lisp_env("(bytevector-copy a 2)", &env), "#u8(3 4 5)");
+        assert_eq!(do_lisp_env("(bytevector-copy a 2 4)", &env), "#u8(3 4)");
+        assert_eq!(do_lisp_env("(eq? a (bytevector-copy a))", &env), "#f");
+
+        do_lisp_env("(define b (bytevector 10 20 30 40 50))", &env);
+        assert_eq!(do_lisp_env("(bytevector-copy! b 1 a 0 2)", &env), "nil");
+        assert_eq!(do_lisp_env("b", &env), "#u8(10 1 2 40 50)");
+        assert_eq!(do_lisp_env("(bytevector-copy! b 0 b 1)", &env), "nil");
+        assert_eq!(do_lisp_env("b", &env), "#u8(1 2 40 50 50)");
+    }
+    #[test]
+    fn bytevector_append() {
+        assert_eq!(do_lisp("(bytevector-append)"), "#u8()");
+        assert_eq!(
+            do_lisp("(bytevector-append #u8(0 1 2) #u8() #u8(3 4 5))"),
+            "#u8(0 1 2 3 4 5)"
+        );
+    }
+    #[test]
+    fn bytevector_fill() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (bytevector 1 2 3 4 5))", &env);
+        assert_eq!(do_lisp_env("(bytevector-fill! a 9 3)", &env), "nil");
+        assert_eq!(do_lisp_env("a", &env), "#u8(1 2 3 9 9)");
+        assert_eq!(do_lisp_env("(bytevector-fill! a 0)", &env), "nil");
+        assert_eq!(do_lisp_env("a", &env), "#u8(0 0 0 0 0)");
+    }
+    #[test]
+    fn utf8() {
+        assert_eq!(do_lisp("(utf8->string #u8(#x41))"), "\"A\"");
+        assert_eq!(
+            do_lisp("(utf8->string #u8(#xe3 #x81 #x82 #x41) 3)"),
+            "\"A\""
+        );
+        assert_eq!(do_lisp("(string->utf8 \"λ\")"), "#u8(206 187)");
+        assert_eq!(do_lisp("(string->utf8 \"aあb\" 1 2)"), "#u8(227 129 130)");
+        assert_eq!(
+            do_lisp("(utf8->string (string->utf8 \"日本語\"))"),
+            "\"日本語\""
+        );
+    }
+    #[test]
+    fn int_ref() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define bv #u8(#x89 #x50 #x4e #x47 0 0 0 #x0d))", &env);
+        assert_eq!(do_lisp_env("(bytevector-u16-ref bv 0 'big)", &env), "35152");
+        assert_eq!(
+            do_lisp_env("(bytevector-u16-ref bv 0 'little)", &env),
+            "20617"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-s16-ref bv 0 'big)", &env),
+            "-30384"
+        );
+        assert_eq!(do_lisp_env("(bytevector-u32-ref bv 4 'big)", &env), "13");
+        assert_eq!(
+            do_lisp_env("(bytevector-u32-ref bv 0 'big-endian)", &env),
+            "2303741511"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-u64-ref (make-bytevector 8 255) 0 'big)", &env),
+            "18446744073709551615"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-s64-ref (make-bytevector 8 255) 0 'big)", &env),
+            "-1"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(= (bytevector-u32-ref bv 0) (bytevector-u32-ref bv 0 (native-endian)))",
+                &env
+            ),
+            "#t"
+        );
+    }
+    #[test]
+    fn int_set() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define bv (make-bytevector 8))", &env);
+        assert_eq!(
+            do_lisp_env("(bytevector-u16-set! bv 0 #x1234 'big)", &env),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-s16-set! bv 2 -2 'little)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("bv", &env), "#u8(18 52 254 255 0 0 0 0)");
+        assert_eq!(
+            do_lisp_env("(bytevector-s32-set! bv 4 -1 'big)", &env),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-u32-ref bv 4 'big)", &env),
+            "4294967295"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-u64-set! bv 0 18446744073709551615)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(bytevector-s64-ref bv 0)", &env), "-1");
+        assert_eq!(
+            do_lisp_env("(bytevector-u32-set! bv 4 1 'little)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(bytevector-u32-ref bv 4 'little)", &env), "1");
+    }
+    #[test]
+    fn float_ref_set() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define bv (make-bytevector 8))", &env);
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-single-set! bv 0 1.5 'big)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("bv", &env), "#u8(63 192 0 0 0 0 0 0)");
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-single-ref bv 0 'big)", &env),
+            "1.5"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-double-set! bv 0 -0.25 'little)", &env),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-double-ref bv 0 'little)", &env),
+            "-0.25"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-double-set! bv 0 1/2)", &env),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env("(bytevector-ieee-double-ref bv 0)", &env),
+            "0.5"
+        );
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp;
+
+    #[test]
+    fn bytevector() {
+        assert_eq!(do_lisp("(bytevector 256)"), "E1021");
+        assert_eq!(do_lisp("(bytevector -1)"), "E1021");
+        assert_eq!(do_lisp("(bytevector 'a)"), "E1002");
+        assert_eq!(do_lisp("(bytevector? 1 2)"), "E1007");
+    }
+    #[test]
+    fn make_bytevector() {
+        assert_eq!(do_lisp("(make-bytevector)"), "E1007");
+        assert_eq!(do_lisp("(make-bytevector 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(make-bytevector -1)"), "E1021");
+        assert_eq!(do_lisp("(make-bytevector 'a)"), "E1002");
+        assert_eq!(do_lisp("(make-bytevector 1 256)"), "E1021");
+    }
+    #[test]
+    fn bytevector_length() {
+        assert_eq!(do_lisp("(bytevector-length)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-length #(1))"), "E1035");
+    }
+    #[test]
+    fn bytevector_u8() {
+        assert_eq!(do_lisp("(bytevector-u8-ref #u8(1))"), "E1007");
+        assert_eq!(do_lisp("(bytevector-u8-ref 1 0)"), "E1035");
+        assert_eq!(do_lisp("(bytevector-u8-ref #u8(1) 1)"), "E1011");
+        assert_eq!(do_lisp("(bytevector-u8-ref #u8(1) 'a)"), "E1002");
+        assert_eq!(do_lisp("(bytevector-u8-set! #u8(1) 0)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-u8-set! #u8(1) 1 0)"), "E1011");
+        assert_eq!(do_lisp("(bytevector-u8-set! #u8(1) 0 256)"), "E1021");
+    }
+    #[test]
+    fn bytevector_copy() {
+        assert_eq!(do_lisp("(bytevector-copy)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-copy #u8() 0 0 0)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-copy 1)"), "E1035");
+        assert_eq!(do_lisp("(bytevector-copy #u8(1) 2)"), "E1021");
+        assert_eq!(do_lisp("(bytevector-copy #u8(1 2) 1 0)"), "E1021");
+        assert_eq!(do_lisp("(bytevector-copy! #u8(1) 0)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-copy! #u8(1) 0 1)"), "E1035");
+        assert_eq!(do_lisp("(bytevector-copy! #u8(1) 0 #u8(1 2))"), "E1021");
+    }
+    #[test]
+    fn bytevector_append() {
+        assert_eq!(do_lisp("(bytevector-append #u8() 1)"), "E1035");
+    }
+    #[test]
+    fn bytevector_fill() {
+        assert_eq!(do_lisp("(bytevector-fill! #u8(1))"), "E1007");
+        assert_eq!(do_lisp("(bytevector-fill! 1 1)"), "E1035");
+        assert_eq!(do_lisp("(bytevector-fill! #u8(1) #t)"), "E1002");
+        assert_eq!(do_lisp("(bytevector-fill! #u8(1) 0 2)"), "E1021");
+    }
+    #[test]
+    fn utf8() {
+        assert_eq!(do_lisp("(utf8->string)"), "E1007");
+        assert_eq!(do_lisp("(utf8->string \"a\")"), "E1035");
+        assert_eq!(do_lisp("(utf8->string #u8(#xff))"), "E1015");
+        assert_eq!(do_lisp("(utf8->string #u8(1) 2)"), "E1021");
+        assert_eq!(do_lisp("(string->utf8)"), "E1007");
+        assert_eq!(do_lisp("(string->utf8 #u8())"), "E1015");
+        assert_eq!(do_lisp("(string->utf8 \"a\" 0 2)"), "E1021");
+    }
+    #[test]
+    fn int_ref() {
+        assert_eq!(do_lisp("(bytevector-u16-ref #u8(1 2))"), "E1007");
+        assert_eq!(do_lisp("(bytevector-u16-ref 1 0)"), "E1035");
+        assert_eq!(do_lisp("(bytevector-u16-ref #u8(1 2) 1)"), "E1011");
+        assert_eq!(do_lisp("(bytevector-u16-ref #u8(1 2) 0 1)"), "E1004");
+        assert_eq!(do_lisp("(bytevector-u16-ref #u8(1 2) 0 'middle)"), "E1021");
+    }
+    #[test]
+    fn int_set() {
+        assert_eq!(do_lisp("(bytevector-u16-set! #u8(1 2) 0)"), "E1007");
+        assert_eq!(do_lisp("(bytevector-u16-set! #u8(1 2) 0 1.5)"), "E1002");
+        assert_eq!(do_lisp("(bytevector-u16-set! #u8(1 2) 0 65536)"), "E1021");
+        assert_eq!(do_lisp("(bytevector-u16-set! #u8(1 2) 0 -1)"), "E1021");
+        assert_eq!(do_lisp("(bytevector-s16-set! #u8(1 2) 0 32768)"), "E1021");
+        assert_eq!(do_lisp("(bytevector-s16-set! #u8(1 2) 1 0)"), "E1011");
+    }
+    #[test]
+    fn float_ref_set() {
+        assert_eq!(
+            do_lisp("(bytevector-ieee-single-ref #u8(1 2 3 4))"),
+            "E1007"
+        );
+        assert_eq!(
+            do_lisp("(bytevector-ieee-double-ref #u8(1 2 3 4) 0)"),
+            "E1011"
+        );
+        assert_eq!(
+            do_lisp("(bytevector-ieee-single-set! #u8(1 2 3 4) 0 'a)"),
+            "E1003"
+        );
+        assert_eq!(
+            do_lisp("(bytevector-ieee-single-set! #u8(1 2 3 4) 0 1+2i)"),
+            "E1031"
+        );
+    }
+}
@@ -312,7 +312,9 @@ mod tests {
     #[test]
     fn print_char() {
         let c = Expression::Char(char::from(0x00));
-        assert_eq!(c.to_string(), "#\\non-printable-char");
+        assert_eq!(c.to_string(), "#\\null");
+        let c = Expression::Char(char::from(0x01));
+        assert_eq!(c.to_string(), "#\\x1");
     }
 }
 #[cfg(test)]
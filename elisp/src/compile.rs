@@ -0,0 +1,704 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::vec::Vec;
+
+use crate::lisp::{BasicBuiltIn, Environment, Expression, Function, Int, ListRc, Param};
+use crate::machine::{find, is_native, lookup, SYNTAX};
+use crate::pair;
+use crate::reference_obj;
+use crate::symbol::Symbol;
+
+// What is done with the value of an expression.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Next {
+    // pushed on the stack
+    Push,
+    // returned from the function (tail position)
+    Return,
+    // returned from the function after the check of boolean (the last of and, or)
+    ReturnBool,
+}
+#[derive(Clone)]
+pub enum Op {
+    Const(Expression),
+    Local(usize),
+    Free(Symbol),
+    // set! needs the bound variable
+    Bound(Symbol),
+    SetFree(Symbol),
+    Bind(usize),
+    Pop,
+    Jump(usize),
+    // jump if #f (error if not boolean)
+    If(usize),
+    // short circuit of and(true), or(false)
+    Logic(bool, usize),
+    Bool,
+    // check the callee (arity, syntax), ex. (f a b) => Check(2, (f a b), scope, pc of next op)
+    Check(usize, Expression, usize, usize, Next),
+    Call(usize, Next),
+    // the builtin function which is called with the values, ex. (car l) => Builtin(car, 1)
+    Builtin(BasicBuiltIn, usize),
+    // the builtin function of 2 integers, ex. (+ i 1) => Arith(Add, +)
+    // it's called if they are not the integers or overflowed.
+    Arith(Arith, BasicBuiltIn),
+    Lambda(Box<Lambda>),
+    Return,
+}
+#[derive(Clone, Copy)]
+pub enum Arith {
+    Add,
+    Sub,
+    Mul,
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+impl Arith {
+    fn new(name: &str) -> Option<Self> {
+        match name {
+            "+" => Some(Arith::Add),
+            "-" => Some(Arith::Sub),
+            "*" => Some(Arith::Mul),
+            "=" => Some(Arith::Eq),
+            "<" => Some(Arith::Lt),
+            "<=" => Some(Arith::Le),
+            ">" => Some(Arith::Gt),
+            ">=" => Some(Arith::Ge),
+            _ => None,
+        }
+    }
+    pub(crate) fn calc(self, x: Int, y: Int) -> Option<Expression> {
+        match self {
+            Arith::Add => x.checked_add(y).map(Expression::Integer),
+            Arith::Sub => x.checked_sub(y).map(Expression::Integer),
+            Arith::Mul => x.checked_mul(y).map(Expression::Integer),
+            Arith::Eq => Some(Expression::Boolean(x == y)),
+            Arith::Lt => Some(Expression::Boolean(x < y)),
+            Arith::Le => Some(Expression::Boolean(x <= y)),
+            Arith::Gt => Some(Expression::Boolean(x > y)),
+            Arith::Ge => Some(Expression::Boolean(x >= y)),
+        }
+    }
+}
+// The lambda in the compiled function, the closures share the code of the template.
+#[derive(Clone)]
+pub struct Lambda {
+    pub(crate) func: Function,
+    // the local variables which are referred in the body, they are copied to the closure.
+    pub(crate) captures: Vec<(Symbol, usize)>,
+    // the name of the named let, which is bound to the closure itself
+    pub(crate) name: Option<Symbol>,
+}
+pub struct Code {
+    pub(crate) ops: Vec<Op>,
+    // the innermost form of each op
+    pub(crate) forms: Vec<Option<ListRc>>,
+    pub(crate) scopes: Vec<Vec<(Symbol, usize)>>,
+    pub(crate) slots: usize,
+}
+struct Compiler<'a> {
+    ops: Vec<Op>,
+    forms: Vec<Option<ListRc>>,
+    form: Vec<ListRc>,
+    scopes: Vec<Vec<(Symbol, usize)>>,
+    scope: Vec<(Symbol, usize)>,
+    slots: usize,
+    env: &'a Environment,
+}
+// Compile the body of the function.
+// None is returned if it is run by the tree-walker,
+// ex. the local variables are changed by set!, the definitions or the syntax like do are in it.
+pub fn compile(f: &Function) -> Option<Code> {
+    let param = f.param();
+    if !param.is_positional() {
+        return None;
+    }
+    let mut c = Compiler {
+        ops: Vec::new(),
+        forms: Vec::new(),
+        form: Vec::new(),
+        scopes: Vec::new(),
+        scope: Vec::new(),
+        slots: 0,
+        env: f.closure_env(),
+    };
+    for s in param.required().iter().chain(param.rest()) {
+        if is_mutated(f.body(), s) {
+            return None;
+        }
+        c.scope.push((s.clone(), c.slots));
+        c.slots += 1;
+    }
+    for e in f.body() {
+        if !c.is_compilable(e) {
+            return None;
+        }
+    }
+    c.seq(f.body(), Next::Return)?;
+
+    debug!("compile {} ({} ops)", f.get_name(), c.ops.len());
+    Some(Code {
+        ops: c.ops,
+        forms: c.forms,
+        scopes: c.scopes,
+        slots: c.slots,
+    })
+}
+// (set! s ...) is in exp
+fn is_mutated(body: &[Expression], s: &Symbol) -> bool {
+    body.iter().any(|e| match e {
+        Expression::List(l) => {
+            let l = &*(reference_obj!(l));
+            if let [f, Expression::Symbol(v), ..] = &l[..] {
+                if is_keyword(f, "set!") && v == s {
+                    return true;
+                }
+            }
+            is_mutated(l, s)
+        }
+        _ => false,
+    })
+}
+impl Compiler<'_> {
+    // The definitions and the macros are not compiled.
+    fn is_compilable(&self, exp: &Expression) -> bool {
+        let l = match exp {
+            Expression::List(l) => l,
+            _ => return true,
+        };
+        let l = &*(reference_obj!(l));
+        match l.first() {
+            Some(Expression::BuildInFunction(f, _)) if f.starts_with("define") => return false,
+            Some(Expression::Symbol(s)) => match find(s, self.env) {
+                Some(Expression::Syntax(_)) => return false,
+                None if s.starts_with("define") => return false,
+                _ => {}
+            },
+            _ => {}
+        }
+        l.iter().all(|e| self.is_compilable(e))
+    }
+    fn emit(&mut self, op: Op) -> usize {
+        self.ops.push(op);
+        self.forms.push(self.form.last().cloned());
+        self.ops.len() - 1
+    }
+    fn patch(&mut self, idx: usize) {
+        let pc = self.ops.len();
+        match &mut self.ops[idx] {
+            Op::Jump(t) | Op::If(t) | Op::Logic(_, t) => *t = pc,
+            Op::Check(_, _, _, t, _) => *t = pc,
+            _ => {}
+        }
+    }
+    fn finish(&mut self, next: Next) {
+        match next {
+            Next::Push => {}
+            Next::Return => {
+                self.emit(Op::Return);
+            }
+            Next::ReturnBool => {
+                self.emit(Op::Bool);
+                self.emit(Op::Return);
+            }
+        }
+    }
+    fn local(&self, s: &Symbol) -> Option<usize> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(v, _)| v == s)
+            .map(|(_, i)| *i)
+    }
+    // The builtin function of the symbol which is not bound, ex. car
+    // It's resolved at the compile time, like as the inlining of the other lisp.
+    fn builtin(&self, s: &Symbol) -> Option<Expression> {
+        if self.local(s).is_some() || find(s, self.env).is_some() {
+            return None;
+        }
+        match lookup(s, self.env) {
+            Ok(e @ Expression::BuildInFunction(_, _)) => Some(e),
+            _ => None,
+        }
+    }
+    fn snapshot(&mut self) -> usize {
+        self.scopes.push(self.scope.clone());
+        self.scopes.len() - 1
+    }
+    fn seq(&mut self, body: &[Expression], next: Next) -> Option<()> {
+        for (i, e) in body.iter().enumerate() {
+            if i + 1 == body.len() {
+                self.exp(e, next)?;
+            } else {
+                self.exp(e, Next::Push)?;
+                self.emit(Op::Pop);
+            }
+        }
+        Some(())
+    }
+    fn exp(&mut self, exp: &Expression, next: Next) -> Option<()> {
+        match exp {
+            Expression::Symbol(s) => {
+                let op = match (self.local(s), self.builtin(s)) {
+                    (Some(i), _) => Op::Local(i),
+                    (None, Some(f)) => Op::Const(f),
+                    (None, None) => Op::Free(s.clone()),
+                };
+                self.emit(op);
+            }
+            Expression::List(l) if !reference_obj!(l).is_empty() => {
+                self.form.push(l.clone());
+                let r = self.list(exp, &reference_obj!(l), next);
+                self.form.pop();
+                return r;
+            }
+            // ex. ()
+            _ => {
+                self.emit(Op::Const(pair::datum(exp)));
+            }
+        }
+        self.finish(next);
+        Some(())
+    }
+    fn list(&mut self, exp: &Expression, l: &[Expression], next: Next) -> Option<()> {
+        let (name, f) = match &l[0] {
+            f @ Expression::BuildInFunction(name, _) => (*name, f.clone()),
+            Expression::Symbol(s) if self.local(s).is_none() => match self.builtin(s) {
+                Some(f @ Expression::BuildInFunction(name, _)) => (name, f),
+                _ => {
+                    if let Some(Expression::BuildInFunction(name, _)) = find(s, self.env) {
+                        if SYNTAX.contains(&name) {
+                            return None;
+                        }
+                    }
+                    return self.call(exp, l, next);
+                }
+            },
+            _ => return self.call(exp, l, next),
+        };
+        match name {
+            "quote" if l.len() == 2 => {
+                self.emit(Op::Const(pair::datum(&l[1])));
+                self.finish(next);
+            }
+            "if" if l.len() == 3 || l.len() == 4 => {
+                self.exp(&l[1], Next::Push)?;
+                let test = self.emit(Op::If(0));
+                self.exp(&l[2], next)?;
+                let jump = if next == Next::Push {
+                    Some(self.emit(Op::Jump(0)))
+                } else {
+                    None
+                };
+                self.patch(test);
+                match l.get(3) {
+                    Some(e) => self.exp(e, next)?,
+                    None => self.exp(&Expression::Nil(), next)?,
+                }
+                if let Some(jump) = jump {
+                    self.patch(jump);
+                }
+            }
+            "begin" if l.len() >= 2 => self.seq(&l[1..], next)?,
+            "and" | "or" if l.len() >= 3 => {
+                let mut jumps = Vec::new();
+                for e in &l[1..l.len() - 1] {
+                    self.exp(e, Next::Push)?;
+                    jumps.push(self.emit(Op::Logic(name == "and", 0)));
+                }
+                if next == Next::Push {
+                    self.exp(&l[l.len() - 1], Next::Push)?;
+                    self.emit(Op::Bool);
+                } else {
+                    self.exp(&l[l.len() - 1], Next::ReturnBool)?;
+                }
+                for j in jumps {
+                    self.patch(j);
+                }
+                if next != Next::Push {
+                    self.emit(Op::Return);
+                }
+            }
+            "cond" if l.len() >= 2 && l[1..].iter().all(is_clause) => self.cond(&l[1..], next)?,
+            "let" if is_let(l) => self.let_f(l, false, next)?,
+            "let*" if is_let(l) => self.let_f(l, true, next)?,
+            // ex. (let loop ((i 0)) (loop (+ i 1))) => ((lambda (i) (loop (+ i 1))) 0)
+            "let" if matches!(l[1], Expression::Symbol(_)) && is_let(&l[1..]) => {
+                let (names, inits) = bindings(&l[2]);
+                let mut form = vec![l[0].clone(), Environment::create_form(names)];
+                form.extend_from_slice(&l[3..]);
+                self.lambda(&form, symbol(&l[1]))?;
+                for e in &inits {
+                    self.exp(e, Next::Push)?;
+                }
+                self.emit(Op::Call(inits.len(), next));
+                self.finish(next);
+            }
+            "lambda" if l.len() >= 3 => {
+                self.lambda(l, None)?;
+                self.finish(next);
+            }
+            "set!" if l.len() == 3 => match &l[1] {
+                Expression::Symbol(s) if self.local(s).is_none() => {
+                    self.emit(Op::Bound(s.clone()));
+                    self.exp(&l[2], Next::Push)?;
+                    self.emit(Op::SetFree(s.clone()));
+                    self.finish(next);
+                }
+                _ => return None,
+            },
+            _ if SYNTAX.contains(&name) => return None,
+            _ => {
+                self.emit(Op::Const(f.clone()));
+                for e in &l[1..] {
+                    self.exp(e, Next::Push)?;
+                }
+                match f {
+                    Expression::BuildInFunction(_, f) if !is_native(name) => {
+                        match Arith::new(name) {
+                            Some(a) if l.len() == 3 => self.emit(Op::Arith(a, f)),
+                            _ => self.emit(Op::Builtin(f, l.len() - 1)),
+                        };
+                    }
+                    _ => {
+                        self.emit(Op::Call(l.len() - 1, next));
+                    }
+                }
+                self.finish(next);
+            }
+        }
+        Some(())
+    }
+    fn call(&mut self, exp: &Expression, l: &[Expression], next: Next) -> Option<()> {
+        self.exp(&l[0], Next::Push)?;
+        let scope = self.snapshot();
+        let check = self.emit(Op::Check(l.len() - 1, exp.clone(), scope, 0, next));
+        for e in &l[1..] {
+            self.exp(e, Next::Push)?;
+        }
+        self.emit(Op::Call(l.len() - 1, next));
+        self.patch(check);
+        self.finish(next);
+        Some(())
+    }
+    fn cond(&mut self, clauses: &[Expression], next: Next) -> Option<()> {
+        let mut jumps = Vec::new();
+        for e in clauses {
+            let c = match e {
+                Expression::List(l) => reference_obj!(l).to_vec(),
+                _ => return None,
+            };
+            if let Expression::Symbol(_) = &c[0] {
+                // else
+                self.seq(&c[1..], next)?;
+                for j in jumps {
+                    self.patch(j);
+                }
+                return Some(());
+            }
+            self.exp(&c[0], Next::Push)?;
+            let test = self.emit(Op::If(0));
+            if c.len() == 1 {
+                self.exp(&Expression::Boolean(true), next)?;
+            } else {
+                self.seq(&c[1..], next)?;
+            }
+            if next == Next::Push {
+                jumps.push(self.emit(Op::Jump(0)));
+            }
+            self.patch(test);
+        }
+        self.exp(&Expression::Nil(), next)?;
+        for j in jumps {
+            self.patch(j);
+        }
+        Some(())
+    }
+    // The variables of let* are bound one by one.
+    fn let_f(&mut self, l: &[Expression], star: bool, next: Next) -> Option<()> {
+        let (names, inits) = bindings(&l[1]);
+        // the variables which are changed, are in the environment of the tree-walker.
+        if names
+            .iter()
+            .any(|s| is_mutated(&l[2..], &symbol(s).unwrap()))
+        {
+            return None;
+        }
+        let base = self.slots;
+        self.slots += names.len();
+        let depth = self.scope.len();
+        for (i, e) in inits.iter().enumerate() {
+            self.exp(e, Next::Push)?;
+            if star {
+                self.emit(Op::Bind(base + i));
+                self.scope.push((symbol(&names[i])?, base + i));
+            }
+        }
+        if !star {
+            for i in (0..names.len()).rev() {
+                self.emit(Op::Bind(base + i));
+            }
+            for (i, s) in names.iter().enumerate() {
+                self.scope.push((symbol(s)?, base + i));
+            }
+        }
+        self.seq(&l[2..], next)?;
+        self.scope.truncate(depth);
+        Some(())
+    }
+    // The closure is created with the local variables which are referred in it.
+    fn lambda(&mut self, l: &[Expression], name: Option<Symbol>) -> Option<()> {
+        Param::new(&l[1]).ok()?;
+        let mut captures = Vec::new();
+        self.captures(&l[1..], &mut captures);
+        let s = name.as_ref().map_or("lambda", |s| s.as_str()).to_string();
+        let func = Function::new(l, s, self.env.clone());
+        self.emit(Op::Lambda(Box::new(Lambda {
+            func,
+            captures,
+            name,
+        })));
+        Some(())
+    }
+    fn captures(&self, body: &[Expression], v: &mut Vec<(Symbol, usize)>) {
+        for e in body {
+            match e {
+                Expression::Symbol(s) => {
+                    if let Some(i) = self.local(s) {
+                        if !v.iter().any(|(c, _)| c == s) {
+                            v.push((s.clone(), i));
+                        }
+                    }
+                }
+                Expression::List(l) => self.captures(&reference_obj!(l), v),
+                _ => {}
+            }
+        }
+    }
+}
+// The symbol or the builtin function of the syntax, ex. set!
+fn is_keyword(exp: &Expression, name: &str) -> bool {
+    match exp {
+        Expression::Symbol(s) => s == name,
+        Expression::BuildInFunction(s, _) => *s == name,
+        _ => false,
+    }
+}
+fn symbol(exp: &Expression) -> Option<Symbol> {
+    match exp {
+        Expression::Symbol(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+// ex. ((a 1) (b 2)) => [a, b], [1, 2]
+fn bindings(exp: &Expression) -> (Vec<Expression>, Vec<Expression>) {
+    let mut names = Vec::new();
+    let mut inits = Vec::new();
+    if let Expression::List(b) = exp {
+        for e in &*(reference_obj!(b)) {
+            if let Expression::List(v) = e {
+                if let [s, e] = &reference_obj!(v)[..] {
+                    names.push(s.clone());
+                    inits.push(e.clone());
+                }
+            }
+        }
+    }
+    (names, inits)
+}
+// (test body ...) or (else body ...)
+fn is_clause(exp: &Expression) -> bool {
+    match exp {
+        Expression::List(l) => match &reference_obj!(l)[..] {
+            [] => false,
+            [Expression::Symbol(s), _, ..] => s == "else",
+            [Expression::Symbol(_)] => false,
+            _ => true,
+        },
+        _ => false,
+    }
+}
+// (let ((v e) ...) body ...), the named let is not compiled.
+fn is_let(l: &[Expression]) -> bool {
+    if l.len() < 3 {
+        return false;
+    }
+    let b = match &l[1] {
+        Expression::List(b) => b,
+        _ => return false,
+    };
+    let b = &*(reference_obj!(b));
+    let mut names: Vec<Symbol> = Vec::new();
+    for e in b {
+        match e {
+            Expression::List(v) => match &reference_obj!(v)[..] {
+                [Expression::Symbol(s), _] => {
+                    if names.contains(s) {
+                        return false;
+                    }
+                    names.push(s.clone());
+                }
+                _ => return false,
+            },
+            _ => return false,
+        }
+    }
+    true
+}
+#[cfg(test)]
+mod tests {
+    use crate::do_lisp_env;
+    use crate::lisp;
+    use crate::lisp::Expression;
+
+    fn is_compiled(name: &str, env: &lisp::Environment) -> bool {
+        match env.find(name) {
+            Some(Expression::Function(f)) => f.code().is_some(),
+            _ => false,
+        }
+    }
+    #[test]
+    fn compile() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1)))))",
+            &env,
+        );
+        assert!(is_compiled("fact", &env));
+        do_lisp_env(
+            "(define (f a) (let ((b 1)) (cond ((= a b) 'c) (else 'd))))",
+            &env,
+        );
+        assert!(is_compiled("f", &env));
+        do_lisp_env("(define (f a) (set! a 10) a)", &env);
+        assert!(!is_compiled("f", &env));
+        do_lisp_env("(define (f a) (define b 10) (+ a b))", &env);
+        assert!(!is_compiled("f", &env));
+        do_lisp_env("(define (f #!optional (a 10)) a)", &env);
+        assert!(!is_compiled("f", &env));
+        do_lisp_env(
+            "(define (f l) (let loop ((l l) (n 0)) (if (null? l) n (loop (cdr l) (+ n 1)))))",
+            &env,
+        );
+        assert!(is_compiled("f", &env));
+        do_lisp_env(
+            "(define (f a) (let* ((b a) (c b)) (lambda (d) (+ c d))))",
+            &env,
+        );
+        assert!(is_compiled("f", &env));
+        do_lisp_env("(define (f a) (let ((b a)) (set! b 1) b))", &env);
+        assert!(!is_compiled("f", &env));
+    }
+    #[test]
+    fn execute() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (f a . b) (let ((a 10) (c a)) (list a b c)))", &env);
+        assert_eq!(do_lisp_env("(f 1 2 3)", &env), "(10 (2 3) 1)");
+        do_lisp_env(
+            "(define (f a) (cond ((= a 1)) ((= a 2) 'b) (else 'c)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 1)", &env), "#t");
+        assert_eq!(do_lisp_env("(f 2)", &env), "b");
+        assert_eq!(do_lisp_env("(f 3)", &env), "c");
+        do_lisp_env(
+            "(define (f a) (and (< 0 a) (or (= a 1) (f (- a 1)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 10000)", &env), "#t");
+        assert_eq!(do_lisp_env("(f 0)", &env), "#f");
+        do_lisp_env("(define c 0)", &env);
+        do_lisp_env("(define (f a) (set! c (+ c a)) c)", &env);
+        assert_eq!(do_lisp_env("(f 10)", &env), "10");
+        assert_eq!(do_lisp_env("(f 10)", &env), "20");
+        do_lisp_env("(define (f a) (lambda (b) (+ a b)))", &env);
+        assert_eq!(do_lisp_env("((f 1) 2)", &env), "3");
+        do_lisp_env("(define (f g a) (g (= a 1) 'a 'b))", &env);
+        assert_eq!(do_lisp_env("(f if 1)", &env), "a");
+        do_lisp_env("(define (f a) (+ 1 (call/cc (lambda (k) (k a)))))", &env);
+        assert_eq!(do_lisp_env("(f 10)", &env), "11");
+    }
+    #[test]
+    fn closure() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f n) (let loop ((i 0) (acc '())) (if (= i n) acc (loop (+ i 1) (cons i acc)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 3)", &env), "(2 1 0)");
+        assert_eq!(do_lisp_env("(length (f 100000))", &env), "100000");
+        do_lisp_env(
+            "(define (f a) (let* ((b (+ a 1)) (c (* b 2))) (list a b c)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 1)", &env), "(1 2 4)");
+        do_lisp_env("(define (f p l) (filter (lambda (x) (< x p)) l))", &env);
+        assert_eq!(do_lisp_env("(f 3 (iota 10))", &env), "(0 1 2)");
+        do_lisp_env(
+            "(define (f a) (map (lambda (g) (g a)) (list (lambda (x) (* x 2)) car)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 2)", &env), "E1005");
+        do_lisp_env("(define (f a) (let ((g (lambda () a))) (g)))", &env);
+        assert_eq!(do_lisp_env("(f 5)", &env), "5");
+    }
+    #[test]
+    fn arith() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f a b) (list (+ a b) (- a b) (* a b) (< a b) (>= a b)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 3 2)", &env), "(5 1 6 #f #t)");
+        assert_eq!(do_lisp_env("(f 1/2 0.5)", &env), "(1.0 0.0 0.25 #f #t)");
+        assert_eq!(
+            do_lisp_env("(f 9223372036854775807 1)", &env),
+            "(9223372036854775808 9223372036854775806 9223372036854775807 #f #t)"
+        );
+    }
+    #[test]
+    fn bytecode_off() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(bytecode-off)", &env);
+        assert!(!env.is_bytecode());
+        do_lisp_env("(define (f a) (if (= a 0) 'a (f (- a 1))))", &env);
+        assert_eq!(do_lisp_env("(f 10)", &env), "a");
+        do_lisp_env("(bytecode-on)", &env);
+        assert!(env.is_bytecode());
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp_env;
+    use crate::lisp;
+
+    #[test]
+    fn execute() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (f a) (if a 1 2))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1001");
+        do_lisp_env("(define (f a) (and (= a 1) a))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1001");
+        do_lisp_env("(define (f a) (a 1))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1006");
+        do_lisp_env("(define (f a) (f))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1007");
+        do_lisp_env("(define (f a) (g a))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1008");
+        do_lisp_env("(define (f a) (set! b a))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1008");
+        do_lisp_env("(define (f a) (+ a 1))", &env);
+        assert_eq!(do_lisp_env("(f 'a)", &env), "E1003");
+        do_lisp_env("(define (f a) (let loop ((i a)) (loop)))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "E1007");
+    }
+}
@@ -18,6 +18,7 @@ use crate::lisp::Environment;
 use crate::lisp::ErrCode;
 use crate::lisp::Error;
 use crate::lisp::Expression;
+use crate::pair;
 
 // ----------------------------------------------------------------
 // set up for draw_line
@@ -100,7 +101,8 @@ fn set_loc(
     } else if exp.len() == (param.1 / 2 + param.0) {
         for i in (0..param.1).step_by(2) {
             if let Some(e) = iter.next() {
-                if let Expression::Pair(x, y) = eval(e, env)? {
+                if let v @ Expression::Pair(_) = eval(e, env)? {
+                    let (x, y) = (pair::car(&v), pair::cdr(&v));
                     if let Expression::Float(f) = eval(&x, env)? {
                         loc[i] = f;
                     } else {
@@ -7,6 +7,9 @@
 use crate::buildin::create_function;
 use crate::buildin::BuildInTable;
 use crate::lisp::{BasicBuiltIn, Expression};
+use crate::source::SourceTbl;
+use crate::symbol::Symbol;
+use std::collections::HashMap;
 
 #[cfg(not(feature = "thread"))]
 use crate::env_single::ExtFunctionRc;
@@ -34,10 +37,17 @@ pub(crate) struct GlobalTbl {
     pub(crate) builtin_tbl: Map<&'static str, BasicBuiltIn>,
     pub(crate) builtin_tbl_ext: Map<&'static str, ExtFunctionRc>,
     pub(crate) tail_recursion: bool,
+    pub(crate) bytecode: bool,
     pub(crate) force_stop: bool,
     pub(crate) cont: Option<Expression>,
+    // The stdin and stdout are created on demand.
+    pub(crate) input_port: Option<Expression>,
+    pub(crate) output_port: Option<Expression>,
     pub(crate) limit_stop: bool,
     pub(crate) eval_count: u32,
+    pub(crate) source: SourceTbl,
+    // the environments of the macro definitions, ex. (define-syntax ...)
+    pub(crate) scopes: HashMap<usize, EnvTable>,
 }
 impl GlobalTbl {
     pub fn new() -> Self {
@@ -47,35 +57,40 @@ impl GlobalTbl {
             builtin_tbl: b,
             builtin_tbl_ext: Map::new(),
             tail_recursion: true,
+            bytecode: true,
             force_stop: false,
             cont: None,
+            input_port: None,
+            output_port: None,
             limit_stop: false,
             eval_count: 0,
+            source: SourceTbl::new(),
+            scopes: HashMap::new(),
         }
     }
 }
 pub(crate) struct SimpleEnv {
-    pub(crate) env_tbl: Map<String, Expression>,
+    pub(crate) env_tbl: HashMap<Symbol, Expression>,
     pub(crate) parent: Option<EnvTable>,
 }
 impl SimpleEnv {
     pub fn new(parent: Option<EnvTable>) -> Self {
         if let Some(p) = parent {
             SimpleEnv {
-                env_tbl: Map::new(),
+                env_tbl: HashMap::new(),
                 parent: Some(p),
             }
         } else {
             SimpleEnv {
-                env_tbl: Map::new(),
+                env_tbl: HashMap::new(),
                 parent,
             }
         }
     }
-    pub fn regist(&mut self, key: String, exp: Expression) {
+    pub fn regist(&mut self, key: Symbol, exp: Expression) {
         self.env_tbl.insert(key, exp);
     }
-    pub fn find(&self, key: &str) -> Option<Expression> {
+    pub fn find(&self, key: &Symbol) -> Option<Expression> {
         match self.env_tbl.get(key) {
             Some(v) => Some(v.clone()),
             None => match self.parent {
@@ -84,15 +99,15 @@ impl SimpleEnv {
             },
         }
     }
-    pub fn update(&mut self, key: &str, exp: Expression) {
-        if self.env_tbl.contains_key(key) {
-            self.env_tbl.insert(key.to_string(), exp);
+    pub fn update(&mut self, key: &Symbol, exp: Expression) {
+        if let Some(v) = self.env_tbl.get_mut(key) {
+            *v = exp;
         } else if let Some(ref p) = self.parent {
             mut_env!(p).update(key, exp)
         }
     }
     #[cfg(feature = "thread")]
-    pub fn regist_root(&mut self, key: String, exp: Expression) {
+    pub fn regist_root(&mut self, key: Symbol, exp: Expression) {
         match &self.parent {
             Some(p) => reference_env!(p).regist_root(key, exp),
             None => {
@@ -105,12 +120,13 @@ impl SimpleEnv {
 #[cfg(feature = "thread")]
 fn test_regist_root() {
     let mut env = SimpleEnv::new(None);
-    env.regist_root("a".to_string(), Expression::Integer(10));
+    env.regist_root(Symbol::from("a"), Expression::Integer(10));
 }
 #[test]
 fn global_tbl() {
     let g = GlobalTbl::new();
     assert!(g.tail_recursion);
+    assert!(g.bytecode);
     assert!(!g.force_stop);
     assert!(!g.builtin_tbl.is_empty());
     assert_eq!(g.builtin_tbl_ext.len(), 0);
@@ -120,18 +136,19 @@ fn simple_env() {
     let mut s = SimpleEnv::new(None);
     assert_eq!(if s.parent.is_some() { "exists" } else { "None" }, "None");
 
-    s.regist("x".to_string(), Expression::Integer(10));
+    let x = Symbol::from("x");
+    s.regist(x.clone(), Expression::Integer(10));
     assert_eq!(
-        if let Some(Expression::Integer(x)) = s.find("x") {
+        if let Some(Expression::Integer(x)) = s.find(&x) {
             x
         } else {
             -1
         },
         10
     );
-    s.update("x", Expression::Integer(20));
+    s.update(&x, Expression::Integer(20));
     assert_eq!(
-        if let Some(Expression::Integer(x)) = s.find("x") {
+        if let Some(Expression::Integer(x)) = s.find(&x) {
             x
         } else {
             -1
@@ -4,14 +4,21 @@
 
    hidekuno@gmail.com
 */
+use std::cell::OnceCell;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::collections::HashMap;
 use std::rc::Rc;
+use std::rc::Weak;
 use std::vec::Vec;
 
+use crate::compile::Code;
 use crate::env::{GlobalTbl, SimpleEnv};
+use crate::hashtable::HashTable;
 use crate::lisp::{BasicBuiltIn, Expression, Function, ResultExpression};
+use crate::pair::Pair;
+use crate::port::Port;
+use crate::source::Position;
+use crate::symbol::Symbol;
 //========================================================================
 pub(crate) type ExtFunction = dyn Fn(&[Expression], &Environment) -> ResultExpression;
 pub(crate) type EnvTable = Rc<RefCell<SimpleEnv>>;
@@ -19,9 +26,15 @@ pub(crate) type EnvTable = Rc<RefCell<SimpleEnv>>;
 pub type FunctionRc = Rc<Function>;
 pub type ExtFunctionRc = Rc<ExtFunction>;
 pub type ListRc = Rc<RefCell<Vec<Expression>>>;
-pub type HashTableRc = Rc<RefCell<HashMap<String, Expression>>>;
-pub type TreeMapRc = Rc<RefCell<BTreeMap<String, Expression>>>;
+pub type PairRc = Rc<RefCell<Pair>>;
+pub(crate) type ListWeak = Weak<RefCell<Vec<Expression>>>;
+pub type HashTableRc = Rc<RefCell<HashTable>>;
+pub type TreeMapRc = Rc<RefCell<BTreeMap<Expression, Expression>>>;
 pub type StringRc = Rc<String>;
+pub type PortRc = Rc<RefCell<Port>>;
+pub type BytesRc = Rc<RefCell<Vec<u8>>>;
+pub type CodeRc = Rc<Code>;
+pub(crate) type CodeCell = Rc<OnceCell<Option<CodeRc>>>;
 
 #[macro_export]
 macro_rules! reference_obj {
@@ -60,55 +73,148 @@ macro_rules! get_ptr {
 pub struct Environment {
     core: EnvTable,
     globals: Rc<RefCell<GlobalTbl>>,
+    // only the syntax is visible, ex. (null-environment 7)
+    syntax_only: bool,
+    // the arguments of the builtin function are the values, ex. called by the bytecode
+    evaluated: bool,
 }
 impl Environment {
     pub fn new() -> Self {
         Environment {
             core: Rc::new(RefCell::new(SimpleEnv::new(None))),
             globals: Rc::new(RefCell::new(GlobalTbl::new())),
+            syntax_only: false,
+            evaluated: false,
         }
     }
     pub fn with_parent(parent: &Environment) -> Self {
         Environment {
             core: Rc::new(RefCell::new(SimpleEnv::new(Some(parent.core.clone())))),
             globals: parent.globals.clone(),
+            syntax_only: parent.syntax_only,
+            evaluated: false,
         }
     }
+    // The new top level environment, which shares the builtin functions.
+    pub fn with_globals(env: &Environment, syntax_only: bool) -> Self {
+        Environment {
+            core: Rc::new(RefCell::new(SimpleEnv::new(None))),
+            globals: env.globals.clone(),
+            syntax_only,
+            evaluated: false,
+        }
+    }
+    // The top level environment, which this belongs to.
+    pub fn root(&self) -> Self {
+        let mut core = self.core.clone();
+        loop {
+            let parent = core.borrow().parent.clone();
+            match parent {
+                Some(p) => core = p,
+                None => break,
+            }
+        }
+        Environment {
+            core,
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated: false,
+        }
+    }
+    pub fn is_syntax_only(&self) -> bool {
+        self.syntax_only
+    }
+    // ex. (+ a b) in the compiled function, a and b aren't evaluated again.
+    pub(crate) fn evaluated(&self, evaluated: bool) -> Self {
+        Environment {
+            core: self.core.clone(),
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated,
+        }
+    }
+    pub(crate) fn is_evaluated(&self) -> bool {
+        self.evaluated
+    }
     pub fn create_func(func: Function) -> Expression {
         Expression::Function(Rc::new(func))
     }
+    pub fn create_func_ext<F>(c: F) -> Expression
+    where
+        F: Fn(&[Expression], &Environment) -> ResultExpression + 'static,
+    {
+        Expression::BuildInFunctionExt(Rc::new(c))
+    }
+    pub fn create_pair(car: Expression, cdr: Expression) -> Expression {
+        Expression::Pair(Rc::new(RefCell::new(Pair::new(car, cdr))))
+    }
+    // ex. [1, 2, 3] => (1 2 3)
     pub fn create_list(l: Vec<Expression>) -> Expression {
+        Environment::create_dotted_list(l, Expression::Null())
+    }
+    // ex. [1, 2], 3 => (1 2 . 3)
+    pub fn create_dotted_list(l: Vec<Expression>, tail: Expression) -> Expression {
+        l.into_iter()
+            .rev()
+            .fold(tail, |cdr, car| Environment::create_pair(car, cdr))
+    }
+    // The list of the source code, which is evaluated.
+    pub fn create_form(l: Vec<Expression>) -> Expression {
         Expression::List(Rc::new(RefCell::new(l)))
     }
+    // The pair which is not referred from the others, it's taken out.
+    pub(crate) fn unique_pair(p: PairRc) -> Option<Pair> {
+        Rc::try_unwrap(p).ok().map(RefCell::into_inner)
+    }
     pub fn create_string(s: String) -> Expression {
         Expression::String(Rc::new(s))
     }
     pub fn create_vector(l: Vec<Expression>) -> Expression {
         Expression::Vector(Rc::new(RefCell::new(l)))
     }
-    pub fn create_hash_table(h: HashMap<String, Expression>) -> Expression {
+    pub fn create_values(l: Vec<Expression>) -> Expression {
+        Expression::Values(Rc::new(RefCell::new(l)))
+    }
+    pub fn create_hash_table(h: HashTable) -> Expression {
         Expression::HashTable(Rc::new(RefCell::new(h)))
     }
-    pub fn create_tree_map(m: BTreeMap<String, Expression>) -> Expression {
+    #[allow(clippy::mutable_key_type)]
+    pub fn create_tree_map(m: BTreeMap<Expression, Expression>) -> Expression {
         Expression::TreeMap(Rc::new(RefCell::new(m)))
     }
-    pub fn create_tail_recursion(func: Function) -> Expression {
-        Expression::TailRecursion(Rc::new(func))
+    pub fn create_port(p: Port) -> Expression {
+        Expression::Port(Rc::new(RefCell::new(p)))
     }
-    pub fn regist(&self, key: String, exp: Expression) {
-        self.core.borrow_mut().regist(key, exp);
+    pub fn create_bytevector(v: Vec<u8>) -> Expression {
+        Expression::ByteVector(Rc::new(RefCell::new(v)))
     }
-    #[inline]
+    pub fn regist<K: Into<Symbol>>(&self, key: K, exp: Expression) {
+        self.core.borrow_mut().regist(key.into(), exp);
+    }
+    // The name which is never interned, is not bound.
     pub fn find(&self, key: &str) -> Option<Expression> {
+        Symbol::lookup(key).and_then(|s| self.find_symbol(&s))
+    }
+    pub fn update(&self, key: &str, exp: Expression) {
+        if let Some(s) = Symbol::lookup(key) {
+            self.update_symbol(&s, exp);
+        }
+    }
+    #[inline]
+    pub fn find_symbol(&self, key: &Symbol) -> Option<Expression> {
         self.core.borrow().find(key)
     }
     #[inline]
-    pub fn update(&self, key: &str, exp: Expression) {
+    pub fn update_symbol(&self, key: &Symbol, exp: Expression) {
         self.core.borrow_mut().update(key, exp);
     }
     #[inline]
-    pub fn get_builtin_func(&self, key: &str) -> Option<BasicBuiltIn> {
-        self.globals.borrow().builtin_tbl.get(key).cloned()
+    pub fn get_builtin_func(&self, key: &str) -> Option<(&'static str, BasicBuiltIn)> {
+        self.globals
+            .borrow()
+            .builtin_tbl
+            .get_key_value(key)
+            .map(|(k, f)| (*k, *f))
     }
     #[inline]
     pub fn get_builtin_ext_func(&self, key: &str) -> Option<Rc<ExtFunction>> {
@@ -129,6 +235,12 @@ impl Environment {
     pub fn is_tail_recursion(&self) -> bool {
         self.globals.borrow().tail_recursion
     }
+    pub fn set_bytecode(&self, b: bool) {
+        self.globals.borrow_mut().bytecode = b;
+    }
+    pub fn is_bytecode(&self) -> bool {
+        self.globals.borrow().bytecode
+    }
     pub fn set_force_stop(&self, b: bool) {
         self.globals.borrow_mut().force_stop = b;
     }
@@ -148,13 +260,19 @@ impl Environment {
     pub fn is_limit_stop(&self) -> bool {
         self.globals.borrow().limit_stop
     }
+    pub(crate) fn set_position(&self, l: &ListRc, pos: Position) {
+        self.globals.borrow_mut().source.insert(l, pos);
+    }
+    pub(crate) fn get_position(&self, l: &ListRc) -> Option<Position> {
+        self.globals.borrow().source.get(l)
+    }
     pub fn get_function_list(&self) -> Option<String> {
         self.get_environment_list(|_k, v| matches!(v, Expression::Function(_)))
     }
     pub fn get_variable_list(&self) -> Option<String> {
         self.get_environment_list(|_k, v| !matches!(v, Expression::Function(_)))
     }
-    fn get_environment_list(&self, func: fn(&String, &Expression) -> bool) -> Option<String> {
+    fn get_environment_list(&self, func: fn(&Symbol, &Expression) -> bool) -> Option<String> {
         let mut list = Vec::new();
         let e = self.core.borrow();
         for (k, v) in e.env_tbl.iter() {
@@ -162,6 +280,7 @@ impl Environment {
                 list.push(k.as_str());
             }
         }
+        list.sort();
         if list.is_empty() {
             None
         } else {
@@ -192,6 +311,38 @@ impl Environment {
     pub fn get_cont(&self) -> Option<Expression> {
         return self.globals.borrow().cont.clone();
     }
+    pub fn set_input_port(&self, p: Option<Expression>) {
+        self.globals.borrow_mut().input_port = p;
+    }
+    pub fn get_input_port(&self) -> Option<Expression> {
+        self.globals.borrow().input_port.clone()
+    }
+    pub fn set_output_port(&self, p: Option<Expression>) {
+        self.globals.borrow_mut().output_port = p;
+    }
+    pub fn get_output_port(&self) -> Option<Expression> {
+        self.globals.borrow().output_port.clone()
+    }
+    // The renamed identifiers of the macro are resolved in the environment of the definition.
+    pub(crate) fn regist_scope(&self) -> usize {
+        let id = get_ptr!(&self.core) as *const u8 as usize;
+        let mut globals = self.globals.borrow_mut();
+        if !globals.scopes.contains_key(&id) {
+            // the environment which is referred from nowhere, is released.
+            globals.scopes.retain(|_, e| Rc::strong_count(e) > 1);
+            globals.scopes.insert(id, self.core.clone());
+        }
+        id
+    }
+    pub(crate) fn get_scope(&self, id: usize) -> Option<Environment> {
+        let core = self.globals.borrow().scopes.get(&id)?.clone();
+        Some(Environment {
+            core,
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated: false,
+        })
+    }
     pub fn as_ptr(&self) -> *const Environment {
         self.core.as_ptr() as *const Environment
     }
@@ -215,7 +366,7 @@ fn test_env_api() {
 
     assert_eq!(env.get_function_list(), Some("f".to_string()));
     assert_eq!(env.get_variable_list(), Some("a".to_string()));
-    assert_eq!(env.get_builtin_func_list().len(), 2529);
+    assert_eq!(env.get_builtin_func_list().len(), 6535);
     assert_eq!(env.get_builtin_ext_list(), "");
 
     let env = Environment::new();
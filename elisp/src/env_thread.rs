@@ -5,14 +5,21 @@
    hidekuno@gmail.com
 */
 use std::collections::BTreeMap;
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::OnceLock;
 use std::sync::RwLock;
+use std::sync::Weak;
 use std::vec::Vec;
 
+use crate::compile::Code;
 use crate::env::{GlobalTbl, SimpleEnv};
+use crate::hashtable::HashTable;
 use crate::lisp::{BasicBuiltIn, Expression, Function, ResultExpression};
+use crate::pair::Pair;
+use crate::port::Port;
+use crate::source::Position;
+use crate::symbol::Symbol;
 //========================================================================
 pub(crate) type ExtFunction =
     Box<dyn Fn(&[Expression], &Environment) -> ResultExpression + Sync + Send + 'static>;
@@ -21,9 +28,15 @@ pub(crate) type EnvTable = Arc<Mutex<SimpleEnv>>;
 pub type FunctionRc = Arc<Function>;
 pub type ExtFunctionRc = Arc<ExtFunction>;
 pub type ListRc = Arc<RwLock<Vec<Expression>>>;
-pub type HashTableRc = Arc<RwLock<HashMap<String, Expression>>>;
-pub type TreeMapRc = Arc<RwLock<BTreeMap<String, Expression>>>;
+pub type PairRc = Arc<RwLock<Pair>>;
+pub(crate) type ListWeak = Weak<RwLock<Vec<Expression>>>;
+pub type HashTableRc = Arc<RwLock<HashTable>>;
+pub type TreeMapRc = Arc<RwLock<BTreeMap<Expression, Expression>>>;
 pub type StringRc = Arc<String>;
+pub type PortRc = Arc<RwLock<Port>>;
+pub type BytesRc = Arc<RwLock<Vec<u8>>>;
+pub type CodeRc = Arc<Code>;
+pub(crate) type CodeCell = Arc<OnceLock<Option<CodeRc>>>;
 //========================================================================
 #[macro_export]
 macro_rules! reference_obj {
@@ -74,58 +87,152 @@ macro_rules! get_ptr {
 pub struct Environment {
     core: EnvTable,
     globals: Arc<Mutex<GlobalTbl>>,
+    // only the syntax is visible, ex. (null-environment 7)
+    syntax_only: bool,
+    // the arguments of the builtin function are the values, ex. called by the bytecode
+    evaluated: bool,
 }
 impl Environment {
     pub fn new() -> Self {
         Environment {
             core: Arc::new(Mutex::new(SimpleEnv::new(None))),
             globals: Arc::new(Mutex::new(GlobalTbl::new())),
+            syntax_only: false,
+            evaluated: false,
         }
     }
     pub fn with_parent(parent: &Environment) -> Self {
         Environment {
             core: Arc::new(Mutex::new(SimpleEnv::new(Some(parent.core.clone())))),
             globals: parent.globals.clone(),
+            syntax_only: parent.syntax_only,
+            evaluated: false,
         }
     }
+    // The new top level environment, which shares the builtin functions.
+    pub fn with_globals(env: &Environment, syntax_only: bool) -> Self {
+        Environment {
+            core: Arc::new(Mutex::new(SimpleEnv::new(None))),
+            globals: env.globals.clone(),
+            syntax_only,
+            evaluated: false,
+        }
+    }
+    // The top level environment, which this belongs to.
+    pub fn root(&self) -> Self {
+        let mut core = self.core.clone();
+        loop {
+            let parent = core.lock().unwrap().parent.clone();
+            match parent {
+                Some(p) => core = p,
+                None => break,
+            }
+        }
+        Environment {
+            core,
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated: false,
+        }
+    }
+    pub fn is_syntax_only(&self) -> bool {
+        self.syntax_only
+    }
+    // ex. (+ a b) in the compiled function, a and b aren't evaluated again.
+    pub(crate) fn evaluated(&self, evaluated: bool) -> Self {
+        Environment {
+            core: self.core.clone(),
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated,
+        }
+    }
+    pub(crate) fn is_evaluated(&self) -> bool {
+        self.evaluated
+    }
     pub fn create_func(func: Function) -> Expression {
         Expression::Function(Arc::new(func))
     }
+    pub fn create_func_ext<F>(c: F) -> Expression
+    where
+        F: Fn(&[Expression], &Environment) -> ResultExpression + Sync + Send + 'static,
+    {
+        Expression::BuildInFunctionExt(Arc::new(Box::new(c)))
+    }
+    pub fn create_pair(car: Expression, cdr: Expression) -> Expression {
+        Expression::Pair(Arc::new(RwLock::new(Pair::new(car, cdr))))
+    }
+    // ex. [1, 2, 3] => (1 2 3)
     pub fn create_list(l: Vec<Expression>) -> Expression {
+        Environment::create_dotted_list(l, Expression::Null())
+    }
+    // ex. [1, 2], 3 => (1 2 . 3)
+    pub fn create_dotted_list(l: Vec<Expression>, tail: Expression) -> Expression {
+        l.into_iter()
+            .rev()
+            .fold(tail, |cdr, car| Environment::create_pair(car, cdr))
+    }
+    // The list of the source code, which is evaluated.
+    pub fn create_form(l: Vec<Expression>) -> Expression {
         Expression::List(Arc::new(RwLock::new(l)))
     }
+    // The pair which is not referred from the others, it's taken out.
+    pub(crate) fn unique_pair(p: PairRc) -> Option<Pair> {
+        Arc::try_unwrap(p).ok().map(|p| p.into_inner().unwrap())
+    }
     pub fn create_string(s: String) -> Expression {
         Expression::String(Arc::new(s))
     }
     pub fn create_vector(l: Vec<Expression>) -> Expression {
         Expression::Vector(Arc::new(RwLock::new(l)))
     }
-    pub fn create_hash_table(h: HashMap<String, Expression>) -> Expression {
+    pub fn create_values(l: Vec<Expression>) -> Expression {
+        Expression::Values(Arc::new(RwLock::new(l)))
+    }
+    pub fn create_hash_table(h: HashTable) -> Expression {
         Expression::HashTable(Arc::new(RwLock::new(h)))
     }
-    pub fn create_tree_map(m: BTreeMap<String, Expression>) -> Expression {
+    #[allow(clippy::mutable_key_type)]
+    pub fn create_tree_map(m: BTreeMap<Expression, Expression>) -> Expression {
         Expression::TreeMap(Arc::new(RwLock::new(m)))
     }
-    pub fn create_tail_recursion(func: Function) -> Expression {
-        Expression::TailRecursion(Arc::new(func))
+    pub fn create_port(p: Port) -> Expression {
+        Expression::Port(Arc::new(RwLock::new(p)))
     }
-    pub fn regist(&self, key: String, exp: Expression) {
-        self.core.lock().unwrap().regist(key, exp);
+    pub fn create_bytevector(v: Vec<u8>) -> Expression {
+        Expression::ByteVector(Arc::new(RwLock::new(v)))
     }
-    pub fn regist_root(&self, key: String, exp: Expression) {
-        self.core.lock().unwrap().regist_root(key, exp);
+    pub fn regist<K: Into<Symbol>>(&self, key: K, exp: Expression) {
+        self.core.lock().unwrap().regist(key.into(), exp);
     }
-    #[inline]
+    pub fn regist_root<K: Into<Symbol>>(&self, key: K, exp: Expression) {
+        self.core.lock().unwrap().regist_root(key.into(), exp);
+    }
+    // The name which is never interned, is not bound.
     pub fn find(&self, key: &str) -> Option<Expression> {
+        Symbol::lookup(key).and_then(|s| self.find_symbol(&s))
+    }
+    pub fn update(&self, key: &str, exp: Expression) {
+        if let Some(s) = Symbol::lookup(key) {
+            self.update_symbol(&s, exp);
+        }
+    }
+    #[inline]
+    pub fn find_symbol(&self, key: &Symbol) -> Option<Expression> {
         self.core.lock().unwrap().find(key)
     }
     #[inline]
-    pub fn update(&self, key: &str, exp: Expression) {
+    pub fn update_symbol(&self, key: &Symbol, exp: Expression) {
         self.core.lock().unwrap().update(key, exp);
     }
     #[inline]
-    pub fn get_builtin_func(&self, key: &str) -> Option<BasicBuiltIn> {
-        self.globals.lock().unwrap().builtin_tbl.get(key).cloned()
+    pub fn get_builtin_func(&self, key: &str) -> Option<(&'static str, BasicBuiltIn)> {
+        self.globals
+            .lock()
+            .unwrap()
+            .builtin_tbl
+            .get_key_value(key)
+            .map(|(k, f)| (*k, *f))
     }
     #[inline]
     pub fn get_builtin_ext_func(&self, key: &str) -> Option<ExtFunctionRc> {
@@ -152,6 +259,12 @@ impl Environment {
     pub fn is_tail_recursion(&self) -> bool {
         self.globals.lock().unwrap().tail_recursion
     }
+    pub fn set_bytecode(&self, b: bool) {
+        self.globals.lock().unwrap().bytecode = b;
+    }
+    pub fn is_bytecode(&self) -> bool {
+        self.globals.lock().unwrap().bytecode
+    }
     pub fn set_force_stop(&self, b: bool) {
         self.globals.lock().unwrap().force_stop = b;
     }
@@ -159,6 +272,38 @@ impl Environment {
         self.globals.lock().unwrap().force_stop
     }
     #[inline]
+    pub fn set_input_port(&self, p: Option<Expression>) {
+        self.globals.lock().unwrap().input_port = p;
+    }
+    pub fn get_input_port(&self) -> Option<Expression> {
+        self.globals.lock().unwrap().input_port.clone()
+    }
+    pub fn set_output_port(&self, p: Option<Expression>) {
+        self.globals.lock().unwrap().output_port = p;
+    }
+    pub fn get_output_port(&self) -> Option<Expression> {
+        self.globals.lock().unwrap().output_port.clone()
+    }
+    // The renamed identifiers of the macro are resolved in the environment of the definition.
+    pub(crate) fn regist_scope(&self) -> usize {
+        let id = get_ptr!(&self.core) as *const u8 as usize;
+        let mut globals = self.globals.lock().unwrap();
+        if !globals.scopes.contains_key(&id) {
+            // the environment which is referred from nowhere, is released.
+            globals.scopes.retain(|_, e| Arc::strong_count(e) > 1);
+            globals.scopes.insert(id, self.core.clone());
+        }
+        id
+    }
+    pub(crate) fn get_scope(&self, id: usize) -> Option<Environment> {
+        let core = self.globals.lock().unwrap().scopes.get(&id)?.clone();
+        Some(Environment {
+            core,
+            globals: self.globals.clone(),
+            syntax_only: self.syntax_only,
+            evaluated: false,
+        })
+    }
     pub fn set_cont(&self, e: &Expression) {
         self.globals.lock().unwrap().cont = Some(e.clone());
     }
@@ -166,6 +311,12 @@ impl Environment {
     pub fn get_cont(&self) -> Option<Expression> {
         return self.globals.lock().unwrap().cont.clone();
     }
+    pub(crate) fn set_position(&self, l: &ListRc, pos: Position) {
+        self.globals.lock().unwrap().source.insert(l, pos);
+    }
+    pub(crate) fn get_position(&self, l: &ListRc) -> Option<Position> {
+        self.globals.lock().unwrap().source.get(l)
+    }
     pub fn inc_eval_count(&self) -> u32 {
         self.globals.lock().unwrap().eval_count += 1;
         self.globals.lock().unwrap().eval_count
@@ -0,0 +1,458 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::vec::Vec;
+
+use crate::buildin::BuildInTable;
+use crate::create_error;
+use crate::create_error_value;
+use crate::lisp::eval;
+use crate::lisp::{Environment, Expression, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::machine;
+use crate::symbol::Symbol;
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("error", error_f);
+    b.regist("raise", raise);
+    b.regist("raise-continuable", raise_continuable);
+    b.regist("with-exception-handler", with_exception_handler);
+    b.regist("guard", guard);
+    b.regist("error-object?", |exp, env| is_condition(exp, env, |_| true));
+    b.regist("read-error?", |exp, env| {
+        is_condition(exp, env, |c| {
+            matches!(
+                c,
+                ErrCode::E0001 | ErrCode::E0002 | ErrCode::E0003 | ErrCode::E0004
+            )
+        })
+    });
+    b.regist("file-error?", |exp, env| {
+        is_condition(exp, env, |c| {
+            matches!(c, ErrCode::E1014 | ErrCode::E1016 | ErrCode::E1034)
+        })
+    });
+    b.regist("error-object-message", error_object_message);
+    b.regist("error-object-irritants", error_object_irritants);
+    b.regist("error-object-code", error_object_code);
+}
+// The error object, the errors of the builtin functions are caught as it.
+#[derive(Clone)]
+pub struct Condition {
+    code: ErrCode,
+    message: String,
+    irritants: Vec<Expression>,
+    value: Option<String>,
+}
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<error {} \"{}\"", self.code.as_str(), self.message)?;
+        for e in &self.irritants {
+            write!(f, " {}", e)?;
+        }
+        write!(f, ">")
+    }
+}
+// The raised object of the error.
+pub(crate) fn error_object(e: &Error) -> Expression {
+    if let Some(obj) = &e.exp {
+        return obj.clone();
+    }
+    Expression::Condition(Box::new(Condition {
+        code: e.code.clone(),
+        message: e.code.get_msg().to_string(),
+        irritants: e
+            .value
+            .iter()
+            .map(|v| Environment::create_string(v.to_string()))
+            .collect(),
+        value: e.value.clone(),
+    }))
+}
+// The error which carries the raised object.
+pub(crate) fn raise_error(obj: Expression) -> Error {
+    let mut e = match &obj {
+        Expression::Condition(c) => {
+            let mut e = create_error!(c.code.clone());
+            e.value = c.value.clone();
+            e
+        }
+        _ => create_error_value!(ErrCode::E1027, obj),
+    };
+    e.exp = Some(obj);
+    e
+}
+fn error_f(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let message = match eval(&exp[1], env)? {
+        Expression::String(s) => s.to_string(),
+        e => return Err(create_error_value!(ErrCode::E1015, e)),
+    };
+    let mut irritants = Vec::new();
+    for e in &exp[2..] {
+        irritants.push(eval(e, env)?);
+    }
+    let mut value = message.clone();
+    for e in &irritants {
+        value.push(' ');
+        value.push_str(&e.to_string());
+    }
+    Err(raise_error(Expression::Condition(Box::new(Condition {
+        code: ErrCode::E1026,
+        message,
+        irritants,
+        value: Some(value),
+    }))))
+}
+fn raise(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Err(raise_error(eval(&exp[1], env)?))
+}
+fn raise_continuable(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    let raise_continuable = Expression::BuildInFunction("raise-continuable", raise_continuable);
+    machine::apply(&raise_continuable, vec![v], env)
+}
+fn with_exception_handler(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut args = Vec::new();
+    for e in &exp[1..] {
+        let v = eval(e, env)?;
+        if !Expression::is_procedure(&v) {
+            return Err(create_error_value!(ErrCode::E1006, v));
+        }
+        args.push(v);
+    }
+    let with_exception_handler =
+        Expression::BuildInFunction("with-exception-handler", with_exception_handler);
+    machine::apply(&with_exception_handler, args, env)
+}
+// (guard (var clause ...) body ...) is run by the machine.
+fn guard(exp: &[Expression], env: &Environment) -> ResultExpression {
+    machine::run(&Environment::create_form(exp.to_vec()), env)
+}
+fn condition(exp: &[Expression], env: &Environment) -> Result<Box<Condition>, Error> {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Condition(c) => Ok(c),
+        e => Err(create_error_value!(ErrCode::E1028, e)),
+    }
+}
+fn is_condition(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(&ErrCode) -> bool,
+) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Expression::Boolean(match eval(&exp[1], env)? {
+        Expression::Condition(c) => func(&c.code),
+        _ => false,
+    }))
+}
+fn error_object_message(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let c = condition(exp, env)?;
+    Ok(Environment::create_string(c.message))
+}
+fn error_object_irritants(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let c = condition(exp, env)?;
+    Ok(Environment::create_list(c.irritants))
+}
+fn error_object_code(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let c = condition(exp, env)?;
+    Ok(Expression::Symbol(Symbol::from(c.code.as_str())))
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn error_f() {
+        assert_eq!(do_lisp("(error \"boom\")"), "E1026");
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object-message e))) (error \"boom\" 1 2))"),
+            "\"boom\""
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object-irritants e))) (error \"boom\" 1 'a))"),
+            "(1 a)"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t e)) (error \"boom\" 1 \"a\"))"),
+            "#<error E1026 \"boom\" 1 \"a\">"
+        );
+    }
+    #[test]
+    fn raise() {
+        assert_eq!(do_lisp("(raise 'boom)"), "E1027");
+        assert_eq!(do_lisp("(guard (e ((symbol? e) e)) (raise 'boom))"), "boom");
+        assert_eq!(
+            do_lisp("(guard (e ((string? e) e)) (+ 1 (raise 10)))"),
+            "E1027"
+        );
+        assert_eq!(
+            do_lisp("(guard (e ((string? e) 1) ((number? e) (* e 2))) (+ 1 (raise 10)))"),
+            "20"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object-code e))) (raise (car '())))"),
+            "E1011"
+        );
+    }
+    #[test]
+    fn raise_continuable() {
+        assert_eq!(
+            do_lisp(
+                "(with-exception-handler (lambda (e) 42) (lambda () (+ (raise-continuable 'c) 1)))"
+            ),
+            "43"
+        );
+        assert_eq!(
+            do_lisp(
+                "(with-exception-handler (lambda (e) (* e 2)) (lambda () (+ (raise-continuable 10) (raise-continuable 20))))"
+            ),
+            "60"
+        );
+        assert_eq!(do_lisp("(raise-continuable 10)"), "E1027");
+    }
+    #[test]
+    fn with_exception_handler() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define path '())", &env);
+        do_lisp_env("(define (add s) (set! path (cons s path)))", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(call/cc (lambda (k) (with-exception-handler (lambda (e) (k (error-object-code e))) (lambda () (+ 1 (car 10))))))",
+                &env
+            ),
+            "E1005"
+        );
+        // the handler returns from raise
+        assert_eq!(
+            do_lisp_env(
+                "(with-exception-handler (lambda (e) (add e) 0) (lambda () (raise 'a)))",
+                &env
+            ),
+            "E1027"
+        );
+        assert_eq!(do_lisp_env("path", &env), "(a)");
+        // the outer handler is called in the handler
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (list 'outer e))) (with-exception-handler (lambda (e) (raise (list 'inner e))) (lambda () (raise 'a))))",
+                &env
+            ),
+            "(outer (inner a))"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (list 'outer e))) (with-exception-handler (lambda (e) (add 'handler) 1) (lambda () (raise 'b))))",
+                &env
+            ),
+            "(outer b)"
+        );
+        assert_eq!(do_lisp_env("path", &env), "(handler a)");
+    }
+    #[test]
+    fn guard() {
+        let env = lisp::Environment::new();
+        assert_eq!(do_lisp_env("(guard (e (#t 0)) 1 2 3)", &env), "3");
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (error-object-code e))) (undefined-var))",
+                &env
+            ),
+            "E1008"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e ((error-object? e) (error-object-code e))) (modulo 10 0))",
+                &env
+            ),
+            "E1013"
+        );
+        assert_eq!(
+            do_lisp_env("(guard (e (#t (error-object-irritants e))) (+ 1 'a))", &env),
+            "(\"a\")"
+        );
+        assert_eq!(
+            do_lisp_env("(guard (e ((string? e) 1) (else 2)) (raise 'a))", &env),
+            "2"
+        );
+        // nested guard
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e ((symbol? e) (list 'outer e))) (guard (e ((string? e) 'inner)) (raise 'a)))",
+                &env
+            ),
+            "(outer a)"
+        );
+        // the error in the nested evaluation
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (error-object-code e))) (map (lambda (x) (car x)) '(1 2)))",
+                &env
+            ),
+            "E1005"
+        );
+        // after thunk of dynamic-wind is called
+        do_lisp_env("(define path '())", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (cons e path))) (dynamic-wind (lambda () (set! path (cons 'in path))) (lambda () (raise 'err)) (lambda () (set! path (cons 'out path)))))",
+                &env
+            ),
+            "(err out in)"
+        );
+        // the handlers are restored
+        assert_eq!(do_lisp_env("(raise 'a)", &env), "E1027");
+        do_lisp_env(
+            "(define (f n) (guard (e (#t n)) (if (= n 0) (raise 'a) (f (- n 1)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 100)", &env), "0");
+
+        // the test is satisfied by any value except #f
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e ((assq 'a e) => cdr) ((assq 'b e))) (raise (list (cons 'a 42))))",
+                &env
+            ),
+            "42"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e ((assq 'a e) => cdr) ((assq 'b e))) (raise (list (cons 'b 23))))",
+                &env
+            ),
+            "(b . 23)"
+        );
+        assert_eq!(
+            do_lisp_env("(guard (e ((memv e '(1 2)) 'found)) (raise 2))", &env),
+            "found"
+        );
+        assert_eq!(do_lisp_env("(guard (e (e)) (raise 10))", &env), "10");
+    }
+    #[test]
+    fn guard_raise_again() {
+        // the condition which no clause handles keeps the location of the raise.
+        for (bar, msg) in [
+            ("(car y)", "Not List: 1 (test.scm:5:3)"),
+            ("(raise 'boom)", "Uncaught Exception: boom (test.scm:5:3)"),
+        ] {
+            let program = format!(
+                "(define (foo x)\n  (+ 1 (guard (e ((string? e) 0))\n    (bar x))))\n(define (bar y)\n  {})\n(foo 1)",
+                bar
+            );
+            for bytecode in [true, false] {
+                let env = lisp::Environment::new();
+                env.set_bytecode(bytecode);
+                match lisp::do_core_logic_file(&program, "test.scm", &env) {
+                    Ok(_) => panic!("no error"),
+                    Err(e) => {
+                        assert_eq!(e.get_msg(), msg);
+                        assert_eq!(
+                            e.get_trace(),
+                            "  at bar (test.scm:5:3)\n  at foo (test.scm:2:8)"
+                        );
+                    }
+                }
+            }
+        }
+    }
+    #[test]
+    fn error_object() {
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object? e))) (error \"a\"))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object? e))) (raise 1))"),
+            "#f"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (read-error? e))) (error \"a\"))"),
+            "#f"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (file-error? e))) (load-file \"/no/such/file\"))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(guard (e ((file-error? e) 'fe)) (open-input-file \"/no/such/file\"))"),
+            "fe"
+        );
+        assert_eq!(
+            do_lisp("(guard (e (#t (error-object-message e))) (vector-ref 10 0))"),
+            "\"Not Vector\""
+        );
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp;
+
+    #[test]
+    fn error_f() {
+        assert_eq!(do_lisp("(error)"), "E1007");
+        assert_eq!(do_lisp("(error 'a)"), "E1015");
+    }
+    #[test]
+    fn raise() {
+        assert_eq!(do_lisp("(raise)"), "E1007");
+        assert_eq!(do_lisp("(raise 1 2)"), "E1007");
+    }
+    #[test]
+    fn raise_continuable() {
+        assert_eq!(do_lisp("(raise-continuable)"), "E1007");
+        assert_eq!(do_lisp("(raise-continuable 1 2)"), "E1007");
+    }
+    #[test]
+    fn with_exception_handler() {
+        assert_eq!(do_lisp("(with-exception-handler (lambda (e) 0))"), "E1007");
+        assert_eq!(do_lisp("(with-exception-handler 1 (lambda () 0))"), "E1006");
+        assert_eq!(
+            do_lisp("(with-exception-handler (lambda (e) 0) 1)"),
+            "E1006"
+        );
+    }
+    #[test]
+    fn guard() {
+        assert_eq!(do_lisp("(guard (e (#t 0)))"), "E1007");
+        assert_eq!(do_lisp("(guard e 1)"), "E1005");
+        assert_eq!(do_lisp("(guard (1 (#t 0)) 1)"), "E1004");
+        assert_eq!(do_lisp("(guard () 1)"), "E1007");
+        assert_eq!(do_lisp("(guard (e (#t => 1)) (raise 1))"), "E1006");
+        assert_eq!(do_lisp("(guard (e (else)) (raise 1))"), "E1007");
+    }
+    #[test]
+    fn error_object() {
+        assert_eq!(do_lisp("(error-object?)"), "E1007");
+        assert_eq!(do_lisp("(error-object-message)"), "E1007");
+        assert_eq!(do_lisp("(error-object-message 1)"), "E1028");
+        assert_eq!(do_lisp("(error-object-irritants 1)"), "E1028");
+        assert_eq!(do_lisp("(error-object-code 1)"), "E1028");
+    }
+}
@@ -0,0 +1,832 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+
+use num_bigint::{BigInt, Sign};
+use std::convert::TryFrom;
+
+use crate::create_error;
+use crate::create_error_value;
+use crate::mut_obj;
+
+use crate::buildin::BuildInTable;
+use crate::io::{default_port, io_error, to_port};
+use crate::lisp::eval;
+use crate::lisp::{Environment, Expression, Int, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::number::float_to_string;
+use crate::pair;
+use crate::printer;
+use crate::printer::Style;
+
+const DIRECTIVES: &str = "asdboxfec%&~t*[];{}^\n";
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("format", format);
+}
+// The prefix parameter of the directive, ex. ~10,'0d => [Int(10), Char('0')]
+#[derive(Clone, Copy)]
+enum Param {
+    Empty,
+    Int(Int),
+    Char(char),
+    // ex. ~vd, it's taken from the arguments.
+    Arg,
+    // ex. ~#d, it's the number of the remaining arguments.
+    Count,
+}
+struct Directive {
+    c: char,
+    params: Vec<Param>,
+    colon: bool,
+    at: bool,
+    // the clauses of ~[ ~] and ~{ ~}
+    clauses: Vec<Clause>,
+    // ex. ~{ ~:}, the body is done at least once.
+    once: bool,
+}
+enum Token {
+    Text(String),
+    Directive(Directive),
+}
+// ex. ~[zero~;one~:;many~], the clause after ~:; is the default.
+struct Clause {
+    tokens: Vec<Token>,
+    default: bool,
+}
+impl Clause {
+    fn new(default: bool) -> Self {
+        Clause {
+            tokens: Vec::new(),
+            default,
+        }
+    }
+}
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+impl Parser {
+    fn new(s: &str) -> Self {
+        Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+    // The clauses until the closing directive(~] or ~}), and the closing directive.
+    fn parse(&mut self, close: Option<char>) -> Result<(Vec<Clause>, Option<Directive>), Error> {
+        let mut clauses = vec![Clause::new(false)];
+        let mut text = String::new();
+
+        while let Some(c) = self.next() {
+            if c != '~' {
+                text.push(c);
+                continue;
+            }
+            let mut d = self.directive()?;
+            if d.c == '\n' {
+                // ex. ~@<newline> keeps the newline, ~:<newline> keeps the whitespaces.
+                if d.at {
+                    text.push('\n');
+                }
+                if !d.colon {
+                    while matches!(self.peek(), Some(c) if c.is_whitespace() && c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                continue;
+            }
+            if !text.is_empty() {
+                let t = Token::Text(std::mem::take(&mut text));
+                clauses.last_mut().unwrap().tokens.push(t);
+            }
+            match d.c {
+                ']' | '}' if Some(d.c) == close => return Ok((clauses, Some(d))),
+                ';' if close == Some(']') => clauses.push(Clause::new(d.colon)),
+                ']' | '}' | ';' => return Err(create_error!(ErrCode::E1018)),
+                '[' | '{' => {
+                    let end = if d.c == '[' { ']' } else { '}' };
+                    let (c, closing) = self.parse(Some(end))?;
+                    d.clauses = c;
+                    d.once = closing.is_some_and(|c| c.colon);
+                    clauses.last_mut().unwrap().tokens.push(Token::Directive(d));
+                }
+                _ => clauses.last_mut().unwrap().tokens.push(Token::Directive(d)),
+            }
+        }
+        if close.is_some() {
+            return Err(create_error!(ErrCode::E1018));
+        }
+        if !text.is_empty() {
+            clauses.last_mut().unwrap().tokens.push(Token::Text(text));
+        }
+        Ok((clauses, None))
+    }
+    // ex. ~10,'0:@d
+    fn directive(&mut self) -> Result<Directive, Error> {
+        let mut params = Vec::new();
+        loop {
+            let p = match self.peek() {
+                Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+                    let mut s = String::new();
+                    s.push(c);
+                    self.pos += 1;
+                    while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+                        s.push(c);
+                        self.pos += 1;
+                    }
+                    match s.parse::<Int>() {
+                        Ok(n) => Param::Int(n),
+                        Err(_) => return Err(create_error!(ErrCode::E1018)),
+                    }
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(c) => Param::Char(c),
+                        None => return Err(create_error!(ErrCode::E1018)),
+                    }
+                }
+                Some('v') | Some('V') => {
+                    self.pos += 1;
+                    Param::Arg
+                }
+                Some('#') => {
+                    self.pos += 1;
+                    Param::Count
+                }
+                _ => Param::Empty,
+            };
+            params.push(p);
+            if self.peek() != Some(',') {
+                break;
+            }
+            self.pos += 1;
+        }
+        let (mut colon, mut at) = (false, false);
+        loop {
+            match self.peek() {
+                Some(':') => colon = true,
+                Some('@') => at = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let c = match self.next() {
+            Some(c) if DIRECTIVES.contains(c.to_ascii_lowercase()) => c,
+            _ => return Err(create_error!(ErrCode::E1018)),
+        };
+        Ok(Directive {
+            c,
+            params,
+            colon,
+            at,
+            clauses: Vec::new(),
+            once: false,
+        })
+    }
+}
+// The arguments of the format, ~{ ~} makes them from the list.
+struct Args {
+    v: Vec<Expression>,
+    pos: usize,
+}
+impl Args {
+    fn new(v: Vec<Expression>) -> Self {
+        Args { v, pos: 0 }
+    }
+    fn next(&mut self) -> Result<Expression, Error> {
+        match self.v.get(self.pos) {
+            Some(e) => {
+                self.pos += 1;
+                Ok(e.clone())
+            }
+            None => Err(create_error!(ErrCode::E1007)),
+        }
+    }
+    fn rest(&self) -> usize {
+        self.v.len() - self.pos
+    }
+}
+fn int(p: &[Param], i: usize, default: Int) -> Result<Int, Error> {
+    Ok(opt(p, i)?.unwrap_or(default))
+}
+fn opt(p: &[Param], i: usize) -> Result<Option<Int>, Error> {
+    match p.get(i) {
+        Some(Param::Int(n)) => Ok(Some(*n)),
+        Some(Param::Char(c)) => Err(create_error_value!(ErrCode::E1002, Expression::Char(*c))),
+        _ => Ok(None),
+    }
+}
+fn chr(p: &[Param], i: usize, default: char) -> Result<char, Error> {
+    match p.get(i) {
+        Some(Param::Char(c)) => Ok(*c),
+        Some(Param::Int(n)) => Err(create_error_value!(ErrCode::E1019, Expression::Integer(*n))),
+        _ => Ok(default),
+    }
+}
+// ex. (format #f "~v,'0d" 5 42) => "00042"
+fn params(d: &Directive, args: &mut Args) -> Result<Vec<Param>, Error> {
+    let mut v = Vec::with_capacity(d.params.len());
+    for p in &d.params {
+        v.push(match p {
+            Param::Arg => match args.next()? {
+                Expression::Integer(n) => Param::Int(n),
+                Expression::Char(c) => Param::Char(c),
+                Expression::Boolean(false) => Param::Empty,
+                e => return Err(create_error_value!(ErrCode::E1002, e)),
+            },
+            Param::Count => Param::Int(args.rest() as Int),
+            p => *p,
+        });
+    }
+    Ok(v)
+}
+fn repeat(c: char, n: Int) -> String {
+    std::iter::repeat_n(c, n.max(0) as usize).collect()
+}
+// ex. (1234567, ',', 3) => 1,234,567
+fn group(digits: &str, c: char, interval: usize) -> String {
+    let v: Vec<char> = digits.chars().collect();
+    let mut s = String::new();
+    for (i, d) in v.iter().enumerate() {
+        if i > 0 && (v.len() - i).is_multiple_of(interval) {
+            s.push(c);
+        }
+        s.push(*d);
+    }
+    s
+}
+// ex. ~^ terminates when no argument remains, ~n,m^ when n equals m.
+fn escape(p: &[Param], args: &Args) -> bool {
+    let v: Vec<Int> = p
+        .iter()
+        .filter_map(|p| match p {
+            Param::Int(n) => Some(*n),
+            _ => None,
+        })
+        .collect();
+    match v[..] {
+        [] => args.rest() == 0,
+        [n] => n == 0,
+        [n, m] => n == m,
+        [n, m, k, ..] => n <= m && m <= k,
+    }
+}
+struct Formatter {
+    out: String,
+}
+impl Formatter {
+    // false is returned, when ~^ terminates the format.
+    fn run(&mut self, tokens: &[Token], args: &mut Args) -> Result<bool, Error> {
+        for t in tokens {
+            match t {
+                Token::Text(s) => self.out.push_str(s),
+                Token::Directive(d) => {
+                    if !self.directive(d, args)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+    fn directive(&mut self, d: &Directive, args: &mut Args) -> Result<bool, Error> {
+        let p = params(d, args)?;
+        match d.c.to_ascii_lowercase() {
+            'a' => {
+                let s = printer::to_string(&args.next()?, Style::Display);
+                self.pad(&s, &p, d.at)?;
+            }
+            's' => {
+                let s = printer::to_string(&args.next()?, Style::Write);
+                self.pad(&s, &p, d.at)?;
+            }
+            'd' => self.integer(args.next()?, 10, d, &p)?,
+            'b' => self.integer(args.next()?, 2, d, &p)?,
+            'o' => self.integer(args.next()?, 8, d, &p)?,
+            'x' => self.integer(args.next()?, 16, d, &p)?,
+            'f' => self.fixed(args.next()?, d, &p)?,
+            'e' => self.exponential(args.next()?, d, &p)?,
+            'c' => match args.next()? {
+                Expression::Char(c) if d.at => self.out.push_str(&printer::char_name(c)),
+                Expression::Char(c) if d.colon => self.out.push_str(&printer::char_name(c)[2..]),
+                Expression::Char(c) => self.out.push(c),
+                e => return Err(create_error_value!(ErrCode::E1019, e)),
+            },
+            '%' => self.out.push_str(&repeat('\n', int(&p, 0, 1)?)),
+            '&' => {
+                let n = int(&p, 0, 1)?;
+                if n > 0 && !self.out.is_empty() && !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.out.push_str(&repeat('\n', n - 1));
+            }
+            '~' => self.out.push_str(&repeat('~', int(&p, 0, 1)?)),
+            't' => self.tabulate(d, &p)?,
+            '*' => {
+                let pos = if d.at {
+                    int(&p, 0, 0)?
+                } else if d.colon {
+                    args.pos as Int - int(&p, 0, 1)?
+                } else {
+                    args.pos as Int + int(&p, 0, 1)?
+                };
+                if pos < 0 || pos as usize > args.v.len() {
+                    return Err(create_error!(ErrCode::E1007));
+                }
+                args.pos = pos as usize;
+            }
+            '[' => return self.condition(d, &p, args),
+            '{' => self.iterate(d, &p, args)?,
+            '^' => return Ok(!escape(&p, args)),
+            _ => {}
+        }
+        Ok(true)
+    }
+    // ex. ~10a => "abc       ", ~10@a => "       abc"
+    fn pad(&mut self, s: &str, p: &[Param], left: bool) -> Result<(), Error> {
+        let (mincol, colinc, minpad) = (int(p, 0, 0)?, int(p, 1, 1)?.max(1), int(p, 2, 0)?);
+        let c = chr(p, 3, ' ')?;
+
+        let len = s.chars().count() as Int;
+        let mut n = minpad.max(0);
+        while len + n < mincol {
+            n += colinc;
+        }
+        if left {
+            self.out.push_str(&repeat(c, n));
+            self.out.push_str(s);
+        } else {
+            self.out.push_str(s);
+            self.out.push_str(&repeat(c, n));
+        }
+        Ok(())
+    }
+    // The number is right-justified, ex. ~5d => "   42"
+    fn justify(&mut self, s: &str, width: Option<Int>, c: char) {
+        if let Some(w) = width {
+            self.out.push_str(&repeat(c, w - s.chars().count() as Int));
+        }
+        self.out.push_str(s);
+    }
+    // ex. ~5,'0d => "00042", ~:d => "1,234,567", ~@d => "+42"
+    fn integer(
+        &mut self,
+        v: Expression,
+        radix: u32,
+        d: &Directive,
+        p: &[Param],
+    ) -> Result<(), Error> {
+        let (mincol, c) = (opt(p, 0)?, chr(p, 1, ' ')?);
+        let n = match &v {
+            Expression::Integer(i) => BigInt::from(*i),
+            Expression::BigInt(i) => i.as_ref().clone(),
+            _ => {
+                // the other than integer is displayed, ex. (format #f "~5d" 1.5) => "  1.5"
+                let s = printer::to_string(&v, Style::Display);
+                self.justify(&s, mincol, c);
+                return Ok(());
+            }
+        };
+        let mut digits = n.magnitude().to_str_radix(radix);
+        if d.c == 'X' {
+            digits = digits.to_uppercase();
+        }
+        if d.colon {
+            let interval = int(p, 3, 3)?.max(1) as usize;
+            digits = group(&digits, chr(p, 2, ',')?, interval);
+        }
+        let sign = match n.sign() {
+            Sign::Minus => "-",
+            _ if d.at => "+",
+            _ => "",
+        };
+        self.justify(&format!("{}{}", sign, digits), mincol, c);
+        Ok(())
+    }
+    fn real(&mut self, v: &Expression, width: Option<Int>, c: char) -> Result<Option<f64>, Error> {
+        match v {
+            Expression::Integer(_)
+            | Expression::BigInt(_)
+            | Expression::Float(_)
+            | Expression::Rational(_) => Ok(Some(Expression::to_number(v)?.to_f64())),
+            _ => {
+                self.justify(&printer::to_string(v, Style::Display), width, c);
+                Ok(None)
+            }
+        }
+    }
+    // The sign and the digits are justified, or filled with the overflow char.
+    fn number(
+        &mut self,
+        x: f64,
+        digits: String,
+        at: bool,
+        width: Option<Int>,
+        overflow: Option<char>,
+        c: char,
+    ) {
+        let sign = if x.is_sign_negative() && x != 0.0 {
+            "-"
+        } else if at {
+            "+"
+        } else {
+            ""
+        };
+        let s = format!("{}{}", sign, digits);
+        match (width, overflow) {
+            (Some(w), Some(o)) if s.chars().count() as Int > w => self.out.push_str(&repeat(o, w)),
+            _ => self.justify(&s, width, c),
+        }
+    }
+    // ~w,d,k,overflowchar,padcharF, ex. ~8,3f => "   3.142"
+    fn fixed(&mut self, v: Expression, d: &Directive, p: &[Param]) -> Result<(), Error> {
+        let (w, n, k) = (opt(p, 0)?, opt(p, 1)?, int(p, 2, 0)?);
+        let (o, c) = (
+            p.get(3).map(|_| chr(p, 3, ' ')).transpose()?,
+            chr(p, 4, ' ')?,
+        );
+
+        let x = match self.real(&v, w, c)? {
+            Some(x) if x.is_finite() => x * 10f64.powi(k as i32),
+            Some(x) => {
+                self.justify(&float_to_string(x), w, c);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        let digits = match n {
+            Some(n) => format!("{:.*}", n.max(0) as usize, x.abs()),
+            None => float_to_string(x.abs()),
+        };
+        self.number(x, digits, d.at, w, o, c);
+        Ok(())
+    }
+    // ~w,d,e,k,overflowchar,padcharE, ex. ~,2e => "1.23e+4"
+    fn exponential(&mut self, v: Expression, d: &Directive, p: &[Param]) -> Result<(), Error> {
+        let (w, n, e) = (opt(p, 0)?, opt(p, 1)?, opt(p, 2)?);
+        let (o, c) = (
+            p.get(4).map(|_| chr(p, 4, ' ')).transpose()?,
+            chr(p, 5, ' ')?,
+        );
+
+        let x = match self.real(&v, w, c)? {
+            Some(x) if x.is_finite() => x,
+            Some(x) => {
+                self.justify(&float_to_string(x), w, c);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        let s = match n {
+            Some(n) => format!("{:.*e}", n.max(0) as usize, x.abs()),
+            None => format!("{:e}", x.abs()),
+        };
+        let (m, exp) = s.split_once('e').unwrap_or((&s, "0"));
+        let exp: i32 = exp.parse().unwrap_or(0);
+        let m = if m.contains('.') {
+            m.to_string()
+        } else {
+            format!("{}.0", m)
+        };
+        let digits = format!(
+            "{}e{}{:0width$}",
+            m,
+            if exp < 0 { '-' } else { '+' },
+            exp.abs(),
+            width = e.unwrap_or(1).max(1) as usize
+        );
+        self.number(x, digits, d.at, w, o, c);
+        Ok(())
+    }
+    fn column(&self) -> Int {
+        let line = match self.out.rfind('\n') {
+            Some(i) => &self.out[i + 1..],
+            None => &self.out,
+        };
+        line.chars().count() as Int
+    }
+    // ~colnum,colincT moves to the column, ~colrel,colinc@T moves relatively.
+    fn tabulate(&mut self, d: &Directive, p: &[Param]) -> Result<(), Error> {
+        let (n, colinc) = (int(p, 0, 1)?, int(p, 1, 1)?);
+        let col = self.column();
+        let spaces = if d.at {
+            let target = col + n;
+            if colinc > 0 && target % colinc != 0 {
+                n + colinc - target % colinc
+            } else {
+                n
+            }
+        } else if col < n {
+            n - col
+        } else if colinc > 0 {
+            colinc - (col - n) % colinc
+        } else {
+            0
+        };
+        self.out.push_str(&repeat(' ', spaces));
+        Ok(())
+    }
+    // ex. ~[zero~;one~:;many~], ~:[false~;true~], ~@[~a~]
+    fn condition(&mut self, d: &Directive, p: &[Param], args: &mut Args) -> Result<bool, Error> {
+        let clause = if d.colon {
+            if d.clauses.len() != 2 {
+                return Err(create_error!(ErrCode::E1018));
+            }
+            match args.next()? {
+                Expression::Boolean(false) => d.clauses.first(),
+                _ => d.clauses.get(1),
+            }
+        } else if d.at {
+            if d.clauses.len() != 1 {
+                return Err(create_error!(ErrCode::E1018));
+            }
+            match args.v.get(args.pos) {
+                Some(Expression::Boolean(false)) => {
+                    args.pos += 1;
+                    None
+                }
+                Some(_) => d.clauses.first(),
+                None => return Err(create_error!(ErrCode::E1007)),
+            }
+        } else {
+            let i = match opt(p, 0)? {
+                Some(i) => i,
+                None => match args.next()? {
+                    Expression::Integer(i) => i,
+                    e => return Err(create_error_value!(ErrCode::E1002, e)),
+                },
+            };
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| d.clauses.get(i))
+                .or_else(|| d.clauses.last().filter(|c| c.default))
+        };
+        match clause {
+            Some(c) => self.run(&c.tokens, args),
+            None => Ok(true),
+        }
+    }
+    // ex. ~{~a~^, ~} with (1 2 3) => "1, 2, 3", ~:{~a=~a ~} with ((a 1) (b 2)) => "a=1 b=2 "
+    fn iterate(&mut self, d: &Directive, p: &[Param], args: &mut Args) -> Result<(), Error> {
+        let body = &d.clauses[0].tokens;
+        let max = opt(p, 0)?;
+        let items = if d.at {
+            args.v[args.pos..].to_vec()
+        } else {
+            pair::get_list(args.next()?)?
+        };
+        let mut sub = Args::new(items);
+        let mut i = 0;
+        while (sub.rest() > 0 || (d.once && i == 0)) && max.is_none_or(|m| i < m) {
+            i += 1;
+            if d.colon {
+                // each element is the arguments of the body.
+                let mut step = match sub.rest() {
+                    0 => Args::new(Vec::new()),
+                    _ => Args::new(pair::get_list(sub.next()?)?),
+                };
+                self.run(body, &mut step)?;
+            } else {
+                let pos = sub.pos;
+                if !self.run(body, &mut sub)? {
+                    break;
+                }
+                // the body which takes no argument, is done only once.
+                if sub.pos == pos {
+                    break;
+                }
+            }
+        }
+        if d.at {
+            args.pos += sub.pos;
+        }
+        Ok(())
+    }
+}
+// (format [destination] format-string arg ...)
+// ex. (format #f "~a: ~5,'0d~%" "id" 42), (format #t "~{~a~^ ~}" '(1 2 3))
+fn format(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v = Vec::with_capacity(exp.len());
+    for e in &exp[1..] {
+        v.push(eval(e, env)?);
+    }
+    // ex. (format "~a" 1) is the same as (format #f "~a" 1)
+    let dest = match v[0] {
+        Expression::String(_) => Expression::Boolean(false),
+        _ => v.remove(0),
+    };
+    let fmt = match v.first() {
+        Some(Expression::String(s)) => s.to_string(),
+        Some(e) => return Err(create_error_value!(ErrCode::E1015, e.clone())),
+        None => return Err(create_error_value!(ErrCode::E1007, exp.len())),
+    };
+    let (tokens, _) = Parser::new(&fmt).parse(None)?;
+    let mut args = Args::new(v.split_off(1));
+
+    let mut f = Formatter { out: String::new() };
+    if f.run(&tokens[0].tokens, &mut args)? && args.rest() > 0 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let port = match dest {
+        Expression::Boolean(false) => return Ok(Environment::create_string(f.out)),
+        Expression::Boolean(true) => default_port(env, false),
+        e => e,
+    };
+    let p = to_port(port, false, false)?;
+    mut_obj!(p).write_str(&f.out).map_err(io_error)?;
+    Ok(Expression::Nil())
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn format() {
+        assert_eq!(do_lisp("(format \"~D\" 10)"), "\"10\"");
+        assert_eq!(do_lisp("(format \"~d\" 10)"), "\"10\"");
+        assert_eq!(do_lisp("(format \"~X\" 10)"), "\"A\"");
+        assert_eq!(do_lisp("(format \"~x\" 10)"), "\"a\"");
+        assert_eq!(do_lisp("(format \"~O\" 10)"), "\"12\"");
+        assert_eq!(do_lisp("(format \"~o\" 10)"), "\"12\"");
+        assert_eq!(do_lisp("(format \"~B\" 10)"), "\"1010\"");
+        assert_eq!(do_lisp("(format \"~b\" 10)"), "\"1010\"");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a \"~D\")", &env);
+        do_lisp_env("(define b 100)", &env);
+        assert_eq!(do_lisp_env("(format a b)", &env), "\"100\"");
+    }
+    #[test]
+    fn destination() {
+        assert_eq!(do_lisp("(format #f \"~a-~a\" 1 2)"), "\"1-2\"");
+        assert_eq!(
+            do_lisp("(with-output-to-string (lambda () (format #t \"~a~%\" 10)))"),
+            "\"10\\n\""
+        );
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-output-string))", &env);
+        do_lisp_env("(format p \"x=~a\" 1)", &env);
+        do_lisp_env("(format p \", y=~a\" 2)", &env);
+        assert_eq!(do_lisp_env("(get-output-string p)", &env), "\"x=1, y=2\"");
+    }
+    #[test]
+    fn directives() {
+        assert_eq!(
+            do_lisp("(format #f \"~a + ~s = ~d\" \"x\" \"y\" 3)"),
+            "\"x + \\\"y\\\" = 3\""
+        );
+        assert_eq!(do_lisp("(format #f \"~a\" '(1 \"a\" #\\b))"), "\"(1 a b)\"");
+        assert_eq!(
+            do_lisp("(format #f \"~s\" '(1 \"a\"))"),
+            "\"(1 \\\"a\\\")\""
+        );
+        assert_eq!(do_lisp("(format #f \"~c~@c\" #\\a #\\b)"), "\"a#\\\\b\"");
+        assert_eq!(do_lisp("(format #f \"100~~~%\")"), "\"100~\\n\"");
+        assert_eq!(do_lisp("(format #f \"a~&b~&~&\")"), "\"a\\nb\\n\"");
+        assert_eq!(do_lisp("(format #f \"a~\n   b\")"), "\"ab\"");
+        assert_eq!(do_lisp("(format #f \"~a ~:*~a ~@*~a\" 1)"), "\"1 1 1\"");
+        assert_eq!(do_lisp("(format #f \"~a~*~a\" 1 2 3)"), "\"13\"");
+    }
+    #[test]
+    fn padding() {
+        assert_eq!(do_lisp("(format #f \"~10,'0d\" 42)"), "\"0000000042\"");
+        assert_eq!(do_lisp("(format #f \"~5d|\" 42)"), "\"   42|\"");
+        assert_eq!(do_lisp("(format #f \"~v,'0d\" 5 42)"), "\"00042\"");
+        assert_eq!(do_lisp("(format #f \"~:d\" 1234567)"), "\"1,234,567\"");
+        assert_eq!(do_lisp("(format #f \"~,,'.,4:d\" 1234567)"), "\"123.4567\"");
+        assert_eq!(do_lisp("(format #f \"~@d ~@d\" 5 -5)"), "\"+5 -5\"");
+        assert_eq!(
+            do_lisp("(format #f \"~x ~8,'0b\" -255 5)"),
+            "\"-ff 00000101\""
+        );
+        assert_eq!(
+            do_lisp("(format #f \"~d\" 123456789012345678901234567890)"),
+            "\"123456789012345678901234567890\""
+        );
+        assert_eq!(do_lisp("(format #f \"~5d\" 1.5)"), "\"  1.5\"");
+        assert_eq!(do_lisp("(format #f \"~10a|\" \"abc\")"), "\"abc       |\"");
+        assert_eq!(do_lisp("(format #f \"~10@a|\" \"abc\")"), "\"       abc|\"");
+        assert_eq!(do_lisp("(format #f \"~5,,,'*a|\" 1)"), "\"1****|\"");
+    }
+    #[test]
+    fn float() {
+        assert_eq!(do_lisp("(format #f \"~8,3f\" 3.14159)"), "\"   3.142\"");
+        assert_eq!(do_lisp("(format #f \"~,2f\" 2)"), "\"2.00\"");
+        assert_eq!(do_lisp("(format #f \"~,2f\" 1/3)"), "\"0.33\"");
+        assert_eq!(do_lisp("(format #f \"~f\" 1.5)"), "\"1.5\"");
+        assert_eq!(do_lisp("(format #f \"~,1@f\" 1.25)"), "\"+1.2\"");
+        assert_eq!(do_lisp("(format #f \"~,1f\" -0.06)"), "\"-0.1\"");
+        assert_eq!(do_lisp("(format #f \"~3,1,,'*f\" 123.45)"), "\"***\"");
+        assert_eq!(do_lisp("(format #f \"~e\" 12345.0)"), "\"1.2345e+4\"");
+        assert_eq!(do_lisp("(format #f \"~,2e\" 12345.0)"), "\"1.23e+4\"");
+        assert_eq!(do_lisp("(format #f \"~,1,2e\" 0.001)"), "\"1.0e-03\"");
+        assert_eq!(do_lisp("(format #f \"~e\" 1)"), "\"1.0e+0\"");
+    }
+    #[test]
+    fn tabulate() {
+        assert_eq!(do_lisp("(format #f \"ab~8tc\")"), "\"ab      c\"");
+        assert_eq!(do_lisp("(format #f \"abcdef~4,4tc\")"), "\"abcdef  c\"");
+        assert_eq!(do_lisp("(format #f \"a~%b~3tc\")"), "\"a\\nb  c\"");
+        assert_eq!(do_lisp("(format #f \"ab~3,4@tc\")"), "\"ab      c\"");
+    }
+    #[test]
+    fn iteration() {
+        assert_eq!(
+            do_lisp("(format #f \"~{~a~^, ~}\" '(1 2 3))"),
+            "\"1, 2, 3\""
+        );
+        assert_eq!(do_lisp("(format #f \"~{~a~^, ~}\" '())"), "\"\"");
+        assert_eq!(
+            do_lisp("(format #f \"~:{~a=~a ~}\" '((a 1) (b 2)))"),
+            "\"a=1 b=2 \""
+        );
+        assert_eq!(do_lisp("(format #f \"~@{~a~^ ~}\" 1 2 3)"), "\"1 2 3\"");
+        assert_eq!(do_lisp("(format #f \"~2{~a~}\" '(1 2 3))"), "\"12\"");
+        assert_eq!(do_lisp("(format #f \"~{x~}\" '(1 2))"), "\"x\"");
+        assert_eq!(do_lisp("(format #f \"~{x~:}\" '())"), "\"x\"");
+        assert_eq!(
+            do_lisp("(format #f \"~{~a~^ ~{~a~}~}\" '(a (1 2) b (3)))"),
+            "\"a 12b 3\""
+        );
+    }
+    #[test]
+    fn condition() {
+        assert_eq!(
+            do_lisp("(format #f \"~[zero~;one~:;many~]\" 0)"),
+            "\"zero\""
+        );
+        assert_eq!(do_lisp("(format #f \"~[zero~;one~:;many~]\" 1)"), "\"one\"");
+        assert_eq!(
+            do_lisp("(format #f \"~[zero~;one~:;many~]\" 5)"),
+            "\"many\""
+        );
+        assert_eq!(do_lisp("(format #f \"~[zero~;one~]\" 5)"), "\"\"");
+        assert_eq!(do_lisp("(format #f \"~1[a~;b~]\")"), "\"b\"");
+        assert_eq!(do_lisp("(format #f \"~:[no~;yes~]\" #t)"), "\"yes\"");
+        assert_eq!(do_lisp("(format #f \"~:[no~;yes~]\" #f)"), "\"no\"");
+        assert_eq!(do_lisp("(format #f \"~@[x=~a~]\" 10)"), "\"x=10\"");
+        assert_eq!(do_lisp("(format #f \"~@[x=~a~]~a\" #f 1)"), "\"1\"");
+        assert_eq!(
+            do_lisp("(format #f \"~d item~:[s~;~]\" 1 #t)"),
+            "\"1 item\""
+        );
+        assert_eq!(do_lisp("(format #f \"~a~^ ~a\" 1)"), "\"1\"");
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp;
+
+    #[test]
+    fn format() {
+        assert_eq!(do_lisp("(format)"), "E1007");
+        assert_eq!(do_lisp("(format \"~B\")"), "E1007");
+        assert_eq!(do_lisp("(format \"~B\" 10 12)"), "E1007");
+        assert_eq!(do_lisp("(format #f)"), "E1007");
+        assert_eq!(do_lisp("(format 10 12)"), "E1015");
+        assert_eq!(do_lisp("(format #f 10)"), "E1015");
+        assert_eq!(do_lisp("(format 10 \"~a\" 1)"), "E1032");
+        assert_eq!(
+            do_lisp("(format (open-input-string \"\") \"~a\" 1)"),
+            "E1032"
+        );
+        assert_eq!(do_lisp("(format \"~q\" 10)"), "E1018");
+        assert_eq!(do_lisp("(format \"~\")"), "E1018");
+        assert_eq!(do_lisp("(format \"~{~a\" '(1))"), "E1018");
+        assert_eq!(do_lisp("(format \"~a~]\" 1)"), "E1018");
+        assert_eq!(do_lisp("(format \"~:[a~]\" #t)"), "E1018");
+        assert_eq!(do_lisp("(format \"~{~a~}\" 10)"), "E1005");
+        assert_eq!(do_lisp("(format \"~:{~a~}\" '(1))"), "E1005");
+        assert_eq!(do_lisp("(format \"~[a~;b~]\" #t)"), "E1002");
+        assert_eq!(do_lisp("(format \"~vd\" #t 1)"), "E1002");
+        assert_eq!(do_lisp("(format \"~'ad\" 1)"), "E1002");
+        assert_eq!(do_lisp("(format \"~5,1d\" 1)"), "E1019");
+        assert_eq!(do_lisp("(format \"~c\" 1)"), "E1019");
+        assert_eq!(do_lisp("(format \"~a~2:*\" 1)"), "E1007");
+        assert_eq!(do_lisp("(format \"~a\" a)"), "E1008");
+    }
+}
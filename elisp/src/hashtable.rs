@@ -4,6 +4,8 @@
 
    hidekuno@gmail.com
 */
+// The keys of the tree map are the atoms(see tree_map_key), they are never changed.
+#![allow(clippy::mutable_key_type)]
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 
@@ -17,52 +19,155 @@ use crate::lisp::eval;
 use crate::lisp::{Environment, Expression, ResultExpression};
 use crate::lisp::{ErrCode, Error};
 use crate::lisp::{HashTableRc, TreeMapRc};
+use crate::machine::{self, Iterate, IterateBox, Start, Step};
+use crate::pair;
+use crate::srfi1::to_proc;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::hash::{Hash, Hasher};
+use std::ops::Bound;
+use std::vec::IntoIter;
 
+// The comparator which is chosen by make-hash-table, ex. (make-hash-table eqv?)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Comparator {
+    Eq,
+    Eqv,
+    Equal,
+    String,
+}
+impl Comparator {
+    fn from(exp: &Expression) -> Option<Comparator> {
+        let name = match exp {
+            Expression::BuildInFunction(s, _) => *s,
+            Expression::Symbol(s) => s.as_str(),
+            _ => return None,
+        };
+        match name {
+            "eq?" => Some(Comparator::Eq),
+            "eqv?" => Some(Comparator::Eqv),
+            "equal?" => Some(Comparator::Equal),
+            "string=?" => Some(Comparator::String),
+            _ => None,
+        }
+    }
+}
+// The key is compared by the comparator of the table.
+#[derive(Clone)]
+pub struct HashKey {
+    key: Expression,
+    test: Comparator,
+}
+impl PartialEq for HashKey {
+    fn eq(&self, other: &Self) -> bool {
+        match self.test {
+            Comparator::Eq => self.key == other.key,
+            Comparator::Eqv | Comparator::String => self.key.eqv(&other.key),
+            Comparator::Equal => self.key.equal(&other.key),
+        }
+    }
+}
+impl Eq for HashKey {}
+impl Hash for HashKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+#[derive(Clone)]
+pub struct HashTable {
+    test: Comparator,
+    map: HashMap<HashKey, Expression>,
+}
+impl HashTable {
+    pub fn new(test: Comparator) -> Self {
+        HashTable {
+            test,
+            map: HashMap::new(),
+        }
+    }
+    fn key(&self, key: &Expression) -> Result<HashKey, Error> {
+        if self.test == Comparator::String && !Expression::is_string(key) {
+            return Err(create_error_value!(ErrCode::E1015, key));
+        }
+        Ok(HashKey {
+            key: key.clone(),
+            test: self.test,
+        })
+    }
+    pub fn get(&self, key: &Expression) -> Result<Option<Expression>, Error> {
+        Ok(self.map.get(&self.key(key)?).cloned())
+    }
+    pub fn insert(&mut self, key: &Expression, exp: Expression) -> Result<(), Error> {
+        let key = self.key(key)?;
+        self.map.insert(key, exp);
+        Ok(())
+    }
+    pub fn remove(&mut self, key: &Expression) -> Result<bool, Error> {
+        Ok(self.map.remove(&self.key(key)?).is_some())
+    }
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Expression, &Expression)> {
+        self.map.iter().map(|(k, v)| (&k.key, v))
+    }
+    // The entries are copied, so that the table can be changed in the procedure.
+    fn entries(&self) -> Vec<(Expression, Expression)> {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
 trait Map<T> {
     fn create_map() -> Expression;
-    fn get(&self, key: &str) -> ResultExpression;
-    fn insert(&mut self, key: String, exp: Expression);
-    fn remove(&mut self, key: String) -> bool;
-    fn has_key(&self, key: String) -> bool;
+    fn get(&self, key: &Expression) -> ResultExpression;
+    fn insert(&mut self, key: &Expression, exp: Expression) -> Result<(), Error>;
+    fn remove(&mut self, key: &Expression) -> Result<bool, Error>;
+    fn has_key(&self, key: &Expression) -> Result<bool, Error>;
     fn clear(&mut self);
-    fn get_map(exp: &Expression, env: &Environment) -> Result<T, Error>;
+    fn to_map(exp: Expression) -> Result<T, Error>;
+    fn get_map(exp: &Expression, env: &Environment) -> Result<T, Error> {
+        Self::to_map(eval(exp, env)?)
+    }
     fn keys(&self) -> Expression;
     fn values(&self) -> Expression;
 }
 impl Map<HashTableRc> for HashTableRc {
     fn create_map() -> Expression {
-        Environment::create_hash_table(HashMap::new())
+        Environment::create_hash_table(HashTable::new(Comparator::Equal))
     }
-    fn insert(&mut self, key: String, exp: Expression) {
+    fn insert(&mut self, key: &Expression, exp: Expression) -> Result<(), Error> {
         let mut m = mut_obj!(self);
-        m.insert(key, exp);
+        m.insert(key, exp)
     }
-    fn get(&self, key: &str) -> ResultExpression {
+    fn get(&self, key: &Expression) -> ResultExpression {
         let m = &*reference_obj!(self);
 
-        if let Some(exp) = m.get(key) {
-            Ok(exp.clone())
+        if let Some(exp) = m.get(key)? {
+            Ok(exp)
         } else {
             Err(create_error!(ErrCode::E1021))
         }
     }
-    fn has_key(&self, key: String) -> bool {
+    fn has_key(&self, key: &Expression) -> Result<bool, Error> {
         let m = &*reference_obj!(self);
-        m.get(&key).is_some()
+        Ok(m.get(key)?.is_some())
     }
-    fn remove(&mut self, key: String) -> bool {
+    fn remove(&mut self, key: &Expression) -> Result<bool, Error> {
         let mut m = mut_obj!(self);
-        m.remove(&key).is_some()
+        m.remove(key)
     }
     fn clear(&mut self) {
         let mut m = mut_obj!(self);
         m.clear();
     }
-    fn get_map(exp: &Expression, env: &Environment) -> Result<HashTableRc, Error> {
-        match eval(exp, env)? {
+    fn to_map(exp: Expression) -> Result<HashTableRc, Error> {
+        match exp {
             Expression::HashTable(v) => Ok(v),
             e => Err(create_error_value!(ErrCode::E1023, e)),
         }
@@ -70,51 +175,67 @@ impl Map<HashTableRc> for HashTableRc {
     fn keys(&self) -> Expression {
         let m = &*reference_obj!(self);
         let mut v = Vec::new();
-        for key in m.keys() {
-            v.push(Expression::Symbol(key.to_string()));
+        for (key, _) in m.iter() {
+            v.push(key.clone());
         }
         Environment::create_list(v)
     }
     fn values(&self) -> Expression {
         let m = &*reference_obj!(self);
         let mut v = Vec::new();
-        for value in m.values() {
+        for (_, value) in m.iter() {
             v.push(value.clone());
         }
         Environment::create_list(v)
     }
 }
+// The key of the tree map is ordered by Expression::cmp, ex. 10, 1.5, #\a, "abc", abc
+fn tree_map_key(key: &Expression) -> Result<Expression, Error> {
+    match key {
+        Expression::Float(v) if v.is_nan() => Err(create_error_value!(ErrCode::E1031, key)),
+        Expression::Complex(_, _) => Err(create_error_value!(ErrCode::E1031, key)),
+        Expression::Integer(_)
+        | Expression::BigInt(_)
+        | Expression::Float(_)
+        | Expression::Rational(_)
+        | Expression::Char(_)
+        | Expression::String(_)
+        | Expression::Symbol(_) => Ok(key.clone()),
+        e => Err(create_error_value!(ErrCode::E1009, e)),
+    }
+}
 impl Map<TreeMapRc> for TreeMapRc {
     fn create_map() -> Expression {
         Environment::create_tree_map(BTreeMap::new())
     }
-    fn insert(&mut self, key: String, exp: Expression) {
+    fn insert(&mut self, key: &Expression, exp: Expression) -> Result<(), Error> {
         let mut v = mut_obj!(self);
-        v.insert(key, exp);
+        v.insert(tree_map_key(key)?, exp);
+        Ok(())
     }
-    fn get(&self, key: &str) -> ResultExpression {
+    fn get(&self, key: &Expression) -> ResultExpression {
         let v = &*reference_obj!(self);
 
-        if let Some(exp) = v.get(key) {
+        if let Some(exp) = v.get(&tree_map_key(key)?) {
             Ok(exp.clone())
         } else {
             Err(create_error!(ErrCode::E1021))
         }
     }
-    fn has_key(&self, key: String) -> bool {
+    fn has_key(&self, key: &Expression) -> Result<bool, Error> {
         let v = &*reference_obj!(self);
-        v.get(&key).is_some()
+        Ok(v.get(&tree_map_key(key)?).is_some())
     }
-    fn remove(&mut self, key: String) -> bool {
+    fn remove(&mut self, key: &Expression) -> Result<bool, Error> {
         let mut v = mut_obj!(self);
-        v.remove(&key).is_some()
+        Ok(v.remove(&tree_map_key(key)?).is_some())
     }
     fn clear(&mut self) {
         let mut v = mut_obj!(self);
         v.clear();
     }
-    fn get_map(exp: &Expression, env: &Environment) -> Result<TreeMapRc, Error> {
-        match eval(exp, env)? {
+    fn to_map(exp: Expression) -> Result<TreeMapRc, Error> {
+        match exp {
             Expression::TreeMap(v) => Ok(v),
             e => Err(create_error_value!(ErrCode::E1024, e)),
         }
@@ -123,7 +244,7 @@ impl Map<TreeMapRc> for TreeMapRc {
         let m = &*reference_obj!(self);
         let mut v = Vec::new();
         for key in m.keys() {
-            v.push(Expression::Symbol(key.to_string()));
+            v.push(key.clone());
         }
         Environment::create_list(v)
     }
@@ -140,17 +261,28 @@ pub fn create_function<T>(b: &mut T)
 where
     T: BuildInTable + ?Sized,
 {
-    b.regist("make-hash-table", make_map::<HashTableRc>);
+    b.regist("make-hash-table", make_hash_table);
     b.regist("hash-table-put!", map_put::<HashTableRc>);
+    b.regist("hash-table-set!", map_put::<HashTableRc>);
     b.regist("hash-table-get", map_get::<HashTableRc>);
+    b.regist("hash-table-ref", machine::native);
+    b.regist("hash-table-ref/default", hash_table_ref_default);
     b.regist("hash-table-exists?", map_exists::<HashTableRc>);
     b.regist("hash-table-contains?", map_exists::<HashTableRc>);
     b.regist("hash-table-size", hash_table_size);
+    b.regist("hash-table-count", machine::native);
     b.regist("hash-table-delete!", map_delete::<HashTableRc>);
     b.regist("hash-table-clear!", map_clear::<HashTableRc>);
     b.regist("hash-table-keys", map_keys::<HashTableRc>);
     b.regist("hash-table-values", map_values::<HashTableRc>);
-    b.regist("alist->hash-table", map_alist::<HashTableRc>);
+    b.regist("hash-table-update!", machine::native);
+    b.regist("hash-table-update!/default", machine::native);
+    b.regist("hash-table-walk", machine::native);
+    b.regist("hash-table-fold", machine::native);
+    b.regist("hash-table-copy", hash_table_copy);
+    b.regist("hash-table->alist", hash_table_alist);
+    b.regist("hash-table-merge!", hash_table_merge);
+    b.regist("alist->hash-table", alist_hash_table);
 
     b.regist("make-tree-map", make_map::<TreeMapRc>);
     b.regist("tree-map-put!", map_put::<TreeMapRc>);
@@ -161,6 +293,23 @@ where
     b.regist("tree-map-keys", map_keys::<TreeMapRc>);
     b.regist("tree-map-values", map_values::<TreeMapRc>);
     b.regist("alist->tree-map", map_alist::<TreeMapRc>);
+    b.regist("tree-map-min", |exp, env| tree_map_edge(exp, env, false));
+    b.regist("tree-map-max", |exp, env| tree_map_edge(exp, env, true));
+    b.regist("tree-map-floor", |exp, env| {
+        tree_map_find(exp, env, true, true)
+    });
+    b.regist("tree-map-ceiling", |exp, env| {
+        tree_map_find(exp, env, true, false)
+    });
+    b.regist("tree-map-predecessor", |exp, env| {
+        tree_map_find(exp, env, false, true)
+    });
+    b.regist("tree-map-successor", |exp, env| {
+        tree_map_find(exp, env, false, false)
+    });
+    b.regist("tree-map-range", tree_map_range);
+    b.regist("tree-map-fold", machine::native);
+    b.regist("tree-map-pop-min!", tree_map_pop_min);
 }
 
 fn make_map<T>(exp: &[Expression], _env: &Environment) -> ResultExpression
@@ -181,12 +330,9 @@ where
     }
     let mut map = T::get_map(&exp[1], env)?;
 
-    let key = match eval(&exp[2], env)? {
-        Expression::Symbol(v) => v,
-        e => return Err(create_error_value!(ErrCode::E1004, e)),
-    };
+    let key = eval(&exp[2], env)?;
     let value = eval(&exp[3], env)?;
-    map.insert(key, value);
+    map.insert(&key, value)?;
 
     Ok(Expression::Nil())
 }
@@ -199,10 +345,7 @@ where
     }
     let map = T::get_map(&exp[1], env)?;
 
-    let key = match eval(&exp[2], env)? {
-        Expression::Symbol(v) => v,
-        e => return Err(create_error_value!(ErrCode::E1004, e)),
-    };
+    let key = eval(&exp[2], env)?;
     map.get(&key)
 }
 fn map_delete<T>(exp: &[Expression], env: &Environment) -> ResultExpression
@@ -214,11 +357,8 @@ where
     }
     let mut map = T::get_map(&exp[1], env)?;
 
-    let key = match eval(&exp[2], env)? {
-        Expression::Symbol(v) => v,
-        e => return Err(create_error_value!(ErrCode::E1004, e)),
-    };
-    Ok(Expression::Boolean(map.remove(key)))
+    let key = eval(&exp[2], env)?;
+    Ok(Expression::Boolean(map.remove(&key)?))
 }
 fn map_clear<T>(exp: &[Expression], env: &Environment) -> ResultExpression
 where
@@ -241,11 +381,8 @@ where
     }
     let map = T::get_map(&exp[1], env)?;
 
-    let key = match eval(&exp[2], env)? {
-        Expression::Symbol(v) => v,
-        e => return Err(create_error_value!(ErrCode::E1004, e)),
-    };
-    Ok(Expression::Boolean(map.has_key(key)))
+    let key = eval(&exp[2], env)?;
+    Ok(Expression::Boolean(map.has_key(&key)?))
 }
 
 fn map_keys<T>(exp: &[Expression], env: &Environment) -> ResultExpression
@@ -275,37 +412,294 @@ where
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-    let l = &*reference_obj!(l);
+    let m = T::create_map();
+    let mut map = T::get_map(&m, env)?;
+    for (k, v) in alist(eval(&exp[1], env)?)? {
+        map.insert(&k, v)?;
+    }
+    Ok(m)
+}
+// ex. ((a 10) (b . 20)) => [(a, 10), (b, 20)]
+fn alist(exp: Expression) -> Result<Vec<(Expression, Expression)>, Error> {
+    let l = pair::get_list(exp)?;
     if l.is_empty() {
         return Err(create_error_value!(ErrCode::E1021, l.len()));
     }
-
-    let m = T::create_map();
-    let mut map = T::get_map(&m, env)?;
-
+    let mut v = Vec::new();
     for e in l {
-        match e {
+        match pair::to_vec(&e) {
             // Proprietary implementation
-            Expression::List(l) => {
-                let l = &*reference_obj!(l);
+            Some(l) => {
                 if l.len() != 2 {
                     return Err(create_error_value!(ErrCode::E1021, l.len()));
                 }
-                match &l[0] {
-                    Expression::Symbol(s) => {
-                        map.insert(s.to_string(), l[1].clone());
-                    }
-                    e => return Err(create_error_value!(ErrCode::E1004, e)),
+                v.push((l[0].clone(), l[1].clone()));
+            }
+            None if Expression::is_pair(&e) => v.push((pair::car(&e), pair::cdr(&e))),
+            None => return Err(create_error_value!(ErrCode::E1005, e)),
+        }
+    }
+    Ok(v)
+}
+fn comparator(exp: &Expression, env: &Environment) -> Result<Comparator, Error> {
+    let v = eval(exp, env)?;
+    match Comparator::from(&v) {
+        Some(c) => Ok(c),
+        None => Err(create_error_value!(ErrCode::E1006, v)),
+    }
+}
+// ex. (make-hash-table), (make-hash-table string=?)
+fn make_hash_table(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let test = match exp.get(1) {
+        Some(e) => comparator(e, env)?,
+        None => Comparator::Equal,
+    };
+    Ok(Environment::create_hash_table(HashTable::new(test)))
+}
+fn alist_hash_table(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = alist(eval(&exp[1], env)?)?;
+    let test = match exp.get(2) {
+        Some(e) => comparator(e, env)?,
+        None => Comparator::Equal,
+    };
+    let mut h = HashTable::new(test);
+    for (k, v) in l {
+        h.insert(&k, v)?;
+    }
+    Ok(Environment::create_hash_table(h))
+}
+// The builtin functions which apply the procedures, run by the machine.
+pub(crate) fn start(name: &str) -> Option<Start> {
+    let f: Start = match name {
+        "hash-table-ref" => hash_table_ref,
+        "hash-table-update!" => |exp| hash_table_update(exp, false),
+        "hash-table-update!/default" => |exp| hash_table_update(exp, true),
+        "hash-table-walk" => |exp| hash_table_entries(exp, EntryKind::Walk),
+        "hash-table-fold" => |exp| hash_table_entries(exp, EntryKind::Fold),
+        "hash-table-count" => hash_table_count,
+        "tree-map-fold" => tree_map_fold,
+        _ => return None,
+    };
+    Some(f)
+}
+// The value when the key isn't found.
+#[derive(Clone)]
+enum Missing {
+    Error,
+    Thunk(Expression),
+    Value(Expression),
+}
+// ex. (hash-table-update! h 'a (lambda (x) (+ x 1)) (lambda () 0))
+//     (hash-table-ref h 'a (lambda () 0)) has no procedure.
+#[derive(Clone)]
+struct Update {
+    h: HashTableRc,
+    key: Expression,
+    proc: Option<Expression>,
+    missing: Missing,
+    updated: bool,
+}
+impl Update {
+    fn update(&mut self, v: Expression) -> Step {
+        match &self.proc {
+            Some(proc) => {
+                self.updated = true;
+                Step::Apply(proc.clone(), vec![v])
+            }
+            None => Step::Return(v),
+        }
+    }
+}
+impl Iterate for Update {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        match v {
+            Some(v) if self.updated => {
+                mut_obj!(self.h).insert(&self.key, v)?;
+                Ok(Step::Return(Expression::Nil()))
+            }
+            Some(v) => Ok(self.update(v)),
+            None => {
+                let v = reference_obj!(self.h).get(&self.key)?;
+                match (v, &self.missing) {
+                    (Some(v), _) => Ok(self.update(v)),
+                    (None, Missing::Thunk(f)) => Ok(Step::Apply(f.clone(), Vec::new())),
+                    (None, Missing::Value(v)) => Ok(self.update(v.clone())),
+                    (None, Missing::Error) => Err(create_error_value!(ErrCode::E1021, self.key)),
                 }
             }
-            e => return Err(create_error_value!(ErrCode::E1005, e)),
         }
     }
-    Ok(m)
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+#[derive(Clone, Copy)]
+enum EntryKind {
+    Walk,
+    Fold,
+    Count,
+}
+// The procedure is applied to the entries, ex. (hash-table-fold h (lambda (k v acc) (+ v acc)) 0)
+#[derive(Clone)]
+struct Entries {
+    kind: EntryKind,
+    func: Expression,
+    entries: IntoIter<(Expression, Expression)>,
+    acc: Expression,
+}
+impl Iterate for Entries {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        match (self.kind, v) {
+            (EntryKind::Fold, Some(v)) => self.acc = v,
+            (EntryKind::Count, Some(Expression::Boolean(false))) => {}
+            (EntryKind::Count, Some(_)) => {
+                if let Expression::Integer(n) = &mut self.acc {
+                    *n += 1;
+                }
+            }
+            _ => {}
+        }
+        match self.entries.next() {
+            Some((k, v)) => {
+                let mut args = vec![k, v];
+                if let EntryKind::Fold = self.kind {
+                    args.push(std::mem::replace(&mut self.acc, Expression::Nil()));
+                }
+                Ok(Step::Apply(self.func.clone(), args))
+            }
+            None => Ok(Step::Return(self.acc.clone())),
+        }
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+fn hash_table_ref(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() != 3 && exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let missing = match exp.get(3) {
+        Some(e) => Missing::Thunk(to_proc(e)?),
+        None => Missing::Error,
+    };
+    Ok(Box::new(Update {
+        h: HashTableRc::to_map(exp[1].clone())?,
+        key: exp[2].clone(),
+        proc: None,
+        missing,
+        updated: false,
+    }))
+}
+// ex. (hash-table-update!/default h 'a (lambda (x) (+ x 1)) 0)
+fn hash_table_update(exp: &[Expression], default: bool) -> Result<IterateBox, Error> {
+    if exp.len() != 5 && (default || exp.len() != 4) {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h = HashTableRc::to_map(exp[1].clone())?;
+    let proc = to_proc(&exp[3])?;
+    let missing = match exp.get(4) {
+        Some(e) if default => Missing::Value(e.clone()),
+        Some(e) => Missing::Thunk(to_proc(e)?),
+        None => Missing::Error,
+    };
+    Ok(Box::new(Update {
+        h,
+        key: exp[2].clone(),
+        proc: Some(proc),
+        missing,
+        updated: false,
+    }))
+}
+// ex. (hash-table-walk h (lambda (k v) (display k)))
+fn hash_table_entries(exp: &[Expression], kind: EntryKind) -> Result<IterateBox, Error> {
+    let n = if let EntryKind::Fold = kind { 4 } else { 3 };
+    if exp.len() != n {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h = HashTableRc::to_map(exp[1].clone())?;
+    let func = to_proc(&exp[2])?;
+    let entries = reference_obj!(h).entries();
+    Ok(Box::new(Entries {
+        kind,
+        func,
+        entries: entries.into_iter(),
+        acc: exp.get(3).cloned().unwrap_or_else(Expression::Nil),
+    }))
+}
+// ex. (hash-table-count h), (hash-table-count (lambda (k v) (odd? v)) h)
+fn hash_table_count(exp: &[Expression]) -> Result<IterateBox, Error> {
+    let (func, h) = match exp.len() {
+        2 => (None, HashTableRc::to_map(exp[1].clone())?),
+        3 => (Some(&exp[1]), HashTableRc::to_map(exp[2].clone())?),
+        _ => return Err(create_error_value!(ErrCode::E1007, exp.len())),
+    };
+    let h = reference_obj!(h);
+    let (func, entries, n) = match func {
+        Some(f) => (to_proc(f)?, h.entries(), 0),
+        None => (Expression::Nil(), Vec::new(), h.len()),
+    };
+    Ok(Box::new(Entries {
+        kind: EntryKind::Count,
+        func,
+        entries: entries.into_iter(),
+        acc: Expression::Integer(n.try_into().unwrap()),
+    }))
+}
+fn hash_table_ref_default(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h = HashTableRc::get_map(&exp[1], env)?;
+    let key = eval(&exp[2], env)?;
+    let v = reference_obj!(h).get(&key)?;
+    match v {
+        Some(v) => Ok(v),
+        None => eval(&exp[3], env),
+    }
+}
+fn hash_table_copy(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h = HashTableRc::get_map(&exp[1], env)?;
+    // The mutable flag of SRFI-69 is accepted, all tables are mutable.
+    if let Some(e) = exp.get(2) {
+        eval(e, env)?;
+    }
+    let h = reference_obj!(h).clone();
+    Ok(Environment::create_hash_table(h))
+}
+fn hash_table_alist(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h = HashTableRc::get_map(&exp[1], env)?;
+    let v = reference_obj!(h)
+        .entries()
+        .into_iter()
+        .map(|(k, v)| Environment::create_pair(k, v))
+        .collect();
+    Ok(Environment::create_list(v))
+}
+// The entries of the second table are added to the first table.
+fn hash_table_merge(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let h1 = HashTableRc::get_map(&exp[1], env)?;
+    let h2 = HashTableRc::get_map(&exp[2], env)?;
+    let entries = reference_obj!(h2).entries();
+    let mut h = mut_obj!(h1);
+    for (k, v) in entries {
+        h.insert(&k, v)?;
+    }
+    Ok(Expression::HashTable(h1.clone()))
 }
 fn hash_table_size(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
@@ -319,6 +713,91 @@ fn hash_table_size(exp: &[Expression], env: &Environment) -> ResultExpression {
 
     Ok(Expression::Integer(hash.len().try_into().unwrap()))
 }
+// ex. (k . v)
+fn entry(k: &Expression, v: &Expression) -> Expression {
+    Environment::create_pair(k.clone(), v.clone())
+}
+// The entry of the minimum(or maximum) key, #f if it's empty.
+fn tree_map_edge(exp: &[Expression], env: &Environment, max: bool) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let m = TreeMapRc::get_map(&exp[1], env)?;
+    let m = &*reference_obj!(m);
+    let e = if max {
+        m.iter().next_back()
+    } else {
+        m.iter().next()
+    };
+    Ok(e.map_or(Expression::Boolean(false), |(k, v)| entry(k, v)))
+}
+// ex. (tree-map-floor m 10) => the entry of the greatest key <= 10
+//     (tree-map-successor m 10) => the entry of the least key > 10
+fn tree_map_find(
+    exp: &[Expression],
+    env: &Environment,
+    inclusive: bool,
+    below: bool,
+) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let m = TreeMapRc::get_map(&exp[1], env)?;
+    let key = tree_map_key(&eval(&exp[2], env)?)?;
+    let bound = if inclusive {
+        Bound::Included(&key)
+    } else {
+        Bound::Excluded(&key)
+    };
+    let m = &*reference_obj!(m);
+    let e = if below {
+        m.range((Bound::Unbounded, bound)).next_back()
+    } else {
+        m.range((bound, Bound::Unbounded)).next()
+    };
+    Ok(e.map_or(Expression::Boolean(false), |(k, v)| entry(k, v)))
+}
+// The entries of from <= key < to, ex. (tree-map-range m 10 20) => ((10 . a) (15 . b))
+fn tree_map_range(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let m = TreeMapRc::get_map(&exp[1], env)?;
+    let from = tree_map_key(&eval(&exp[2], env)?)?;
+    let to = tree_map_key(&eval(&exp[3], env)?)?;
+    if from >= to {
+        return Ok(Environment::create_list(Vec::new()));
+    }
+    let m = &*reference_obj!(m);
+    let v = m.range(from..to).map(|(k, v)| entry(k, v)).collect();
+    Ok(Environment::create_list(v))
+}
+// The keys are visited in ascending order, ex. (tree-map-fold m (lambda (k v acc) (+ v acc)) 0)
+fn tree_map_fold(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let m = TreeMapRc::to_map(exp[1].clone())?;
+    let func = to_proc(&exp[2])?;
+    let entries: Vec<(Expression, Expression)> = reference_obj!(m)
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    Ok(Box::new(Entries {
+        kind: EntryKind::Fold,
+        func,
+        entries: entries.into_iter(),
+        acc: exp[3].clone(),
+    }))
+}
+fn tree_map_pop_min(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let m = TreeMapRc::get_map(&exp[1], env)?;
+    let e = mut_obj!(m).pop_first();
+    Ok(e.map_or(Expression::Boolean(false), |(k, v)| entry(&k, &v)))
+}
 #[cfg(test)]
 mod tests {
     use crate::lisp;
@@ -419,6 +898,222 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hash_table_key() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-hash-table))", &env);
+        do_lisp_env("(hash-table-put! a 1 'int)", &env);
+        do_lisp_env("(hash-table-put! a \"1\" 'str)", &env);
+        do_lisp_env("(hash-table-put! a #\\1 'char)", &env);
+        do_lisp_env("(hash-table-put! a '(1 2) 'lst)", &env);
+        do_lisp_env("(hash-table-put! a 1.5 'float)", &env);
+        assert_eq!(do_lisp_env("(hash-table-get a 1)", &env), "int");
+        assert_eq!(do_lisp_env("(hash-table-get a \"1\")", &env), "str");
+        assert_eq!(do_lisp_env("(hash-table-get a #\\1)", &env), "char");
+        assert_eq!(do_lisp_env("(hash-table-get a (list 1 2))", &env), "lst");
+        assert_eq!(do_lisp_env("(hash-table-get a (/ 3 2.0))", &env), "float");
+        assert_eq!(do_lisp_env("(hash-table-size a)", &env), "5");
+    }
+    #[test]
+    fn hash_table_comparator() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define k (list 1 2))", &env);
+        do_lisp_env("(define a (make-hash-table eq?))", &env);
+        do_lisp_env("(hash-table-put! a k 10)", &env);
+        assert_eq!(do_lisp_env("(hash-table-contains? a k)", &env), "#t");
+        assert_eq!(
+            do_lisp_env("(hash-table-contains? a (list 1 2))", &env),
+            "#f"
+        );
+
+        do_lisp_env("(define a (make-hash-table 'eqv?))", &env);
+        do_lisp_env("(hash-table-put! a 8/2 10)", &env);
+        assert_eq!(do_lisp_env("(hash-table-get a 4)", &env), "10");
+        do_lisp_env("(hash-table-put! a k 10)", &env);
+        assert_eq!(
+            do_lisp_env("(hash-table-contains? a (list 1 2))", &env),
+            "#f"
+        );
+
+        do_lisp_env("(define a (make-hash-table equal?))", &env);
+        do_lisp_env("(hash-table-put! a k 10)", &env);
+        assert_eq!(do_lisp_env("(hash-table-get a (list 1 2))", &env), "10");
+
+        do_lisp_env("(define a (make-hash-table string=?))", &env);
+        do_lisp_env("(hash-table-put! a \"abc\" 10)", &env);
+        assert_eq!(
+            do_lisp_env("(hash-table-get a (string-append \"a\" \"bc\"))", &env),
+            "10"
+        );
+    }
+    #[test]
+    fn hash_table_ref() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-hash-table))", &env);
+        do_lisp_env("(hash-table-set! a 'abc 10)", &env);
+        assert_eq!(do_lisp_env("(hash-table-ref a 'abc)", &env), "10");
+        assert_eq!(
+            do_lisp_env("(hash-table-ref a 'def (lambda () 0))", &env),
+            "0"
+        );
+        assert_eq!(do_lisp_env("(hash-table-ref/default a 'abc 0)", &env), "10");
+        assert_eq!(do_lisp_env("(hash-table-ref/default a 'def 0)", &env), "0");
+    }
+    #[test]
+    fn hash_table_update() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-hash-table))", &env);
+        do_lisp_env("(hash-table-put! a 'abc 10)", &env);
+        assert_eq!(
+            do_lisp_env("(hash-table-update! a 'abc (lambda (x) (+ x 1)))", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(hash-table-get a 'abc)", &env), "11");
+        assert_eq!(
+            do_lisp_env(
+                "(hash-table-update! a 'def (lambda (x) (* x 2)) (lambda () 5))",
+                &env
+            ),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(hash-table-get a 'def)", &env), "10");
+        assert_eq!(
+            do_lisp_env(
+                "(hash-table-update!/default a 'ghi (lambda (x) (cons 1 x)) '())",
+                &env
+            ),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(hash-table-update!/default a 'ghi (lambda (x) (cons 2 x)) '())",
+                &env
+            ),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(hash-table-get a 'ghi)", &env), "(2 1)");
+    }
+    #[test]
+    fn hash_table_walk() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1) (b 2) (c 3))))", &env);
+        do_lisp_env("(define s 0)", &env);
+        assert_eq!(
+            do_lisp_env("(hash-table-walk a (lambda (k v) (set! s (+ s v))))", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("s", &env), "6");
+        assert_eq!(
+            do_lisp_env(
+                "(hash-table-walk a (lambda (k v) (hash-table-delete! a k)))",
+                &env
+            ),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(hash-table-size a)", &env), "0");
+    }
+    #[test]
+    fn hash_table_fold() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1) (b 2) (c 3))))", &env);
+        assert_eq!(
+            do_lisp_env("(hash-table-fold a (lambda (k v acc) (+ v acc)) 0)", &env),
+            "6"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(sort (hash-table-fold a (lambda (k v acc) (cons k acc)) '()))",
+                &env
+            ),
+            "(a b c)"
+        );
+    }
+    #[test]
+    fn hash_table_machine() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1))))", &env);
+        do_lisp_env(
+            "(define (deep n) (if (= n 0) 0 (hash-table-fold a (lambda (k v acc) (+ v (deep (- n 1)))) 0)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(deep 100000)", &env), "100000");
+        do_lisp_env(
+            "(define (deep-walk n) (if (> n 0) (hash-table-walk a (lambda (k v) (deep-walk (- n 1))))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(deep-walk 100000)", &env), "nil");
+
+        // the continuation is re-entered in the procedure
+        do_lisp_env("(define c #f)", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(+ 1000 (hash-table-fold a (lambda (k v acc) (call/cc (lambda (k) (set! c k) (+ v acc)))) 0))",
+                &env
+            ),
+            "1001"
+        );
+        assert_eq!(do_lisp_env("(c 100)", &env), "1100");
+        do_lisp_env(
+            "(hash-table-update! a 'a (lambda (x) (call/cc (lambda (k) (set! c k) (+ x 1)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(hash-table-get a 'a)", &env), "2");
+        do_lisp_env("(c 10)", &env);
+        assert_eq!(do_lisp_env("(hash-table-get a 'a)", &env), "10");
+        assert_eq!(
+            do_lisp_env(
+                "(+ 1 (hash-table-ref a 'b (lambda () (call/cc (lambda (k) (set! c k) 0)))))",
+                &env
+            ),
+            "1"
+        );
+        assert_eq!(do_lisp_env("(c 5)", &env), "6");
+    }
+    #[test]
+    fn hash_table_copy() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1)) eq?))", &env);
+        do_lisp_env("(define b (hash-table-copy a #t))", &env);
+        do_lisp_env("(hash-table-put! b 'b 2)", &env);
+        assert_eq!(do_lisp_env("(hash-table-size a)", &env), "1");
+        assert_eq!(do_lisp_env("(hash-table-size b)", &env), "2");
+        assert_eq!(do_lisp_env("(equal? a (hash-table-copy a))", &env), "#t");
+    }
+    #[test]
+    fn hash_table_alist() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1))))", &env);
+        assert_eq!(do_lisp_env("(hash-table->alist a)", &env), "((a . 1))");
+        do_lisp_env(
+            "(define a (alist->hash-table (list (cons \"a\" 1)) string=?))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(hash-table->alist a)", &env), "((\"a\" . 1))");
+        assert_eq!(do_lisp("(hash-table->alist (make-hash-table))"), "()");
+    }
+    #[test]
+    fn hash_table_count() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1) (b 2) (c 3))))", &env);
+        assert_eq!(do_lisp_env("(hash-table-count a)", &env), "3");
+        assert_eq!(
+            do_lisp_env("(hash-table-count (lambda (k v) (odd? v)) a)", &env),
+            "2"
+        );
+    }
+    #[test]
+    fn hash_table_merge() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->hash-table '((a 1) (b 2))))", &env);
+        do_lisp_env("(define b (alist->hash-table '((b 20) (c 30))))", &env);
+        assert_eq!(do_lisp_env("(eq? a (hash-table-merge! a b))", &env), "#t");
+        assert_eq!(
+            do_lisp_env("(sort (hash-table-values a))", &env),
+            "(1 20 30)"
+        );
+        assert_eq!(do_lisp_env("(hash-table-size b)", &env), "2");
+    }
+
     #[test]
     fn make_tree_map() {
         assert_eq!(do_lisp("(make-tree-map)"), "TreeMap");
@@ -497,13 +1192,118 @@ mod tests {
         assert_eq!(do_lisp_env("(sort (tree-map-keys a))", &env), "(a b c)");
         assert_eq!(do_lisp_env("(tree-map-values a)", &env), "(10 20 30)");
     }
+    #[test]
+    fn tree_map_key() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-tree-map))", &env);
+        do_lisp_env("(tree-map-put! a 10 'ten)", &env);
+        do_lisp_env("(tree-map-put! a 9 'nine)", &env);
+        do_lisp_env("(tree-map-put! a 9.5 'nine-half)", &env);
+        do_lisp_env("(tree-map-put! a \"10\" 'str)", &env);
+        do_lisp_env("(tree-map-put! a #\\a 'char)", &env);
+        do_lisp_env("(tree-map-put! a 'abc 'sym)", &env);
+        assert_eq!(
+            do_lisp_env("(tree-map-keys a)", &env),
+            "(9 9.5 10 #\\a \"10\" abc)"
+        );
+        assert_eq!(do_lisp_env("(tree-map-get a 10)", &env), "ten");
+        assert_eq!(do_lisp_env("(tree-map-get a 19/2)", &env), "nine-half");
+        assert_eq!(do_lisp_env("(tree-map-get a \"10\")", &env), "str");
+    }
+    #[test]
+    fn tree_map_min_max() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-tree-map))", &env);
+        assert_eq!(do_lisp_env("(tree-map-min a)", &env), "#f");
+        assert_eq!(do_lisp_env("(tree-map-max a)", &env), "#f");
+        do_lisp_env("(define a (alist->tree-map '((30 c) (10 a) (20 b))))", &env);
+        assert_eq!(do_lisp_env("(tree-map-min a)", &env), "(10 . a)");
+        assert_eq!(do_lisp_env("(tree-map-max a)", &env), "(30 . c)");
+    }
+    #[test]
+    fn tree_map_find() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->tree-map '((30 c) (10 a) (20 b))))", &env);
+        assert_eq!(do_lisp_env("(tree-map-floor a 20)", &env), "(20 . b)");
+        assert_eq!(do_lisp_env("(tree-map-floor a 25)", &env), "(20 . b)");
+        assert_eq!(do_lisp_env("(tree-map-floor a 5)", &env), "#f");
+        assert_eq!(do_lisp_env("(tree-map-ceiling a 20)", &env), "(20 . b)");
+        assert_eq!(do_lisp_env("(tree-map-ceiling a 15)", &env), "(20 . b)");
+        assert_eq!(do_lisp_env("(tree-map-ceiling a 35)", &env), "#f");
+        assert_eq!(do_lisp_env("(tree-map-predecessor a 20)", &env), "(10 . a)");
+        assert_eq!(do_lisp_env("(tree-map-predecessor a 10)", &env), "#f");
+        assert_eq!(do_lisp_env("(tree-map-successor a 20)", &env), "(30 . c)");
+        assert_eq!(do_lisp_env("(tree-map-successor a 30)", &env), "#f");
+        assert_eq!(do_lisp_env("(tree-map-successor a 10.5)", &env), "(20 . b)");
+    }
+    #[test]
+    fn tree_map_range() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define a (alist->tree-map '((30 c) (10 a) (20 b) (40 d))))",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env("(tree-map-range a 10 30)", &env),
+            "((10 . a) (20 . b))"
+        );
+        assert_eq!(
+            do_lisp_env("(tree-map-range a 15 100)", &env),
+            "((20 . b) (30 . c) (40 . d))"
+        );
+        assert_eq!(do_lisp_env("(tree-map-range a 20 20)", &env), "()");
+        assert_eq!(do_lisp_env("(tree-map-range a 30 10)", &env), "()");
+    }
+    #[test]
+    fn tree_map_fold() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->tree-map '((3 c) (1 a) (2 b))))", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(tree-map-fold a (lambda (k v acc) (cons v acc)) '())",
+                &env
+            ),
+            "(c b a)"
+        );
+        assert_eq!(
+            do_lisp_env("(tree-map-fold a (lambda (k v acc) (+ k acc)) 0)", &env),
+            "6"
+        );
+        do_lisp_env(
+            "(define (deep n) (if (= n 0) 0 (tree-map-fold a (lambda (k v acc) (if (= k 1) (+ 1 (deep (- n 1))) acc)) 0)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(deep 100000)", &env), "100000");
+
+        // the continuation is re-entered in the procedure
+        do_lisp_env("(define c #f)", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(+ 1000 (tree-map-fold a (lambda (k v acc) (call/cc (lambda (x) (if (= k 2) (set! c x)) (+ k acc)))) 0))",
+                &env
+            ),
+            "1006"
+        );
+        assert_eq!(do_lisp_env("(c 100)", &env), "1103");
+    }
+    #[test]
+    fn tree_map_pop_min() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (alist->tree-map '((2 b) (1 a))))", &env);
+        assert_eq!(do_lisp_env("(tree-map-pop-min! a)", &env), "(1 . a)");
+        assert_eq!(do_lisp_env("(tree-map-pop-min! a)", &env), "(2 . b)");
+        assert_eq!(do_lisp_env("(tree-map-pop-min! a)", &env), "#f");
+    }
 }
 #[cfg(test)]
 mod error_tests {
     use crate::do_lisp;
     #[test]
     fn make_hash_table() {
-        assert_eq!(do_lisp("(make-hash-table 10)"), "E1007");
+        assert_eq!(do_lisp("(make-hash-table eq? 10)"), "E1007");
+        assert_eq!(do_lisp("(make-hash-table 10)"), "E1006");
+        assert_eq!(do_lisp("(make-hash-table car)"), "E1006");
+        assert_eq!(do_lisp("(make-hash-table a)"), "E1008");
     }
     #[test]
     fn hash_table_put() {
@@ -511,8 +1311,8 @@ mod error_tests {
         assert_eq!(do_lisp("(hash-table-put! 10 20 30 40)"), "E1007");
         assert_eq!(do_lisp("(hash-table-put! 10 20 30)"), "E1023");
         assert_eq!(
-            do_lisp("(hash-table-put! (make-hash-table) \"ABC\" 30)"),
-            "E1004"
+            do_lisp("(hash-table-put! (make-hash-table string=?) 'ABC 30)"),
+            "E1015"
         );
         assert_eq!(
             do_lisp("(hash-table-put! (make-hash-table) 'ABC a)"),
@@ -525,8 +1325,8 @@ mod error_tests {
         assert_eq!(do_lisp("(hash-table-get 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(hash-table-get 10 20)"), "E1023");
         assert_eq!(
-            do_lisp("(hash-table-get (make-hash-table) \"ABC\")"),
-            "E1004"
+            do_lisp("(hash-table-get (make-hash-table string=?) 'ABC)"),
+            "E1015"
         );
         assert_eq!(do_lisp("(hash-table-get (make-hash-table) 'abc)"), "E1021");
     }
@@ -536,8 +1336,8 @@ mod error_tests {
         assert_eq!(do_lisp("(hash-table-exists? 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(hash-table-exists? 10 20)"), "E1023");
         assert_eq!(
-            do_lisp("(hash-table-exists? (make-hash-table) \"ABC\")"),
-            "E1004"
+            do_lisp("(hash-table-exists? (make-hash-table string=?) 1)"),
+            "E1015"
         );
     }
     #[test]
@@ -552,8 +1352,8 @@ mod error_tests {
         assert_eq!(do_lisp("(hash-table-delete! 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(hash-table-delete! 10 20)"), "E1023");
         assert_eq!(
-            do_lisp("(hash-table-delete! (make-hash-table) \"ABC\")"),
-            "E1004"
+            do_lisp("(hash-table-delete! (make-hash-table string=?) 1)"),
+            "E1015"
         );
     }
     #[test]
@@ -577,12 +1377,99 @@ mod error_tests {
     #[test]
     fn alist_hash_table() {
         assert_eq!(do_lisp("(alist->hash-table)"), "E1007");
-        assert_eq!(do_lisp("(alist->hash-table 10 20)"), "E1007");
+        assert_eq!(do_lisp("(alist->hash-table 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(alist->hash-table 10)"), "E1005");
         assert_eq!(do_lisp("(alist->hash-table (list))"), "E1021");
         assert_eq!(do_lisp("(alist->hash-table (list 10))"), "E1005");
         assert_eq!(do_lisp("(alist->hash-table (list (list 10)))"), "E1021");
-        assert_eq!(do_lisp("(alist->hash-table (list (list 10 10)))"), "E1004");
+        assert_eq!(do_lisp("(alist->hash-table '((a 10)) 1)"), "E1006");
+        assert_eq!(do_lisp("(alist->hash-table '((a 10)) string=?)"), "E1015");
+    }
+
+    #[test]
+    fn hash_table_ref() {
+        assert_eq!(do_lisp("(hash-table-ref (make-hash-table))"), "E1007");
+        assert_eq!(do_lisp("(hash-table-ref 1 'a)"), "E1023");
+        assert_eq!(do_lisp("(hash-table-ref (make-hash-table) 'a)"), "E1021");
+        assert_eq!(do_lisp("(hash-table-ref (make-hash-table) 'a 1)"), "E1006");
+        assert_eq!(
+            do_lisp("(hash-table-ref/default (make-hash-table) 'a)"),
+            "E1007"
+        );
+        assert_eq!(do_lisp("(hash-table-ref/default 1 'a 0)"), "E1023");
+    }
+    #[test]
+    fn hash_table_update() {
+        assert_eq!(
+            do_lisp("(hash-table-update! (make-hash-table) 'a)"),
+            "E1007"
+        );
+        assert_eq!(do_lisp("(hash-table-update! 1 'a car)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-update! (make-hash-table) 'a car)"),
+            "E1021"
+        );
+        assert_eq!(
+            do_lisp("(hash-table-update! (make-hash-table) 'a 1 (lambda () 0))"),
+            "E1006"
+        );
+        assert_eq!(
+            do_lisp("(hash-table-update!/default (make-hash-table) 'a car)"),
+            "E1007"
+        );
+        assert_eq!(do_lisp("(hash-table-update!/default 1 'a car 0)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-update!/default (make-hash-table) 'a car 0)"),
+            "E1005"
+        );
+    }
+    #[test]
+    fn hash_table_walk() {
+        assert_eq!(do_lisp("(hash-table-walk (make-hash-table))"), "E1007");
+        assert_eq!(do_lisp("(hash-table-walk 1 car)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-walk (alist->hash-table '((a 1))) 1)"),
+            "E1006"
+        );
+    }
+    #[test]
+    fn hash_table_fold() {
+        assert_eq!(do_lisp("(hash-table-fold (make-hash-table) car)"), "E1007");
+        assert_eq!(do_lisp("(hash-table-fold 1 car 0)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-fold (alist->hash-table '((a 1))) 1 0)"),
+            "E1006"
+        );
+    }
+    #[test]
+    fn hash_table_copy() {
+        assert_eq!(do_lisp("(hash-table-copy)"), "E1007");
+        assert_eq!(do_lisp("(hash-table-copy 1)"), "E1023");
+    }
+    #[test]
+    fn hash_table_alist() {
+        assert_eq!(do_lisp("(hash-table->alist)"), "E1007");
+        assert_eq!(do_lisp("(hash-table->alist 1)"), "E1023");
+    }
+    #[test]
+    fn hash_table_count() {
+        assert_eq!(do_lisp("(hash-table-count)"), "E1007");
+        assert_eq!(do_lisp("(hash-table-count 1)"), "E1023");
+        assert_eq!(do_lisp("(hash-table-count car 1)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-count 1 (alist->hash-table '((a 1))))"),
+            "E1006"
+        );
+    }
+    #[test]
+    fn hash_table_merge() {
+        assert_eq!(do_lisp("(hash-table-merge! (make-hash-table))"), "E1007");
+        assert_eq!(do_lisp("(hash-table-merge! 1 (make-hash-table))"), "E1023");
+        assert_eq!(do_lisp("(hash-table-merge! (make-hash-table) 1)"), "E1023");
+        assert_eq!(
+            do_lisp("(hash-table-merge! (make-hash-table string=?) (alist->hash-table '((a 1))))"),
+            "E1015"
+        );
     }
 
     #[test]
@@ -594,10 +1481,12 @@ mod error_tests {
         assert_eq!(do_lisp("(tree-map-put!)"), "E1007");
         assert_eq!(do_lisp("(tree-map-put! 10 20 30 40)"), "E1007");
         assert_eq!(do_lisp("(tree-map-put! 10 20 30)"), "E1024");
+        assert_eq!(do_lisp("(tree-map-put! (make-tree-map) '(1) 30)"), "E1009");
         assert_eq!(
-            do_lisp("(tree-map-put! (make-tree-map) \"ABC\" 30)"),
-            "E1004"
+            do_lisp("(tree-map-put! (make-tree-map) (/ 0. 0) 30)"),
+            "E1031"
         );
+        assert_eq!(do_lisp("(tree-map-put! (make-tree-map) 1+i 30)"), "E1031");
         assert_eq!(do_lisp("(tree-map-put! (make-tree-map) 'ABC a)"), "E1008");
     }
     #[test]
@@ -605,7 +1494,7 @@ mod error_tests {
         assert_eq!(do_lisp("(tree-map-get)"), "E1007");
         assert_eq!(do_lisp("(tree-map-get 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(tree-map-get 10 20)"), "E1024");
-        assert_eq!(do_lisp("(tree-map-get (make-tree-map) \"ABC\")"), "E1004");
+        assert_eq!(do_lisp("(tree-map-get (make-tree-map) #t)"), "E1009");
         assert_eq!(do_lisp("(tree-map-get (make-tree-map) 'abc)"), "E1021");
     }
     #[test]
@@ -613,20 +1502,14 @@ mod error_tests {
         assert_eq!(do_lisp("(tree-map-exists?)"), "E1007");
         assert_eq!(do_lisp("(tree-map-exists? 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(tree-map-exists? 10 20)"), "E1024");
-        assert_eq!(
-            do_lisp("(tree-map-exists? (make-tree-map) \"ABC\")"),
-            "E1004"
-        );
+        assert_eq!(do_lisp("(tree-map-exists? (make-tree-map) #t)"), "E1009");
     }
     #[test]
     fn tree_map_delete() {
         assert_eq!(do_lisp("(tree-map-delete!)"), "E1007");
         assert_eq!(do_lisp("(tree-map-delete! 10 20 30)"), "E1007");
         assert_eq!(do_lisp("(tree-map-delete! 10 20)"), "E1024");
-        assert_eq!(
-            do_lisp("(tree-map-delete! (make-tree-map) \"ABC\")"),
-            "E1004"
-        );
+        assert_eq!(do_lisp("(tree-map-delete! (make-tree-map) #t)"), "E1009");
     }
     #[test]
     fn tree_map_clear() {
@@ -642,6 +1525,45 @@ mod error_tests {
         assert_eq!(do_lisp("(alist->tree-map (list))"), "E1021");
         assert_eq!(do_lisp("(alist->tree-map (list 10))"), "E1005");
         assert_eq!(do_lisp("(alist->tree-map (list (list 10)))"), "E1021");
-        assert_eq!(do_lisp("(alist->tree-map (list (list 10 10)))"), "E1004");
+        assert_eq!(do_lisp("(alist->tree-map (list (list #t 10)))"), "E1009");
+    }
+    #[test]
+    fn tree_map_min_max() {
+        assert_eq!(do_lisp("(tree-map-min)"), "E1007");
+        assert_eq!(do_lisp("(tree-map-min 1)"), "E1024");
+        assert_eq!(do_lisp("(tree-map-max (make-tree-map) 1)"), "E1007");
+        assert_eq!(do_lisp("(tree-map-max 1)"), "E1024");
+    }
+    #[test]
+    fn tree_map_find() {
+        assert_eq!(do_lisp("(tree-map-floor (make-tree-map))"), "E1007");
+        assert_eq!(do_lisp("(tree-map-floor 1 1)"), "E1024");
+        assert_eq!(do_lisp("(tree-map-ceiling (make-tree-map) '(1))"), "E1009");
+        assert_eq!(
+            do_lisp("(tree-map-predecessor (make-tree-map) 1 2)"),
+            "E1007"
+        );
+        assert_eq!(do_lisp("(tree-map-successor (make-tree-map) 1+i)"), "E1031");
+    }
+    #[test]
+    fn tree_map_range() {
+        assert_eq!(do_lisp("(tree-map-range (make-tree-map) 1)"), "E1007");
+        assert_eq!(do_lisp("(tree-map-range 1 1 2)"), "E1024");
+        assert_eq!(do_lisp("(tree-map-range (make-tree-map) #t 2)"), "E1009");
+        assert_eq!(do_lisp("(tree-map-range (make-tree-map) 1 #t)"), "E1009");
+    }
+    #[test]
+    fn tree_map_fold() {
+        assert_eq!(do_lisp("(tree-map-fold (make-tree-map) car)"), "E1007");
+        assert_eq!(do_lisp("(tree-map-fold 1 car 0)"), "E1024");
+        assert_eq!(
+            do_lisp("(tree-map-fold (alist->tree-map '((1 a))) 1 0)"),
+            "E1006"
+        );
+    }
+    #[test]
+    fn tree_map_pop_min() {
+        assert_eq!(do_lisp("(tree-map-pop-min!)"), "E1007");
+        assert_eq!(do_lisp("(tree-map-pop-min! 1)"), "E1024");
     }
 }
@@ -8,33 +8,122 @@
 use log::{debug, error, info, warn};
 
 use std::fs::File;
-use std::io::BufRead;
 use std::io::BufReader;
-use std::io::Write;
+use std::io::BufWriter;
 use std::path::Path;
 
 use crate::create_error;
 use crate::create_error_value;
+use crate::mut_obj;
+use crate::reference_obj;
 
 use crate::buildin::BuildInTable;
-use crate::lisp::{count_parenthesis, eval, parse, repl, tokenize};
-use crate::lisp::{Environment, Expression, ResultExpression};
+use crate::bytevector::{range_arg, to_byte, to_bytevector};
+use crate::lisp::{eval, load};
+use crate::lisp::{Environment, Expression, Int, ResultExpression};
 use crate::lisp::{ErrCode, Error};
-use crate::lisp::{NEWLINE, SPACE, TAB};
+use crate::machine;
+use crate::pair;
+use crate::port::Port;
+use crate::printer;
+use crate::printer::Style;
+use crate::reader::{datum_label, Reader};
+
+#[cfg(feature = "thread")]
+use crate::env_thread::PortRc;
+
+#[cfg(not(feature = "thread"))]
+use crate::env_single::PortRc;
 
 pub fn create_function<T>(b: &mut T)
 where
     T: BuildInTable + ?Sized,
 {
     b.regist("load-file", load_file);
-    b.regist("display", display);
+    b.regist("display", |exp, env| print(exp, env, Style::Display));
+    b.regist("write", |exp, env| print(exp, env, Style::Write));
+    b.regist("write-simple", |exp, env| print(exp, env, Style::Simple));
+    b.regist("write-shared", |exp, env| print(exp, env, Style::Shared));
     b.regist("newline", newline);
-    b.regist("read", |exp, env| {
-        read(exp, env, &mut BufReader::new(std::io::stdin()))
+    b.regist("read", read);
+    b.regist("read-char", |exp, env| read_char(exp, env, Port::read_char));
+    b.regist("peek-char", |exp, env| read_char(exp, env, Port::peek_char));
+    b.regist("read-line", read_line);
+    b.regist("read-string", read_string);
+    b.regist("char-ready?", char_ready);
+    b.regist("write-char", write_char);
+    b.regist("write-string", write_string);
+
+    b.regist("read-u8", |exp, env| read_u8(exp, env, Port::read_u8));
+    b.regist("peek-u8", |exp, env| read_u8(exp, env, Port::peek_u8));
+    b.regist("write-u8", write_u8);
+    b.regist("read-bytevector", read_bytevector);
+    b.regist("read-bytevector!", read_bytevector_effect);
+    b.regist("write-bytevector", write_bytevector);
+
+    b.regist("open-input-file", |exp, env| {
+        open_file(exp, env, true, false)
+    });
+    b.regist("open-output-file", |exp, env| {
+        open_file(exp, env, false, false)
+    });
+    b.regist("open-binary-input-file", |exp, env| {
+        open_file(exp, env, true, true)
+    });
+    b.regist("open-binary-output-file", |exp, env| {
+        open_file(exp, env, false, true)
+    });
+    b.regist("call-with-input-file", |exp, env| {
+        call_with_file(exp, env, true)
+    });
+    b.regist("call-with-output-file", |exp, env| {
+        call_with_file(exp, env, false)
+    });
+    b.regist("call-with-port", call_with_port);
+    b.regist("open-input-string", open_input_string);
+    b.regist("open-output-string", open_output_string);
+    b.regist("get-output-string", get_output_string);
+    b.regist("open-input-bytevector", open_input_bytevector);
+    b.regist("open-output-bytevector", open_output_bytevector);
+    b.regist("get-output-bytevector", get_output_bytevector);
+    b.regist("with-output-to-string", with_output_to_string);
+    b.regist("with-input-from-file", |exp, env| with_file(exp, env, true));
+    b.regist("with-output-to-file", |exp, env| with_file(exp, env, false));
+
+    b.regist("current-input-port", |exp, env| {
+        current_port(exp, env, true)
+    });
+    b.regist("current-output-port", |exp, env| {
+        current_port(exp, env, false)
+    });
+    b.regist("close-port", |exp, env| close_port(exp, env, None));
+    b.regist("close-input-port", |exp, env| {
+        close_port(exp, env, Some(true))
+    });
+    b.regist("close-output-port", |exp, env| {
+        close_port(exp, env, Some(false))
+    });
+    b.regist("flush-output-port", flush_output_port);
+
+    b.regist("port?", |exp, env| is_port(exp, env, |_| true));
+    b.regist("input-port?", |exp, env| is_port(exp, env, Port::is_input));
+    b.regist("output-port?", |exp, env| {
+        is_port(exp, env, |p| !p.is_input())
+    });
+    b.regist("textual-port?", |exp, env| {
+        is_port(exp, env, |p| !p.is_binary())
+    });
+    b.regist("binary-port?", |exp, env| {
+        is_port(exp, env, Port::is_binary)
     });
-    b.regist("read-char", |exp, env| {
-        read_char(exp, env, &mut BufReader::new(std::io::stdin()))
+    b.regist("input-port-open?", |exp, env| {
+        is_port(exp, env, |p| p.is_input() && p.is_open())
     });
+    b.regist("output-port-open?", |exp, env| {
+        is_port(exp, env, |p| !p.is_input() && p.is_open())
+    });
+    b.regist("eof-object", eof_object);
+    b.regist("eof-object?", is_eof_object);
 }
 fn load_file(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
@@ -57,119 +146,579 @@ fn load_file(exp: &[Expression], env: &Environment) -> ResultExpression {
             return Err(create_error!(ErrCode::E1016));
         }
         let mut stream = BufReader::new(file);
-        match repl(&mut stream, env, None) {
+        match load(&mut stream, env, s.as_ref()) {
             Err(e) => return Err(create_error_value!(ErrCode::E9999, e)),
             Ok(_) => return Ok(Expression::Nil()),
         }
     }
     Err(create_error!(ErrCode::E1015))
 }
-fn display(exp: &[Expression], env: &Environment) -> ResultExpression {
-    if exp.len() < 2 {
+pub(crate) fn io_error(e: std::io::Error) -> Error {
+    create_error_value!(ErrCode::E9999, e)
+}
+// The default port, it is made when it is used at first.
+pub(crate) fn default_port(env: &Environment, input: bool) -> Expression {
+    let p = if input {
+        env.get_input_port()
+    } else {
+        env.get_output_port()
+    };
+    if let Some(p) = p {
+        return p;
+    }
+    if input {
+        let p = Environment::create_port(Port::stdin());
+        env.set_input_port(Some(p.clone()));
+        p
+    } else {
+        let p = Environment::create_port(Port::stdout());
+        env.set_output_port(Some(p.clone()));
+        p
+    }
+}
+pub(crate) fn to_port(v: Expression, input: bool, binary: bool) -> Result<PortRc, Error> {
+    let p = match &v {
+        Expression::Port(p) => p.clone(),
+        _ => return Err(create_error_value!(ErrCode::E1032, v)),
+    };
+    let (kind, open) = {
+        let port = reference_obj!(p);
+        (
+            port.is_input() == input && port.is_binary() == binary,
+            port.is_open(),
+        )
+    };
+    if !kind {
+        return Err(create_error_value!(ErrCode::E1032, v));
+    }
+    if !open {
+        return Err(create_error_value!(ErrCode::E1033, v));
+    }
+    Ok(p)
+}
+// The optional port argument, ex. (display "abc" port)
+fn port_arg(
+    exp: &[Expression],
+    i: usize,
+    env: &Environment,
+    input: bool,
+    binary: bool,
+) -> Result<PortRc, Error> {
+    let v = match exp.get(i) {
+        Some(e) => eval(e, env)?,
+        None => default_port(env, input),
+    };
+    to_port(v, input, binary)
+}
+fn eval_string(e: &Expression, env: &Environment) -> Result<String, Error> {
+    match eval(e, env)? {
+        Expression::String(s) => Ok(s.to_string()),
+        e => Err(create_error_value!(ErrCode::E1015, e)),
+    }
+}
+fn print(exp: &[Expression], env: &Environment, style: Style) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    for e in &exp[1..] {
-        let v = eval(e, env)?;
-        if let Expression::Char(c) = v {
-            print!("{} ", c);
-        } else if let Expression::String(s) = v {
-            print!("{} ", s);
-        } else {
-            print!("{} ", v);
-        }
-        std::io::stdout().flush().unwrap();
+    let s = printer::to_string(&eval(&exp[1], env)?, style);
+    let p = port_arg(exp, 2, env, false, false)?;
+    mut_obj!(p).write_str(&s).map_err(io_error)?;
+    Ok(Expression::Nil())
+}
+fn newline(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
+    let p = port_arg(exp, 1, env, false, false)?;
+    mut_obj!(p).write_str("\n").map_err(io_error)?;
     Ok(Expression::Nil())
 }
-fn newline(exp: &[Expression], _env: &Environment) -> ResultExpression {
-    if exp.len() != 1 {
+fn write_char(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    println!();
+    let c = match eval(&exp[1], env)? {
+        Expression::Char(c) => c,
+        e => return Err(create_error_value!(ErrCode::E1019, e)),
+    };
+    let p = port_arg(exp, 2, env, false, false)?;
+    mut_obj!(p).write_str(&c.to_string()).map_err(io_error)?;
     Ok(Expression::Nil())
 }
-fn read(exp: &[Expression], env: &Environment, stream: &mut dyn BufRead) -> ResultExpression {
-    if exp.len() != 1 {
+fn write_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 5 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
+    let s = eval_string(&exp[1], env)?;
+    let p = port_arg(exp, 2, env, false, false)?;
 
-    let mut buffer = String::new();
-    let mut expression: Vec<String> = Vec::new();
-
-    let result = loop {
-        buffer.clear();
-        let n = match stream.read_line(&mut buffer) {
-            Ok(n) => n,
-            Err(_) => return Err(create_error!(ErrCode::E9999)),
+    let mut range = [0, s.chars().count()];
+    for (i, e) in exp.iter().skip(3).enumerate() {
+        range[i] = match eval(e, env)? {
+            Expression::Integer(i) if i >= 0 => i as usize,
+            Expression::Integer(i) => return Err(create_error_value!(ErrCode::E1021, i)),
+            e => return Err(create_error_value!(ErrCode::E1002, e)),
         };
-        if n == 1 {
-            continue;
+    }
+    let [start, end] = range;
+    if start > end || end > s.chars().count() {
+        return Err(create_error_value!(ErrCode::E1021, end));
+    }
+    let s: String = s.chars().skip(start).take(end - start).collect();
+    mut_obj!(p).write_str(&s).map_err(io_error)?;
+    Ok(Expression::Nil())
+}
+fn read(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = port_arg(exp, 1, env, true, false)?;
+    let lisp = read_datum(&mut mut_obj!(p)).map_err(io_error)?;
+    let lisp = match lisp {
+        Some(lisp) => lisp,
+        None => return Ok(Expression::Eof()),
+    };
+    match Reader::new(&lisp).read(env)? {
+        Some((exp, _)) => Ok(pair::datum(&exp)),
+        None => Ok(Expression::Eof()),
+    }
+}
+// The text of a datum, ex. "(a b)", "'x", "\"abc\""
+fn read_datum(port: &mut Port) -> std::io::Result<Option<String>> {
+    let prefix: &[char] = &['\'', '`', ',', '@'];
+    let delimiter = |c: Option<char>| c.is_none_or(|c| c.is_whitespace() || "()\";".contains(c));
+
+    let mut s = String::new();
+    let mut depth = 0;
+    while let Some(c) = port.read_char()? {
+        match c {
+            ';' => {
+                while !matches!(port.read_char()?, Some('\n') | None) {}
+                if depth > 0 {
+                    s.push('\n');
+                }
+                continue;
+            }
+            // ex. "abc", |a b|
+            '"' | '|' => {
+                s.push(c);
+                while let Some(d) = port.read_char()? {
+                    s.push(d);
+                    if d == c {
+                        break;
+                    } else if d == '\\' {
+                        if let Some(d) = port.read_char()? {
+                            s.push(d);
+                        }
+                    }
+                }
+            }
+            // ex. #| ... |#
+            '#' if port.peek_char()? == Some('|') => {
+                port.read_char()?;
+                let mut level = 1;
+                while level > 0 {
+                    match port.read_char()? {
+                        Some('|') if port.peek_char()? == Some('#') => level -= 1,
+                        Some('#') if port.peek_char()? == Some('|') => level += 1,
+                        Some(_) => continue,
+                        None => break,
+                    }
+                    port.read_char()?;
+                }
+                if depth > 0 {
+                    s.push(' ');
+                }
+                continue;
+            }
+            // ex. #;(a b), the next datum is skipped.
+            '#' if port.peek_char()? == Some(';') => {
+                port.read_char()?;
+                read_datum(port)?;
+                continue;
+            }
+            '#' if port.peek_char()? == Some('\\') => {
+                s.push(c);
+                s.push('\\');
+                port.read_char()?;
+                if let Some(c) = port.read_char()? {
+                    s.push(c);
+                }
+            }
+            '#' if port.peek_char()? == Some('(') => {
+                s.push(c);
+                continue;
+            }
+            '(' => {
+                depth += 1;
+                s.push(c);
+                continue;
+            }
+            ')' => {
+                depth -= 1;
+                s.push(c);
+            }
+            c if c.is_whitespace() => {
+                if depth > 0 {
+                    s.push(c);
+                }
+                continue;
+            }
+            _ => s.push(c),
         }
-        expression.push(buffer.trim().to_string());
-        let lisp = expression.join(" ");
-        let (left, right) = count_parenthesis(&lisp);
-        if left > right {
+        if depth > 0 || s.trim_start_matches(prefix).is_empty() {
             continue;
         }
-        let token = tokenize(&lisp);
-        break parse(&token, &mut 1, env);
+        if c == ')' || c == '"' || c == '|' || delimiter(port.peek_char()?) {
+            // ex. #0=(a #0#), the label is followed by the datum.
+            let last = s
+                .rsplit(|c: char| c.is_whitespace() || "()".contains(c))
+                .next();
+            if last.and_then(|t| datum_label(t, '=')).is_none() {
+                return Ok(Some(s));
+            }
+        }
+    }
+    Ok(if s.is_empty() { None } else { Some(s) })
+}
+fn read_char(
+    exp: &[Expression],
+    env: &Environment,
+    f: fn(&mut Port) -> std::io::Result<Option<char>>,
+) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = port_arg(exp, 1, env, true, false)?;
+    let c = f(&mut mut_obj!(p)).map_err(io_error)?;
+    Ok(c.map_or(Expression::Eof(), Expression::Char))
+}
+fn read_line(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = port_arg(exp, 1, env, true, false)?;
+    let s = mut_obj!(p).read_line().map_err(io_error)?;
+    Ok(s.map_or(Expression::Eof(), Environment::create_string))
+}
+fn read_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let k = match eval(&exp[1], env)? {
+        Expression::Integer(k) if k >= 0 => k,
+        Expression::Integer(k) => return Err(create_error_value!(ErrCode::E1021, k)),
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
+    };
+    let p = port_arg(exp, 2, env, true, false)?;
+    let mut port = mut_obj!(p);
+
+    let mut s = String::new();
+    for _ in 0..k {
+        match port.read_char().map_err(io_error)? {
+            Some(c) => s.push(c),
+            None => break,
+        }
+    }
+    if s.is_empty() && k > 0 {
+        return Ok(Expression::Eof());
+    }
+    Ok(Environment::create_string(s))
+}
+fn char_ready(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    port_arg(exp, 1, env, true, false)?;
+    Ok(Expression::Boolean(true))
+}
+fn read_u8(
+    exp: &[Expression],
+    env: &Environment,
+    f: fn(&mut Port) -> std::io::Result<Option<u8>>,
+) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = port_arg(exp, 1, env, true, true)?;
+    let b = f(&mut mut_obj!(p)).map_err(io_error)?;
+    Ok(b.map_or(Expression::Eof(), |b| Expression::Integer(Int::from(b))))
+}
+fn write_u8(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let b = to_byte(eval(&exp[1], env)?)?;
+    let p = port_arg(exp, 2, env, false, true)?;
+    mut_obj!(p).write_bytes(&[b]).map_err(io_error)?;
+    Ok(Expression::Nil())
+}
+fn read_bytes(port: &mut Port, k: usize) -> std::io::Result<Vec<u8>> {
+    let mut v = Vec::new();
+    while v.len() < k {
+        match port.read_u8()? {
+            Some(b) => v.push(b),
+            None => break,
+        }
+    }
+    Ok(v)
+}
+fn read_bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let k = match eval(&exp[1], env)? {
+        Expression::Integer(k) if k >= 0 => k as usize,
+        Expression::Integer(k) => return Err(create_error_value!(ErrCode::E1021, k)),
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
+    };
+    let p = port_arg(exp, 2, env, true, true)?;
+    let v = read_bytes(&mut mut_obj!(p), k).map_err(io_error)?;
+    if v.is_empty() && k > 0 {
+        return Ok(Expression::Eof());
+    }
+    Ok(Environment::create_bytevector(v))
+}
+// ex. (read-bytevector! bv port start end), the count of the bytes is returned.
+fn read_bytevector_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 5 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let bv = to_bytevector(eval(&exp[1], env)?)?;
+    let p = port_arg(exp, 2, env, true, true)?;
+    let len = reference_obj!(bv).len();
+    let (start, end) = range_arg(exp, 3, env, len)?;
+
+    let v = read_bytes(&mut mut_obj!(p), end - start).map_err(io_error)?;
+    if v.is_empty() && end > start {
+        return Ok(Expression::Eof());
+    }
+    mut_obj!(bv)[start..start + v.len()].copy_from_slice(&v);
+    Ok(Expression::Integer(v.len() as _))
+}
+fn write_bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 || exp.len() > 5 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let bv = to_bytevector(eval(&exp[1], env)?)?;
+    let p = port_arg(exp, 2, env, false, true)?;
+    let bv = reference_obj!(bv).clone();
+    let (start, end) = range_arg(exp, 3, env, bv.len())?;
+    mut_obj!(p).write_bytes(&bv[start..end]).map_err(io_error)?;
+    Ok(Expression::Nil())
+}
+fn open(path: &str, input: bool, binary: bool) -> Result<Port, Error> {
+    let r = if input {
+        File::open(path).map(|f| Port::input(path, BufReader::new(f), binary))
+    } else {
+        File::create(path).map(|f| Port::output(path, BufWriter::new(f), binary))
     };
-    result
+    r.map_err(|e| create_error_value!(ErrCode::E1034, e))
+}
+fn open_file(exp: &[Expression], env: &Environment, input: bool, binary: bool) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let path = eval_string(&exp[1], env)?;
+    Ok(Environment::create_port(open(&path, input, binary)?))
+}
+// Apply the procedure to the port, and the port is closed after that.
+fn apply_port(proc: &Expression, port: Expression, env: &Environment) -> ResultExpression {
+    let r = machine::apply(proc, vec![port.clone()], env);
+    if let Expression::Port(p) = port {
+        mut_obj!(p).close().map_err(io_error)?;
+    }
+    r
+}
+fn call_with_file(exp: &[Expression], env: &Environment, input: bool) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let path = eval_string(&exp[1], env)?;
+    let proc = eval(&exp[2], env)?;
+    let port = Environment::create_port(open(&path, input, false)?);
+    apply_port(&proc, port, env)
+}
+fn call_with_port(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let port = eval(&exp[1], env)?;
+    if !matches!(port, Expression::Port(_)) {
+        return Err(create_error_value!(ErrCode::E1032, port));
+    }
+    let proc = eval(&exp[2], env)?;
+    apply_port(&proc, port, env)
+}
+fn open_input_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let s = eval_string(&exp[1], env)?;
+    Ok(Environment::create_port(Port::string_input(&s)))
+}
+fn open_output_string(exp: &[Expression], _env: &Environment) -> ResultExpression {
+    if exp.len() != 1 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Environment::create_port(Port::string_output()))
+}
+fn get_output_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    if let Expression::Port(p) = &v {
+        if let Some(s) = reference_obj!(p).get_string() {
+            return Ok(Environment::create_string(s));
+        }
+    }
+    Err(create_error_value!(ErrCode::E1032, v))
 }
-fn read_char(exp: &[Expression], env: &Environment, stream: &mut dyn BufRead) -> ResultExpression {
+fn open_input_bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let bv = to_bytevector(eval(&exp[1], env)?)?;
+    let v = reference_obj!(bv).clone();
+    Ok(Environment::create_port(Port::bytes_input(v)))
+}
+fn open_output_bytevector(exp: &[Expression], _env: &Environment) -> ResultExpression {
     if exp.len() != 1 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
+    Ok(Environment::create_port(Port::bytes_output()))
+}
+fn get_output_bytevector(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    if let Expression::Port(p) = &v {
+        if let Some(b) = reference_obj!(p).get_bytes() {
+            return Ok(Environment::create_bytevector(b));
+        }
+    }
+    Err(create_error_value!(ErrCode::E1032, v))
+}
+fn with_output_to_string(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let thunk = eval(&exp[1], env)?;
+    let port = Environment::create_port(Port::string_output());
 
-    let mut buffer = [0; 4];
-    let mut exp_char = String::new();
+    let current = env.get_output_port();
+    env.set_output_port(Some(port.clone()));
+    let r = machine::apply(&thunk, Vec::new(), env);
+    env.set_output_port(current);
+    r?;
 
-    // pub trait BufRead: Read {
-    //    ...
-    // }
-    let n = match stream.read(&mut buffer) {
-        Ok(n) => n,
-        Err(_) => return Err(create_error!(ErrCode::E9999)),
-    };
-    if n == 0 {
-        exp_char.push_str(NEWLINE.1);
+    get_output_string(&[Expression::Nil(), port], env)
+}
+// The current port is the file while the thunk is called, ex. (with-output-to-file "a.txt" thunk)
+fn with_file(exp: &[Expression], env: &Environment, input: bool) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let path = eval_string(&exp[1], env)?;
+    let thunk = eval(&exp[2], env)?;
+    let port = Environment::create_port(open(&path, input, false)?);
+
+    let current = if input {
+        env.get_input_port()
     } else {
-        let s = match std::str::from_utf8(&buffer) {
-            Ok(s) => s,
-            Err(_) => return Err(create_error!(ErrCode::E9999)),
-        };
-        if let Some(c) = s.chars().next() {
-            match c {
-                ' ' => exp_char.push_str(SPACE.1),
-                '\t' => exp_char.push_str(TAB.1),
-                _ => {
-                    exp_char.push_str("#\\");
-                    exp_char.push(buffer[0] as char);
-                }
-            }
+        env.get_output_port()
+    };
+    let set = |p| {
+        if input {
+            env.set_input_port(p)
+        } else {
+            env.set_output_port(p)
         }
+    };
+    set(Some(port.clone()));
+    let r = machine::apply(&thunk, Vec::new(), env);
+    set(current);
+
+    if let Expression::Port(p) = port {
+        mut_obj!(p).close().map_err(io_error)?;
     }
-    parse(&[exp_char], &mut 1, env)
+    r
+}
+fn current_port(exp: &[Expression], env: &Environment, input: bool) -> ResultExpression {
+    if exp.len() != 1 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(default_port(env, input))
+}
+fn close_port(exp: &[Expression], env: &Environment, input: Option<bool>) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    if let Expression::Port(p) = &v {
+        let mut port = mut_obj!(p);
+        if input.is_none_or(|b| b == port.is_input()) {
+            port.close().map_err(io_error)?;
+            return Ok(Expression::Nil());
+        }
+    }
+    Err(create_error_value!(ErrCode::E1032, v))
+}
+fn flush_output_port(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = match exp.get(1) {
+        Some(e) => eval(e, env)?,
+        None => default_port(env, false),
+    };
+    if let Expression::Port(p) = &v {
+        let mut port = mut_obj!(p);
+        if !port.is_input() {
+            port.flush().map_err(io_error)?;
+            return Ok(Expression::Nil());
+        }
+    }
+    Err(create_error_value!(ErrCode::E1032, v))
+}
+fn is_port(exp: &[Expression], env: &Environment, f: fn(&Port) -> bool) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Port(p) => Ok(Expression::Boolean(f(&reference_obj!(p)))),
+        _ => Ok(Expression::Boolean(false)),
+    }
+}
+fn eof_object(exp: &[Expression], _env: &Environment) -> ResultExpression {
+    if exp.len() != 1 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Expression::Eof())
+}
+fn is_eof_object(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Expression::Boolean(Expression::is_eof(&eval(
+        &exp[1], env,
+    )?)))
 }
 #[cfg(test)]
 mod tests {
-    use crate::io;
     use crate::lisp;
     use crate::{do_lisp, do_lisp_env};
-    use lisp::Expression;
     use std::env;
     use std::fs::File;
     use std::io::Cursor;
     use std::io::Write;
     use std::path::Path;
 
-    fn read_char_test(data: &str) -> String {
-        let mut cur = Cursor::new(data.as_bytes());
-        let env = lisp::Environment::new();
-        match io::read_char(&[Expression::Nil()], &env, &mut cur) {
-            Ok(s) => s.to_string(),
-            Err(_) => "error".to_string(),
-        }
+    fn test_file(name: &str) -> String {
+        let f = env::temp_dir().join(name);
+        f.to_str().expect("die").to_string()
     }
     #[test]
     #[allow(unused_must_use)]
@@ -215,41 +764,339 @@ mod tests {
         assert_eq!(do_lisp_env("(testchr)", &env), "#\\(");
     }
     #[test]
+    fn load_position() {
+        let mut cur = Cursor::new(
+            "; comment\n(define (f x)\n; comment\n  (car x))\n\n(define (g x)\n  (f x))".as_bytes(),
+        );
+        let env = lisp::Environment::new();
+        lisp::load(&mut cur, &env, "test.scm").unwrap();
+        match lisp::do_core_logic("(g 1)", &env) {
+            Ok(_) => panic!("no error"),
+            Err(e) => {
+                assert_eq!(e.get_msg(), "Not List: 1 (test.scm:4:3)");
+                assert_eq!(e.trace.len(), 1);
+                assert_eq!(e.trace[0].name, "f");
+            }
+        }
+    }
+    #[test]
     fn display() {
         let env = lisp::Environment::new();
         do_lisp_env("(define a 100)", &env);
         assert_eq!(do_lisp_env("(display \"abc\")", &env), "nil");
         assert_eq!(do_lisp_env("(display #\\a)", &env), "nil");
         assert_eq!(do_lisp_env("(display a)", &env), "nil");
+
+        do_lisp_env("(define p (open-output-string))", &env);
+        assert_eq!(do_lisp_env("(display \"abc\" p)", &env), "nil");
+        assert_eq!(do_lisp_env("(display #\\d p)", &env), "nil");
+        assert_eq!(do_lisp_env("(display a p)", &env), "nil");
+        assert_eq!(do_lisp_env("(get-output-string p)", &env), "\"abcd100\"");
+    }
+    #[test]
+    fn write() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-output-string))", &env);
+        do_lisp_env("(define l (list 1 2 3))", &env);
+        assert_eq!(
+            do_lisp_env("(write (list \"abc\" #\\a 1.5 #\\space) p)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(list-set! l 1 l)", &env), "nil");
+        assert_eq!(do_lisp_env("(write l p)", &env), "nil");
+        assert_eq!(
+            do_lisp_env("(display (list \"abc\" #\\a l) p)", &env),
+            "nil"
+        );
+        assert_eq!(
+            do_lisp_env("(get-output-string p)", &env),
+            "\"(\\\"abc\\\" #\\\\a 1.5 #\\\\space)#0=(1 #0# 3)(abc a #0=(1 #0# 3))\""
+        );
+        assert_eq!(do_lisp("(write 10)"), "nil");
+
+        // the builtin names are written as the symbols
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-output-string))", &env);
+        do_lisp_env("(write '(car x) p)", &env);
+        do_lisp_env("(write ''a p)", &env);
+        do_lisp_env("(write '#(vector cons) p)", &env);
+        assert_eq!(
+            do_lisp_env("(get-output-string p)", &env),
+            "\"(car x)(quote a)#(vector cons)\""
+        );
+    }
+    #[test]
+    fn write_shared() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-output-string))", &env);
+        do_lisp_env("(define a (list 1))", &env);
+        do_lisp_env("(define v (vector 1 2))", &env);
+        do_lisp_env("(vector-set! v 1 v)", &env);
+        assert_eq!(do_lisp_env("(write-shared (list a a) p)", &env), "nil");
+        assert_eq!(do_lisp_env("(write (list a a) p)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-shared v p)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-simple (list a a) p)", &env), "nil");
+        assert_eq!(
+            do_lisp_env("(get-output-string p)", &env),
+            "\"(#0=(1) #0#)((1) (1))#0=#(1 #0#)((1) (1))\""
+        );
+        assert_eq!(do_lisp("(write-shared 10)"), "nil");
+        assert_eq!(do_lisp("(write-simple 10)"), "nil");
     }
     #[test]
     fn newline() {
         assert_eq!(do_lisp("(newline)"), "nil");
+        assert_eq!(
+            do_lisp("(let ((p (open-output-string))) (newline p) (get-output-string p))"),
+            "\"\\n\""
+        );
     }
     #[test]
     fn read() {
-        let mut cur = Cursor::new("abcdef".as_bytes());
-        let env = lisp::Environment::new();
-        if let Ok(s) = io::read(&[Expression::Nil()], &env, &mut cur) {
-            assert_eq!(s.to_string(), "abcdef")
-        }
+        let f = test_file("elisp_read.scm");
+        std::fs::write(
+            &f,
+            "abcdef 1 (2\n ) '(a \")\" #\\)) ; c\n#(1 2) #0= (a #0#) #| c |# |a b| #;x \"a\\nb\"",
+        )
+        .unwrap();
 
-        let mut cur = Cursor::new("1".as_bytes());
-        if let Ok(s) = io::read(&[Expression::Nil()], &env, &mut cur) {
-            assert_eq!(s.to_string(), "1")
-        }
-        let mut cur = Cursor::new("(2\n )".as_bytes());
-        if let Ok(s) = io::read(&[Expression::Nil()], &env, &mut cur) {
-            assert_eq!(s.to_string(), "(2)")
-        }
+        let env = lisp::Environment::new();
+        do_lisp_env(&format!("(define p (open-input-file \"{}\"))", f), &env);
+        assert_eq!(do_lisp_env("(read p)", &env), "abcdef");
+        assert_eq!(do_lisp_env("(read p)", &env), "1");
+        assert_eq!(do_lisp_env("(read p)", &env), "(2)");
+        assert_eq!(do_lisp_env("(car (cdr (read p)))", &env), "(a \")\" #\\))");
+        assert_eq!(
+            do_lisp_env("(eval (read p) (interaction-environment))", &env),
+            "#(1 2)"
+        );
+        assert_eq!(do_lisp_env("(read p)", &env), "#0=(a #0#)");
+        assert_eq!(do_lisp_env("(read p)", &env), "|a b|");
+        assert_eq!(do_lisp_env("(read p)", &env), "\"a\\nb\"");
+        assert_eq!(do_lisp_env("(read p)", &env), "#<eof>");
+        std::fs::remove_file(&f).unwrap();
+    }
+    #[test]
+    fn read_datum() {
+        assert_eq!(
+            do_lisp("(symbol? (car (read (open-input-string \"(car x)\"))))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(vector? (read (open-input-string \"#(1 2)\")))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(eq? 'car (car (read (open-input-string \"(car x)\"))))"),
+            "#t"
+        );
     }
     #[test]
     fn read_char() {
-        assert_eq!(read_char_test("a"), "#\\a");
-        assert_eq!(read_char_test("\n"), "#\\newline");
-        assert_eq!(read_char_test("\t"), "#\\tab");
-        assert_eq!(read_char_test(" "), "#\\space");
-        assert_eq!(read_char_test(""), "#\\newline");
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-input-string \"a\n\t あ\"))", &env);
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#\\a");
+        assert_eq!(do_lisp_env("(peek-char p)", &env), "#\\newline");
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#\\newline");
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#\\tab");
+        assert_eq!(do_lisp_env("(char-ready? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#\\space");
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#\\あ");
+        assert_eq!(do_lisp_env("(peek-char p)", &env), "#<eof>");
+        assert_eq!(do_lisp_env("(read-char p)", &env), "#<eof>");
+    }
+    #[test]
+    fn read_line() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-input-string \"abc\r\n\ndef\"))", &env);
+        assert_eq!(do_lisp_env("(read-line p)", &env), "\"abc\"");
+        assert_eq!(do_lisp_env("(read-line p)", &env), "\"\"");
+        assert_eq!(do_lisp_env("(read-line p)", &env), "\"def\"");
+        assert_eq!(do_lisp_env("(read-line p)", &env), "#<eof>");
+    }
+    #[test]
+    fn read_string() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-input-string \"abcdeあ\"))", &env);
+        assert_eq!(do_lisp_env("(read-string 0 p)", &env), "\"\"");
+        assert_eq!(do_lisp_env("(read-string 2 p)", &env), "\"ab\"");
+        assert_eq!(do_lisp_env("(read-string 10 p)", &env), "\"cdeあ\"");
+        assert_eq!(do_lisp_env("(read-string 1 p)", &env), "#<eof>");
+    }
+    #[test]
+    fn write_string() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-output-string))", &env);
+        assert_eq!(do_lisp_env("(write-string \"abc\" p)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-string \"defgh\" p 1)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-string \"ijklm\" p 1 3)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-char #\\z p)", &env), "nil");
+        assert_eq!(do_lisp_env("(get-output-string p)", &env), "\"abcefghjkz\"");
+        assert_eq!(do_lisp("(write-string \"abc\")"), "nil");
+        assert_eq!(do_lisp("(write-char #\\a)"), "nil");
+    }
+    #[test]
+    fn file_port() {
+        let env = lisp::Environment::new();
+        let f = test_file("elisp_file_port.txt");
+        do_lisp_env(&format!("(define f \"{}\")", f), &env);
+
+        do_lisp_env("(define p (open-output-file f))", &env);
+        assert_eq!(do_lisp_env("(output-port? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(display \"abc\" p)", &env), "nil");
+        assert_eq!(do_lisp_env("(newline p)", &env), "nil");
+        assert_eq!(do_lisp_env("(flush-output-port p)", &env), "nil");
+        assert_eq!(do_lisp_env("(close-port p)", &env), "nil");
+        assert_eq!(do_lisp_env("(output-port-open? p)", &env), "#f");
+        assert_eq!(
+            do_lisp_env(
+                "(call-with-output-file f (lambda (p) (write-string \"(1 2)\" p) 10))",
+                &env
+            ),
+            "10"
+        );
+        assert_eq!(
+            do_lisp_env("(call-with-input-file f (lambda (p) (read p)))", &env),
+            "(1 2)"
+        );
+        do_lisp_env("(define p (open-input-file f))", &env);
+        assert_eq!(do_lisp_env("(input-port? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(read-line p)", &env), "\"(1 2)\"");
+        assert_eq!(do_lisp_env("(close-input-port p)", &env), "nil");
+        assert_eq!(do_lisp_env("(input-port-open? p)", &env), "#f");
+        assert_eq!(
+            do_lisp_env(
+                "(with-output-to-file f (lambda () (write '(3 4)) (display 5) 10))",
+                &env
+            ),
+            "10"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(with-input-from-file f (lambda () (list (read) (read))))",
+                &env
+            ),
+            "((3 4) 5)"
+        );
+        assert_eq!(
+            do_lisp_env("(current-output-port)", &env),
+            "#<output-port stdout>"
+        );
+        std::fs::remove_file(&f).unwrap();
+    }
+    #[test]
+    fn binary_port() {
+        let env = lisp::Environment::new();
+        let f = test_file("elisp_binary_port.bin");
+        do_lisp_env(&format!("(define f \"{}\")", f), &env);
+
+        do_lisp_env("(define p (open-binary-output-file f))", &env);
+        assert_eq!(do_lisp_env("(binary-port? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(textual-port? p)", &env), "#f");
+        assert_eq!(do_lisp_env("(write-u8 0 p)", &env), "nil");
+        assert_eq!(do_lisp_env("(write-u8 255 p)", &env), "nil");
+        assert_eq!(do_lisp_env("(close-output-port p)", &env), "nil");
+
+        do_lisp_env("(define p (open-binary-input-file f))", &env);
+        assert_eq!(do_lisp_env("(peek-u8 p)", &env), "0");
+        assert_eq!(do_lisp_env("(read-u8 p)", &env), "0");
+        assert_eq!(do_lisp_env("(read-u8 p)", &env), "255");
+        assert_eq!(do_lisp_env("(read-u8 p)", &env), "#<eof>");
+        assert_eq!(do_lisp_env("(close-port p)", &env), "nil");
+        std::fs::remove_file(&f).unwrap();
+    }
+    #[test]
+    fn bytevector_port() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define p (open-input-bytevector #u8(137 80 78 71 13 10)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("p", &env), "#<input-port bytevector>");
+        assert_eq!(do_lisp_env("(binary-port? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(read-u8 p)", &env), "137");
+        assert_eq!(do_lisp_env("(read-bytevector 3 p)", &env), "#u8(80 78 71)");
+        do_lisp_env("(define bv (make-bytevector 4 0))", &env);
+        assert_eq!(do_lisp_env("(read-bytevector! bv p 1)", &env), "2");
+        assert_eq!(do_lisp_env("bv", &env), "#u8(0 13 10 0)");
+        assert_eq!(do_lisp_env("(read-bytevector 3 p)", &env), "#<eof>");
+        assert_eq!(do_lisp_env("(read-bytevector! bv p)", &env), "#<eof>");
+        assert_eq!(do_lisp_env("(read-bytevector 0 p)", &env), "#u8()");
+
+        do_lisp_env("(define p (open-output-bytevector))", &env);
+        assert_eq!(do_lisp_env("(write-u8 1 p)", &env), "nil");
+        assert_eq!(
+            do_lisp_env("(write-bytevector #u8(2 3 4 5) p 1 3)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(get-output-bytevector p)", &env), "#u8(1 3 4)");
+
+        let f = test_file("elisp_bytevector_port.bin");
+        do_lisp_env(&format!("(define f \"{}\")", f), &env);
+        do_lisp_env("(define p (open-binary-output-file f))", &env);
+        assert_eq!(
+            do_lisp_env("(write-bytevector (string->utf8 \"abc\") p)", &env),
+            "nil"
+        );
+        assert_eq!(do_lisp_env("(close-port p)", &env), "nil");
+        do_lisp_env("(define p (open-binary-input-file f))", &env);
+        assert_eq!(
+            do_lisp_env("(utf8->string (read-bytevector 10 p))", &env),
+            "\"abc\""
+        );
+        assert_eq!(do_lisp_env("(close-port p)", &env), "nil");
+        std::fs::remove_file(&f).unwrap();
+    }
+    #[test]
+    fn string_port() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (open-input-string \"abc\"))", &env);
+        assert_eq!(do_lisp_env("p", &env), "#<input-port string>");
+        assert_eq!(do_lisp_env("(textual-port? p)", &env), "#t");
+        assert_eq!(
+            do_lisp("(call-with-port (open-input-string \"abc\") read-line)"),
+            "\"abc\""
+        );
+        assert_eq!(
+            do_lisp("(with-output-to-string (lambda () (display 10) (write-string \"a\")))"),
+            "\"10a\""
+        );
+        assert_eq!(
+            do_lisp("(with-output-to-string (lambda () (display 1 (current-output-port))))"),
+            "\"1\""
+        );
+    }
+    #[test]
+    fn current_port() {
+        let env = lisp::Environment::new();
+        assert_eq!(
+            do_lisp_env("(current-input-port)", &env),
+            "#<input-port stdin>"
+        );
+        assert_eq!(
+            do_lisp_env("(current-output-port)", &env),
+            "#<output-port stdout>"
+        );
+        assert_eq!(
+            do_lisp_env("(eq? (current-output-port) (current-output-port))", &env),
+            "#t"
+        );
+    }
+    #[test]
+    fn is_port() {
+        assert_eq!(do_lisp("(port? (current-output-port))"), "#t");
+        assert_eq!(do_lisp("(port? 10)"), "#f");
+        assert_eq!(do_lisp("(input-port? (current-output-port))"), "#f");
+        assert_eq!(do_lisp("(output-port? (current-input-port))"), "#f");
+        assert_eq!(do_lisp("(input-port-open? (current-input-port))"), "#t");
+        assert_eq!(do_lisp("(output-port-open? (current-output-port))"), "#t");
+    }
+    #[test]
+    fn eof_object() {
+        assert_eq!(do_lisp("(eof-object)"), "#<eof>");
+        assert_eq!(do_lisp("(eof-object? (eof-object))"), "#t");
+        assert_eq!(do_lisp("(eof-object? 10)"), "#f");
+        assert_eq!(do_lisp("(eq? (eof-object) (eof-object))"), "#t");
     }
 }
 #[cfg(test)]
@@ -269,18 +1116,203 @@ mod error_tests {
     #[test]
     fn display() {
         assert_eq!(do_lisp("(display)"), "E1007");
+        assert_eq!(do_lisp("(display 1 2 3)"), "E1007");
         assert_eq!(do_lisp("(display a)"), "E1008");
+        assert_eq!(do_lisp("(display 1 2)"), "E1032");
+        assert_eq!(do_lisp("(display 1 (current-input-port))"), "E1032");
+        assert_eq!(
+            do_lisp("(let ((p (open-output-string))) (close-port p) (display 1 p))"),
+            "E1033"
+        );
+    }
+    #[test]
+    fn write() {
+        assert_eq!(do_lisp("(write)"), "E1007");
+        assert_eq!(do_lisp("(write 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(write a)"), "E1008");
+        assert_eq!(do_lisp("(write 1 2)"), "E1032");
+        assert_eq!(do_lisp("(write-simple)"), "E1007");
+        assert_eq!(do_lisp("(write-simple 1 2)"), "E1032");
+        assert_eq!(do_lisp("(write-shared)"), "E1007");
+        assert_eq!(do_lisp("(write-shared 1 2)"), "E1032");
     }
     #[test]
     fn newline() {
-        assert_eq!(do_lisp("(newline 123)"), "E1007");
+        assert_eq!(do_lisp("(newline 1 2)"), "E1007");
+        assert_eq!(do_lisp("(newline 123)"), "E1032");
     }
     #[test]
     fn read() {
-        assert_eq!(do_lisp("(read 123)"), "E1007");
+        assert_eq!(do_lisp("(read 1 2)"), "E1007");
+        assert_eq!(do_lisp("(read 123)"), "E1032");
+        assert_eq!(do_lisp("(read (open-input-string \")\"))"), "E0003");
     }
     #[test]
     fn read_char() {
-        assert_eq!(do_lisp("(read-char 123)"), "E1007");
+        assert_eq!(do_lisp("(read-char 1 2)"), "E1007");
+        assert_eq!(do_lisp("(read-char 123)"), "E1032");
+        assert_eq!(do_lisp("(peek-char 1 2)"), "E1007");
+        assert_eq!(do_lisp("(peek-char (open-output-string))"), "E1032");
+        assert_eq!(do_lisp("(char-ready? 1 2)"), "E1007");
+        assert_eq!(do_lisp("(char-ready? 1)"), "E1032");
+    }
+    #[test]
+    fn read_line() {
+        assert_eq!(do_lisp("(read-line 1 2)"), "E1007");
+        assert_eq!(do_lisp("(read-line 1)"), "E1032");
+    }
+    #[test]
+    fn read_string() {
+        assert_eq!(do_lisp("(read-string)"), "E1007");
+        assert_eq!(do_lisp("(read-string 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(read-string #t)"), "E1002");
+        assert_eq!(do_lisp("(read-string -1)"), "E1021");
+        assert_eq!(do_lisp("(read-string 1 2)"), "E1032");
+    }
+    #[test]
+    fn write_string() {
+        assert_eq!(do_lisp("(write-string)"), "E1007");
+        assert_eq!(do_lisp("(write-string \"a\" 1 2 3 4)"), "E1007");
+        assert_eq!(do_lisp("(write-string 1)"), "E1015");
+        assert_eq!(do_lisp("(write-string \"a\" 1)"), "E1032");
+        assert_eq!(
+            do_lisp("(write-string \"a\" (open-output-string) #t)"),
+            "E1002"
+        );
+        assert_eq!(
+            do_lisp("(write-string \"a\" (open-output-string) 0 2)"),
+            "E1021"
+        );
+        assert_eq!(
+            do_lisp("(write-string \"a\" (open-output-string) 1 0)"),
+            "E1021"
+        );
+        assert_eq!(do_lisp("(write-char)"), "E1007");
+        assert_eq!(do_lisp("(write-char 1)"), "E1019");
+        assert_eq!(do_lisp("(write-char #\\a 1)"), "E1032");
+    }
+    #[test]
+    fn binary_port() {
+        assert_eq!(do_lisp("(read-u8 1 2)"), "E1007");
+        assert_eq!(do_lisp("(read-u8 (open-input-string \"a\"))"), "E1032");
+        assert_eq!(do_lisp("(peek-u8 1)"), "E1032");
+        assert_eq!(do_lisp("(write-u8)"), "E1007");
+        assert_eq!(do_lisp("(write-u8 #t)"), "E1002");
+        assert_eq!(do_lisp("(write-u8 256)"), "E1021");
+        assert_eq!(do_lisp("(write-u8 1 (open-output-string))"), "E1032");
+    }
+    #[test]
+    fn bytevector_port() {
+        assert_eq!(do_lisp("(read-bytevector)"), "E1007");
+        assert_eq!(do_lisp("(read-bytevector 'a)"), "E1002");
+        assert_eq!(do_lisp("(read-bytevector -1)"), "E1021");
+        assert_eq!(
+            do_lisp("(read-bytevector 1 (open-input-string \"a\"))"),
+            "E1032"
+        );
+        assert_eq!(do_lisp("(read-bytevector!)"), "E1007");
+        assert_eq!(do_lisp("(read-bytevector! 1)"), "E1035");
+        assert_eq!(
+            do_lisp("(read-bytevector! #u8(1) (open-input-bytevector #u8()) 2)"),
+            "E1021"
+        );
+        assert_eq!(do_lisp("(write-bytevector)"), "E1007");
+        assert_eq!(do_lisp("(write-bytevector 1)"), "E1035");
+        assert_eq!(
+            do_lisp("(write-bytevector #u8() (open-output-string))"),
+            "E1032"
+        );
+        assert_eq!(
+            do_lisp("(write-bytevector #u8(1) (open-output-bytevector) 0 2)"),
+            "E1021"
+        );
+        assert_eq!(do_lisp("(open-input-bytevector)"), "E1007");
+        assert_eq!(do_lisp("(open-input-bytevector \"a\")"), "E1035");
+        assert_eq!(do_lisp("(open-output-bytevector 1)"), "E1007");
+        assert_eq!(do_lisp("(get-output-bytevector)"), "E1007");
+        assert_eq!(
+            do_lisp("(get-output-bytevector (open-output-string))"),
+            "E1032"
+        );
+        assert_eq!(
+            do_lisp("(get-output-string (open-output-bytevector))"),
+            "E1032"
+        );
+    }
+    #[test]
+    fn open_file() {
+        assert_eq!(do_lisp("(open-input-file)"), "E1007");
+        assert_eq!(do_lisp("(open-input-file 1)"), "E1015");
+        assert_eq!(do_lisp("(open-input-file \"/etc/test.scm\")"), "E1034");
+        assert_eq!(do_lisp("(open-output-file \"/tmp\")"), "E1034");
+        assert_eq!(do_lisp("(open-binary-input-file)"), "E1007");
+        assert_eq!(do_lisp("(open-binary-output-file 1)"), "E1015");
+        assert_eq!(do_lisp("(call-with-input-file \"/tmp\")"), "E1007");
+        assert_eq!(do_lisp("(call-with-input-file 1 car)"), "E1015");
+        assert_eq!(
+            do_lisp("(call-with-input-file \"/etc/test.scm\" car)"),
+            "E1034"
+        );
+        assert_eq!(do_lisp("(call-with-output-file 1 car)"), "E1015");
+        assert_eq!(do_lisp("(with-output-to-file \"/tmp\")"), "E1007");
+        assert_eq!(do_lisp("(with-output-to-file 1 car)"), "E1015");
+        assert_eq!(
+            do_lisp("(with-input-from-file \"/etc/test.scm\" car)"),
+            "E1034"
+        );
+        assert_eq!(do_lisp("(call-with-port 1)"), "E1007");
+        assert_eq!(do_lisp("(call-with-port 1 car)"), "E1032");
+    }
+    #[test]
+    fn string_port() {
+        assert_eq!(do_lisp("(open-input-string)"), "E1007");
+        assert_eq!(do_lisp("(open-input-string 1)"), "E1015");
+        assert_eq!(do_lisp("(open-output-string 1)"), "E1007");
+        assert_eq!(do_lisp("(get-output-string)"), "E1007");
+        assert_eq!(do_lisp("(get-output-string 1)"), "E1032");
+        assert_eq!(
+            do_lisp("(get-output-string (open-input-string \"a\"))"),
+            "E1032"
+        );
+        assert_eq!(do_lisp("(with-output-to-string)"), "E1007");
+        assert_eq!(do_lisp("(with-output-to-string 1)"), "E1006");
+        assert_eq!(
+            do_lisp("(with-output-to-string (lambda () (display a)))"),
+            "E1008"
+        );
+    }
+    #[test]
+    fn current_port() {
+        assert_eq!(do_lisp("(current-input-port 1)"), "E1007");
+        assert_eq!(do_lisp("(current-output-port 1)"), "E1007");
+    }
+    #[test]
+    fn close_port() {
+        assert_eq!(do_lisp("(close-port)"), "E1007");
+        assert_eq!(do_lisp("(close-port 1)"), "E1032");
+        assert_eq!(do_lisp("(close-input-port (open-output-string))"), "E1032");
+        assert_eq!(
+            do_lisp("(close-output-port (open-input-string \"a\"))"),
+            "E1032"
+        );
+        assert_eq!(do_lisp("(flush-output-port 1 2)"), "E1007");
+        assert_eq!(do_lisp("(flush-output-port (current-input-port))"), "E1032");
+    }
+    #[test]
+    fn is_port() {
+        assert_eq!(do_lisp("(port?)"), "E1007");
+        assert_eq!(do_lisp("(input-port? 1 2)"), "E1007");
+        assert_eq!(do_lisp("(output-port?)"), "E1007");
+        assert_eq!(do_lisp("(textual-port?)"), "E1007");
+        assert_eq!(do_lisp("(binary-port?)"), "E1007");
+        assert_eq!(do_lisp("(input-port-open?)"), "E1007");
+        assert_eq!(do_lisp("(output-port-open?)"), "E1007");
+        assert_eq!(do_lisp("(port? a)"), "E1008");
+    }
+    #[test]
+    fn eof_object() {
+        assert_eq!(do_lisp("(eof-object 1)"), "E1007");
+        assert_eq!(do_lisp("(eof-object?)"), "E1007");
+        assert_eq!(do_lisp("(eof-object? a)"), "E1008");
     }
 }
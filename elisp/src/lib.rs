@@ -9,19 +9,35 @@
 extern crate lazy_static;
 pub mod boolean;
 pub mod buildin;
+pub mod bytevector;
 pub mod chars;
+pub mod compile;
 pub mod env;
+pub mod exception;
+pub mod format;
 pub mod hashtable;
 pub mod io;
 pub mod lisp;
 pub mod list;
+pub mod machine;
+pub mod macros;
 pub mod math;
 pub mod number;
 pub mod operation;
+pub mod pair;
+pub mod parameter;
+pub mod port;
+pub mod printer;
+pub mod reader;
+pub mod record;
+pub mod source;
+pub mod srfi1;
 pub mod strings;
+pub mod symbol;
 pub mod syntax;
 pub mod unix;
 pub mod util;
+pub mod vm;
 
 #[cfg(not(feature = "thread"))]
 pub mod draw;
@@ -68,6 +84,52 @@ mod tests {
         assert_eq!(do_lisp("+"), "<+> BuildIn Function");
     }
     #[test]
+    fn atom_lexical() {
+        assert_eq!(do_lisp("#true"), "#t");
+        assert_eq!(do_lisp("#false"), "#f");
+        assert_eq!(do_lisp("#\\x41"), "#\\A");
+        assert_eq!(do_lisp("#\\x"), "#\\x");
+        assert_eq!(do_lisp("#\\nul"), "#\\null");
+        assert_eq!(do_lisp("#\\null"), "#\\null");
+        assert_eq!(do_lisp("#\\alarm"), "#\\alarm");
+        assert_eq!(do_lisp("#\\backspace"), "#\\backspace");
+        assert_eq!(do_lisp("#\\delete"), "#\\delete");
+        assert_eq!(do_lisp("#\\escape"), "#\\escape");
+        assert_eq!(do_lisp("(char->integer #\\x3042)"), "12354");
+        assert_eq!(do_lisp("\"a\\tb\\nc\""), "\"a\\tb\\nc\"");
+        assert_eq!(do_lisp("(string-length \"\\a\\b\\r\\\"\\\\\\|\")"), "6");
+        assert_eq!(do_lisp("\"\\x41;\\x3042;\""), "\"Aあ\"");
+        assert_eq!(do_lisp("\"abc \\  \n   def\""), "\"abc def\"");
+        assert_eq!(do_lisp("'|a b|"), "|a b|");
+        assert_eq!(do_lisp("(symbol->string '|a\\x41;\\|b|)"), "\"aA|b\"");
+        assert_eq!(do_lisp("(eq? '|abc| 'abc)"), "#t");
+        assert_eq!(do_lisp("(|car| '(1 2))"), "1");
+        assert_eq!(do_lisp("(+ 1 2) ; comment"), "3");
+        assert_eq!(do_lisp("#| a #| b |# c |# 10"), "10");
+        assert_eq!(do_lisp("(list 1 #;(+ 1 2) 3)"), "(1 3)");
+        assert_eq!(do_lisp("(list 1 #; #;a b 3)"), "(1 3)");
+        assert_eq!(do_lisp("'(a #;#0=(b) c)"), "(a c)");
+    }
+    #[test]
+    fn atom_number() {
+        assert_eq!(do_lisp("1.0"), "1.0");
+        assert_eq!(do_lisp("1e10"), "10000000000.0");
+        assert_eq!(do_lisp("#x1F"), "31");
+        assert_eq!(do_lisp("#b101"), "5");
+        assert_eq!(do_lisp("#o-17"), "-15");
+        assert_eq!(do_lisp("#e1.5"), "3/2");
+        assert_eq!(do_lisp("#i1/4"), "0.25");
+        assert_eq!(do_lisp("#x#e10"), "16");
+        assert_eq!(do_lisp("+inf.0"), "+inf.0");
+        assert_eq!(do_lisp("-inf.0"), "-inf.0");
+        assert_eq!(do_lisp("+nan.0"), "+nan.0");
+        assert_eq!(do_lisp("1+2i"), "1.0+2.0i");
+        assert_eq!(do_lisp("-i"), "-1.0i");
+        assert_eq!(do_lisp("1@0"), "1.0");
+        assert_eq!(do_lisp("'inf"), "inf");
+        assert_eq!(do_lisp("'1+"), "1+");
+    }
+    #[test]
     fn atom_utf8() {
         assert_eq!(do_lisp("#\\山"), "#\\山");
         assert_eq!(do_lisp("\"山田太郎\""), "\"山田太郎\"");
@@ -78,6 +140,20 @@ mod tests {
         assert_eq!(do_lisp_env("山", &env), "200");
     }
     #[test]
+    fn datum_label() {
+        assert_eq!(do_lisp("'#0=(a b #0#)"), "#0=(a b #0#)");
+        assert_eq!(do_lisp("'(#1=(x) #1#)"), "((x) (x))");
+        assert_eq!(do_lisp("'#0=10"), "10");
+        assert_eq!(
+            do_lisp("(let ((x '#0=(a #0#))) (eq? x (car (cdr x))))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(let ((x '(#1=(x) #1#))) (eq? (car x) (car (cdr x))))"),
+            "#t"
+        );
+    }
+    #[test]
     fn tail_recurcieve_1() {
         // stack overflow check
         assert_eq!(
@@ -165,6 +241,51 @@ mod tests {
         assert_eq!(do_lisp_env("(let loop ((i 0))(define c 10))", &env), "c");
         assert_eq!(do_lisp_env("(let loop ((i 0))(begin 1000))", &env), "1000");
     }
+    #[test]
+    fn tail_recurcieve_5() {
+        // mutual recursion
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (even? n) (if (= n 0) #t (odd? (- n 1))))", &env);
+        do_lisp_env("(define (odd? n) (if (= n 0) #f (even? (- n 1))))", &env);
+        assert_eq!(do_lisp_env("(even? 10000)", &env), "#t");
+        assert_eq!(do_lisp_env("(odd? 10001)", &env), "#t");
+
+        // and, or
+        do_lisp_env("(define (ev? n) (or (= n 0) (od? (- n 1))))", &env);
+        do_lisp_env("(define (od? n) (and (not (= n 0)) (ev? (- n 1))))", &env);
+        assert_eq!(do_lisp_env("(ev? 10000)", &env), "#t");
+        assert_eq!(do_lisp_env("(od? 10000)", &env), "#f");
+    }
+    #[test]
+    fn tail_recurcieve_6() {
+        let env = lisp::Environment::new();
+        // lambda held in a variable
+        do_lisp_env(
+            "(define f (lambda (g n) (if (= n 0) 0 (g g (- n 1)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f f 10000)", &env), "0");
+
+        // let, case, apply
+        do_lisp_env(
+            "(define (a n) (let ((m (- n 1))) (case (modulo n 3) ((0) (b m)) ((1) (apply c (list m))) (else (if (= n 0) 0 (a m))))))",
+            &env,
+        );
+        do_lisp_env("(define (b n) (if (< n 0) 'b (a n)))", &env);
+        do_lisp_env("(define (c n) (if (< n 0) 'c (a n)))", &env);
+        assert_eq!(do_lisp_env("(a 10000)", &env), "b");
+
+        // state machine
+        do_lisp_env(
+            "(define (state-a n) (cond ((= n 0) 'a) (else (state-b (- n 1)))))",
+            &env,
+        );
+        do_lisp_env(
+            "(define (state-b n) (cond ((= n 0) 'b) (else (let loop ((i 0)) (if (< i 2) (loop (+ i 1)) (state-a (- n 1)))))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(state-a 10000)", &env), "a");
+    }
 }
 #[cfg(test)]
 mod error_tests {
@@ -183,6 +304,21 @@ mod error_tests {
         assert_eq!(do_lisp("\"a"), "E0004");
         assert_eq!(do_lisp("a\""), "E0004");
         assert_eq!(do_lisp("3/0"), "E1013");
+        assert_eq!(do_lisp("#e+inf.0"), "E1021");
+        assert_eq!(do_lisp("#e1+2i"), "E1031");
+        assert_eq!(do_lisp("#\\foo"), "E0004");
+        assert_eq!(do_lisp("#\\x110000"), "E0004");
+        assert_eq!(do_lisp("\"\\q\""), "E0004");
+        assert_eq!(do_lisp("\"\\x41\""), "E0004");
+        assert_eq!(do_lisp("\"a\\ b\""), "E0004");
+        assert_eq!(do_lisp("'|abc"), "E0004");
+        assert_eq!(do_lisp("#| abc"), "E0001");
+    }
+    #[test]
+    fn datum_label() {
+        assert_eq!(do_lisp("'#0#"), "E0005");
+        assert_eq!(do_lisp("'(#0=(a) #1#)"), "E0005");
+        assert_eq!(do_lisp("'#0="), "E0001");
     }
     #[test]
     fn atom_utf8() {
@@ -7,35 +7,63 @@
 use std::cmp::Ord;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Write;
 use std::string::ToString;
+use std::sync::Arc;
 use std::vec::Vec;
 
+use num_bigint::BigInt;
+
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 
 #[cfg(feature = "signal")]
 use super::unix::signal::{catch_sig_intr_status, clear_sig_intr_status, init_sig_intr};
 
-use crate::number::Number;
+use crate::compile;
+use crate::exception::Condition;
+use crate::machine;
+use crate::machine::Continuation;
+use crate::macros::SyntaxRules;
 use crate::number::Rat;
-use crate::syntax::Continuation;
+use crate::number::{complex_to_string, float_to_string, parse_number, Number};
+use crate::pair;
+use crate::parameter::Parameter;
+use crate::printer;
+use crate::printer::{char_name, escape_string, symbol_name, Style};
+use crate::reader::Reader;
+use crate::record::{Record, RecordType};
+use crate::source::{Call, Position};
+use crate::symbol::Symbol;
+use crate::syntax::quoted;
 
 #[cfg(feature = "thread")]
-pub use crate::env_thread::{ExtFunctionRc, FunctionRc, HashTableRc, ListRc, StringRc, TreeMapRc};
+use crate::env_thread::CodeCell;
+#[cfg(feature = "thread")]
+pub use crate::env_thread::{
+    BytesRc, CodeRc, ExtFunctionRc, FunctionRc, HashTableRc, ListRc, PairRc, PortRc, StringRc,
+    TreeMapRc,
+};
 #[cfg(feature = "thread")]
 pub type Environment = crate::env_thread::Environment;
 
 #[cfg(not(feature = "thread"))]
-pub use crate::env_single::{ExtFunctionRc, FunctionRc, HashTableRc, ListRc, StringRc, TreeMapRc};
+use crate::env_single::CodeCell;
+#[cfg(not(feature = "thread"))]
+pub use crate::env_single::{
+    BytesRc, CodeRc, ExtFunctionRc, FunctionRc, HashTableRc, ListRc, PairRc, PortRc, StringRc,
+    TreeMapRc,
+};
 #[cfg(not(feature = "thread"))]
 pub type Environment = crate::env_single::Environment;
 
 use crate::get_ptr;
-use crate::mut_obj;
 use crate::reference_obj;
 //========================================================================
 #[derive(Clone, Debug)]
@@ -44,6 +72,7 @@ pub enum ErrCode {
     E0002,
     E0003,
     E0004,
+    E0005,
     E1001,
     E1002,
     E1003,
@@ -68,6 +97,17 @@ pub enum ErrCode {
     E1022,
     E1023,
     E1024,
+    E1025,
+    E1026,
+    E1027,
+    E1028,
+    E1029,
+    E1030,
+    E1031,
+    E1032,
+    E1033,
+    E1034,
+    E1035,
     E9000,
     E9002,
     E9999,
@@ -80,6 +120,7 @@ impl ErrCode {
             ErrCode::E0002 => "E0002",
             ErrCode::E0003 => "E0003",
             ErrCode::E0004 => "E0004",
+            ErrCode::E0005 => "E0005",
             ErrCode::E1001 => "E1001",
             ErrCode::E1002 => "E1002",
             ErrCode::E1003 => "E1003",
@@ -104,12 +145,26 @@ impl ErrCode {
             ErrCode::E1022 => "E1022",
             ErrCode::E1023 => "E1023",
             ErrCode::E1024 => "E1024",
+            ErrCode::E1025 => "E1025",
+            ErrCode::E1026 => "E1026",
+            ErrCode::E1027 => "E1027",
+            ErrCode::E1028 => "E1028",
+            ErrCode::E1029 => "E1029",
+            ErrCode::E1030 => "E1030",
+            ErrCode::E1031 => "E1031",
+            ErrCode::E1032 => "E1032",
+            ErrCode::E1033 => "E1033",
+            ErrCode::E1034 => "E1034",
+            ErrCode::E1035 => "E1035",
             ErrCode::E9000 => "E9000",
             ErrCode::E9002 => "E9002",
             ErrCode::E9999 => "E9999",
             ErrCode::Cont => "CONT",
         }
     }
+    pub fn get_msg(&self) -> &'static str {
+        ERRMSG_TBL.get(self.as_str()).unwrap()
+    }
 }
 impl PartialEq<ErrCode> for ErrCode {
     fn eq(&self, other: &Self) -> bool {
@@ -123,6 +178,7 @@ lazy_static! {
         e.insert(ErrCode::E0002.as_str(), "Unexpected ')' while reading");
         e.insert(ErrCode::E0003.as_str(), "Extra close parenthesis `)'");
         e.insert(ErrCode::E0004.as_str(), "Charactor syntax error");
+        e.insert(ErrCode::E0005.as_str(), "Undefined Datum Label");
         e.insert(ErrCode::E1001.as_str(), "Not Boolean");
         e.insert(ErrCode::E1002.as_str(), "Not Integer");
         e.insert(ErrCode::E1003.as_str(), "Not Number");
@@ -147,6 +203,17 @@ lazy_static! {
         e.insert(ErrCode::E1022.as_str(), "Not Vector");
         e.insert(ErrCode::E1023.as_str(), "Not HashTable");
         e.insert(ErrCode::E1024.as_str(), "Not TreeMap");
+        e.insert(ErrCode::E1025.as_str(), "Not Syntax Rules");
+        e.insert(ErrCode::E1026.as_str(), "Error");
+        e.insert(ErrCode::E1027.as_str(), "Uncaught Exception");
+        e.insert(ErrCode::E1028.as_str(), "Not Error Object");
+        e.insert(ErrCode::E1029.as_str(), "Not Environment");
+        e.insert(ErrCode::E1030.as_str(), "Not Record");
+        e.insert(ErrCode::E1031.as_str(), "Not Real Number");
+        e.insert(ErrCode::E1032.as_str(), "Not Port");
+        e.insert(ErrCode::E1033.as_str(), "Closed Port");
+        e.insert(ErrCode::E1034.as_str(), "Can Not Open File");
+        e.insert(ErrCode::E1035.as_str(), "Not ByteVector");
         e.insert(ErrCode::E9000.as_str(), "Forced stop");
         e.insert(
             ErrCode::E9002.as_str(),
@@ -157,33 +224,58 @@ lazy_static! {
         e
     };
 }
+#[derive(Clone)]
 pub struct Error {
     pub code: ErrCode,
     pub line: u32,
     pub file: &'static str,
     pub value: Option<String>,
     pub exp: Option<Expression>,
+    pub pos: Option<Box<Position>>,
+    pub trace: Vec<Call>,
+    // the procedures of the running machine have been added to the trace.
+    pub traced: bool,
 }
 impl Error {
     pub fn get_code(&self) -> String {
         String::from(self.code.as_str())
     }
+    // The lisp location is shown if it's known, otherwise the rust location.
     pub fn get_msg(&self) -> String {
+        let at = match &self.pos {
+            Some(p) => p.to_string(),
+            None => format!("{}:{}", self.file, self.line),
+        };
         if let Some(s) = &self.value {
             format!(
-                "{}: {} ({}:{})",
+                "{}: {} ({})",
                 ERRMSG_TBL.get(self.code.as_str()).unwrap(),
                 s,
-                self.file,
-                self.line
+                at
             )
         } else {
-            format!(
-                "{} ({}:{})",
-                ERRMSG_TBL.get(self.code.as_str()).unwrap(),
-                self.file,
-                self.line
-            )
+            format!("{} ({})", ERRMSG_TBL.get(self.code.as_str()).unwrap(), at)
+        }
+    }
+    // The lisp procedures which were active, the innermost first.
+    pub fn get_trace(&self) -> String {
+        self.trace
+            .iter()
+            .map(|c| format!("  {}", c))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+    pub(crate) fn with_pos(mut self, pos: &Option<Position>) -> Self {
+        if self.pos.is_none() {
+            self.pos = pos.clone().map(Box::new);
+        }
+        self
+    }
+    pub fn get_report(&self) -> String {
+        if self.trace.is_empty() {
+            self.get_msg()
+        } else {
+            format!("{}\n{}", self.get_msg(), self.get_trace())
         }
     }
 }
@@ -196,6 +288,9 @@ macro_rules! create_error {
             file: file!(),
             value: None,
             exp: None,
+            pos: None,
+            trace: Vec::new(),
+            traced: false,
         }
     };
 }
@@ -208,25 +303,31 @@ macro_rules! create_error_value {
             file: file!(),
             value: Some($v.to_string()),
             exp: None,
+            pos: None,
+            trace: Vec::new(),
+            traced: false,
         }
     };
 }
 #[macro_export]
 macro_rules! create_continuation {
-    ($e: expr, $n:expr) => {
+    ($c: expr, $v:expr) => {
         Error {
             code: ErrCode::Cont,
             line: line!(),
             file: file!(),
-            value: Some($n.to_string()),
-            exp: Some($e),
+            value: None,
+            exp: Some(Environment::create_form(vec![$c, $v])),
+            pos: None,
+            trace: Vec::new(),
+            traced: false,
         }
     };
 }
 #[macro_export]
 macro_rules! print_error {
     ($e: expr) => {
-        println!("{}", $e.get_msg())
+        println!("{}", $e.get_report())
     };
 }
 //========================================================================
@@ -242,25 +343,36 @@ pub type Int = i128;
 #[derive(Clone)]
 pub enum Expression {
     Integer(Int),
+    BigInt(Box<BigInt>),
     Float(f64),
     Char(char),
     Boolean(bool),
     List(ListRc),
-    Pair(Box<Expression>, Box<Expression>),
-    Symbol(String),
+    Pair(PairRc),
+    Symbol(Symbol),
     String(StringRc),
     Function(FunctionRc),
-    BuildInFunction(String, BasicBuiltIn),
+    BuildInFunction(&'static str, BasicBuiltIn),
     BuildInFunctionExt(ExtFunctionRc),
-    TailLoop(),
     Nil(),
-    TailRecursion(FunctionRc),
+    Null(),
     Promise(Box<Expression>, Environment),
-    Rational(Rat),
+    Rational(Box<Rat>),
+    Complex(f64, f64),
     Continuation(Box<Continuation>),
+    Condition(Box<Condition>),
     Vector(ListRc),
     HashTable(HashTableRc),
     TreeMap(TreeMapRc),
+    Syntax(Box<SyntaxRules>),
+    Values(ListRc),
+    Environment(Environment),
+    Record(Box<Record>),
+    RecordType(Arc<RecordType>),
+    Parameter(Box<Parameter>),
+    Port(PortRc),
+    Eof(),
+    ByteVector(BytesRc),
 }
 impl Expression {
     pub fn is_hashtable(exp: &Expression) -> bool {
@@ -269,14 +381,37 @@ impl Expression {
     pub fn is_tree_map(exp: &Expression) -> bool {
         matches!(exp, Expression::TreeMap(_))
     }
+    pub fn is_environment(exp: &Expression) -> bool {
+        matches!(exp, Expression::Environment(_))
+    }
+    pub fn is_record(exp: &Expression) -> bool {
+        matches!(exp, Expression::Record(_))
+    }
+    pub fn is_port(exp: &Expression) -> bool {
+        matches!(exp, Expression::Port(_))
+    }
+    pub fn is_eof(exp: &Expression) -> bool {
+        matches!(exp, Expression::Eof())
+    }
     pub fn is_vector(exp: &Expression) -> bool {
         matches!(exp, Expression::Vector(_))
     }
+    pub fn is_bytevector(exp: &Expression) -> bool {
+        matches!(exp, Expression::ByteVector(_))
+    }
+    // The proper list, ex. (1 2), ()
     pub fn is_list(exp: &Expression) -> bool {
-        matches!(exp, Expression::List(_))
+        pair::is_list(exp)
     }
     pub fn is_pair(exp: &Expression) -> bool {
-        matches!(exp, Expression::Pair(_, _))
+        matches!(exp, Expression::Pair(_))
+    }
+    pub fn is_null(exp: &Expression) -> bool {
+        matches!(exp, Expression::Null())
+    }
+    // The list of the source code, ex. (+ 1 2)
+    pub fn is_form(exp: &Expression) -> bool {
+        matches!(exp, Expression::List(_))
     }
     pub fn is_char(exp: &Expression) -> bool {
         matches!(exp, Expression::Char(_))
@@ -290,15 +425,20 @@ impl Expression {
             Expression::Function(_)
                 | Expression::BuildInFunction(_, _)
                 | Expression::BuildInFunctionExt(_)
+                | Expression::Parameter(_)
         )
     }
     pub fn is_integer(exp: &Expression) -> bool {
-        matches!(exp, Expression::Integer(_))
+        matches!(exp, Expression::Integer(_) | Expression::BigInt(_))
     }
     pub fn is_number(exp: &Expression) -> bool {
         matches!(
             exp,
-            Expression::Integer(_) | Expression::Float(_) | Expression::Rational(_)
+            Expression::Integer(_)
+                | Expression::BigInt(_)
+                | Expression::Float(_)
+                | Expression::Rational(_)
+                | Expression::Complex(_, _)
         )
     }
     pub fn is_symbol(exp: &Expression) -> bool {
@@ -314,42 +454,23 @@ impl Expression {
         match x {
             Expression::Float(v) => Ok(Number::Float(*v)),
             Expression::Integer(v) => Ok(Number::Integer(*v)),
-            Expression::Rational(v) => Ok(Number::Rational(*v)),
+            Expression::BigInt(v) => Ok(Number::BigInt(v.as_ref().clone())),
+            Expression::Rational(v) => Ok(Number::Rational(v.as_ref().clone())),
+            Expression::Complex(a, b) => Ok(Number::Complex(*a, *b)),
             e => Err(create_error_value!(ErrCode::E1003, e)),
         }
     }
-    fn list_string(exp: &[Expression]) -> String {
-        let mut s = String::from("(");
-
-        for (c, e) in exp.iter().enumerate() {
-            match e {
-                Expression::List(l) | Expression::Vector(l) => {
-                    if let Expression::Vector(_) = e {
-                        s.push('#');
-                    }
-                    let l = &*(reference_obj!(l));
-                    s.push_str(Expression::list_string(&l[..]).as_str());
-                }
-                _ => {
-                    s.push_str(e.to_string().as_str());
-                }
-            }
-            if c != exp.len() - 1 {
-                s.push(' ');
-            }
-        }
-        s.push(')');
-        s
-    }
-    fn vector_string(exp: &[Expression]) -> String {
-        format!("#{}", Expression::list_string(exp))
-    }
     fn eq_value(&self, other: &Self) -> bool {
         if let (Expression::Integer(x), Expression::Rational(y)) = (self, other) {
-            return Number::Integer(*x) == Number::Rational(*y);
+            return Number::Integer(*x) == Number::Rational(y.as_ref().clone());
         }
         if let (Expression::Rational(x), Expression::Integer(y)) = (self, other) {
-            return Number::Rational(*x) == Number::Integer(*y);
+            return Number::Rational(x.as_ref().clone()) == Number::Integer(*y);
+        }
+        if let (Expression::BigInt(a), Expression::BigInt(b)) = (self, other) {
+            if a == b {
+                return true;
+            }
         }
 
         if let (Expression::Integer(a), Expression::Integer(b)) = (self, other) {
@@ -367,6 +488,11 @@ impl Expression {
                 return true;
             }
         }
+        if let (Expression::Complex(a, b), Expression::Complex(c, d)) = (self, other) {
+            if a == c && b == d {
+                return true;
+            }
+        }
         if let (Expression::Char(a), Expression::Char(b)) = (self, other) {
             if a == b {
                 return true;
@@ -377,6 +503,12 @@ impl Expression {
                 return true;
             }
         }
+        if let (Expression::Eof(), Expression::Eof()) = (self, other) {
+            return true;
+        }
+        if let (Expression::Null(), Expression::Null()) = (self, other) {
+            return true;
+        }
         if let (Expression::Symbol(a), Expression::Symbol(b)) = (self, other) {
             if a == b {
                 return true;
@@ -393,8 +525,131 @@ impl Expression {
                 return true;
             }
         }
+        if let (Expression::Pair(a), Expression::Pair(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::Record(a), Expression::Record(b)) = (self, other) {
+            if a.ptr_eq(b) {
+                return true;
+            }
+        }
+        if let (Expression::Port(a), Expression::Port(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::Parameter(a), Expression::Parameter(b)) = (self, other) {
+            if a.ptr_eq(b) {
+                return true;
+            }
+        }
+        if let (Expression::ByteVector(a), Expression::ByteVector(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::HashTable(a), Expression::HashTable(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::TreeMap(a), Expression::TreeMap(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
         false
     }
+    pub fn equal(&self, other: &Self) -> bool {
+        Expression::equal_iter(self, other, &mut HashSet::new())
+    }
+    // The pair of the containers in comparison is assumed to be equal,
+    // so that the cyclic structures are terminated.
+    fn equal_iter(&self, other: &Self, visit: &mut HashSet<(usize, usize)>) -> bool {
+        fn equal_seq(
+            a: &[Expression],
+            b: &[Expression],
+            visit: &mut HashSet<(usize, usize)>,
+        ) -> bool {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(x, y)| Expression::equal_iter(x, y, visit))
+        }
+        match (self, other) {
+            (Expression::List(a), Expression::List(b))
+            | (Expression::Vector(a), Expression::Vector(b)) => {
+                if get_ptr!(a) == get_ptr!(b)
+                    || !visit.insert((get_ptr!(a) as usize, get_ptr!(b) as usize))
+                {
+                    return true;
+                }
+                let (a, b) = (&*reference_obj!(a), &*reference_obj!(b));
+                equal_seq(a, b, visit)
+            }
+            (Expression::Record(r1), Expression::Record(r2)) => {
+                let (a, b) = (r1.values(), r2.values());
+                if get_ptr!(a) == get_ptr!(b)
+                    || !visit.insert((get_ptr!(a) as usize, get_ptr!(b) as usize))
+                {
+                    return true;
+                }
+                let (a, b) = (&*reference_obj!(a), &*reference_obj!(b));
+                r1.is_same_type(r2) && equal_seq(a, b, visit)
+            }
+            // the cdr is followed in the loop, for the long list.
+            (Expression::Pair(_), Expression::Pair(_)) => {
+                let (mut x, mut y) = (self.clone(), other.clone());
+                while let (Expression::Pair(a), Expression::Pair(b)) = (&x, &y) {
+                    if get_ptr!(a) == get_ptr!(b)
+                        || !visit.insert((get_ptr!(a) as usize, get_ptr!(b) as usize))
+                    {
+                        return true;
+                    }
+                    let (a, b) = (reference_obj!(a), reference_obj!(b));
+                    if !Expression::equal_iter(&a.car, &b.car, visit) {
+                        return false;
+                    }
+                    let next = (a.cdr.clone(), b.cdr.clone());
+                    drop((a, b));
+                    (x, y) = next;
+                }
+                Expression::equal_iter(&x, &y, visit)
+            }
+            (Expression::String(a), Expression::String(b)) => a == b,
+            (Expression::ByteVector(a), Expression::ByteVector(b)) => {
+                *reference_obj!(a) == *reference_obj!(b)
+            }
+            (Expression::HashTable(a), Expression::HashTable(b)) => {
+                if get_ptr!(a) == get_ptr!(b)
+                    || !visit.insert((get_ptr!(a) as usize, get_ptr!(b) as usize))
+                {
+                    return true;
+                }
+                let (a, b) = (&*reference_obj!(a), &*reference_obj!(b));
+                a.len() == b.len()
+                    && a.iter().all(|(k, x)| match b.get(k) {
+                        Ok(Some(y)) => Expression::equal_iter(x, &y, visit),
+                        _ => false,
+                    })
+            }
+            (Expression::TreeMap(a), Expression::TreeMap(b)) => {
+                if get_ptr!(a) == get_ptr!(b)
+                    || !visit.insert((get_ptr!(a) as usize, get_ptr!(b) as usize))
+                {
+                    return true;
+                }
+                let (a, b) = (&*reference_obj!(a), &*reference_obj!(b));
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|((k1, x), (k2, y))| k1 == k2 && Expression::equal_iter(x, y, visit))
+            }
+            _ => Expression::eqv(self, other),
+        }
+    }
 }
 // ToString -> Display
 // https://rust-lang.github.io/rust-clippy/master/index.html#/to_string_trait_impl
@@ -402,25 +657,9 @@ impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Expression::Integer(v) => write!(f, "{}", v),
-            Expression::Float(v) => write!(f, "{}", v),
-            Expression::Char(v) => {
-                if v.is_control() || v.is_whitespace() {
-                    let c: u8 = *v as u8;
-                    if c == SPACE.0 {
-                        write!(f, "{}", SPACE.1)
-                    } else if c == TAB.0 {
-                        write!(f, "{}", TAB.1)
-                    } else if c == NEWLINE.0 {
-                        write!(f, "{}", NEWLINE.1)
-                    } else if c == CARRIAGERETRUN.0 {
-                        write!(f, "{}", CARRIAGERETRUN.1)
-                    } else {
-                        write!(f, "#\\non-printable-char")
-                    }
-                } else {
-                    write!(f, "#\\{}", v)
-                }
-            }
+            Expression::BigInt(v) => write!(f, "{}", v),
+            Expression::Float(v) => write!(f, "{}", float_to_string(*v)),
+            Expression::Char(v) => write!(f, "{}", char_name(*v)),
             Expression::Boolean(v) => {
                 if *v {
                     write!(f, "{}", TRUE)
@@ -428,53 +667,60 @@ impl fmt::Display for Expression {
                     write!(f, "{}", FALSE)
                 }
             }
-            Expression::Symbol(v) => write!(f, "{}", v),
-            Expression::String(v) => write!(f, "\"{}\"", v),
-            Expression::List(v) => {
-                let l = &*(reference_obj!(v));
-                write!(f, "{}", Expression::list_string(&l[..]))
-            }
-            Expression::Vector(v) => {
-                let l = &*(reference_obj!(v));
-                write!(f, "{}", Expression::vector_string(&l[..]))
-            }
+            Expression::Symbol(v) => write!(f, "{}", symbol_name(v)),
+            Expression::String(v) => write!(f, "{}", escape_string(v)),
+            Expression::List(_)
+            | Expression::Vector(_)
+            | Expression::Pair(_)
+            | Expression::Record(_) => write!(f, "{}", printer::to_string(self, Style::Write)),
             Expression::HashTable(_) => write!(f, "HashTable"),
             Expression::TreeMap(_) => write!(f, "TreeMap"),
-            Expression::Pair(car, cdr) => write!(f, "({} . {})", car, cdr),
             Expression::Function(_) => write!(f, "Function"),
             Expression::BuildInFunction(s, _) => write!(f, "<{}> BuildIn Function", s),
             Expression::BuildInFunctionExt(_) => write!(f, "BuildIn Function Ext"),
             Expression::Nil() => write!(f, "nil"),
-            Expression::TailLoop() => write!(f, "tail loop"),
-            Expression::TailRecursion(_) => write!(f, "Tail Recursion"),
+            Expression::Null() => write!(f, "()"),
             Expression::Promise(_, _) => write!(f, "Promise"),
             Expression::Rational(v) => write!(f, "{}", v),
+            Expression::Complex(a, b) => write!(f, "{}", complex_to_string(*a, *b)),
             Expression::Continuation(_) => write!(f, "Continuation"),
+            Expression::Condition(c) => write!(f, "{}", c),
+            Expression::Syntax(_) => write!(f, "Syntax"),
+            Expression::Environment(_) => write!(f, "Environment"),
+            Expression::RecordType(t) => write!(f, "{}", t),
+            Expression::Parameter(p) => write!(f, "{}", p),
+            Expression::Port(p) => write!(f, "{}", reference_obj!(p)),
+            Expression::Eof() => write!(f, "#<eof>"),
+            Expression::ByteVector(v) => {
+                let v: Vec<String> = reference_obj!(v).iter().map(|b| b.to_string()).collect();
+                write!(f, "#u8({})", v.join(" "))
+            }
+            Expression::Values(_) => write!(f, "{}", printer::to_string(self, Style::Write)),
         }
     }
 }
 impl Ord for Expression {
-    // Support number, string, char
+    // Support number, string, char, symbol, they are ordered in the kind,
+    // and the different kinds are ordered in this order, ex. 1 < #\a < "a" < a
     fn cmp(&self, other: &Self) -> Ordering {
-        match Expression::to_number(self) {
-            Ok(m) => match Expression::to_number(other) {
-                Ok(n) => m.cmp(&n),
-                Err(_) => Ordering::Less,
-            },
-            _ => match &self {
-                Expression::String(m) => match &other {
-                    Expression::String(n) => m.cmp(n),
-                    _ => Ordering::Less,
-                },
-                Expression::Char(m) => match &other {
-                    Expression::Char(n) => m.cmp(n),
-                    _ => Ordering::Less,
-                },
-                Expression::Symbol(m) => match &other {
-                    Expression::Symbol(n) => m.cmp(n),
-                    _ => Ordering::Less,
-                },
-                _ => Ordering::Less,
+        fn rank(e: &Expression) -> u8 {
+            match e {
+                Expression::Boolean(_) => 0,
+                e if Expression::is_number(e) => 1,
+                Expression::Char(_) => 2,
+                Expression::String(_) => 3,
+                Expression::Symbol(_) => 4,
+                _ => 5,
+            }
+        }
+        match (self, other) {
+            (Expression::Boolean(m), Expression::Boolean(n)) => m.cmp(n),
+            (Expression::Char(m), Expression::Char(n)) => m.cmp(n),
+            (Expression::String(m), Expression::String(n)) => m.cmp(n),
+            (Expression::Symbol(m), Expression::Symbol(n)) => m.cmp(n),
+            _ => match (Expression::to_number(self), Expression::to_number(other)) {
+                (Ok(m), Ok(n)) => m.cmp(&n),
+                _ => rank(self).cmp(&rank(other)),
             },
         }
     }
@@ -504,30 +750,306 @@ impl PartialEq for Expression {
                 return true;
             }
         }
+        if let (Expression::Pair(a), Expression::Pair(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::Record(a), Expression::Record(b)) = (self, other) {
+            if a.ptr_eq(b) {
+                return true;
+            }
+        }
+        if let (Expression::Port(a), Expression::Port(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::ByteVector(a), Expression::ByteVector(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::HashTable(a), Expression::HashTable(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
+        if let (Expression::TreeMap(a), Expression::TreeMap(b)) = (self, other) {
+            if get_ptr!(a) == get_ptr!(b) {
+                return true;
+            }
+        }
         false
     }
 }
 impl Eq for Expression {}
+// The hash is consistent with equal?(and so eqv?, eq?),
+// the containers are hashed up to the depth, so that the cyclic structures are terminated.
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_iter(state, 4);
+    }
+}
+impl Expression {
+    fn hash_iter<H: Hasher>(&self, state: &mut H, depth: usize) {
+        fn hash_seq<H: Hasher>(l: &[Expression], state: &mut H, depth: usize) {
+            l.len().hash(state);
+            if depth > 0 {
+                for e in l.iter() {
+                    e.hash_iter(state, depth - 1);
+                }
+            }
+        }
+        match self {
+            Expression::Integer(v) => v.hash(state),
+            Expression::BigInt(v) => v.hash(state),
+            // ex. (eqv? 8/2 4) => #t
+            Expression::Rational(v) => match Int::try_from(&v.numer) {
+                Ok(i) if v.denom == BigInt::from(1) => i.hash(state),
+                _ => {
+                    v.numer.hash(state);
+                    v.denom.hash(state);
+                }
+            },
+            // ex. (eqv? 0.0 -0.0) => #t
+            Expression::Float(v) if *v == 0.0 => 0.0_f64.to_bits().hash(state),
+            Expression::Float(v) => v.to_bits().hash(state),
+            Expression::Complex(a, b) => {
+                a.to_bits().hash(state);
+                b.to_bits().hash(state);
+            }
+            Expression::Char(v) => v.hash(state),
+            Expression::Boolean(v) => v.hash(state),
+            Expression::Symbol(v) => v.hash(state),
+            Expression::String(v) => v.as_str().hash(state),
+            Expression::List(l) | Expression::Vector(l) | Expression::Values(l) => {
+                std::mem::discriminant(self).hash(state);
+                hash_seq(&reference_obj!(l), state, depth)
+            }
+            Expression::Pair(_) => {
+                std::mem::discriminant(self).hash(state);
+                if depth > 0 {
+                    for p in pair::iter(self).take(depth) {
+                        reference_obj!(p).car.hash_iter(state, depth - 1);
+                    }
+                }
+            }
+            Expression::Null() => std::mem::discriminant(self).hash(state),
+            Expression::ByteVector(v) => reference_obj!(v).hash(state),
+            _ => {}
+        }
+    }
+}
+#[derive(Clone, PartialEq)]
+enum ParamMode {
+    Required,
+    Optional,
+    Rest,
+    Key,
+}
+// ex. (a b #!optional (c 10) #!rest d #!key (e 20))
+//     (a b . c)
+//     a
+#[derive(Clone, Default)]
+pub struct Param {
+    required: Vec<Symbol>,
+    optional: Vec<(Symbol, Expression)>,
+    rest: Option<Symbol>,
+    key: Vec<(Symbol, Expression)>,
+}
+impl Param {
+    pub fn new(exp: &Expression) -> Result<Self, Error> {
+        let mut param = Param::default();
+
+        let l = match exp {
+            Expression::Symbol(s) => {
+                param.rest = Some(s.clone());
+                return Ok(param);
+            }
+            Expression::List(l) => l,
+            Expression::Null() => return Ok(param),
+            _ => return Err(create_error!(ErrCode::E1005)),
+        };
+        let l = &*(reference_obj!(l));
+        let mut mode = ParamMode::Required;
+        let mut i = 0;
+        while i < l.len() {
+            match (&l[i], &mode) {
+                (Expression::Symbol(s), _) if s == "." => {
+                    if i + 2 != l.len() || mode == ParamMode::Rest || param.rest.is_some() {
+                        return Err(create_error!(ErrCode::E1004));
+                    }
+                    mode = ParamMode::Rest;
+                }
+                (Expression::Symbol(s), ParamMode::Required) if s == "#!optional" => {
+                    mode = ParamMode::Optional;
+                }
+                (Expression::Symbol(s), ParamMode::Required | ParamMode::Optional)
+                    if s == "#!rest" =>
+                {
+                    mode = ParamMode::Rest;
+                }
+                (Expression::Symbol(s), ParamMode::Rest) if s == "#!key" => {
+                    if param.rest.is_none() {
+                        return Err(create_error!(ErrCode::E1004));
+                    }
+                    mode = ParamMode::Key;
+                }
+                (Expression::Symbol(s), ParamMode::Required | ParamMode::Optional)
+                    if s == "#!key" =>
+                {
+                    mode = ParamMode::Key;
+                }
+                (Expression::Symbol(s), _) if s.starts_with("#!") => {
+                    return Err(create_error_value!(ErrCode::E1004, s));
+                }
+                (Expression::Symbol(s), ParamMode::Required) => {
+                    param.required.push(s.clone());
+                }
+                (Expression::Symbol(s), ParamMode::Rest) => {
+                    if param.rest.is_some() {
+                        return Err(create_error_value!(ErrCode::E1004, s));
+                    }
+                    param.rest = Some(s.clone());
+                }
+                (e, ParamMode::Optional | ParamMode::Key) => {
+                    let v = Param::param_default(e)?;
+                    if mode == ParamMode::Optional {
+                        param.optional.push(v);
+                    } else {
+                        param.key.push(v);
+                    }
+                }
+                _ => return Err(create_error!(ErrCode::E1004)),
+            }
+            i += 1;
+        }
+        if mode == ParamMode::Rest && param.rest.is_none() {
+            return Err(create_error!(ErrCode::E1004));
+        }
+        Ok(param)
+    }
+    // ex. a => (a #f), (a 10) => (a 10)
+    fn param_default(exp: &Expression) -> Result<(Symbol, Expression), Error> {
+        match exp {
+            Expression::Symbol(s) => Ok((s.clone(), Expression::Boolean(false))),
+            Expression::List(l) => {
+                let l = &*(reference_obj!(l));
+                match &l[..] {
+                    [Expression::Symbol(s), e] => Ok((s.clone(), e.clone())),
+                    _ => Err(create_error!(ErrCode::E1004)),
+                }
+            }
+            _ => Err(create_error!(ErrCode::E1004)),
+        }
+    }
+    pub fn is_acceptable(&self, n: usize) -> bool {
+        if n < self.required.len() {
+            return false;
+        }
+        self.rest.is_some()
+            || !self.key.is_empty()
+            || n <= self.required.len() + self.optional.len()
+    }
+    fn expected(&self) -> String {
+        if self.rest.is_some() || !self.key.is_empty() {
+            format!("at least {}", self.required.len())
+        } else if !self.optional.is_empty() {
+            format!(
+                "{} to {}",
+                self.required.len(),
+                self.required.len() + self.optional.len()
+            )
+        } else {
+            self.required.len().to_string()
+        }
+    }
+    // The parameters are bound by position (required and rest only).
+    pub(crate) fn is_positional(&self) -> bool {
+        self.optional.is_empty() && self.key.is_empty()
+    }
+    pub(crate) fn required(&self) -> &[Symbol] {
+        &self.required
+    }
+    pub(crate) fn rest(&self) -> Option<&Symbol> {
+        self.rest.as_ref()
+    }
+    pub fn arity_error(&self, n: usize) -> Error {
+        create_error_value!(
+            ErrCode::E1007,
+            format!("expected {}, but got {}", self.expected(), n)
+        )
+    }
+    // Default values are evaluated in env, after the preceding parameters are bound.
+    pub(crate) fn bind<F>(
+        &self,
+        args: Vec<Expression>,
+        env: &Environment,
+        mut set: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&Symbol, Expression),
+    {
+        if !self.is_acceptable(args.len()) {
+            return Err(self.arity_error(args.len()));
+        }
+        let mut args = args.into_iter();
+        for s in &self.required {
+            set(s, args.next().unwrap());
+        }
+        for (s, e) in &self.optional {
+            match args.next() {
+                Some(v) => set(s, v),
+                None => set(s, eval(e, env)?),
+            }
+        }
+        let args: Vec<Expression> = args.collect();
+        if let Some(s) = &self.rest {
+            set(s, Environment::create_list(args.clone()));
+        }
+        if self.key.is_empty() {
+            return Ok(());
+        }
+        if !args.len().is_multiple_of(2) {
+            return Err(create_error_value!(ErrCode::E1007, args.len()));
+        }
+        let mut keys: Vec<Option<Expression>> = vec![None; self.key.len()];
+        for kv in args.chunks(2) {
+            let name = match &kv[0] {
+                Expression::Symbol(k) if k.starts_with(':') => &k[1..],
+                e => return Err(create_error_value!(ErrCode::E1004, e)),
+            };
+            match self.key.iter().position(|(s, _)| s == name) {
+                Some(i) => keys[i] = Some(kv[1].clone()),
+                None => {
+                    if self.rest.is_none() {
+                        return Err(create_error_value!(ErrCode::E1004, kv[0]));
+                    }
+                }
+            }
+        }
+        for ((s, e), v) in self.key.iter().zip(keys) {
+            match v {
+                Some(v) => set(s, v),
+                None => set(s, eval(e, env)?),
+            }
+        }
+        Ok(())
+    }
+}
 #[derive(Clone)]
 pub struct Function {
-    param: Vec<String>,
+    param: Param,
     body: Vec<Expression>,
     name: String,
     closure_env: Environment,
-    tail_recurcieve: bool,
+    code: CodeCell,
 }
 impl Function {
     pub fn new(sexp: &[Expression], name: String, closure_env: Environment) -> Self {
-        let mut param: Vec<String> = Vec::new();
+        let param = Param::new(&sexp[1]).unwrap_or_default();
 
-        if let Expression::List(l) = &sexp[1] {
-            let l = &*(reference_obj!(l));
-            for n in l {
-                if let Expression::Symbol(s) = n {
-                    param.push(s.to_string());
-                }
-            }
-        }
         let mut vec: Vec<Expression> = Vec::new();
         vec.extend_from_slice(&sexp[2..]);
         Function {
@@ -535,123 +1057,53 @@ impl Function {
             body: vec,
             name,
             closure_env,
-            tail_recurcieve: false,
+            code: CodeCell::default(),
         }
     }
-    pub fn set_param(&self, exp: &[Expression], env: &Environment) -> ResultExpression {
-        if self.param.len() != (exp.len() - 1) {
-            return Err(create_error_value!(ErrCode::E1007, exp.len()));
-        }
-        // param eval
-        let mut vec: Vec<Expression> = Vec::new();
-        // env set
-        for e in &exp[1..] {
-            vec.push(eval(e, env)?);
-        }
-        for (i, e) in vec.into_iter().enumerate() {
-            env.update(&self.param[i], e);
+    // The closure which shares the compiled code, ex. (lambda (x) (< x p)) in the function
+    pub(crate) fn with_env(&self, closure_env: Environment) -> Self {
+        Function {
+            param: self.param.clone(),
+            body: self.body.clone(),
+            name: self.name.clone(),
+            closure_env,
+            code: self.code.clone(),
         }
-        Ok(Expression::TailLoop())
+    }
+    pub fn is_acceptable(&self, n: usize) -> bool {
+        self.param.is_acceptable(n)
     }
     pub fn execute(&self, exp: &[Expression], env: &Environment) -> ResultExpression {
-        if self.param.len() != (exp.len() - 1) {
-            return Err(create_error_value!(ErrCode::E1007, exp.len()));
+        if !self.param.is_acceptable(exp.len() - 1) {
+            return Err(self.param.arity_error(exp.len() - 1));
         }
         // param eval
         let mut vec: Vec<Expression> = Vec::new();
         for e in &exp[1..] {
             vec.push(eval(e, env)?);
         }
-        // env.create();
-        let env = Environment::with_parent(&self.closure_env);
-        for (i, s) in self.param.iter().enumerate() {
-            env.regist(s.to_string(), vec[i].clone());
-        }
-        // execute!
-        let mut ret = Expression::Nil();
-        for e in &self.body {
-            ret = loop {
-                match eval(e, &env) {
-                    Ok(n) => match n {
-                        Expression::TailLoop() => {
-                            if self.tail_recurcieve {
-                                continue;
-                            }
-                        }
-                        v => break v,
-                    },
-                    Err(e) => match &e.code {
-                        ErrCode::Cont => {
-                            let s = if let Some(ref s) = e.value {
-                                s.clone()
-                            } else {
-                                return Err(e);
-                            };
-                            if self.param.len() == 1 && self.param[0] == s {
-                                if let Expression::Continuation(_) = &exp[1] {
-                                    break e.exp.unwrap();
-                                }
-                            }
-                            return Err(e);
-                        }
-                        _ => {
-                            return Err(e);
-                        }
-                    },
-                };
-            }
-        }
-        Ok(ret)
+        machine::apply(&Environment::create_func(self.clone()), vec, env)
     }
-    pub fn get_tail_recurcieve(&self) -> bool {
-        self.tail_recurcieve
+    pub(crate) fn param(&self) -> &Param {
+        &self.param
     }
-    pub fn set_tail_recurcieve(&mut self) {
-        if let Some(l) = self.parse_tail_recurcieve(self.body.as_slice()) {
-            self.tail_recurcieve = true;
-
-            let mut l = mut_obj!(l);
-            l[0] = Environment::create_tail_recursion(self.clone());
-        }
+    pub(crate) fn body(&self) -> &[Expression] {
+        &self.body
     }
-    fn parse_tail_recurcieve(&self, exp: &[Expression]) -> Option<ListRc> {
-        let mut n = 0;
-        let mut vec: Option<ListRc> = None;
-
-        for (i, e) in exp.iter().enumerate() {
-            if let Expression::List(l) = e {
-                let l = &*(reference_obj!(l));
-                if 1 >= l.len() {
-                    continue;
-                }
-                if let Expression::BuildInFunction(s, _) = &l[0] {
-                    match s.as_str() {
-                        "if" | "cond" => {
-                            return self.parse_tail_recurcieve(&l[1..]);
-                        }
-                        "begin" => {
-                            return self.parse_tail_recurcieve(&l[1..]);
-                        }
-                        _ => {}
-                    }
-                } else if let Expression::Symbol(s) = &l[0] {
-                    if *s == self.name {
-                        // check tail
-                        if (exp.len() - 1) == i {
-                            debug!("set tail_recurcieve {}", s);
-                            if let Expression::List(ref v) = exp[i] {
-                                // check calling times
-                                vec = if n == 0 { Some(v.clone()) } else { None };
-                            }
-                        }
-                        n += 1;
-                    } else if *s == "else" {
-                        return self.parse_tail_recurcieve(&l[1..]);
-                    }
-                }
-            }
-        }
-        vec
+    pub(crate) fn closure_env(&self) -> &Environment {
+        &self.closure_env
+    }
+    pub(crate) fn arity_error(&self, n: usize) -> Error {
+        self.param.arity_error(n)
+    }
+    // The body is compiled at the first call, None if it can't be compiled.
+    pub(crate) fn code(&self) -> Option<&CodeRc> {
+        self.code
+            .get_or_init(|| compile::compile(self).map(CodeRc::new))
+            .as_ref()
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
     }
 }
 //========================================================================
@@ -659,6 +1111,8 @@ const PROMPT: &str = "rust.elisp> ";
 const QUIT: &str = "(quit)";
 const TAIL_OFF: &str = "(tail-recursion-off)";
 const TAIL_ON: &str = "(tail-recursion-on)";
+const BYTECODE_OFF: &str = "(bytecode-off)";
+const BYTECODE_ON: &str = "(bytecode-on)";
 const FORCE_STOP: &str = "(force-stop)";
 const LIMIT_STOPL_ON: &str = "(limit-stop-on)";
 
@@ -667,6 +1121,19 @@ pub const SPACE: ControlChar = ControlChar(0x20, "#\\space");
 pub const TAB: ControlChar = ControlChar(0x09, "#\\tab");
 pub const NEWLINE: ControlChar = ControlChar(0x0A, "#\\newline");
 pub const CARRIAGERETRUN: ControlChar = ControlChar(0x0D, "#\\return");
+// The names of the characters, ex. #\alarm
+pub const CHAR_NAMES: [(&str, char); 10] = [
+    ("null", '\0'),
+    ("nul", '\0'),
+    ("alarm", '\x07'),
+    ("backspace", '\x08'),
+    ("tab", '\t'),
+    ("newline", '\n'),
+    ("return", '\r'),
+    ("escape", '\x1b'),
+    ("space", ' '),
+    ("delete", '\x7f'),
+];
 
 const TRUE: &str = "#t";
 const FALSE: &str = "#f";
@@ -709,21 +1176,23 @@ pub fn repl(
                     continue 'outer;
                 }
             }
-            if buffer.as_bytes()[0] as char == ';' {
-                continue;
-            }
-            program.push(buffer.trim().to_string());
-            let lisp = program.join(" ");
+            program.push(buffer.trim_end().to_string());
+            let lisp = program.join("\n");
             let (left, right) = count_parenthesis(&lisp);
             if left > right {
                 continue;
             }
+            // ex. <rust-elisp> ; comment
+            if let Ok(None) = Reader::new(&lisp).read(env) {
+                program.clear();
+                continue 'outer;
+            }
             break lisp;
         };
         #[cfg(feature = "signal")]
         clear_sig_intr_status();
         debug!("{}", program.iter().cloned().collect::<String>());
-        match do_core_logic(&lisp, env) {
+        match eval_program(&lisp, None, env) {
             Ok(n) => println!("{}", n),
             Err(e) => {
                 if ErrCode::E9000.as_str() == e.get_code() {
@@ -736,294 +1205,242 @@ pub fn repl(
     }
     Ok(())
 }
-pub fn count_parenthesis(program: &str) -> (i32, i32) {
-    #[derive(PartialEq)]
-    enum CharMode {
-        Init,
-        Whitespace,
-        Sharp,
-        Backslash,
-    }
-    let mut left = 0;
-    let mut right = 0;
-    let mut str_mode = false;
-    let mut char_mode = CharMode::Whitespace;
-    let mut pre = ' ';
-
-    for c in program.chars() {
-        if str_mode {
-            if pre != '\\' && c == '"' {
-                str_mode = false;
-            }
-        } else if char_mode == CharMode::Backslash {
-            char_mode = CharMode::Init;
-        } else if pre != '\\' && c == '"' {
-            str_mode = true;
-        } else if c.is_whitespace() && char_mode == CharMode::Init {
-            char_mode = CharMode::Whitespace;
-        } else if c == '#' && char_mode == CharMode::Whitespace {
-            char_mode = CharMode::Sharp;
-        } else if c == '\\' && char_mode == CharMode::Sharp {
-            char_mode = CharMode::Backslash;
-        } else if c == '(' {
-            left += 1;
-        } else if c == ')' {
-            right += 1;
-        }
-        pre = c;
-    }
-    (left, right)
-}
-pub fn do_core_logic(program: &str, env: &Environment) -> ResultExpression {
-    let mut token = tokenize(program);
-    let mut c: i32 = 1;
-    let mut ret = Expression::Nil();
+// The file is read at once, and evaluated form by form.
+// The errors are reported with the positions in the file, ex. (load-file "test.scm")
+pub fn load(
+    stream: &mut dyn BufRead,
+    env: &Environment,
+    file: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut program = String::new();
+    stream.read_to_string(&mut program)?;
 
-    env.reset_eval_count();
+    let mut reader = Reader::with_position(&program, &Position::new(file, 1, 1));
     loop {
-        let exp = parse(&token, &mut c, env)?;
-
-        match exp.to_string().as_str() {
-            TAIL_ON => {
-                env.set_tail_recursion(true);
-            }
-            TAIL_OFF => {
-                env.set_tail_recursion(false);
-            }
-            FORCE_STOP => {
-                env.set_force_stop(true);
-            }
-            LIMIT_STOPL_ON => {
-                env.set_limit_stop(true);
-            }
-            _ => {
-                env.set_cont(&exp);
-                ret = eval(&exp, env)?;
+        env.reset_eval_count();
+        let r = match reader.read(env) {
+            Ok(Some((_, span))) if &program[span.start..span.end] == QUIT => {
+                println!("Bye");
+                break;
             }
-        }
-        debug!("{:?} c = {} token = {}", token.to_vec(), c, token.len());
-        if c == token.len() as i32 {
-            break;
-        } else {
-            for _ in 0..c as usize {
-                token.remove(0);
+            Ok(Some((exp, _))) => {
+                #[cfg(feature = "signal")]
+                clear_sig_intr_status();
+                eval_command(exp, env)
             }
-            c = 1;
-        }
-    }
-    Ok(ret)
-}
-struct TokenState {
-    tokens: Vec<String>,
-    name: String,
-    left: i32,
-    right: i32,
-    string_mode: bool,
-    quote_mode: bool,
-    idx: usize,
-}
-impl TokenState {
-    fn new() -> Self {
-        TokenState {
-            tokens: Vec::new(),
-            name: String::new(),
-            left: 0,
-            right: 0,
-            string_mode: false,
-            quote_mode: false,
-            idx: 0,
-        }
-    }
-    fn push_if_quote(&mut self, s: String) {
-        if let Some(last) = self.tokens.last() {
-            if self.quote_mode && last == "quote" {
-                self.tokens.push(s);
-                self.tokens.push(")".into());
-                self.quote_mode = false;
-                return;
+            Ok(None) => break,
+            // the reader goes on after the wrong datum, ex. (define s "(")) ; extra ')'
+            Err(e) => Err(e),
+        };
+        match r {
+            Ok(n) => println!("{}", n),
+            Err(e) => {
+                if ErrCode::E9000.as_str() == e.get_code() {
+                    env.set_force_stop(false);
+                }
+                print_error!(e);
             }
         }
-        self.tokens.push(s);
-    }
-    fn set_quote(&mut self) {
-        self.left = 0;
-        self.right = 0;
-        self.quote_mode = true;
-        self.tokens.push("(".into());
-        self.tokens.push("quote".into());
-    }
-    fn tokens(self) -> Vec<String> {
-        self.tokens
     }
+    Ok(())
 }
-pub(crate) fn tokenize(program: &str) -> Vec<String> {
-    let mut token = TokenState::new();
-    let mut from = 0;
-    let mut vector_mode = false;
+// The unterminated string(or block comment) is counted as the left parenthesis.
+pub fn count_parenthesis(program: &str) -> (i32, i32) {
+    let mut left = 0;
+    let mut right = 0;
+    let mut chars = program.chars().peekable();
 
-    macro_rules! set_token_name {
-        ($i: expr, $c: expr) => {
-            token.name.push($c);
-            if program.chars().count() - 1 == $i {
-                // ex. <rust-elisp> abc
-                token.push_if_quote(token.name.to_string());
-            } else {
-                // ex. <rust-elisp> abc def ghi
-                match program.chars().nth($i + 1).unwrap() {
-                    ' ' | '\r' | '\n' | '\t' => {
-                        token.push_if_quote(token.name.to_string());
-                        token.name.clear();
-                    }
-                    '(' | ')' => {
-                        if token.name != "#\\" {
-                            token.push_if_quote(token.name.to_string());
-                            token.name.clear();
-                        }
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '|' => {
+                let mut closed = false;
+                while let Some(d) = chars.next() {
+                    if d == '\\' {
+                        chars.next();
+                    } else if d == c {
+                        closed = true;
+                        break;
                     }
-                    _ => {}
                 }
-            }
-        };
-    }
-
-    //A String is a wrapper over a Vec<u8>.(https://doc.rust-lang.org/book/ch08-02-strings.html)
-    for (i, c) in program.chars().enumerate() {
-        if token.string_mode {
-            if c == '"' {
-                // ex. <rust-elisp> "abc \""
-                if program.chars().nth(i - 1).unwrap() != '\\' {
-                    let ls = &program[from..(token.idx + 1)];
-                    token.push_if_quote(ls.to_string());
-                    token.string_mode = false;
+                if !closed {
+                    left += 1;
                 }
             }
-        } else if token.name.starts_with("#\\") {
-            set_token_name!(i, c);
-        } else {
-            match c {
-                '\'' => {
-                    token.set_quote();
+            ';' => {
+                for d in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
                 }
-                '"' => {
-                    from = token.idx;
-                    token.string_mode = true;
+            }
+            '#' => match chars.peek() {
+                // ex. #\(
+                Some('\\') => {
+                    chars.next();
+                    chars.next();
                 }
-                '(' => {
-                    token.left += 1;
-                    token.tokens.push("(".into());
-                    if vector_mode {
-                        token.tokens.push("vector".into());
-                        vector_mode = false;
-                    }
+                // ex. #;(a b)
+                Some(';') => {
+                    chars.next();
                 }
-                ')' => {
-                    token.right += 1;
-                    token.tokens.push(")".into());
-
-                    if token.quote_mode && (token.left == token.right) {
-                        token.tokens.push(")".into());
-                        token.quote_mode = false;
+                // ex. #| ... |#
+                Some('|') => {
+                    chars.next();
+                    let mut depth = 1;
+                    while let Some(d) = chars.next() {
+                        if d == '|' && chars.peek() == Some(&'#') {
+                            chars.next();
+                            depth -= 1;
+                        } else if d == '#' && chars.peek() == Some(&'|') {
+                            chars.next();
+                            depth += 1;
+                        }
+                        if depth == 0 {
+                            break;
+                        }
                     }
-                }
-                _ => {
-                    if c == '#'
-                        && i + 1 < program.chars().count()
-                        && program.chars().nth(i + 1).unwrap() == '('
-                    {
-                        vector_mode = true;
-                    } else if !c.is_whitespace() {
-                        set_token_name!(i, c);
+                    if depth > 0 {
+                        left += 1;
                     }
                 }
-            }
+                _ => {}
+            },
+            '(' => left += 1,
+            ')' => right += 1,
+            _ => {}
         }
-        token.idx += c.len_utf8();
     }
+    (left, right)
+}
+pub fn do_core_logic(program: &str, env: &Environment) -> ResultExpression {
+    eval_program(program, None, env)
+}
+// The errors are reported with the positions in the file.
+pub fn do_core_logic_file(program: &str, file: &str, env: &Environment) -> ResultExpression {
+    eval_program(program, Some(&Position::new(file, 1, 1)), env)
+}
+fn eval_program(program: &str, start: Option<&Position>, env: &Environment) -> ResultExpression {
+    let mut reader = match start {
+        Some(p) => Reader::with_position(program, p),
+        None => Reader::new(program),
+    };
+    let mut ret = None;
 
-    // For Occur charactor syntax error ex. <rust-elisp> "abc
-    if token.string_mode {
-        token.push_if_quote(program[from..token.idx].to_string());
+    env.reset_eval_count();
+    while let Some((exp, _)) = reader.read(env)? {
+        ret = Some(eval_command(exp, env)?);
     }
-    debug!("{:?}", token.tokens);
-    token.tokens()
+    // ex. <rust-elisp> ; comment
+    ret.ok_or_else(|| create_error!(ErrCode::E0001))
 }
-pub(crate) fn parse(tokens: &[String], count: &mut i32, env: &Environment) -> ResultExpression {
-    if tokens.is_empty() {
-        return Err(create_error!(ErrCode::E0001));
-    }
-
-    let token = &tokens[0];
-    if "(" == token {
-        if tokens.len() <= 1 {
-            return Err(create_error!(ErrCode::E0001));
-        }
-        let mut list: Vec<Expression> = Vec::new();
-
-        *count = 1;
-        loop {
-            if tokens[*count as usize] == ")" {
-                *count += 1;
-                break;
-            }
-            let mut c: i32 = 1;
-            let o = parse(&tokens[*count as usize..], &mut c, env)?;
-            list.push(o);
-
-            *count += c;
-            if tokens.len() <= *count as usize {
-                return Err(create_error!(ErrCode::E0002));
-            }
-        }
-        Ok(Environment::create_list(list))
-    } else if ")" == token {
-        Err(create_error!(ErrCode::E0003))
-    } else {
-        // string check ex. <rust-elisp> "abc
-        if (token == "\"") || (token.starts_with('\"') && !token.ends_with('\"')) {
-            return Err(create_error!(ErrCode::E0004));
+// The commands of the interpreter are not evaluated, ex. (bytecode-off)
+fn eval_command(exp: Expression, env: &Environment) -> ResultExpression {
+    match exp.to_string().as_str() {
+        TAIL_ON => env.set_tail_recursion(true),
+        TAIL_OFF => env.set_tail_recursion(false),
+        BYTECODE_ON => env.set_bytecode(true),
+        BYTECODE_OFF => env.set_bytecode(false),
+        FORCE_STOP => env.set_force_stop(true),
+        LIMIT_STOPL_ON => env.set_limit_stop(true),
+        _ => {
+            env.set_cont(&exp);
+            return eval(&exp, env);
         }
-        atom(token, env)
     }
+    Ok(Expression::Nil())
 }
-fn atom(token: &str, env: &Environment) -> ResultExpression {
+pub(crate) fn atom(token: &str) -> ResultExpression {
     let v = if let Ok(n) = token.parse::<Int>() {
         Expression::Integer(n)
-    } else if let Ok(n) = token.parse::<f64>() {
-        Expression::Float(n)
-    } else if token == TRUE {
+    } else if let Some(n) = parse_number(token, 10).map_err(|c| create_error!(c))? {
+        n.to_expression()
+    } else if token == TRUE || token == "#true" {
         Expression::Boolean(true)
-    } else if token == FALSE {
+    } else if token == FALSE || token == "#false" {
         Expression::Boolean(false)
-    } else if token == SPACE.1 {
-        Expression::Char(char::from(SPACE.0))
-    } else if token == TAB.1 {
-        Expression::Char(char::from(TAB.0))
-    } else if token == NEWLINE.1 {
-        Expression::Char(char::from(NEWLINE.0))
-    } else if token == CARRIAGERETRUN.1 {
-        Expression::Char(char::from(CARRIAGERETRUN.0))
-    } else if (token.starts_with("#\\")) && (token.chars().count() == 3) {
-        let c = token.chars().collect::<Vec<char>>();
-        Expression::Char(c[2])
+    } else if let Some(name) = token.strip_prefix("#\\") {
+        match char_literal(name) {
+            Some(c) => Expression::Char(c),
+            None => return Err(create_error_value!(ErrCode::E0004, token)),
+        }
     } else if (token.len() >= 2) && (token.starts_with('\"')) && (token.ends_with('\"')) {
-        let s = token[1..token.len() - 1].to_string();
-        Environment::create_string(s)
-    } else if let Some(f) = env.get_builtin_func(token) {
-        Expression::BuildInFunction(token.to_string(), f)
-    } else if let Some(f) = env.get_builtin_ext_func(token) {
-        Expression::BuildInFunctionExt(f)
+        match unescape(&token[1..token.len() - 1]) {
+            Some(s) => Environment::create_string(s),
+            None => return Err(create_error_value!(ErrCode::E0004, token)),
+        }
+    } else if (token.len() >= 2) && (token.starts_with('|')) && (token.ends_with('|')) {
+        match unescape(&token[1..token.len() - 1]) {
+            Some(s) => symbol(&s),
+            None => return Err(create_error_value!(ErrCode::E0004, token)),
+        }
     } else {
-        match Rat::from(token) {
-            Ok(n) => Expression::Rational(n),
-            Err(n) => {
-                if n.code != ErrCode::E1020 {
-                    return Err(create_error!(n.code));
+        symbol(token)
+    };
+    Ok(v)
+}
+// The builtin functions are also symbols, they are resolved at the evaluation.
+fn symbol(token: &str) -> Expression {
+    Expression::Symbol(Symbol::from(token))
+}
+// ex. "a" => 'a', "space" => ' ', "x41" => 'A'
+fn char_literal(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    if let Some((_, c)) = CHAR_NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(*c);
+    }
+    let hex = name.strip_prefix('x')?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+// ex. "a\\tb" => "a\tb", the escape sequences of the string and the symbol.
+fn unescape(s: &str) -> Option<String> {
+    let mut v = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            v.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'a' => v.push('\x07'),
+            'b' => v.push('\x08'),
+            't' => v.push('\t'),
+            'n' => v.push('\n'),
+            'r' => v.push('\r'),
+            '"' => v.push('"'),
+            '\\' => v.push('\\'),
+            '|' => v.push('|'),
+            'x' => {
+                let mut hex = String::new();
+                loop {
+                    match chars.next()? {
+                        ';' => break,
+                        c => hex.push(c),
+                    }
+                }
+                v.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            // ex. "abc \<newline>  def" => "abc def"
+            c @ (' ' | '\t' | '\r' | '\n') => {
+                let mut newline = c == '\n';
+                while let Some(&c) = chars.peek() {
+                    match c {
+                        ' ' | '\t' | '\r' => {}
+                        '\n' if !newline => newline = true,
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                if !newline {
+                    return None;
                 }
-                Expression::Symbol(token.to_string())
             }
+            _ => return None,
         }
-    };
-    Ok(v)
+    }
+    Some(v)
 }
 pub fn eval(sexp: &Expression, env: &Environment) -> ResultExpression {
     #[cfg(feature = "signal")]
@@ -1036,30 +1453,28 @@ pub fn eval(sexp: &Expression, env: &Environment) -> ResultExpression {
         return Err(create_error!(ErrCode::E9000));
     }
     if let Expression::Symbol(val) = sexp {
-        match env.find(val) {
-            Some(v) => Ok(v),
-            None => Err(create_error_value!(ErrCode::E1008, val)),
+        if env.is_evaluated() {
+            return Ok(sexp.clone());
         }
+        machine::lookup(val, env)
     } else if let Expression::List(val) = sexp {
         debug!("eval = {:?}", get_ptr!(val));
-
-        let v = &*(reference_obj!(val));
-        if v.is_empty() {
-            return Ok(sexp.clone());
-        }
-        match &v[0] {
-            Expression::BuildInFunction(_, f) => f(&v[..], env),
-            Expression::BuildInFunctionExt(f) => f(&v[..], env),
-            Expression::TailRecursion(f) => f.set_param(&v[..], env),
-            Expression::Function(f) => f.execute(&v[..], env),
-            _ => match eval(&v[0], env)? {
-                Expression::Function(f) => f.execute(&v[..], env),
-                Expression::BuildInFunction(_, f) => f(&v[..], env),
-                Expression::BuildInFunctionExt(f) => f(&v[..], env),
-                Expression::Continuation(f) => f.execute(&v[..], env),
-                e => Err(create_error_value!(ErrCode::E1006, e)),
-            },
+        let quote = {
+            let v = &*(reference_obj!(val));
+            if v.is_empty() {
+                return Ok(Expression::Null());
+            }
+            match &v[..] {
+                [Expression::BuildInFunction(s, _), _] => *s == "quote",
+                [Expression::Symbol(s), _] => s == "quote" && env.find_symbol(s).is_none(),
+                _ => false,
+            }
+        };
+        // ex. (quote a), builtin functions are called with the quoted values.
+        if quote {
+            return Ok(quoted(val));
         }
+        machine::run(sexp, env)
     } else {
         Ok(sexp.clone())
     }
@@ -1082,28 +1497,35 @@ fn test_expression_ord() {
     );
     assert_eq!(
         Environment::create_string("A".to_string()).cmp(&Expression::Integer(10)),
-        Ordering::Less
+        Ordering::Greater
     );
     assert_eq!(
         Expression::Char('A').cmp(&Expression::Integer(10)),
-        Ordering::Less
+        Ordering::Greater
     );
     assert_eq!(
-        Expression::Symbol("A".to_string()).cmp(&Expression::Integer(10)),
-        Ordering::Less
+        Expression::Symbol(Symbol::from("A")).cmp(&Environment::create_string("B".to_string())),
+        Ordering::Greater
     );
     assert_eq!(
         Expression::Boolean(true).cmp(&Expression::Integer(10)),
         Ordering::Less
     );
+    assert_eq!(
+        Expression::Float(1.5).cmp(&Expression::Integer(1)),
+        Ordering::Greater
+    );
 }
 #[test]
 fn test_expression_eq() {
-    let a = Environment::create_list(vec![]);
+    let a = Environment::create_list(vec![Expression::Integer(1)]);
     assert!(a.eq(&a));
 
-    let b = Environment::create_list(vec![]);
+    let b = Environment::create_list(vec![Expression::Integer(1)]);
     assert!(!a.eq(&b));
+    assert!(a.equal(&b));
+
+    assert!(Environment::create_list(vec![]).eq(&Expression::Null()));
 
     let x = Environment::create_vector(vec![]);
     assert!(x.eq(&x));
@@ -1131,5 +1553,88 @@ fn test_limit_stop_on() {
 #[test]
 fn test_parse() {
     let env = Environment::new();
-    let _ = parse(&[], &mut 0, &env).map_err(|e| assert_eq!(e.get_code(), "E0001"));
+    let _ = do_core_logic("", &env).map_err(|e| assert_eq!(e.get_code(), "E0001"));
+}
+#[test]
+fn test_count_parenthesis() {
+    assert_eq!(count_parenthesis("(a (b)"), (2, 1));
+    assert_eq!(count_parenthesis("(a) ; ("), (1, 1));
+    assert_eq!(count_parenthesis("(a \"(\\\"\" #\\()"), (1, 1));
+    assert_eq!(count_parenthesis("(a |(| #;(b))"), (2, 2));
+    assert_eq!(count_parenthesis("#| ( #| ( |# |# ()"), (1, 1));
+    assert_eq!(count_parenthesis("\"abc"), (1, 0));
+    assert_eq!(count_parenthesis("#| abc"), (1, 0));
+}
+#[test]
+fn test_repl_comment() {
+    use std::io::Cursor;
+
+    let env = Environment::new();
+    let mut cur = Cursor::new(
+        "; comment\n(define a 10) ; (\n#| a\n(define a 20) |#\n(define b (+ a 1)) #;(+ 1)"
+            .as_bytes(),
+    );
+    repl(&mut cur, &env, None).unwrap();
+    assert_eq!(crate::do_lisp_env("b", &env), "11");
+}
+#[test]
+fn test_load() {
+    use std::io::Cursor;
+
+    let env = Environment::new();
+    let program = format!(
+        "(define a (+ 0\n{}))\n(car 1)) ; (\n(define b (+ a 1))\n(quit)\n(define b 0)",
+        "1\n".repeat(10000)
+    );
+    let mut cur = Cursor::new(program.as_bytes());
+    load(&mut cur, &env, "test.scm").unwrap();
+    assert_eq!(crate::do_lisp_env("b", &env), "10001");
+}
+#[test]
+fn test_error_position() {
+    let error = |r: ResultExpression| match r {
+        Ok(_) => panic!("no error"),
+        Err(e) => e,
+    };
+    // the bytecode and the tree-walker locate the same call.
+    for (foo, pos) in [
+        ("(+ x (bar x))", "2:8"),
+        ("(if (bar x) 1 2)", "2:7"),
+        ("(* 2 (+ 1 (bar x)))", "2:13"),
+        ("(cond ((bar x) 1) (else 2))", "2:10"),
+        ("(and #t (bar x) 1)", "2:11"),
+        ("(begin (bar x) 1)", "2:10"),
+    ] {
+        let program = format!(
+            "(define (foo x)\n  {})\n(define (bar y)\n  (car y))\n(foo 1)",
+            foo
+        );
+        for bytecode in [true, false] {
+            let env = Environment::new();
+            env.set_bytecode(bytecode);
+            let e = error(do_core_logic_file(&program, "test.scm", &env));
+            assert_eq!(e.get_code(), "E1005");
+            assert_eq!(e.get_msg(), "Not List: 1 (test.scm:4:3)");
+            assert_eq!(
+                e.get_trace(),
+                format!("  at bar (test.scm:4:3)\n  at foo (test.scm:{})", pos)
+            );
+        }
+    }
+    let env = Environment::new();
+    let e = error(do_core_logic_file(
+        "(define a 10)\n  (list a\n    (+ 1 2)",
+        "test.scm",
+        &env,
+    ));
+    assert_eq!(e.get_msg(), "Unexpected ')' while reading (test.scm:2:3)");
+    let e = error(do_core_logic_file("(list a\n    \"abc", "test.scm", &env));
+    assert_eq!(e.get_msg(), "Charactor syntax error (test.scm:2:5)");
+    let e = error(do_core_logic_file("\n(list 1 3/0)", "test.scm", &env));
+    assert_eq!(e.get_msg(), "Calculate A Division By Zero (test.scm:2:9)");
+
+    // the rust location is shown, if the program has no position.
+    let e = error(do_core_logic("(car 1)", &env));
+    assert!(e.get_msg().starts_with("Not List: 1 (src/"));
+    assert_eq!(e.get_report(), e.get_msg());
 }
@@ -7,6 +7,7 @@
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 use std::cmp::Ordering;
+use std::vec::IntoIter;
 use std::vec::Vec;
 
 use crate::create_error;
@@ -16,8 +17,10 @@ use crate::reference_obj;
 
 use crate::buildin::BuildInTable;
 use crate::lisp::eval;
-use crate::lisp::{Environment, Expression, Int, ListRc, ResultExpression};
+use crate::lisp::{Environment, Expression, Int, ListRc, PairRc, ResultExpression};
 use crate::lisp::{ErrCode, Error};
+use crate::machine::{self, Iterate, IterateBox, Start, Step};
+use crate::pair;
 use crate::syntax::quote;
 
 pub fn create_function<T>(b: &mut T)
@@ -34,21 +37,28 @@ where
     b.regist("cons", cons);
     b.regist("append", append);
     b.regist("append!", append_effect);
-    b.regist("take", |exp, env| take_drop(exp, env, |l, n| &l[0..n]));
-    b.regist("drop", |exp, env| take_drop(exp, env, |l, n| &l[n..]));
+    b.regist("take", |exp, env| take_drop(exp, env, take_list));
+    b.regist("drop", |exp, env| take_drop(exp, env, drop_list));
     b.regist("delete", delete);
     b.regist("delete!", delete_effect);
     b.regist("last", last);
     b.regist("reverse", reverse);
+    b.regist("reverse!", reverse_effect);
     b.regist("iota", iota);
     b.regist("map", map);
-    b.regist("filter", filter);
-    b.regist("reduce", reduce);
+    b.regist("filter", machine::native);
+    b.regist("reduce", machine::native);
     b.regist("for-each", for_each);
     b.regist("list-ref", list_ref);
     b.regist("list-set!", list_set);
     b.regist("set-car!", set_car);
     b.regist("set-cdr!", set_cdr);
+    b.regist("memq", |exp, env| member(exp, env, Expression::eq, 3));
+    b.regist("memv", |exp, env| member(exp, env, Expression::eqv, 3));
+    b.regist("member", |exp, env| member(exp, env, Expression::equal, 4));
+    b.regist("assq", |exp, env| assoc(exp, env, Expression::eq, 3));
+    b.regist("assv", |exp, env| assoc(exp, env, Expression::eqv, 3));
+    b.regist("assoc", |exp, env| assoc(exp, env, Expression::equal, 4));
 
     b.regist("sort", sort);
     b.regist("sort!", sort_effect);
@@ -67,21 +77,10 @@ where
     b.regist("vector-ref", vector_ref);
     b.regist("vector-set!", vector_set);
 }
-fn get_sequence(exp: Expression, err: ErrCode) -> Result<ListRc, Error> {
-    if let Expression::List(l) = exp {
-        if err != ErrCode::E1005 {
-            Err(create_error!(err))
-        } else {
-            Ok(l)
-        }
-    } else if let Expression::Vector(l) = exp {
-        if err != ErrCode::E1022 {
-            Err(create_error!(err))
-        } else {
-            Ok(l)
-        }
-    } else {
-        Err(create_error!(err))
+fn get_vector(exp: Expression) -> Result<ListRc, Error> {
+    match exp {
+        Expression::Vector(l) => Ok(l),
+        e => Err(create_error_value!(ErrCode::E1022, e)),
     }
 }
 fn list(exp: &[Expression], env: &Environment) -> ResultExpression {
@@ -117,38 +116,26 @@ fn null_f(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-            Ok(Expression::Boolean(l.is_empty()))
-        }
-        _ => Ok(Expression::Boolean(false)),
-    }
+    let v = eval(&exp[1], env)?;
+    Ok(Expression::Boolean(Expression::is_null(&v)))
 }
 fn length(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_length(exp, env, ErrCode::E1005)
-}
-fn seq_length(exp: &[Expression], env: &Environment, err: ErrCode) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = get_sequence(eval(&exp[1], env)?, err)?;
-    let l = &*(reference_obj!(l));
-    Ok(Expression::Integer(l.len() as Int))
+    let l = eval(&exp[1], env)?;
+    match pair::length(&l) {
+        Some(n) => Ok(Expression::Integer(n as Int)),
+        None => Err(create_error_value!(ErrCode::E1005, l)),
+    }
 }
 fn car(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-            if l.is_empty() {
-                return Err(create_error!(ErrCode::E1011));
-            }
-            Ok(l[0].clone())
-        }
-        Expression::Pair(car, _cdr) => Ok((*car).clone()),
+        Expression::Pair(p) => Ok(reference_obj!(p).car.clone()),
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
         e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
@@ -157,15 +144,8 @@ fn cdr(exp: &[Expression], env: &Environment) -> ResultExpression {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-            match l.len() {
-                0 => Err(create_error!(ErrCode::E1011)),
-                1 => Ok(Environment::create_list(Vec::new())),
-                _ => Ok(Environment::create_list(l[1..].to_vec())),
-            }
-        }
-        Expression::Pair(_car, cdr) => Ok((*cdr).clone()),
+        Expression::Pair(p) => Ok(reference_obj!(p).cdr.clone()),
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
         e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
@@ -173,14 +153,13 @@ fn cadr(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    if let Expression::List(l) = eval(&exp[1], env)? {
-        let l = &*(reference_obj!(l));
-        if l.len() <= 1 {
-            return Err(create_error!(ErrCode::E1011));
-        }
-        Ok(l[1].clone())
-    } else {
-        Err(create_error!(ErrCode::E1005))
+    match eval(&exp[1], env)? {
+        Expression::Pair(p) => match pair::cdr(&Expression::Pair(p)) {
+            Expression::Pair(p) => Ok(reference_obj!(p).car.clone()),
+            _ => Err(create_error!(ErrCode::E1011)),
+        },
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
+        e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
 fn cons(exp: &[Expression], env: &Environment) -> ResultExpression {
@@ -190,135 +169,128 @@ fn cons(exp: &[Expression], env: &Environment) -> ResultExpression {
     let car = eval(&exp[1], env)?;
     let cdr = eval(&exp[2], env)?;
 
-    if let Expression::List(l) = cdr {
-        let l = reference_obj!(l);
-        let mut v: Vec<Expression> = vec![car];
-        v.append(&mut l.to_vec());
-        Ok(Environment::create_list(v))
-    } else {
-        Ok(Expression::Pair(Box::new(car), Box::new(cdr)))
-    }
+    Ok(Environment::create_pair(car, cdr))
 }
+// ex. (append '(1) '(2) l), the elements are copied except the last one, it's shared.
+// The last one may not be a list, ex. (append '(1) 2) => (1 . 2)
 fn append(exp: &[Expression], env: &Environment) -> ResultExpression {
-    let v = seq_append(exp, env, ErrCode::E1005)?;
-    Ok(Environment::create_list(v))
-}
-fn seq_append(
-    exp: &[Expression],
-    env: &Environment,
-    err: ErrCode,
-) -> Result<Vec<Expression>, Error> {
     if exp.len() < 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     let mut v: Vec<Expression> = Vec::new();
-    for e in &exp[1..] {
-        let l = get_sequence(eval(e, env)?, err.clone())?;
-        let l = reference_obj!(l);
-        v.append(&mut l.to_vec());
+    for e in &exp[1..exp.len() - 1] {
+        v.extend(pair::get_list(eval(e, env)?)?);
     }
-    Ok(v)
+    let tail = eval(&exp[exp.len() - 1], env)?;
+    Ok(Environment::create_dotted_list(v, tail))
 }
+// ex. (append! l1 l2), the last pair of l1 is linked to l2.
 fn append_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
-    let v = seq_append_effect(exp, env, ErrCode::E1005)?;
-    Ok(Expression::List(v))
-}
-fn seq_append_effect(exp: &[Expression], env: &Environment, err: ErrCode) -> Result<ListRc, Error> {
     if exp.len() < 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let rc = get_sequence(eval(&exp[1], env)?, err.clone())?;
-
-    let mut v = mut_obj!(&rc);
-    for e in &exp[2..] {
-        let l = get_sequence(eval(e, env)?, err.clone())?;
-        let l = reference_obj!(l);
-        v.append(&mut l.to_vec());
+    let mut lists = Vec::new();
+    for e in &exp[1..] {
+        let l = eval(e, env)?;
+        if !Expression::is_list(&l) {
+            return Err(create_error_value!(ErrCode::E1005, l));
+        }
+        if Expression::is_pair(&l) {
+            lists.push(l);
+        }
+    }
+    for w in lists.windows(2) {
+        if let Some(p) = pair::iter(&w[0]).last() {
+            mut_obj!(p).cdr = w[1].clone();
+        }
     }
-    Ok(rc.clone())
+    Ok(lists.into_iter().next().unwrap_or(Expression::Null()))
 }
 fn take_drop(
     exp: &[Expression],
     env: &Environment,
-    func: fn(l: &Vec<Expression>, n: usize) -> &[Expression],
+    func: fn(&Expression, usize) -> Expression,
 ) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
+    let l = eval(&exp[1], env)?;
+    let len = match pair::length(&l) {
+        Some(n) => n,
+        None => return Err(create_error_value!(ErrCode::E1005, l)),
     };
-
-    let l = reference_obj!(l);
-
     let n = match eval(&exp[2], env)? {
         Expression::Integer(n) => n,
         e => return Err(create_error_value!(ErrCode::E1002, e)),
     };
-    if l.len() < n as usize || n < 0 {
+    if len < n as usize || n < 0 {
         return Err(create_error!(ErrCode::E1011));
     }
-    let mut vec = Vec::new();
-    vec.extend_from_slice(func(&l, n as usize));
-
-    Ok(Environment::create_list(vec))
+    Ok(func(&l, n as usize))
+}
+// ex. (take '(1 2 3) 2) => (1 2), the elements are copied.
+pub(crate) fn take_list(l: &Expression, n: usize) -> Expression {
+    Environment::create_list(
+        pair::iter(l)
+            .take(n)
+            .map(|p| reference_obj!(p).car.clone())
+            .collect(),
+    )
+}
+// ex. (drop '(1 2 3) 2) => (3), the tail is shared.
+pub(crate) fn drop_list(l: &Expression, n: usize) -> Expression {
+    (0..n).fold(l.clone(), |l, _| pair::cdr(&l))
 }
+// ex. (delete x list) or (delete x list compare)
 fn delete(exp: &[Expression], env: &Environment) -> ResultExpression {
-    if exp.len() != 3 {
-        return Err(create_error_value!(ErrCode::E1007, exp.len()));
-    }
-    let other = eval(&exp[1], env)?;
-    let l = match eval(&exp[2], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-
-    let l = &*(reference_obj!(l));
-    let mut vec = Vec::new();
-    for e in l {
-        if Expression::eqv(e, &other) {
-            continue;
-        }
-        vec.push(e.clone());
-    }
-    Ok(Environment::create_list(vec))
+    let v = delete_impl(exp, env)?;
+    Ok(Environment::create_list(
+        v.iter().map(|p| reference_obj!(p).car.clone()).collect(),
+    ))
 }
+// the pairs which are left, are linked again.
 fn delete_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
-    if exp.len() != 3 {
+    let v = delete_impl(exp, env)?;
+    Ok(relink(v, Expression::Null()))
+}
+fn delete_impl(exp: &[Expression], env: &Environment) -> Result<Vec<PairRc>, Error> {
+    if exp.len() < 3 || 4 < exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     let other = eval(&exp[1], env)?;
-    let rc = match eval(&exp[2], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-
-    let mut l = mut_obj!(&rc);
-    let mut vec = Vec::new();
-    for e in l.iter() {
-        if Expression::eqv(e, &other) {
-            continue;
+    let l = eval(&exp[2], env)?;
+    if !Expression::is_list(&l) {
+        return Err(create_error_value!(ErrCode::E1005, l));
+    }
+    let compare = get_compare(exp, env)?;
+
+    // the compare procedure is called without the borrow of the list.
+    let mut v = Vec::new();
+    for p in pair::iter(&l) {
+        let e = reference_obj!(p).car.clone();
+        if !is_match(&compare, Expression::equal, &other, &e, env)? {
+            v.push(p);
         }
-        vec.push(e.clone());
     }
-    l.clear();
-    l.extend_from_slice(&vec);
-    Ok(Expression::List(rc.clone()))
+    Ok(v)
+}
+// ex. [p1, p2, p3] => (p1 p2 p3 . tail), the cdr of each pair is updated.
+pub(crate) fn relink(v: Vec<PairRc>, tail: Expression) -> Expression {
+    v.into_iter().rev().fold(tail, |cdr, p| {
+        mut_obj!(p).cdr = cdr;
+        Expression::Pair(p)
+    })
 }
 fn last(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-            match l.len() {
-                0 => Err(create_error!(ErrCode::E1011)),
-                _ => Ok(l[l.len() - 1].clone()),
-            }
-        }
-        Expression::Pair(car, _) => Ok(*car),
+        Expression::Pair(p) => match pair::iter(&Expression::Pair(p)).last() {
+            Some(p) => Ok(reference_obj!(p).car.clone()),
+            None => Err(create_error!(ErrCode::E1011)),
+        },
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
         e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
@@ -326,15 +298,22 @@ fn reverse(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-            let mut l = l.to_vec();
-            l.reverse();
-            Ok(Environment::create_list(l))
-        }
-        e => Err(create_error_value!(ErrCode::E1005, e)),
+    let v = pair::get_list(eval(&exp[1], env)?)?;
+    Ok(v.into_iter()
+        .fold(Expression::Null(), |l, e| Environment::create_pair(e, l)))
+}
+// the pairs are reused, the cdr of each pair is turned back.
+fn reverse_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
+    let l = eval(&exp[1], env)?;
+    if !Expression::is_list(&l) {
+        return Err(create_error_value!(ErrCode::E1005, l));
+    }
+    let mut v: Vec<PairRc> = pair::iter(&l).collect();
+    v.reverse();
+    Ok(relink(v, Expression::Null()))
 }
 fn iota(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() <= 1 || 4 < exp.len() {
@@ -363,126 +342,236 @@ fn iota(exp: &[Expression], env: &Environment) -> ResultExpression {
     Ok(Environment::create_list(l))
 }
 fn map(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 3 {
+        return machine::native(exp, env);
+    }
     fn func(
         sexp: Vec<Expression>,
         env: &Environment,
         result: &mut Vec<Expression>,
         _e: &Expression,
     ) -> ResultExpression {
-        result.push(eval(&Environment::create_list(sexp), env)?);
+        result.push(eval(&Environment::create_form(sexp), env)?);
         Ok(Expression::Nil())
     }
 
     do_list_proc(exp, env, func)
 }
-fn filter(exp: &[Expression], env: &Environment) -> ResultExpression {
-    fn func(
-        sexp: Vec<Expression>,
-        env: &Environment,
-        result: &mut Vec<Expression>,
-        e: &Expression,
-    ) -> ResultExpression {
-        match eval(&Environment::create_list(sexp), env)? {
-            Expression::Boolean(b) => {
-                if b {
-                    result.push(e.clone());
-                }
-            }
-            e => return Err(create_error_value!(ErrCode::E1001, e)),
-        }
-        Ok(Expression::Nil())
-    }
-    do_list_proc(exp, env, func)
-}
 fn for_each(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 3 {
+        return machine::native(exp, env);
+    }
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
 
     let callable = eval(&exp[1], env)?;
 
-    match eval(&exp[2], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-
-            for e in l {
-                eval(
-                    &Environment::create_list(make_evaled_list(&callable, &[e.clone()], &None)),
-                    env,
-                )?;
-            }
-            Ok(Expression::Nil())
-        }
-        e => Err(create_error_value!(ErrCode::E1005, e)),
+    for e in &pair::get_list(eval(&exp[2], env)?)? {
+        eval(
+            &Environment::create_form(make_evaled_list(&callable, std::slice::from_ref(e), &None)),
+            env,
+        )?;
     }
+    Ok(Expression::Nil())
 }
-fn reduce(exp: &[Expression], env: &Environment) -> ResultExpression {
-    if exp.len() != 4 {
-        return Err(create_error_value!(ErrCode::E1007, exp.len()));
-    }
-    let callable = eval(&exp[1], env)?;
-
-    if let Expression::List(l) = eval(&exp[3], env)? {
-        let l = &*(reference_obj!(l));
-        if l.is_empty() {
-            return eval(&exp[2], env);
+// The builtin functions which apply the procedures, run by the machine.
+pub(crate) fn start(name: &str) -> Option<Start> {
+    let f: Start = match name {
+        "filter" => filter,
+        "reduce" => reduce,
+        _ => return None,
+    };
+    Some(f)
+}
+// ex. (filter odd? '(1 2 3)), the result of the procedure is the boolean.
+#[derive(Clone)]
+struct Filter {
+    func: Expression,
+    items: IntoIter<Expression>,
+    item: Expression,
+    acc: Vec<Expression>,
+}
+impl Iterate for Filter {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        match v {
+            Some(Expression::Boolean(true)) => self.acc.push(self.item.clone()),
+            Some(Expression::Boolean(false)) | None => {}
+            Some(e) => return Err(create_error_value!(ErrCode::E1001, e)),
         }
-        let mut result = l[0].clone();
-        // not carfully length,  safety
-        for e in &l[1..] {
-            result = eval(
-                &Environment::create_list(make_evaled_list(&callable, &[e.clone()], &Some(result))),
-                env,
-            )?;
+        match self.items.next() {
+            Some(e) => {
+                self.item = e.clone();
+                Ok(Step::Apply(self.func.clone(), vec![e]))
+            }
+            None => Ok(Step::Return(Environment::create_list(std::mem::take(
+                &mut self.acc,
+            )))),
         }
-        Ok(result)
-    } else {
-        Err(create_error!(ErrCode::E1005))
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
     }
 }
-fn list_ref(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_list_ref(exp, env, ErrCode::E1005)
+// ex. (reduce + 0 '(1 2 3)), the procedure is called with (acc e).
+#[derive(Clone)]
+struct Reduce {
+    func: Expression,
+    items: IntoIter<Expression>,
+    acc: Expression,
 }
-fn seq_list_ref(exp: &[Expression], env: &Environment, err: ErrCode) -> ResultExpression {
-    if exp.len() != 3 {
-        return Err(create_error_value!(ErrCode::E1007, exp.len()));
-    }
-    let l = get_sequence(eval(&exp[1], env)?, err)?;
-    let l = &*(reference_obj!(l));
-    match eval(&exp[2], env)? {
-        Expression::Integer(i) => {
-            if i < 0 || l.len() <= i as usize {
-                Err(create_error!(ErrCode::E1011))
-            } else {
-                Ok(l[i as usize].clone())
+impl Iterate for Reduce {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        if let Some(v) = v {
+            self.acc = v;
+        }
+        match self.items.next() {
+            Some(e) => {
+                let acc = std::mem::replace(&mut self.acc, Expression::Nil());
+                Ok(Step::Apply(self.func.clone(), vec![acc, e]))
             }
+            None => Ok(Step::Return(self.acc.clone())),
         }
-        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
     }
 }
-fn list_set(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_list_set(exp, env, ErrCode::E1005)
+fn filter(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Box::new(Filter {
+        func: exp[1].clone(),
+        items: pair::get_list(exp[2].clone())?.into_iter(),
+        item: Expression::Nil(),
+        acc: Vec::new(),
+    }))
 }
-
-fn seq_list_set(exp: &[Expression], env: &Environment, err: ErrCode) -> ResultExpression {
+fn reduce(exp: &[Expression]) -> Result<IterateBox, Error> {
     if exp.len() != 4 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = get_sequence(eval(&exp[1], env)?, err)?;
+    let mut items = pair::get_list(exp[3].clone())?.into_iter();
+    let acc = match items.next() {
+        Some(e) => e,
+        None => exp[2].clone(),
+    };
+    Ok(Box::new(Reduce {
+        func: exp[1].clone(),
+        items,
+        acc,
+    }))
+}
+// ex. (list-ref '(1 2 3) 1) => the car of the 2nd pair
+fn get_nth_pair(exp: &[Expression], env: &Environment) -> Result<PairRc, Error> {
+    let l = eval(&exp[1], env)?;
+    if !Expression::is_pair(&l) && !Expression::is_null(&l) {
+        return Err(create_error_value!(ErrCode::E1005, l));
+    }
     let i = match eval(&exp[2], env)? {
         Expression::Integer(i) => i,
-        _ => {
-            return Err(create_error!(ErrCode::E1002));
-        }
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
     };
-    let mut l = mut_obj!(l);
-    if i < 0 || l.len() <= i as usize {
+    if i < 0 {
         return Err(create_error!(ErrCode::E1011));
     }
-    l[i as usize] = eval(&exp[3], env)?;
+    match pair::iter(&l).nth(i as usize) {
+        Some(p) => Ok(p),
+        None => Err(create_error!(ErrCode::E1011)),
+    }
+}
+fn list_ref(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = get_nth_pair(exp, env)?;
+    let v = reference_obj!(p).car.clone();
+    Ok(v)
+}
+fn list_set(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let p = get_nth_pair(exp, env)?;
+    let v = eval(&exp[3], env)?;
+    mut_obj!(p).car = v;
 
     Ok(Expression::Nil())
 }
+// ex. (member x list) or (member x list compare)
+fn member(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(&Expression, &Expression) -> bool,
+    max: usize,
+) -> ResultExpression {
+    if exp.len() < 3 || max < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let x = eval(&exp[1], env)?;
+    let l = eval(&exp[2], env)?;
+    if !Expression::is_list(&l) {
+        return Err(create_error_value!(ErrCode::E1005, l));
+    }
+    let compare = get_compare(exp, env)?;
+    // the found pair is returned, it's the tail of the list.
+    for p in pair::iter(&l) {
+        let e = reference_obj!(p).car.clone();
+        if is_match(&compare, func, &x, &e, env)? {
+            return Ok(Expression::Pair(p));
+        }
+    }
+    Ok(Expression::Boolean(false))
+}
+// ex. (assoc key alist) or (assoc key alist compare)
+fn assoc(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(&Expression, &Expression) -> bool,
+    max: usize,
+) -> ResultExpression {
+    if exp.len() < 3 || max < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let x = eval(&exp[1], env)?;
+    let l = pair::get_list(eval(&exp[2], env)?)?;
+    let compare = get_compare(exp, env)?;
+    for e in l {
+        let key = match &e {
+            Expression::Pair(p) => reference_obj!(p).car.clone(),
+            _ => return Err(create_error_value!(ErrCode::E1005, e)),
+        };
+        if is_match(&compare, func, &x, &key, env)? {
+            return Ok(e);
+        }
+    }
+    Ok(Expression::Boolean(false))
+}
+fn get_compare(exp: &[Expression], env: &Environment) -> Result<Option<Expression>, Error> {
+    if exp.len() < 4 {
+        return Ok(None);
+    }
+    match eval(&exp[3], env)? {
+        e if Expression::is_procedure(&e) => Ok(Some(e)),
+        e => Err(create_error_value!(ErrCode::E1006, e)),
+    }
+}
+fn is_match(
+    compare: &Option<Expression>,
+    func: fn(&Expression, &Expression) -> bool,
+    x: &Expression,
+    y: &Expression,
+    env: &Environment,
+) -> Result<bool, Error> {
+    match compare {
+        Some(f) => Ok(!matches!(
+            machine::apply(f, vec![x.clone(), y.clone()], env)?,
+            Expression::Boolean(false)
+        )),
+        None => Ok(func(x, y)),
+    }
+}
 pub fn make_evaled_list(
     callable: &Expression,
     exp: &[Expression],
@@ -491,11 +580,8 @@ pub fn make_evaled_list(
     let mut sexp: Vec<Expression> = Vec::new();
 
     fn set_evaled_list_inner(sexp: &mut Vec<Expression>, exp: &Expression) {
-        let ql: Vec<Expression> = vec![
-            Expression::BuildInFunction("quote".to_string(), quote),
-            exp.clone(),
-        ];
-        sexp.push(Environment::create_list(ql));
+        let ql: Vec<Expression> = vec![Expression::BuildInFunction("quote", quote), exp.clone()];
+        sexp.push(Environment::create_form(ql));
     }
 
     sexp.push(callable.clone());
@@ -527,39 +613,31 @@ fn do_list_proc(
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     let callable = eval(&exp[1], env)?;
+    let l = pair::get_list(eval(&exp[2], env)?)?;
 
-    match eval(&exp[2], env)? {
-        Expression::List(l) => {
-            let l = &*(reference_obj!(l));
-
-            let mut result: Vec<Expression> = Vec::new();
-
-            for e in l {
-                func(
-                    make_evaled_list(&callable, &[e.clone()], &None),
-                    env,
-                    &mut result,
-                    e,
-                )?;
-            }
-            Ok(Environment::create_list(result))
-        }
-        e => Err(create_error_value!(ErrCode::E1005, e)),
+    let mut result: Vec<Expression> = Vec::new();
+
+    for e in &l {
+        func(
+            make_evaled_list(&callable, std::slice::from_ref(e), &None),
+            env,
+            &mut result,
+            e,
+        )?;
     }
+    Ok(Environment::create_list(result))
 }
 fn set_car(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::List(r) => {
-            let mut l = mut_obj!(r);
-            if l.is_empty() {
-                return Err(create_error!(ErrCode::E1011));
-            }
-            l[0] = eval(&exp[2], env)?;
+        Expression::Pair(p) => {
+            let v = eval(&exp[2], env)?;
+            mut_obj!(p).car = v;
             Ok(Expression::Nil())
         }
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
         e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
@@ -568,27 +646,13 @@ fn set_cdr(exp: &[Expression], env: &Environment) -> ResultExpression {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::List(r) => {
-            let mut l = mut_obj!(r);
-            if l.is_empty() {
-                return Err(create_error!(ErrCode::E1011));
-            }
-
-            let e = eval(&exp[2], env)?;
-            let tmp = l[0].clone();
-            l.clear();
-            l.push(tmp);
-            match e {
-                Expression::List(m) => {
-                    let m = &*(reference_obj!(m));
-                    l.extend_from_slice(m);
-                }
-                _ => {
-                    l.push(e);
-                }
-            }
+        Expression::Pair(p) => {
+            // ex. (set-cdr! l l), the cyclic list is made.
+            let v = eval(&exp[2], env)?;
+            mut_obj!(p).cdr = v;
             Ok(Expression::Nil())
         }
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
         e => Err(create_error_value!(ErrCode::E1005, e)),
     }
 }
@@ -628,7 +692,7 @@ fn sort_impl(exp: &[Expression], env: &Environment, kind: SortKind) -> ResultExp
         } else {
             let func = eval(&exp[2], env)?;
             match func {
-                Expression::BuildInFunction(ref s, _) => match &s[..] {
+                Expression::BuildInFunction(s, _) => match s {
                     "string>?" | "string>=?" => {
                         return {
                             v.sort_by(|a, b| b.cmp(a));
@@ -694,26 +758,22 @@ fn sort_impl(exp: &[Expression], env: &Environment, kind: SortKind) -> ResultExp
     if 2 > exp.len() || 3 < exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let rc = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-    let effect = match &kind {
-        SortKind::Stable(k) => k,
-        SortKind::Unstable(k) => k,
-    };
-    match &effect {
-        ListProcKind::Copy => {
-            let l = &*(reference_obj!(rc));
-            let mut v = Vec::new();
-            v.extend_from_slice(&l[..]);
-            _sort_impl(exp, env, kind, &mut v)?;
-            Ok(Environment::create_list(v))
-        }
-        ListProcKind::Effect => {
-            _sort_impl(exp, env, kind, &mut mut_obj!(rc))?;
-            Ok(Expression::List(rc))
+    let l = eval(&exp[1], env)?;
+    let mut v = pair::get_list(l.clone())?;
+
+    let effect = matches!(
+        kind,
+        SortKind::Stable(ListProcKind::Effect) | SortKind::Unstable(ListProcKind::Effect)
+    );
+    _sort_impl(exp, env, kind, &mut v)?;
+    if effect {
+        // the sorted elements are set to the pairs of the list.
+        for (p, e) in pair::iter(&l).zip(v) {
+            mut_obj!(p).car = e;
         }
+        Ok(l)
+    } else {
+        Ok(Environment::create_list(v))
     }
 }
 fn merge(exp: &[Expression], env: &Environment) -> ResultExpression {
@@ -788,17 +848,8 @@ fn merge(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 3 > exp.len() || 4 < exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l1 = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-    let l1 = &*(reference_obj!(l1));
-
-    let l2 = match eval(&exp[2], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-    let l2 = &*(reference_obj!(l2));
+    let l1 = pair::get_list(eval(&exp[1], env)?)?;
+    let l2 = pair::get_list(eval(&exp[2], env)?)?;
 
     if exp.len() == 4 {
         let func = eval(&exp[3], env)?;
@@ -807,21 +858,17 @@ fn merge(exp: &[Expression], env: &Environment) -> ResultExpression {
             Expression::Function(_) => {}
             e => return Err(create_error_value!(ErrCode::E1006, e)),
         }
-        merge_iter_by(l1, l2, env, func)
+        merge_iter_by(&l1, &l2, env, func)
     } else {
-        Ok(merge_iter(l1, l2))
+        Ok(merge_iter(&l1, &l2))
     }
 }
 fn is_sorted(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 2 > exp.len() || 3 < exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
+    let l = pair::get_list(eval(&exp[1], env)?)?;
 
-    let l = &*(reference_obj!(l));
     if exp.len() == 2 {
         let b = &l[..].windows(2).all(|w| w[0] <= w[1]);
         Ok(Expression::Boolean(*b))
@@ -844,7 +891,7 @@ fn is_sorted(exp: &[Expression], env: &Environment) -> ResultExpression {
             }
         };
         match func {
-            Expression::BuildInFunction(ref s, _) => match &s[..] {
+            Expression::BuildInFunction(s, _) => match s {
                 "string>?" | "string>=?" | "char>?" | "char>=?" | ">=" | ">" => {
                     let b = &l[..].windows(2).all(|w| w[0] >= w[1]);
                     Ok(Expression::Boolean(*b))
@@ -875,39 +922,93 @@ fn make_vector(exp: &[Expression], env: &Environment) -> ResultExpression {
     Ok(Environment::create_vector(l))
 }
 fn vector_length(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_length(exp, env, ErrCode::E1022)
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = get_vector(eval(&exp[1], env)?)?;
+    let l = &*(reference_obj!(l));
+    Ok(Expression::Integer(l.len() as Int))
 }
 fn list_vector(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::List(l) => Ok(Expression::Vector(l)),
-        e => Err(create_error_value!(ErrCode::E1005, e)),
-    }
+    let v = pair::get_list(eval(&exp[1], env)?)?;
+    Ok(Environment::create_vector(v))
 }
 fn vector_list(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::Vector(l) => Ok(Expression::List(l)),
-        e => Err(create_error_value!(ErrCode::E1022, e)),
-    }
+    let l = get_vector(eval(&exp[1], env)?)?;
+    let v = reference_obj!(l).to_vec();
+    Ok(Environment::create_list(v))
 }
 fn vector_append(exp: &[Expression], env: &Environment) -> ResultExpression {
-    let v = seq_append(exp, env, ErrCode::E1022)?;
+    let v = seq_append(exp, env)?;
     Ok(Environment::create_vector(v))
 }
+fn seq_append(exp: &[Expression], env: &Environment) -> Result<Vec<Expression>, Error> {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v: Vec<Expression> = Vec::new();
+    for e in &exp[1..] {
+        let l = get_vector(eval(e, env)?)?;
+        let l = reference_obj!(l);
+        v.append(&mut l.to_vec());
+    }
+    Ok(v)
+}
 fn vector_append_effect(exp: &[Expression], env: &Environment) -> ResultExpression {
-    let v = seq_append_effect(exp, env, ErrCode::E1022)?;
-    Ok(Expression::Vector(v))
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let rc = get_vector(eval(&exp[1], env)?)?;
+    // ex. (vector-append! v v), the elements are copied before the update.
+    let mut v: Vec<Expression> = Vec::new();
+    for e in &exp[2..] {
+        let l = get_vector(eval(e, env)?)?;
+        v.extend_from_slice(&reference_obj!(l));
+    }
+    mut_obj!(&rc).extend(v);
+    Ok(Expression::Vector(rc))
 }
 fn vector_ref(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_list_ref(exp, env, ErrCode::E1022)
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = get_vector(eval(&exp[1], env)?)?;
+    let l = &*(reference_obj!(l));
+    match eval(&exp[2], env)? {
+        Expression::Integer(i) => {
+            if i < 0 || l.len() <= i as usize {
+                Err(create_error!(ErrCode::E1011))
+            } else {
+                Ok(l[i as usize].clone())
+            }
+        }
+        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
 }
 fn vector_set(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_list_set(exp, env, ErrCode::E1022)
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = get_vector(eval(&exp[1], env)?)?;
+    let i = match eval(&exp[2], env)? {
+        Expression::Integer(i) => i,
+        _ => {
+            return Err(create_error!(ErrCode::E1002));
+        }
+    };
+    let mut l = mut_obj!(l);
+    if i < 0 || l.len() <= i as usize {
+        return Err(create_error!(ErrCode::E1011));
+    }
+    l[i as usize] = eval(&exp[3], env)?;
+
+    Ok(Expression::Nil())
 }
 #[cfg(test)]
 mod tests {
@@ -988,6 +1089,33 @@ mod tests {
         assert_eq!(do_lisp_env("(cons #t a)", &env), "(#t 0 1 2 3 4 5 6 7 8 9)");
         assert_eq!(do_lisp_env("a", &env), "(0 1 2 3 4 5 6 7 8 9)");
         assert_eq!(do_lisp_env("b", &env), "(0 1 2 3 4 5 6 7 8 9)");
+        assert_eq!(do_lisp_env("(eq? (cdr (cons 1 a)) a)", &env), "#t");
+        assert_eq!(do_lisp("(cons 1 (cons 2 3))"), "(1 2 . 3)");
+    }
+    #[test]
+    fn shared_tail() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3))", &env);
+        do_lisp_env("(define b (cons 0 (cdr a)))", &env);
+        do_lisp_env("(set-cdr! (cdr a) '(10))", &env);
+        assert_eq!(do_lisp_env("a", &env), "(1 2 10)");
+        assert_eq!(do_lisp_env("b", &env), "(0 2 10)");
+
+        do_lisp_env("(define c (append '(5) a))", &env);
+        do_lisp_env("(set-car! a 100)", &env);
+        assert_eq!(do_lisp_env("c", &env), "(5 100 2 10)");
+        assert_eq!(do_lisp_env("(eq? (drop c 1) a)", &env), "#t");
+        assert_eq!(do_lisp_env("(eq? (memv 2 a) (cdr a))", &env), "#t");
+    }
+    #[test]
+    fn long_list() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (iota 1000000))", &env);
+        assert_eq!(do_lisp_env("(length a)", &env), "1000000");
+        assert_eq!(do_lisp_env("(car (drop a 999999))", &env), "999999");
+        assert_eq!(do_lisp_env("(car (reverse! a))", &env), "999999");
+        do_lisp_env("(set! a '())", &env);
+        assert_eq!(do_lisp_env("a", &env), "()");
     }
     #[test]
     fn append() {
@@ -998,6 +1126,10 @@ mod tests {
             "((10) 2 3)"
         );
         assert_eq!(do_lisp("(append (iota 5) (list 100))"), "(0 1 2 3 4 100)");
+        assert_eq!(do_lisp("(append '(1) 2)"), "(1 . 2)");
+        assert_eq!(do_lisp("(append '(1) '(2) '(3 . 4))"), "(1 2 3 . 4)");
+        assert_eq!(do_lisp("(append '() 10)"), "10");
+        assert_eq!(do_lisp("(append 10)"), "10");
 
         let env = lisp::Environment::new();
         do_lisp_env("(define a (iota 5))", &env);
@@ -1073,32 +1205,40 @@ mod tests {
             do_lisp_env("(delete #t a)", &env),
             "(10 10.5 3/5 \"ABC\" #\\a)"
         );
+        assert_eq!(do_lisp("(delete '(1) (list '(1) 2))"), "(2)");
+        assert_eq!(do_lisp("(delete 3 (list 1 2 3 4 5) <)"), "(1 2 3)");
     }
     #[test]
     fn delete_effect() {
         let env = lisp::Environment::new();
         do_lisp_env("(define a (list 10 10.5 3/5 \"ABC\" #\\a #t))", &env);
 
-        do_lisp_env("(delete! 10 a)", &env);
+        do_lisp_env("(set! a (delete! 10 a))", &env);
         assert_eq!(do_lisp_env("a", &env), "(10.5 3/5 \"ABC\" #\\a #t)");
 
         assert_eq!(
             do_lisp_env("(delete! 10.5 a)", &env),
             "(3/5 \"ABC\" #\\a #t)"
         );
+        assert_eq!(do_lisp_env("a", &env), "(10.5 3/5 \"ABC\" #\\a #t)");
+        do_lisp_env("(set! a (delete! 10.5 a))", &env);
         assert_eq!(do_lisp_env("(delete! 3/5 a)", &env), "(\"ABC\" #\\a #t)");
+        do_lisp_env("(set! a (delete! 3/5 a))", &env);
 
-        do_lisp_env("(delete! \"ABC\" a)", &env);
+        do_lisp_env("(set! a (delete! \"ABC\" a))", &env);
         assert_eq!(do_lisp_env("a", &env), "(#\\a #t)");
 
-        do_lisp_env("(delete! #\\a a)", &env);
-        assert_eq!(do_lisp_env("a", &env), "(#t)");
+        do_lisp_env("(delete! #t a)", &env);
+        assert_eq!(do_lisp_env("a", &env), "(#\\a)");
 
-        do_lisp_env("(delete! #f a)", &env);
-        assert_eq!(do_lisp_env("a", &env), "(#t)");
+        do_lisp_env("(set! a (delete! #f a))", &env);
+        assert_eq!(do_lisp_env("a", &env), "(#\\a)");
 
-        do_lisp_env("(delete! #t a)", &env);
-        assert_eq!(do_lisp_env("a", &env), "()");
+        assert_eq!(do_lisp_env("(delete! #\\a a)", &env), "()");
+
+        do_lisp_env("(define b (list 1 2 3 4))", &env);
+        do_lisp_env("(delete! 2 b (lambda (x y) (set-car! b 0) (< x y)))", &env);
+        assert_eq!(do_lisp_env("b", &env), "(0 2)");
     }
     #[test]
     fn last() {
@@ -1113,6 +1253,14 @@ mod tests {
         assert_eq!(do_lisp("(reverse (list))"), "()");
     }
     #[test]
+    fn reverse_effect() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3))", &env);
+        assert_eq!(do_lisp_env("(reverse! a)", &env), "(3 2 1)");
+        assert_eq!(do_lisp_env("a", &env), "(1)");
+        assert_eq!(do_lisp("(reverse! (list))"), "()");
+    }
+    #[test]
     fn iota() {
         assert_eq!(do_lisp("(iota 10)"), "(0 1 2 3 4 5 6 7 8 9)");
         assert_eq!(do_lisp("(iota 10 1)"), "(1 2 3 4 5 6 7 8 9 10)");
@@ -1161,6 +1309,11 @@ mod tests {
             do_lisp_env("(map (lambda (n) (car n)) d)", &env),
             "((1) (2) (3))"
         );
+        assert_eq!(do_lisp("(map + (list 1 2 3) (list 10 20))"), "(11 22)");
+        assert_eq!(
+            do_lisp("(map list '(1 2) '(a b) '(x y))"),
+            "((1 a x) (2 b y))"
+        );
     }
     #[test]
     fn filter() {
@@ -1190,6 +1343,23 @@ mod tests {
             do_lisp_env("(filter (lambda (n) (not (= n 100))) (list a b c))", &env),
             "(200 300)"
         );
+        assert_eq!(
+            do_lisp("(length (filter (lambda (n) (< n 50000)) (iota 100000)))"),
+            "50000"
+        );
+        do_lisp_env("(define k #f)", &env);
+        do_lisp_env("(define n 0)", &env);
+        do_lisp_env(
+            "(define (f x) (call/cc (lambda (c) (if (= x 2) (set! k c)) #t)))",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(let ((r (filter f (list 1 2 3)))) (if (= n 0) (begin (set! n 1) (k #f)) r))",
+                &env
+            ),
+            "(1 3)"
+        );
     }
     #[test]
     fn reduce() {
@@ -1212,6 +1382,11 @@ mod tests {
         );
         assert_eq!(do_lisp("(reduce 0 (list) (list))"), "()");
         assert_eq!(do_lisp("(reduce + 10 (list 1 2 3))"), "6");
+        assert_eq!(do_lisp("(reduce - 0 (list 1 2 3))"), "-4");
+        assert_eq!(
+            do_lisp("(reduce (lambda (a b) (+ a b)) 0 (iota 100000))"),
+            "4999950000"
+        );
     }
     #[test]
     fn for_each() {
@@ -1219,6 +1394,11 @@ mod tests {
         do_lisp_env("(define c 0)", &env);
         do_lisp_env("(for-each (lambda (n) (set! c (+ c n)))(iota 5))", &env);
         assert_eq!(do_lisp_env("c", &env), "10");
+        do_lisp_env(
+            "(for-each (lambda (n m) (set! c (+ c (* n m))))(iota 3)(iota 5))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("c", &env), "15");
     }
     #[test]
     fn list_ref() {
@@ -1253,10 +1433,54 @@ mod tests {
         let env = lisp::Environment::new();
         do_lisp_env("(define a (list 1 2 3 4 5))", &env);
         do_lisp_env("(set-cdr! a 100)", &env);
-        assert_eq!(do_lisp_env("a", &env), "(1 100)");
+        assert_eq!(do_lisp_env("a", &env), "(1 . 100)");
 
         do_lisp_env("(set-cdr! a (list 10 20))", &env);
         assert_eq!(do_lisp_env("a", &env), "(1 10 20)");
+
+        do_lisp_env("(define b (cdr a))", &env);
+        do_lisp_env("(set-car! b 30)", &env);
+        assert_eq!(do_lisp_env("a", &env), "(1 30 20)");
+
+        do_lisp_env("(set-cdr! a a)", &env);
+        assert_eq!(do_lisp_env("a", &env), "#0=(1 . #0#)");
+    }
+    #[test]
+    fn member() {
+        assert_eq!(do_lisp("(memq 'c '(a b c d))"), "(c d)");
+        assert_eq!(do_lisp("(memq 'e '(a b c d))"), "#f");
+        assert_eq!(do_lisp("(memq (list 1) (list (list 1)))"), "#f");
+        assert_eq!(do_lisp("(memv 2.5 '(1.5 2.5 3.5))"), "(2.5 3.5)");
+        assert_eq!(do_lisp("(memv 101 '(100 101 102))"), "(101 102)");
+        assert_eq!(do_lisp("(member (list 'a) '(b (a) c))"), "((a) c)");
+        assert_eq!(do_lisp("(member \"b\" '(\"a\" \"b\"))"), "(\"b\")");
+        assert_eq!(do_lisp("(member 2.0 '(1 2 3) =)"), "(2 3)");
+        assert_eq!(
+            do_lisp("(member 5 '(1 7 3) (lambda (a b) (< a b)))"),
+            "(7 3)"
+        );
+        assert_eq!(do_lisp("(member 1 '())"), "#f");
+    }
+    #[test]
+    fn assoc() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define e '((a 1) (b 2) (c 3)))", &env);
+        assert_eq!(do_lisp_env("(assq 'a e)", &env), "(a 1)");
+        assert_eq!(do_lisp_env("(assq 'b e)", &env), "(b 2)");
+        assert_eq!(do_lisp_env("(assq 'd e)", &env), "#f");
+        assert_eq!(do_lisp("(assq (list 'a) '(((a)) ((b)) ((c))))"), "#f");
+        assert_eq!(do_lisp("(assoc (list 'a) '(((a)) ((b)) ((c))))"), "((a))");
+        assert_eq!(do_lisp("(assoc 2.0 '((1 1) (2 4) (3 9)) =)"), "(2 4)");
+        assert_eq!(do_lisp("(assv 5 '((2 3) (5 7) (11 13)))"), "(5 7)");
+        assert_eq!(
+            do_lisp("(assoc \"b\" (list (cons \"a\" 1) (cons \"b\" 2)))"),
+            "(\"b\" . 2)"
+        );
+        assert_eq!(
+            do_lisp("(cdr (assoc \"Content-Type\" (list (cons \"Content-Type\" \"text/html\"))))"),
+            "\"text/html\""
+        );
+        assert_eq!(do_lisp("(assoc 1 '())"), "#f");
     }
     #[test]
     fn sort() {
@@ -1703,7 +1927,12 @@ mod tests {
         assert_eq!(do_lisp("(vector-ref (list->vector (iota 10)) 8)"), "8");
         assert_eq!(do_lisp("(vector-ref (list->vector (iota 10)) 9)"), "9");
         assert_eq!(do_lisp("(vector-ref #(#\\a #\\b #\\c) 1)"), "#\\b");
-        assert_eq!(do_lisp("(vector-ref #((vector 0 1) 1 2 3) 0)"), "#(0 1)");
+        assert_eq!(do_lisp("(vector-ref #(#(0 1) 1 2 3) 0)"), "#(0 1)");
+        assert_eq!(
+            do_lisp("(vector-ref #((vector 0 1) 1 2 3) 0)"),
+            "(vector 0 1)"
+        );
+        assert_eq!(do_lisp("(symbol? (vector-ref #(a b) 0))"), "#t");
     }
     #[test]
     fn vector_set() {
@@ -1778,8 +2007,8 @@ mod error_tests {
     #[test]
     fn append() {
         assert_eq!(do_lisp("(append)"), "E1007");
-        assert_eq!(do_lisp("(append 10)"), "E1005");
-        assert_eq!(do_lisp("(append (list 1) 105)"), "E1005");
+        assert_eq!(do_lisp("(append 10 (list 1))"), "E1005");
+        assert_eq!(do_lisp("(append '(1 . 2) (list 1))"), "E1005");
         assert_eq!(do_lisp("(append (list 1) a)"), "E1008");
     }
     #[test]
@@ -1815,7 +2044,8 @@ mod error_tests {
     fn delete() {
         assert_eq!(do_lisp("(delete)"), "E1007");
         assert_eq!(do_lisp("(delete 10)"), "E1007");
-        assert_eq!(do_lisp("(delete 10 (list 10 20) 3)"), "E1007");
+        assert_eq!(do_lisp("(delete 10 (list 10 20) = 3)"), "E1007");
+        assert_eq!(do_lisp("(delete 10 (list 10 20) 3)"), "E1006");
         assert_eq!(do_lisp("(delete 10 20)"), "E1005");
         assert_eq!(do_lisp("(delete 10 a)"), "E1008");
     }
@@ -1823,7 +2053,8 @@ mod error_tests {
     fn delete_effect() {
         assert_eq!(do_lisp("(delete!)"), "E1007");
         assert_eq!(do_lisp("(delete! 10)"), "E1007");
-        assert_eq!(do_lisp("(delete! 10 (list 10 20) 3)"), "E1007");
+        assert_eq!(do_lisp("(delete! 10 (list 10 20) = 3)"), "E1007");
+        assert_eq!(do_lisp("(delete! 10 (list 10 20) 3)"), "E1006");
         assert_eq!(do_lisp("(delete! 10 20)"), "E1005");
         assert_eq!(do_lisp("(delete! 10 a)"), "E1008");
     }
@@ -1843,6 +2074,12 @@ mod error_tests {
         assert_eq!(do_lisp("(reverse a)"), "E1008");
     }
     #[test]
+    fn reverse_effect() {
+        assert_eq!(do_lisp("(reverse!)"), "E1007");
+        assert_eq!(do_lisp("(reverse! (list 1)(list 2))"), "E1007");
+        assert_eq!(do_lisp("(reverse! 29)"), "E1005");
+    }
+    #[test]
     fn iota() {
         assert_eq!(do_lisp("(iota)"), "E1007");
         assert_eq!(do_lisp("(iota 1 2 3 4)"), "E1007");
@@ -1859,7 +2096,8 @@ mod error_tests {
             do_lisp("(map (lambda (a b) (* 10 a)) (list 1 2 3))"),
             "E1007"
         );
-        assert_eq!(do_lisp("(map 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(map 1 2 3)"), "E1006");
+        assert_eq!(do_lisp("(map car '(1) 3)"), "E1005");
         assert_eq!(do_lisp("(map (iota 10) (iota 10))"), "E1006");
         assert_eq!(do_lisp("(map  (lambda (n) n) 10)"), "E1005");
     }
@@ -1888,7 +2126,8 @@ mod error_tests {
     fn for_each() {
         assert_eq!(do_lisp("(for-each)"), "E1007");
         assert_eq!(do_lisp("(for-each (lambda (n) n))"), "E1007");
-        assert_eq!(do_lisp("(for-each 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(for-each 1 2 3)"), "E1006");
+        assert_eq!(do_lisp("(for-each car '(1) 3)"), "E1005");
         assert_eq!(do_lisp("(for-each 10 (list 1))"), "E1006");
         assert_eq!(do_lisp("(for-each (lambda (n) n) 10)"), "E1005");
     }
@@ -1938,6 +2177,27 @@ mod error_tests {
         assert_eq!(do_lisp("(set-cdr! () 20)"), "E1011");
     }
     #[test]
+    fn member() {
+        assert_eq!(do_lisp("(memq 1)"), "E1007");
+        assert_eq!(do_lisp("(memq 1 '(1) =)"), "E1007");
+        assert_eq!(do_lisp("(member 1 '(1) = =)"), "E1007");
+        assert_eq!(do_lisp("(member a '(1))"), "E1008");
+        assert_eq!(do_lisp("(member 1 10)"), "E1005");
+        assert_eq!(do_lisp("(member 1 '(1) 10)"), "E1006");
+        assert_eq!(do_lisp("(member 1 '(1) (lambda (a) a))"), "E1007");
+    }
+    #[test]
+    fn assoc() {
+        assert_eq!(do_lisp("(assq 1)"), "E1007");
+        assert_eq!(do_lisp("(assq 1 '((1 2)) =)"), "E1007");
+        assert_eq!(do_lisp("(assoc 1 '((1 2)) = =)"), "E1007");
+        assert_eq!(do_lisp("(assoc a '((1 2)))"), "E1008");
+        assert_eq!(do_lisp("(assoc 1 10)"), "E1005");
+        assert_eq!(do_lisp("(assoc 1 '(1 2))"), "E1005");
+        assert_eq!(do_lisp("(assoc 1 '(()))"), "E1005");
+        assert_eq!(do_lisp("(assoc 1 '((1 2)) 10)"), "E1006");
+    }
+    #[test]
     fn sort() {
         assert_eq!(do_lisp("(sort)"), "E1007");
         assert_eq!(do_lisp("(sort (list 1) + +)"), "E1007");
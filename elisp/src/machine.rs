@@ -0,0 +1,1556 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec::Vec;
+
+#[cfg(feature = "signal")]
+use crate::unix::signal::catch_sig_intr_status;
+
+use crate::compile::Next;
+use crate::create_continuation;
+use crate::create_error;
+use crate::create_error_value;
+use crate::exception::{error_object, raise_error};
+use crate::hashtable;
+use crate::lisp::{Environment, Expression, FunctionRc, ListRc, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::list::{self, make_evaled_list};
+use crate::pair;
+use crate::parameter::{self, Parameter};
+use crate::reference_obj;
+use crate::source::Call;
+use crate::srfi1;
+use crate::symbol::Symbol;
+use crate::syntax::{expand, let_setup, quoted};
+use crate::util::eqv;
+use crate::vm::{Vm, Yield};
+
+// The builtin functions which take the unevaluated arguments.
+// They are called with the original form, the others are called with the evaluated values.
+pub(crate) const SYNTAX: [&str; 30] = [
+    "define",
+    "lambda",
+    "let",
+    "let*",
+    "letrec",
+    "letrec*",
+    "let-values",
+    "let*-values",
+    "define-values",
+    "receive",
+    "define-record-type",
+    "set!",
+    "if",
+    "and",
+    "or",
+    "cond",
+    "case",
+    "begin",
+    "delay",
+    "quote",
+    "quasiquote",
+    "do",
+    "case-lambda",
+    "define-syntax",
+    "let-syntax",
+    "letrec-syntax",
+    "syntax-rules",
+    "time",
+    "guard",
+    "parameterize",
+];
+
+// The builtin functions which are run by the machine, with the evaluated values.
+pub(crate) const NATIVE: [&str; 11] = [
+    "apply",
+    "map",
+    "for-each",
+    "call/cc",
+    "call-with-current-continuation",
+    "dynamic-wind",
+    "with-exception-handler",
+    "raise-continuable",
+    "call-with-values",
+    "eval",
+    "make-parameter",
+];
+
+// The request of the builtin function which applies the procedures.
+pub(crate) enum Step {
+    Apply(Expression, Vec<Expression>),
+    Return(Expression),
+}
+// The builtin function which applies the procedures, ex. fold
+// it's resumed with the value of the last application (None at first).
+pub(crate) trait Iterate {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error>;
+    fn box_clone(&self) -> IterateBox;
+}
+#[cfg(not(feature = "thread"))]
+pub(crate) type IterateBox = Box<dyn Iterate>;
+#[cfg(feature = "thread")]
+pub(crate) type IterateBox = Box<dyn Iterate + Send + Sync>;
+
+impl Clone for IterateBox {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+// It's called with the evaluated values, the function is placed at first.
+pub(crate) type Start = fn(&[Expression]) -> Result<IterateBox, Error>;
+
+// The number of the procedures in the backtrace.
+const TRACE_LIMIT: usize = 32;
+
+static RUN_ID: AtomicUsize = AtomicUsize::new(1);
+static WIND_ID: AtomicUsize = AtomicUsize::new(1);
+static GUARD_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    // machines which are running on this thread (outermost first)
+    static RUNS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    // dynamic-wind (outermost first)
+    static WINDS: RefCell<Vec<Wind>> = const { RefCell::new(Vec::new()) };
+    // exception handlers (outermost first)
+    static HANDLERS: RefCell<Vec<Handler>> = const { RefCell::new(Vec::new()) };
+}
+#[derive(Clone)]
+struct Wind {
+    id: usize,
+    before: Expression,
+    after: Expression,
+    env: Environment,
+}
+#[derive(Clone)]
+enum Handler {
+    Proc(Expression, Environment),
+    Guard(usize),
+}
+// The captured control stack, it can be invoked any number of times.
+#[derive(Clone)]
+pub struct Continuation {
+    stack: Vec<(Frame, Option<Active>)>,
+    winds: Vec<Wind>,
+    handlers: Vec<Handler>,
+    base: usize,
+}
+// The activation of the lisp procedure, id is unique in the machine.
+#[derive(Clone)]
+pub(crate) struct Active {
+    func: FunctionRc,
+    id: usize,
+}
+impl Active {
+    pub(crate) fn new(func: &FunctionRc, id: usize) -> Self {
+        Active {
+            func: func.clone(),
+            id,
+        }
+    }
+}
+#[derive(Clone)]
+enum Body {
+    List(ListRc),
+    Func(FunctionRc),
+}
+impl Body {
+    // return (expression, is last)
+    fn get(&self, idx: usize) -> Option<(Expression, bool)> {
+        let get = |v: &[Expression]| v.get(idx).map(|e| (e.clone(), idx + 1 == v.len()));
+        match self {
+            Body::List(l) => get(&reference_obj!(l)),
+            Body::Func(f) => get(f.body()),
+        }
+    }
+}
+#[derive(Clone)]
+enum Frame {
+    Head {
+        form: ListRc,
+        env: Environment,
+    },
+    Call {
+        form: ListRc,
+        idx: usize,
+        vals: Vec<Expression>,
+        env: Environment,
+        builtin: bool,
+    },
+    Seq {
+        body: Body,
+        idx: usize,
+        env: Environment,
+    },
+    If {
+        form: ListRc,
+        env: Environment,
+    },
+    Define {
+        name: Symbol,
+        env: Environment,
+    },
+    Set {
+        name: Symbol,
+        env: Environment,
+    },
+    Logic {
+        form: ListRc,
+        idx: usize,
+        and: bool,
+        env: Environment,
+    },
+    Bool,
+    Cond {
+        form: ListRc,
+        idx: usize,
+        env: Environment,
+    },
+    Case {
+        form: ListRc,
+        env: Environment,
+    },
+    Clause {
+        form: ListRc,
+        idx: usize,
+        env: Environment,
+        error: Box<Error>,
+    },
+    Receiver {
+        value: Expression,
+        env: Environment,
+    },
+    Map {
+        func: Expression,
+        list: Expression,
+        acc: Vec<Expression>,
+        collect: bool,
+        env: Environment,
+    },
+    WindBody {
+        wind: Wind,
+        thunk: Expression,
+    },
+    WindAfter {
+        wind: Wind,
+    },
+    Restore(Expression),
+    MakeParameter(Expression),
+    Parameterize {
+        binds: Vec<Expression>,
+        vals: Vec<Expression>,
+        convert: bool,
+        body: ListRc,
+        env: Environment,
+    },
+    Values {
+        consumer: Expression,
+        env: Environment,
+    },
+    Handlers(Vec<Handler>),
+    Raise(Expression),
+    Guard {
+        id: usize,
+        var: Symbol,
+        clauses: Vec<Expression>,
+        handlers: Vec<Handler>,
+        winds: usize,
+        env: Environment,
+    },
+    Iterate(IterateBox, Environment),
+    Vm(Vm),
+}
+impl Frame {
+    // The form which is being evaluated, used for the error report.
+    fn form(&self) -> Option<ListRc> {
+        match self {
+            Frame::Head { form, .. }
+            | Frame::Call { form, .. }
+            | Frame::If { form, .. }
+            | Frame::Logic { form, .. }
+            | Frame::Cond { form, .. }
+            | Frame::Clause { form, .. }
+            | Frame::Case { form, .. } => Some(form.clone()),
+            Frame::Seq {
+                body: Body::List(l),
+                ..
+            } => Some(l.clone()),
+            _ => None,
+        }
+    }
+    // The subform which is evaluated now, ex. (bar x) of (+ x (bar x))
+    fn child(&self) -> Option<ListRc> {
+        let nth = |form: &ListRc, i: usize| reference_obj!(form).get(i).cloned();
+        let e = match self {
+            Frame::Head { form, .. } => nth(form, 0),
+            Frame::If { form, .. } | Frame::Case { form, .. } => nth(form, 1),
+            Frame::Call { form, idx, .. } | Frame::Logic { form, idx, .. } => nth(form, *idx),
+            Frame::Cond { form, idx, .. } => match nth(form, *idx) {
+                Some(Expression::List(l)) => nth(&l, 0),
+                _ => None,
+            },
+            Frame::Seq { body, idx, .. } => body.get(idx - 1).map(|(e, _)| e),
+            _ => None,
+        };
+        match e {
+            Some(Expression::List(l)) => Some(l),
+            _ => None,
+        }
+    }
+}
+enum State {
+    Eval(Expression, Environment),
+    Return(Expression),
+    Apply(Expression, Vec<Expression>, Environment),
+}
+// The forms are evaluated, even if it's called from the builtin function with the values.
+pub fn run(exp: &Expression, env: &Environment) -> ResultExpression {
+    Machine::execute(State::Eval(exp.clone(), env.evaluated(false)), env)
+}
+pub fn apply(func: &Expression, args: Vec<Expression>, env: &Environment) -> ResultExpression {
+    Machine::execute(State::Apply(func.clone(), args, env.evaluated(false)), env)
+}
+// The builtin function which is run by the machine, ex. (fold + 0 '(1 2))
+pub(crate) fn native(exp: &[Expression], env: &Environment) -> ResultExpression {
+    run(&Environment::create_form(exp.to_vec()), env)
+}
+fn start(name: &str) -> Option<Start> {
+    srfi1::start(name)
+        .or_else(|| hashtable::start(name))
+        .or_else(|| list::start(name))
+}
+pub(crate) fn is_native(name: &str) -> bool {
+    NATIVE.contains(&name) || start(name).is_some()
+}
+pub(crate) fn lookup(s: &Symbol, env: &Environment) -> ResultExpression {
+    match env.find_symbol(s) {
+        Some(v) => Ok(v),
+        None if s.origin().is_some() => {
+            let (s, env) = alias(s, env).unwrap();
+            lookup(&s, &env)
+        }
+        // ex. :key (keyword for #!key parameter)
+        None if s.len() > 1 && s.starts_with(':') => Ok(Expression::Symbol(s.clone())),
+        // ex. (eval (list (string->symbol "+") 1 2) env)
+        None => match env.get_builtin_func(s) {
+            Some((name, f)) if !env.is_syntax_only() || SYNTAX.contains(&name) => {
+                Ok(Expression::BuildInFunction(name, f))
+            }
+            Some(_) => Err(create_error_value!(ErrCode::E1008, s)),
+            None => match env.get_builtin_ext_func(s) {
+                Some(f) if !env.is_syntax_only() => Ok(Expression::BuildInFunctionExt(f)),
+                _ => Err(create_error_value!(ErrCode::E1008, s)),
+            },
+        },
+    }
+}
+// The renamed identifier of the macro which isn't bound by the expansion,
+// refers to the original symbol in the environment of the definition.
+fn alias(s: &Symbol, env: &Environment) -> Option<(Symbol, Environment)> {
+    let (origin, scope) = s.origin()?;
+    let scope = env.get_scope(scope).unwrap_or_else(|| env.clone());
+    Some((origin.clone(), scope))
+}
+pub(crate) fn find(s: &Symbol, env: &Environment) -> Option<Expression> {
+    match env.find_symbol(s) {
+        Some(v) => Some(v),
+        None => {
+            let (s, env) = alias(s, env)?;
+            find(&s, &env)
+        }
+    }
+}
+// The variable and the environment which set! updates.
+pub(crate) fn bound(s: &Symbol, env: &Environment) -> Option<(Symbol, Environment)> {
+    if env.find_symbol(s).is_some() {
+        return Some((s.clone(), env.clone()));
+    }
+    let (s, env) = alias(s, env)?;
+    bound(&s, &env)
+}
+// The builtin functions except the syntax are unbound, ex. (null-environment 7)
+fn unbind_builtin(e: &Expression) -> Expression {
+    match e {
+        Expression::BuildInFunction(name, _) if !SYNTAX.contains(name) => {
+            Expression::Symbol(Symbol::from(*name))
+        }
+        Expression::List(l) => {
+            Environment::create_form(reference_obj!(l).iter().map(unbind_builtin).collect())
+        }
+        _ => e.clone(),
+    }
+}
+// the values of the call, the function is placed at first
+fn first(form: &ListRc, func: &Expression) -> Vec<Expression> {
+    let mut v = Vec::with_capacity(reference_obj!(form).len());
+    v.push(func.clone());
+    v
+}
+// The evaluated values with the function at first, ex. [fold, +, 0, (1 2)]
+fn make_args(func: &Expression, args: Vec<Expression>) -> Vec<Expression> {
+    let mut v = Vec::with_capacity(args.len() + 1);
+    v.push(func.clone());
+    v.extend(args);
+    v
+}
+fn list_rc(v: Vec<Expression>) -> ListRc {
+    match Environment::create_form(v) {
+        Expression::List(l) => l,
+        _ => unreachable!(),
+    }
+}
+// run after thunks until the depth
+fn unwind(depth: usize) {
+    loop {
+        let w = WINDS.with(|w| {
+            let mut w = w.borrow_mut();
+            if w.len() > depth {
+                w.pop()
+            } else {
+                None
+            }
+        });
+        match w {
+            Some(w) => {
+                if let Err(e) = apply(&w.after, Vec::new(), &w.env) {
+                    error!("{}", e.get_msg());
+                }
+            }
+            None => break,
+        }
+    }
+}
+fn rewind(winds: &[Wind]) -> Result<(), Error> {
+    let common = WINDS.with(|w| {
+        w.borrow()
+            .iter()
+            .zip(winds)
+            .take_while(|(a, b)| a.id == b.id)
+            .count()
+    });
+    while let Some(w) = WINDS.with(|w| {
+        let mut w = w.borrow_mut();
+        if w.len() > common {
+            w.pop()
+        } else {
+            None
+        }
+    }) {
+        apply(&w.after, Vec::new(), &w.env)?;
+    }
+    for w in &winds[common..] {
+        apply(&w.before, Vec::new(), &w.env)?;
+        WINDS.with(|v| v.borrow_mut().push(w.clone()));
+    }
+    Ok(())
+}
+pub(crate) fn check(env: &Environment) -> Result<(), Error> {
+    #[cfg(feature = "signal")]
+    catch_sig_intr_status(env);
+
+    if env.is_limit_stop() && env.inc_eval_count() > 100_000_000 {
+        return Err(create_error!(ErrCode::E9000));
+    }
+    if env.is_force_stop() {
+        return Err(create_error!(ErrCode::E9000));
+    }
+    Ok(())
+}
+// Each frame has the activation which pushed it, they are the backtrace.
+struct Machine {
+    id: usize,
+    stack: Vec<(Frame, Option<Active>)>,
+    proc: Option<Active>,
+    form: Option<ListRc>,
+    calls: usize,
+    // the vm which has finished, it's used again for the next call.
+    spare: Option<Vm>,
+}
+impl Machine {
+    fn execute(state: State, env: &Environment) -> ResultExpression {
+        let mut m = Machine {
+            id: RUN_ID.fetch_add(1, Ordering::Relaxed),
+            stack: Vec::new(),
+            proc: None,
+            form: None,
+            calls: 0,
+            spare: None,
+        };
+        let depth = WINDS.with(|w| w.borrow().len());
+        let handlers = HANDLERS.with(|h| h.borrow().clone());
+        RUNS.with(|r| r.borrow_mut().push(m.id));
+
+        let mut ret = m.run(state);
+
+        RUNS.with(|r| r.borrow_mut().pop());
+        if let Err(e) = &mut ret {
+            if e.code != ErrCode::Cont {
+                m.trace(e, env);
+                // the outer machine adds its procedures.
+                e.traced = false;
+                HANDLERS.with(|h| *h.borrow_mut() = handlers);
+                unwind(depth);
+            }
+        }
+        ret
+    }
+    fn push(&mut self, frame: Frame) {
+        self.stack.push((frame, self.proc.clone()));
+    }
+    // The lisp location and the active procedures are added, the innermost first.
+    // The error which has been traced by the inner machine, keeps its location.
+    fn trace(&self, e: &mut Error, env: &Environment) {
+        if e.traced {
+            return;
+        }
+        e.traced = true;
+        let top = (self.form.clone(), self.proc.as_ref());
+        let frames = self.stack.iter().rev().flat_map(|(f, proc)| match f {
+            Frame::Vm(vm) => vm.trace(),
+            // the call in the function is located, as the vm does.
+            f => {
+                let form = f.child().filter(|l| env.get_position(l).is_some());
+                vec![(form.or_else(|| f.form()), proc.as_ref())]
+            }
+        });
+        let mut last = None;
+        for (form, proc) in std::iter::once(top).chain(frames) {
+            let pos = form.and_then(|l| env.get_position(&l));
+            if e.pos.is_none() {
+                e.pos = pos.clone().map(Box::new);
+            }
+            match proc {
+                Some(a) if last != Some(a.id) => {
+                    if e.trace.len() >= TRACE_LIMIT {
+                        break;
+                    }
+                    last = Some(a.id);
+                    e.trace.push(Call {
+                        name: a.func.get_name().to_string(),
+                        pos,
+                    });
+                }
+                Some(_) => {
+                    if let Some(c) = e.trace.last_mut() {
+                        if c.pos.is_none() {
+                            c.pos = pos;
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+    fn run(&mut self, mut state: State) -> ResultExpression {
+        loop {
+            let next = match state {
+                State::Eval(exp, env) => {
+                    if let Expression::List(l) = &exp {
+                        self.form = Some(l.clone());
+                    }
+                    self.eval(exp, env)
+                }
+                State::Return(v) => match self.stack.pop() {
+                    Some((f, proc)) => {
+                        self.proc = proc;
+                        self.form = f.form();
+                        self.resume(f, v)
+                    }
+                    None => return Ok(v),
+                },
+                State::Apply(func, args, env) => self.apply(func, args, env),
+            };
+            state = match next {
+                Ok(s) => s,
+                Err(e) if e.code == ErrCode::Cont => self.throw(e)?,
+                Err(e) => self.handle(e, false)?,
+            };
+        }
+    }
+    // A continuation is installed by the machine which captured it.
+    // If the machine has already finished, the outermost machine installs it.
+    fn throw(&mut self, e: Error) -> Result<State, Error> {
+        let kv = match &e.exp {
+            Some(Expression::List(l)) => match &reference_obj!(l)[..] {
+                [Expression::Continuation(k), v] => Some((k.clone(), v.clone())),
+                _ => None,
+            },
+            _ => None,
+        };
+        let (k, v) = match kv {
+            Some(kv) => kv,
+            None => return Err(e),
+        };
+        let install = RUNS.with(|r| {
+            let r = r.borrow();
+            k.base == self.id || (!r.contains(&k.base) && r.first() == Some(&self.id))
+        });
+        if !install {
+            return Err(e);
+        }
+        rewind(&k.winds)?;
+        let handlers = k.handlers;
+        HANDLERS.with(|h| *h.borrow_mut() = handlers);
+        self.stack = k.stack;
+        self.proc = None;
+        Ok(State::Return(v))
+    }
+    fn eval(&mut self, exp: Expression, env: Environment) -> Result<State, Error> {
+        check(&env)?;
+        let form = match &exp {
+            Expression::Symbol(s) => return Ok(State::Return(lookup(s, &env)?)),
+            Expression::List(l) => l.clone(),
+            _ => return Ok(State::Return(exp)),
+        };
+        // the form is not borrowed in the call, ex. the quote form is updated.
+        let head = reference_obj!(form).first().cloned();
+        let func = match head {
+            Some(Expression::Symbol(s)) => lookup(&s, &env)?,
+            Some(e @ Expression::List(_)) => {
+                self.push(Frame::Head {
+                    form: form.clone(),
+                    env: env.clone(),
+                });
+                return Ok(State::Eval(e, env));
+            }
+            Some(e) => return self.call(form, &e, env),
+            None => return Ok(State::Return(Expression::Null())),
+        };
+        self.call(form, &func, env)
+    }
+    fn call(&mut self, form: ListRc, func: &Expression, env: Environment) -> Result<State, Error> {
+        let n = reference_obj!(form).len() - 1;
+        match func {
+            Expression::BuildInFunction(name, f) => self.call_builtin(form, func, name, *f, env),
+            Expression::BuildInFunctionExt(f) => Ok(State::Return(f(&reference_obj!(form), &env)?)),
+            Expression::Syntax(s) => {
+                let e = s.expand(&reference_obj!(form))?;
+                Ok(State::Eval(e, env))
+            }
+            Expression::Function(f) => {
+                if !f.is_acceptable(n) {
+                    return Err(f.arity_error(n));
+                }
+                {
+                    let vals = first(&form, func);
+                    self.next_arg(form, 1, vals, env, false)
+                }
+            }
+            Expression::Continuation(_) => {
+                if n != 1 {
+                    return Err(create_error_value!(ErrCode::E1007, n + 1));
+                }
+                {
+                    let vals = first(&form, func);
+                    self.next_arg(form, 1, vals, env, false)
+                }
+            }
+            Expression::Parameter(p) => {
+                if n != 0 {
+                    return Err(create_error_value!(ErrCode::E1007, n + 1));
+                }
+                Ok(State::Return(p.get()))
+            }
+            _ => Err(create_error_value!(ErrCode::E1006, func)),
+        }
+    }
+    fn call_builtin(
+        &mut self,
+        form: ListRc,
+        func: &Expression,
+        name: &str,
+        f: fn(&[Expression], &Environment) -> ResultExpression,
+        env: Environment,
+    ) -> Result<State, Error> {
+        if name == "quote" && reference_obj!(form).len() == 2 {
+            return Ok(State::Return(quoted(&form)));
+        }
+        let v = &*(reference_obj!(form));
+        match name {
+            "if" if v.len() >= 3 => {
+                self.push(Frame::If {
+                    form: form.clone(),
+                    env: env.clone(),
+                });
+                Ok(State::Eval(v[1].clone(), env))
+            }
+            "define" | "set!" if v.len() == 3 && Expression::is_symbol(&v[1]) => {
+                let s = match &v[1] {
+                    Expression::Symbol(s) => s.clone(),
+                    _ => unreachable!(),
+                };
+                if name == "define" {
+                    self.push(Frame::Define {
+                        name: s,
+                        env: env.clone(),
+                    });
+                } else {
+                    let (name, env) = match bound(&s, &env) {
+                        Some(b) => b,
+                        None => return Err(create_error_value!(ErrCode::E1008, s)),
+                    };
+                    self.push(Frame::Set { name, env });
+                }
+                Ok(State::Eval(v[2].clone(), env))
+            }
+            "begin" if v.len() >= 2 => Ok(self.seq(Body::List(form.clone()), 1, env)),
+            "and" | "or" if v.len() >= 3 => {
+                self.push(Frame::Logic {
+                    form: form.clone(),
+                    idx: 1,
+                    and: name == "and",
+                    env: env.clone(),
+                });
+                Ok(State::Eval(v[1].clone(), env))
+            }
+            "cond" if v.len() >= 2 => self.cond(form.clone(), 1, env),
+            "case" if v.len() >= 2 => {
+                self.push(Frame::Case {
+                    form: form.clone(),
+                    env: env.clone(),
+                });
+                Ok(State::Eval(v[1].clone(), env))
+            }
+            "let" => {
+                let (func, args, env) = let_setup(v, &env)?;
+                let mut l = vec![Expression::Nil()];
+                l.extend(args);
+                self.next_arg(
+                    list_rc(l),
+                    1,
+                    vec![Environment::create_func(func)],
+                    env,
+                    false,
+                )
+            }
+            "let*" | "letrec" | "letrec*" | "let-values" | "let*-values" | "define-values"
+            | "receive" => Ok(State::Eval(expand(name, v)?, env)),
+            "map" | "for-each" if v.len() >= 3 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "apply" | "call-with-values" | "eval" if v.len() == 3 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "call/cc" | "call-with-current-continuation" if v.len() == 2 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "dynamic-wind" if v.len() == 4 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "with-exception-handler" if v.len() == 3 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "raise-continuable" if v.len() == 2 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "make-parameter" if v.len() == 3 => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            "guard" => self.guard(v, env),
+            "parameterize" => {
+                if v.len() < 2 {
+                    return Err(create_error_value!(ErrCode::E1007, v.len()));
+                }
+                let binds = parameter::bindings(&v[1])?;
+                let body = list_rc(v[1..].to_vec());
+                self.parameterize(binds, Vec::new(), body, env)
+            }
+            _ if NATIVE.contains(&name) => Ok(State::Return(f(v, &env)?)),
+            _ if start(name).is_some() => {
+                self.next_arg(form.clone(), 1, first(&form, func), env, false)
+            }
+            _ if SYNTAX.contains(&name) => Ok(State::Return(f(v, &env)?)),
+            _ => {
+                if v[1..].iter().any(Expression::is_form) {
+                    self.next_arg(form.clone(), 1, first(&form, func), env, true)
+                } else {
+                    Ok(State::Return(f(v, &env)?))
+                }
+            }
+        }
+    }
+    // Evaluate the arguments from left to right.
+    // In builtin mode, the builtin function is called with the values.
+    fn next_arg(
+        &mut self,
+        form: ListRc,
+        mut idx: usize,
+        mut vals: Vec<Expression>,
+        env: Environment,
+        builtin: bool,
+    ) -> Result<State, Error> {
+        let v = &*(reference_obj!(form));
+        while idx < v.len() {
+            match &v[idx] {
+                Expression::List(_) => {
+                    let e = v[idx].clone();
+                    self.push(Frame::Call {
+                        form: form.clone(),
+                        idx,
+                        vals,
+                        env: env.clone(),
+                        builtin,
+                    });
+                    return Ok(State::Eval(e, env));
+                }
+                Expression::Symbol(s) => vals.push(lookup(s, &env)?),
+                e => vals.push(e.clone()),
+            }
+            idx += 1;
+        }
+        if builtin {
+            if let Expression::BuildInFunction(_, f) = &vals[0] {
+                return Ok(State::Return(f(&vals, &env.evaluated(true))?));
+            }
+        }
+        let func = vals.remove(0);
+        Ok(State::Apply(func, vals, env))
+    }
+    fn seq(&mut self, body: Body, idx: usize, env: Environment) -> State {
+        match body.get(idx) {
+            Some((e, last)) => {
+                if !last {
+                    self.push(Frame::Seq {
+                        body,
+                        idx: idx + 1,
+                        env: env.clone(),
+                    });
+                }
+                State::Eval(e, env)
+            }
+            None => State::Return(Expression::Nil()),
+        }
+    }
+    fn apply(
+        &mut self,
+        func: Expression,
+        args: Vec<Expression>,
+        env: Environment,
+    ) -> Result<State, Error> {
+        match &func {
+            Expression::Function(f) => {
+                self.calls += 1;
+                let proc = Active::new(f, self.calls);
+                self.proc = Some(proc.clone());
+                self.form = None;
+                if env.is_bytecode() {
+                    if let Some(code) = f.code() {
+                        check(&env)?;
+                        let vm = match self.spare.take() {
+                            Some(mut vm) => {
+                                vm.call(f, code, args, proc)?;
+                                vm
+                            }
+                            None => Vm::new(f, code, args, proc)?,
+                        };
+                        return self.vm(vm);
+                    }
+                }
+                let e = Environment::with_parent(f.closure_env());
+                f.param().bind(args, &e, |s, v| e.regist(s.clone(), v))?;
+                Ok(self.seq(Body::Func(f.clone()), 0, e))
+            }
+            Expression::Continuation(_) => {
+                if args.len() != 1 {
+                    return Err(create_error_value!(ErrCode::E1007, args.len() + 1));
+                }
+                let v = args.into_iter().next().unwrap();
+                self.throw(create_continuation!(func, v))
+            }
+            Expression::BuildInFunction(name, f) => self.apply_builtin(&func, name, *f, args, env),
+            Expression::BuildInFunctionExt(f) => Ok(State::Return(f(
+                &make_evaled_list(&func, &args, &None),
+                &env,
+            )?)),
+            Expression::Parameter(p) => {
+                if !args.is_empty() {
+                    return Err(create_error_value!(ErrCode::E1007, args.len() + 1));
+                }
+                Ok(State::Return(p.get()))
+            }
+            _ => Err(create_error_value!(ErrCode::E1006, func)),
+        }
+    }
+    // Run the compiled function until it needs the machine.
+    fn vm(&mut self, mut vm: Vm) -> Result<State, Error> {
+        let y = match vm.run(&mut self.calls) {
+            Ok(y) => y,
+            Err(e) => {
+                // the callers in the vm are kept for the backtrace.
+                self.form = vm.form();
+                self.proc = vm.proc();
+                if vm.leave() {
+                    self.push(Frame::Vm(vm));
+                }
+                return Err(e);
+            }
+        };
+        self.form = None;
+        let (state, next) = match y {
+            Yield::Return(v) => {
+                self.spare = Some(vm);
+                return Ok(State::Return(v));
+            }
+            Yield::Apply(func, args, next) => (State::Apply(func, args, vm.env().clone()), next),
+            Yield::Eval(exp, env, next) => (State::Eval(exp, env), next),
+        };
+        self.proc = vm.proc();
+        let bool = next == Next::ReturnBool || (next == Next::Return && vm.is_bool());
+        // the value of the tail call is returned to the caller in the vm.
+        if next == Next::Push || vm.leave() {
+            self.push(Frame::Vm(vm));
+        }
+        if bool && !matches!(self.stack.last(), Some((Frame::Bool, _))) {
+            self.push(Frame::Bool);
+        }
+        Ok(state)
+    }
+    fn apply_builtin(
+        &mut self,
+        func: &Expression,
+        name: &str,
+        f: fn(&[Expression], &Environment) -> ResultExpression,
+        args: Vec<Expression>,
+        env: Environment,
+    ) -> Result<State, Error> {
+        match name {
+            "apply" if args.len() == 2 => {
+                if let Some(l) = pair::to_vec(&args[1]) {
+                    return Ok(State::Apply(args[0].clone(), l, env));
+                }
+            }
+            "call/cc" | "call-with-current-continuation" if args.len() == 1 => {
+                if let Expression::Function(_) = &args[0] {
+                    let k = Continuation {
+                        stack: self.stack.clone(),
+                        winds: WINDS.with(|w| w.borrow().clone()),
+                        handlers: HANDLERS.with(|h| h.borrow().clone()),
+                        base: self.id,
+                    };
+                    let k = Expression::Continuation(Box::new(k));
+                    return Ok(State::Apply(args[0].clone(), vec![k], env));
+                }
+            }
+            "dynamic-wind" if args.len() == 3 => {
+                let mut args = args.into_iter();
+                let before = args.next().unwrap();
+                let thunk = args.next().unwrap();
+                let after = args.next().unwrap();
+                let wind = Wind {
+                    id: WIND_ID.fetch_add(1, Ordering::Relaxed),
+                    before: before.clone(),
+                    after,
+                    env: env.clone(),
+                };
+                self.push(Frame::WindBody { wind, thunk });
+                return Ok(State::Apply(before, Vec::new(), env));
+            }
+            "with-exception-handler"
+                if args.len() == 2 && args.iter().all(Expression::is_procedure) =>
+            {
+                let mut args = args.into_iter();
+                let handler = args.next().unwrap();
+                let thunk = args.next().unwrap();
+                let handlers = HANDLERS.with(|h| {
+                    let mut h = h.borrow_mut();
+                    let saved = h.clone();
+                    h.push(Handler::Proc(handler, env.clone()));
+                    saved
+                });
+                self.push(Frame::Handlers(handlers));
+                return Ok(State::Apply(thunk, Vec::new(), env));
+            }
+            "eval" if args.len() == 2 => {
+                if let Expression::Environment(e) = &args[1] {
+                    let exp = pair::form(&args[0]);
+                    let exp = if e.is_syntax_only() {
+                        unbind_builtin(&exp)
+                    } else {
+                        exp
+                    };
+                    return Ok(State::Eval(exp, e.clone()));
+                }
+            }
+            "call-with-values" if args.len() == 2 => {
+                let mut args = args.into_iter();
+                let producer = args.next().unwrap();
+                let consumer = args.next().unwrap();
+                self.push(Frame::Values {
+                    consumer,
+                    env: env.clone(),
+                });
+                return Ok(State::Apply(producer, Vec::new(), env));
+            }
+            "raise-continuable" if args.len() == 1 => {
+                let obj = args.into_iter().next().unwrap();
+                return self.handle(raise_error(obj), true);
+            }
+            "make-parameter" if args.len() == 2 && Expression::is_procedure(&args[1]) => {
+                let mut args = args.into_iter();
+                let v = args.next().unwrap();
+                let converter = args.next().unwrap();
+                self.push(Frame::MakeParameter(converter.clone()));
+                return Ok(State::Apply(converter, vec![v], env));
+            }
+            "map" | "for-each" if args.len() == 2 && Expression::is_list(&args[1]) => {
+                return Ok(self.map(
+                    args[0].clone(),
+                    args[1].clone(),
+                    Vec::new(),
+                    name == "map",
+                    env,
+                ));
+            }
+            "map" | "for-each" if args.len() > 2 => {
+                let it = srfi1::map_lists(&make_args(func, args), name == "map")?;
+                return self.iterate(it, None, env);
+            }
+            _ => {}
+        }
+        if let Some(start) = start(name) {
+            let it = start(&make_args(func, args))?;
+            return self.iterate(it, None, env);
+        }
+        Ok(State::Return(f(
+            &make_args(func, args),
+            &env.evaluated(true),
+        )?))
+    }
+    fn map(
+        &mut self,
+        func: Expression,
+        list: Expression,
+        acc: Vec<Expression>,
+        collect: bool,
+        env: Environment,
+    ) -> State {
+        match list {
+            Expression::Pair(p) => {
+                let (e, list) = {
+                    let p = reference_obj!(p);
+                    (p.car.clone(), p.cdr.clone())
+                };
+                self.push(Frame::Map {
+                    func: func.clone(),
+                    list,
+                    acc,
+                    collect,
+                    env: env.clone(),
+                });
+                State::Apply(func, vec![e], env)
+            }
+            _ => State::Return(if collect {
+                Environment::create_list(acc)
+            } else {
+                Expression::Nil()
+            }),
+        }
+    }
+    fn iterate(
+        &mut self,
+        mut it: IterateBox,
+        mut v: Option<Expression>,
+        env: Environment,
+    ) -> Result<State, Error> {
+        loop {
+            let (func, args) = match it.next(v)? {
+                Step::Apply(func, args) => (func, args),
+                Step::Return(v) => return Ok(State::Return(v)),
+            };
+            // the procedure which returns at once is applied here, ex. (filter odd? l)
+            self.push(Frame::Iterate(it, env.clone()));
+            let depth = self.stack.len();
+            match self.apply(func, args, env.clone())? {
+                State::Return(r) if self.stack.len() == depth => match self.stack.pop() {
+                    Some((Frame::Iterate(i, _), proc)) => {
+                        self.proc = proc;
+                        it = i;
+                        v = Some(r);
+                    }
+                    _ => unreachable!(),
+                },
+                s => return Ok(s),
+            }
+        }
+    }
+    // Evaluate the parameters and the values of parameterize, ex. [p, 1, q, 2]
+    fn parameterize(
+        &mut self,
+        binds: Vec<Expression>,
+        vals: Vec<Expression>,
+        body: ListRc,
+        env: Environment,
+    ) -> Result<State, Error> {
+        let e = match binds.get(vals.len()) {
+            Some(e) => e.clone(),
+            None => return self.parameterize_body(vals, body, env),
+        };
+        let v = match e {
+            Expression::List(_) => {
+                self.push(Frame::Parameterize {
+                    binds,
+                    vals,
+                    convert: false,
+                    body,
+                    env: env.clone(),
+                });
+                return Ok(State::Eval(e, env));
+            }
+            Expression::Symbol(s) => lookup(&s, &env)?,
+            e => e,
+        };
+        self.parameterize_value(binds, vals, v, body, env)
+    }
+    // The value is converted by the converter of the parameter.
+    fn parameterize_value(
+        &mut self,
+        binds: Vec<Expression>,
+        mut vals: Vec<Expression>,
+        v: Expression,
+        body: ListRc,
+        env: Environment,
+    ) -> Result<State, Error> {
+        let v = match vals.last() {
+            Some(p) if vals.len() % 2 == 1 => parameter::check(p, v)?,
+            _ => v,
+        };
+        let converter = match vals.last() {
+            Some(Expression::Parameter(p)) if vals.len() % 2 == 1 => p.converter().cloned(),
+            _ => None,
+        };
+        if let Some(converter) = converter {
+            self.push(Frame::Parameterize {
+                binds,
+                vals,
+                convert: true,
+                body,
+                env: env.clone(),
+            });
+            return Ok(State::Apply(converter, vec![v], env));
+        }
+        vals.push(v);
+        self.parameterize(binds, vals, body, env)
+    }
+    // The values are restored by the after thunk of dynamic-wind, also on the escape.
+    fn parameterize_body(
+        &mut self,
+        vals: Vec<Expression>,
+        body: ListRc,
+        env: Environment,
+    ) -> Result<State, Error> {
+        let mut params = Vec::new();
+        let mut saved = Vec::new();
+        let mut vals = vals.into_iter();
+        while let (Some(p), Some(v)) = (vals.next(), vals.next()) {
+            params.push(p);
+            saved.push(v);
+        }
+        let saved = list_rc(saved);
+        parameter::swap(&params, &saved, &env);
+
+        let swap = Environment::create_func_ext(move |_, env| {
+            parameter::swap(&params, &saved, env);
+            Ok(Expression::Nil())
+        });
+        let wind = Wind {
+            id: WIND_ID.fetch_add(1, Ordering::Relaxed),
+            before: swap.clone(),
+            after: swap,
+            env: env.clone(),
+        };
+        WINDS.with(|w| w.borrow_mut().push(wind.clone()));
+        self.push(Frame::WindAfter { wind });
+        Ok(self.seq(Body::List(body), 1, env))
+    }
+    // (guard (var clause ...) body ...)
+    fn guard(&mut self, v: &[Expression], env: Environment) -> Result<State, Error> {
+        if v.len() < 3 {
+            return Err(create_error_value!(ErrCode::E1007, v.len()));
+        }
+        let spec = match &v[1] {
+            Expression::List(l) => reference_obj!(l).to_vec(),
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        };
+        let var = match spec.first() {
+            Some(Expression::Symbol(s)) => s.clone(),
+            Some(e) => return Err(create_error_value!(ErrCode::E1004, e)),
+            None => return Err(create_error_value!(ErrCode::E1007, spec.len())),
+        };
+        let id = GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        let handlers = HANDLERS.with(|h| {
+            let mut h = h.borrow_mut();
+            let saved = h.clone();
+            h.push(Handler::Guard(id));
+            saved
+        });
+        self.push(Frame::Guard {
+            id,
+            var,
+            clauses: spec[1..].to_vec(),
+            handlers,
+            winds: WINDS.with(|w| w.borrow().len()),
+            env: env.clone(),
+        });
+        let body = list_rc(v[1..].to_vec());
+        Ok(self.seq(Body::List(body), 1, env))
+    }
+    // The innermost handler is called in the dynamic environment of the raise,
+    // except that the current handler is the outer one.
+    fn handle(&mut self, e: Error, continuable: bool) -> Result<State, Error> {
+        if e.code == ErrCode::E9000 {
+            return Err(e);
+        }
+        let handler = HANDLERS.with(|h| h.borrow().last().cloned());
+        match handler {
+            None => Err(e),
+            Some(Handler::Guard(id)) => self.catch(id, e),
+            Some(Handler::Proc(f, env)) => {
+                let obj = error_object(&e);
+                let handlers = HANDLERS.with(|h| {
+                    let mut h = h.borrow_mut();
+                    let saved = h.clone();
+                    h.pop();
+                    saved
+                });
+                self.push(if continuable {
+                    Frame::Handlers(handlers)
+                } else {
+                    Frame::Raise(obj.clone())
+                });
+                Ok(State::Apply(f, vec![obj], env))
+            }
+        }
+    }
+    // The clauses of guard are evaluated in the dynamic environment of guard.
+    // If the guard is in the outer machine, the error is returned to it.
+    fn catch(&mut self, id: usize, mut e: Error) -> Result<State, Error> {
+        let idx = self
+            .stack
+            .iter()
+            .rposition(|(f, _)| matches!(f, Frame::Guard { id: i, .. } if *i == id));
+        let idx = match idx {
+            Some(idx) => idx,
+            None => return Err(e),
+        };
+        // the location of the raise is kept, before the frames are dropped.
+        if let (Frame::Guard { env, .. }, _) = &self.stack[idx] {
+            let env = env.clone();
+            self.trace(&mut e, &env);
+        }
+        self.stack.truncate(idx + 1);
+        let (var, clauses, handlers, winds, env) = match self.stack.pop() {
+            Some((
+                Frame::Guard {
+                    var,
+                    clauses,
+                    handlers,
+                    winds,
+                    env,
+                    ..
+                },
+                proc,
+            )) => {
+                self.proc = proc;
+                (var, clauses, handlers, winds, env)
+            }
+            _ => unreachable!(),
+        };
+        HANDLERS.with(|h| *h.borrow_mut() = handlers);
+        unwind(winds);
+
+        let local_env = Environment::with_parent(&env);
+        local_env.regist(var, error_object(&e));
+
+        let mut form = vec![Expression::Nil()];
+        form.extend(clauses);
+        self.clause(list_rc(form), 1, local_env, Box::new(e))
+    }
+    // The clause of guard, the test is satisfied by any value except #f.
+    // If no clause is satisfied, the condition is raised again with its location.
+    fn clause(
+        &mut self,
+        form: ListRc,
+        idx: usize,
+        env: Environment,
+        error: Box<Error>,
+    ) -> Result<State, Error> {
+        let l = match reference_obj!(form).get(idx) {
+            Some(Expression::List(l)) => l.clone(),
+            Some(_) => return Err(create_error!(ErrCode::E1005)),
+            None => return Err(*error),
+        };
+        let c = &*(reference_obj!(l));
+        match c.first() {
+            None => Err(create_error!(ErrCode::E1012)),
+            Some(Expression::Symbol(s)) if s == "else" => {
+                if c.len() < 2 {
+                    return Err(create_error_value!(ErrCode::E1007, c.len()));
+                }
+                Ok(self.seq(Body::List(l.clone()), 1, env))
+            }
+            Some(e) => {
+                self.push(Frame::Clause {
+                    form: form.clone(),
+                    idx,
+                    env: env.clone(),
+                    error,
+                });
+                Ok(State::Eval(e.clone(), env))
+            }
+        }
+    }
+    fn cond(&mut self, form: ListRc, idx: usize, env: Environment) -> Result<State, Error> {
+        let v = &*(reference_obj!(form));
+        if idx >= v.len() {
+            return Ok(State::Return(Expression::Nil()));
+        }
+        let l = match &v[idx] {
+            Expression::List(l) => l.clone(),
+            _ => return Err(create_error!(ErrCode::E1005)),
+        };
+        let c = &*(reference_obj!(l));
+        match c.first() {
+            None => Err(create_error!(ErrCode::E1012)),
+            Some(Expression::Symbol(s)) => {
+                if s != "else" {
+                    lookup(s, &env)?;
+                }
+                if c.len() < 2 {
+                    return Err(create_error_value!(ErrCode::E1007, c.len()));
+                }
+                Ok(self.seq(Body::List(l.clone()), 1, env))
+            }
+            Some(e) => {
+                self.push(Frame::Cond {
+                    form: form.clone(),
+                    idx,
+                    env: env.clone(),
+                });
+                Ok(State::Eval(e.clone(), env))
+            }
+        }
+    }
+    fn case(&mut self, form: ListRc, key: Expression, env: Environment) -> Result<State, Error> {
+        let v = &*(reference_obj!(form));
+        let mut param: Vec<Expression> = vec![Expression::Nil(), key, Expression::Nil()];
+
+        for e in &v[2..] {
+            let l = match e {
+                Expression::List(l) => l,
+                _ => return Err(create_error!(ErrCode::E1005)),
+            };
+            let c = &*(reference_obj!(l));
+            if c.is_empty() {
+                continue;
+            }
+            match &c[0] {
+                Expression::Symbol(s) => {
+                    if s != "else" {
+                        return Err(create_error!(ErrCode::E1017));
+                    }
+                    if 1 < c.len() {
+                        return Ok(self.seq(Body::List(l.clone()), 1, env));
+                    } else {
+                        return Ok(State::Return(Expression::Integer(0)));
+                    }
+                }
+                Expression::List(r) => {
+                    for e in &*(reference_obj!(r)) {
+                        param[2] = crate::lisp::eval(e, &env)?;
+                        if let Expression::Boolean(true) = eqv(&param, &env)? {
+                            if 1 < c.len() {
+                                return Ok(self.seq(Body::List(l.clone()), 1, env));
+                            } else {
+                                return Ok(State::Return(pair::datum(&c[0])));
+                            }
+                        }
+                    }
+                }
+                _ => return Err(create_error!(ErrCode::E1017)),
+            }
+        }
+        Ok(State::Return(Expression::Nil()))
+    }
+    fn resume(&mut self, frame: Frame, v: Expression) -> Result<State, Error> {
+        match frame {
+            Frame::Head { form, env } => self.call(form, &v, env),
+            Frame::Call {
+                form,
+                idx,
+                mut vals,
+                env,
+                builtin,
+            } => {
+                vals.push(v);
+                self.next_arg(form, idx + 1, vals, env, builtin)
+            }
+            Frame::Seq { body, idx, env } => Ok(self.seq(body, idx, env)),
+            Frame::If { form, env } => {
+                let l = &*(reference_obj!(form));
+                match v {
+                    Expression::Boolean(true) => Ok(State::Eval(l[2].clone(), env)),
+                    Expression::Boolean(false) => match l.get(3) {
+                        Some(e) => Ok(State::Eval(e.clone(), env)),
+                        None => Ok(State::Return(Expression::Nil())),
+                    },
+                    _ => Err(create_error!(ErrCode::E1001)),
+                }
+            }
+            Frame::Define { name, env } => {
+                env.regist(name.clone(), v);
+                Ok(State::Return(Expression::Symbol(name)))
+            }
+            Frame::Set { name, env } => {
+                env.update_symbol(&name, v);
+                Ok(State::Return(Expression::Symbol(name)))
+            }
+            Frame::Logic {
+                form,
+                idx,
+                and,
+                env,
+            } => match v {
+                Expression::Boolean(b) if b != and => Ok(State::Return(v)),
+                Expression::Boolean(_) => {
+                    let l = &*(reference_obj!(form));
+                    match l.get(idx + 1) {
+                        // the last one is in tail position, only the result is checked.
+                        Some(e) if idx + 2 == l.len() => {
+                            if !matches!(self.stack.last(), Some((Frame::Bool, _))) {
+                                self.push(Frame::Bool);
+                            }
+                            Ok(State::Eval(e.clone(), env))
+                        }
+                        Some(e) => {
+                            self.push(Frame::Logic {
+                                form: form.clone(),
+                                idx: idx + 1,
+                                and,
+                                env: env.clone(),
+                            });
+                            Ok(State::Eval(e.clone(), env))
+                        }
+                        None => Ok(State::Return(v)),
+                    }
+                }
+                _ => Err(create_error!(ErrCode::E1001)),
+            },
+            Frame::Bool => match v {
+                Expression::Boolean(_) => Ok(State::Return(v)),
+                _ => Err(create_error!(ErrCode::E1001)),
+            },
+            Frame::Cond { form, idx, env } => match v {
+                Expression::Boolean(false) => self.cond(form, idx + 1, env),
+                Expression::Boolean(true) => {
+                    let l = match &reference_obj!(form)[idx] {
+                        Expression::List(l) => l.clone(),
+                        _ => unreachable!(),
+                    };
+                    if reference_obj!(l).len() == 1 {
+                        return Ok(State::Return(v));
+                    }
+                    Ok(self.seq(Body::List(l), 1, env))
+                }
+                _ => Err(create_error!(ErrCode::E1001)),
+            },
+            Frame::Case { form, env } => self.case(form, v, env),
+            Frame::Clause {
+                form,
+                idx,
+                env,
+                error,
+            } => {
+                if let Expression::Boolean(false) = v {
+                    return self.clause(form, idx + 1, env, error);
+                }
+                let l = match &reference_obj!(form)[idx] {
+                    Expression::List(l) => l.clone(),
+                    _ => unreachable!(),
+                };
+                let c = &*(reference_obj!(l));
+                match &c[1..] {
+                    [] => Ok(State::Return(v)),
+                    // ex. ((assq 'a e) => cdr)
+                    [Expression::Symbol(s), e] if s == "=>" => {
+                        self.push(Frame::Receiver {
+                            value: v,
+                            env: env.clone(),
+                        });
+                        Ok(State::Eval(e.clone(), env))
+                    }
+                    _ => Ok(self.seq(Body::List(l.clone()), 1, env)),
+                }
+            }
+            Frame::Receiver { value, env } => Ok(State::Apply(v, vec![value], env)),
+            Frame::Map {
+                func,
+                list,
+                mut acc,
+                collect,
+                env,
+            } => {
+                if collect {
+                    acc.push(v);
+                }
+                Ok(self.map(func, list, acc, collect, env))
+            }
+            Frame::WindBody { wind, thunk } => {
+                let env = wind.env.clone();
+                WINDS.with(|w| w.borrow_mut().push(wind.clone()));
+                self.push(Frame::WindAfter { wind });
+                Ok(State::Apply(thunk, Vec::new(), env))
+            }
+            Frame::WindAfter { wind } => {
+                WINDS.with(|w| w.borrow_mut().pop());
+                self.push(Frame::Restore(v));
+                Ok(State::Apply(wind.after, Vec::new(), wind.env))
+            }
+            Frame::Restore(v) => Ok(State::Return(v)),
+            Frame::MakeParameter(converter) => {
+                Ok(State::Return(Parameter::create(v, Some(converter))))
+            }
+            Frame::Parameterize {
+                binds,
+                mut vals,
+                convert,
+                body,
+                env,
+            } => {
+                if convert {
+                    vals.push(v);
+                    self.parameterize(binds, vals, body, env)
+                } else {
+                    self.parameterize_value(binds, vals, v, body, env)
+                }
+            }
+            Frame::Values { consumer, env } => {
+                let args = match v {
+                    Expression::Values(l) => reference_obj!(l).to_vec(),
+                    v => vec![v],
+                };
+                Ok(State::Apply(consumer, args, env))
+            }
+            Frame::Handlers(handlers) => {
+                HANDLERS.with(|h| *h.borrow_mut() = handlers);
+                Ok(State::Return(v))
+            }
+            // the handler returned from the non-continuable exception
+            Frame::Raise(obj) => Err(raise_error(obj)),
+            Frame::Guard { handlers, .. } => {
+                HANDLERS.with(|h| *h.borrow_mut() = handlers);
+                Ok(State::Return(v))
+            }
+            Frame::Iterate(it, env) => self.iterate(it, Some(v), env),
+            Frame::Vm(mut vm) => {
+                vm.push(v);
+                self.vm(vm)
+            }
+        }
+    }
+}
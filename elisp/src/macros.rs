@@ -0,0 +1,689 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::vec::Vec;
+
+use crate::buildin::BuildInTable;
+use crate::create_error_value;
+use crate::lisp::eval;
+use crate::lisp::{Environment, Expression, ListRc, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::pair;
+use crate::reference_obj;
+use crate::symbol::Symbol;
+
+const ELLIPSIS: &str = "...";
+const UNDERSCORE: &str = "_";
+const DOT: &str = ".";
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("define-syntax", define_syntax);
+    b.regist("let-syntax", |exp, env| let_syntax(exp, env, false));
+    b.regist("letrec-syntax", |exp, env| let_syntax(exp, env, true));
+    b.regist("syntax-rules", syntax_rules);
+}
+#[derive(Clone)]
+enum Binding {
+    One(Expression),
+    Many(Vec<Binding>),
+}
+type Bindings = HashMap<String, Binding>;
+type Renames = HashMap<Symbol, Symbol>;
+
+#[derive(Clone)]
+pub struct SyntaxRules {
+    name: String,
+    ellipsis: String,
+    literals: Vec<String>,
+    rules: Vec<(Expression, Expression)>,
+    // the environment of the definition
+    scope: usize,
+}
+impl SyntaxRules {
+    pub fn expand(&self, exp: &[Expression]) -> ResultExpression {
+        for (pattern, template) in &self.rules {
+            let p = match pattern {
+                Expression::List(p) => p,
+                _ => continue,
+            };
+            let p = &*(reference_obj!(p));
+            if p.is_empty() {
+                continue;
+            }
+            // The keyword position is always ignored.
+            let mut b = Bindings::new();
+            if self.match_list(&p[1..], &exp[1..], &mut b) {
+                // the identifiers of the template are renamed (hygiene)
+                let mut renames = Renames::new();
+                let e = self.expand_template(template, &b, &mut renames)?;
+                debug!("expand {} => {}", self.name, e);
+                return Ok(e);
+            }
+        }
+        Err(create_error_value!(
+            ErrCode::E1025,
+            Environment::create_form(exp.to_vec())
+        ))
+    }
+    fn is_ellipsis(&self, e: &Expression) -> bool {
+        ident_name(e) == Some(self.ellipsis.as_str())
+    }
+    fn is_literal(&self, s: &str) -> bool {
+        self.literals.iter().any(|l| l == s)
+    }
+    fn match_list(&self, pat: &[Expression], form: &[Expression], b: &mut Bindings) -> bool {
+        // (p1 p2 . rest)
+        let (pat, tail) = match pat.iter().position(|e| ident_name(e) == Some(DOT)) {
+            Some(i) if i + 2 == pat.len() => (&pat[..i], Some(&pat[i + 1])),
+            _ => (pat, None),
+        };
+        let ellipsis = pat.iter().position(|e| self.is_ellipsis(e));
+        let (before, after) = match ellipsis {
+            Some(i) => (i - 1, pat.len() - i - 1),
+            None => (pat.len(), 0),
+        };
+        if form.len() < before + after {
+            return false;
+        }
+        if ellipsis.is_none() && tail.is_none() && form.len() != before {
+            return false;
+        }
+        for (p, f) in pat[..before].iter().zip(form) {
+            if !self.match_pattern(p, f, b) {
+                return false;
+            }
+        }
+        let end = match ellipsis {
+            Some(i) => {
+                let end = form.len() - after;
+                let mut items = Vec::new();
+                for f in &form[before..end] {
+                    let mut m = Bindings::new();
+                    if !self.match_pattern(&pat[i - 1], f, &mut m) {
+                        return false;
+                    }
+                    items.push(m);
+                }
+                let mut vars = Vec::new();
+                self.pattern_vars(&pat[i - 1], &mut vars);
+                for v in vars {
+                    let l = items.iter_mut().filter_map(|m| m.remove(&v)).collect();
+                    b.insert(v, Binding::Many(l));
+                }
+                for (p, f) in pat[i + 1..].iter().zip(&form[end..]) {
+                    if !self.match_pattern(p, f, b) {
+                        return false;
+                    }
+                }
+                form.len()
+            }
+            None => before,
+        };
+        match tail {
+            Some(t) => self.match_pattern(t, &Environment::create_form(form[end..].to_vec()), b),
+            None => true,
+        }
+    }
+    fn match_pattern(&self, pat: &Expression, form: &Expression, b: &mut Bindings) -> bool {
+        if let Some(s) = ident_name(pat) {
+            if s == UNDERSCORE {
+                return true;
+            }
+            if self.is_literal(s) {
+                return ident_name(form) == Some(s);
+            }
+            b.insert(s.to_string(), Binding::One(form.clone()));
+            return true;
+        }
+        match (pat, form) {
+            (Expression::List(p), Expression::List(f)) => {
+                let p = &*(reference_obj!(p));
+                let f = &*(reference_obj!(f));
+                self.match_list(p, f, b)
+            }
+            (Expression::List(_), _) => false,
+            // ex. (_ #(a ...))
+            (Expression::Vector(p), Expression::Vector(f)) => {
+                let p = vector_items(p);
+                let f = vector_items(f);
+                self.match_list(&p, &f, b)
+            }
+            (Expression::Vector(_), _) => false,
+            _ => Expression::eqv(pat, form),
+        }
+    }
+    fn pattern_vars(&self, pat: &Expression, vars: &mut Vec<String>) {
+        if let Some(s) = ident_name(pat) {
+            if s != UNDERSCORE && s != DOT && !self.is_ellipsis(pat) && !self.is_literal(s) {
+                vars.push(s.to_string());
+            }
+        } else if let Expression::List(l) | Expression::Vector(l) = pat {
+            let l = &*(reference_obj!(l));
+            for e in l {
+                self.pattern_vars(e, vars);
+            }
+        }
+    }
+    fn template_vars(&self, tmpl: &Expression, b: &Bindings, vars: &mut Vec<String>) {
+        if let Some(s) = ident_name(tmpl) {
+            if let Some(Binding::Many(_)) = b.get(s) {
+                if !vars.iter().any(|v| v == s) {
+                    vars.push(s.to_string());
+                }
+            }
+        } else if let Expression::List(l) | Expression::Vector(l) = tmpl {
+            let l = &*(reference_obj!(l));
+            for e in l {
+                self.template_vars(e, b, vars);
+            }
+        }
+    }
+    fn expand_template(
+        &self,
+        tmpl: &Expression,
+        b: &Bindings,
+        renames: &mut Renames,
+    ) -> ResultExpression {
+        match tmpl {
+            Expression::Symbol(s) => match b.get(s.as_str()) {
+                Some(Binding::One(e)) => Ok(e.clone()),
+                Some(Binding::Many(_)) => Err(create_error_value!(ErrCode::E1025, s)),
+                None => Ok(Expression::Symbol(self.rename(s, renames))),
+            },
+            Expression::BuildInFunction(s, _) => match b.get(*s) {
+                Some(Binding::One(e)) => Ok(e.clone()),
+                Some(Binding::Many(_)) => Err(create_error_value!(ErrCode::E1025, s)),
+                None => Ok(tmpl.clone()),
+            },
+            Expression::List(l) => {
+                let l = &*(reference_obj!(l));
+                // (... ...) => ...
+                if l.len() == 2 && self.is_ellipsis(&l[0]) {
+                    return Ok(l[1].clone());
+                }
+                let mut v = Vec::new();
+                let mut i = 0;
+                while i < l.len() {
+                    let mut depth = 0;
+                    while i + depth + 1 < l.len() && self.is_ellipsis(&l[i + depth + 1]) {
+                        depth += 1;
+                    }
+                    if depth > 0 {
+                        self.expand_ellipsis(&l[i], b, renames, depth, &mut v)?;
+                    } else if ident_name(&l[i]) == Some(DOT) && i + 2 == l.len() {
+                        // (a . rest) => (a rest1 rest2 ...)
+                        match self.expand_template(&l[i + 1], b, renames)? {
+                            Expression::List(r) => {
+                                let r = &*(reference_obj!(r));
+                                v.extend_from_slice(r);
+                            }
+                            e => {
+                                v.push(l[i].clone());
+                                v.push(e);
+                            }
+                        }
+                        break;
+                    } else {
+                        v.push(self.expand_template(&l[i], b, renames)?);
+                    }
+                    i += depth + 1;
+                }
+                Ok(Environment::create_form(v))
+            }
+            Expression::Vector(l) => {
+                let l = Environment::create_form(vector_items(l));
+                match self.expand_template(&l, b, renames)? {
+                    Expression::List(l) => Ok(Environment::create_vector(
+                        reference_obj!(l).iter().map(pair::datum).collect(),
+                    )),
+                    e => Ok(e),
+                }
+            }
+            _ => Ok(tmpl.clone()),
+        }
+    }
+    // The renamed identifier refers to the binding of the definition,
+    // unless the expansion itself binds it, ex. (let ((tmp a)) ...)
+    fn rename(&self, s: &Symbol, renames: &mut Renames) -> Symbol {
+        if s == DOT || s.starts_with("#!") || (s.len() > 1 && s.starts_with(':')) {
+            return s.clone();
+        }
+        renames
+            .entry(s.clone())
+            .or_insert_with(|| Symbol::rename(s, self.scope))
+            .clone()
+    }
+    fn expand_ellipsis(
+        &self,
+        tmpl: &Expression,
+        b: &Bindings,
+        renames: &mut Renames,
+        depth: usize,
+        result: &mut Vec<Expression>,
+    ) -> Result<(), Error> {
+        let mut vars = Vec::new();
+        self.template_vars(tmpl, b, &mut vars);
+        if vars.is_empty() {
+            return Err(create_error_value!(ErrCode::E1025, tmpl));
+        }
+        let mut len = None;
+        for v in &vars {
+            if let Some(Binding::Many(l)) = b.get(v) {
+                match len {
+                    Some(n) if n != l.len() => {
+                        return Err(create_error_value!(ErrCode::E1025, v));
+                    }
+                    _ => len = Some(l.len()),
+                }
+            }
+        }
+        for i in 0..len.unwrap_or(0) {
+            let mut m = b.clone();
+            for v in &vars {
+                if let Some(Binding::Many(l)) = b.get(v) {
+                    m.insert(v.to_string(), l[i].clone());
+                }
+            }
+            if depth > 1 {
+                self.expand_ellipsis(tmpl, &m, renames, depth - 1, result)?;
+            } else {
+                result.push(self.expand_template(tmpl, &m, renames)?);
+            }
+        }
+        Ok(())
+    }
+}
+fn ident_name(e: &Expression) -> Option<&str> {
+    match e {
+        Expression::Symbol(s) => Some(s.as_str()),
+        Expression::BuildInFunction(s, _) => Some(s),
+        _ => None,
+    }
+}
+// The elements of the vector literal are matched as the forms.
+fn vector_items(l: &ListRc) -> Vec<Expression> {
+    reference_obj!(l).iter().map(pair::form).collect()
+}
+// The ellipsis follows the subpattern, ex. (_ ... a) or (_ (... a))
+fn leading_ellipsis(pat: &Expression, ellipsis: &str) -> bool {
+    fn is_leading(l: &[Expression], ellipsis: &str) -> bool {
+        l.first().and_then(ident_name) == Some(ellipsis)
+            || l.iter().any(|e| match e {
+                Expression::List(l) | Expression::Vector(l) => {
+                    is_leading(&reference_obj!(l), ellipsis)
+                }
+                _ => false,
+            })
+    }
+    match pat {
+        // the keyword position is ignored, so the first subpattern is next to it.
+        Expression::List(l) => {
+            let l = &*(reference_obj!(l));
+            is_leading(l, ellipsis) || l.get(1).and_then(ident_name) == Some(ellipsis)
+        }
+        _ => false,
+    }
+}
+fn syntax_rules(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut idx = 1;
+    let mut ellipsis = ELLIPSIS.to_string();
+
+    // (syntax-rules ellipsis (literal ...) rule ...)
+    if let Expression::Symbol(s) = &exp[idx] {
+        ellipsis = s.to_string();
+        idx += 1;
+        if exp.len() <= idx {
+            return Err(create_error_value!(ErrCode::E1007, exp.len()));
+        }
+    }
+    let mut literals = Vec::new();
+    match &exp[idx] {
+        Expression::List(l) => {
+            let l = &*(reference_obj!(l));
+            for e in l {
+                match ident_name(e) {
+                    Some(s) => literals.push(s.to_string()),
+                    None => return Err(create_error_value!(ErrCode::E1004, e)),
+                }
+            }
+        }
+        e => return Err(create_error_value!(ErrCode::E1005, e)),
+    }
+    let mut rules = Vec::new();
+    for e in &exp[idx + 1..] {
+        if let Expression::List(l) = e {
+            let l = &*(reference_obj!(l));
+            if l.len() == 2 && Expression::is_form(&l[0]) && !leading_ellipsis(&l[0], &ellipsis) {
+                rules.push((l[0].clone(), l[1].clone()));
+                continue;
+            }
+        }
+        return Err(create_error_value!(ErrCode::E1025, e));
+    }
+    Ok(Expression::Syntax(Box::new(SyntaxRules {
+        name: String::from("syntax-rules"),
+        ellipsis,
+        literals,
+        rules,
+        scope: env.regist_scope(),
+    })))
+}
+fn make_syntax(name: &str, exp: &Expression, env: &Environment) -> ResultExpression {
+    match eval(exp, env)? {
+        Expression::Syntax(mut s) => {
+            s.name = name.to_string();
+            Ok(Expression::Syntax(s))
+        }
+        e => Err(create_error_value!(ErrCode::E1025, e)),
+    }
+}
+fn define_syntax(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let name = match &exp[1] {
+        Expression::Symbol(s) => s,
+        e => return Err(create_error_value!(ErrCode::E1004, e)),
+    };
+    let s = make_syntax(name, &exp[2], env)?;
+    env.regist(name.clone(), s);
+
+    Ok(Expression::Symbol(name.clone()))
+}
+fn let_syntax(exp: &[Expression], env: &Environment, rec: bool) -> ResultExpression {
+    if exp.len() < 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let local_env = Environment::with_parent(env);
+    let l = match &exp[1] {
+        Expression::List(l) => l,
+        e => return Err(create_error_value!(ErrCode::E1005, e)),
+    };
+    let l = &*(reference_obj!(l));
+    for e in l {
+        let p = match e {
+            Expression::List(p) => p,
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        };
+        let p = &*(reference_obj!(p));
+        if p.len() != 2 {
+            return Err(create_error_value!(ErrCode::E1007, p.len()));
+        }
+        let name = match &p[0] {
+            Expression::Symbol(s) => s,
+            e => return Err(create_error_value!(ErrCode::E1004, e)),
+        };
+        let s = make_syntax(name, &p[1], if rec { &local_env } else { env })?;
+        local_env.regist(name.clone(), s);
+    }
+    let mut ret = Expression::Nil();
+    for e in &exp[2..] {
+        ret = eval(e, &local_env)?;
+    }
+    Ok(ret)
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn define_syntax() {
+        let env = lisp::Environment::new();
+        assert_eq!(
+            do_lisp_env(
+                "(define-syntax my-if (syntax-rules () ((_ c t e) (cond (c t) (else e)))))",
+                &env
+            ),
+            "my-if"
+        );
+        assert_eq!(do_lisp_env("(my-if (= 1 1) 10 20)", &env), "10");
+        assert_eq!(do_lisp_env("(my-if (= 1 2) 10 20)", &env), "20");
+        assert_eq!(do_lisp_env("my-if", &env), "Syntax");
+
+        do_lisp_env(
+            "(define-syntax when (syntax-rules () ((_ c e ...) (if c (begin e ...) #f))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(when (= 1 1) 1 2 3)", &env), "3");
+        assert_eq!(do_lisp_env("(when (= 1 2) 1 2 3)", &env), "#f");
+
+        do_lisp_env(
+            "(define-syntax while (syntax-rules () ((_ c body ...) (let loop () (if c (begin body ... (loop)) #f)))))",
+            &env,
+        );
+        do_lisp_env("(define i 0)", &env);
+        do_lisp_env("(define loop 100)", &env);
+        do_lisp_env("(while (< i 10) (set! i (+ i 1)))", &env);
+        assert_eq!(do_lisp_env("i", &env), "10");
+        assert_eq!(do_lisp_env("loop", &env), "100");
+    }
+    #[test]
+    fn hygiene() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-syntax swap! (syntax-rules () ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))",
+            &env,
+        );
+        do_lisp_env("(define tmp 1)", &env);
+        do_lisp_env("(define other 2)", &env);
+        do_lisp_env("(swap! tmp other)", &env);
+        assert_eq!(do_lisp_env("(list tmp other)", &env), "(2 1)");
+
+        do_lisp_env(
+            "(define-syntax my-or (syntax-rules () ((_) #f) ((_ e) e) ((_ e r ...) (let ((t e)) (if t t (my-or r ...))))))",
+            &env,
+        );
+        do_lisp_env("(define t 5)", &env);
+        assert_eq!(do_lisp_env("(my-or #f t)", &env), "5");
+        assert_eq!(do_lisp_env("(my-or)", &env), "#f");
+        assert_eq!(do_lisp_env("(my-or #f #f 7)", &env), "7");
+
+        // the free identifier refers to the binding of the definition
+        do_lisp_env("(define (helper) 'global)", &env);
+        do_lisp_env("(define-syntax m (syntax-rules () ((_) (helper))))", &env);
+        assert_eq!(
+            do_lisp_env("(let ((helper (lambda () 'local))) (m))", &env),
+            "global"
+        );
+        do_lisp_env("(define (f) (let ((helper list)) (m)))", &env);
+        assert_eq!(do_lisp_env("(f)", &env), "global");
+        assert_eq!(
+            do_lisp_env("(let ((if list) (else #f)) (my-or #f 3))", &env),
+            "3"
+        );
+        do_lisp_env("(define n 0)", &env);
+        do_lisp_env(
+            "(define-syntax inc! (syntax-rules () ((_) (set! n (+ n 1)))))",
+            &env,
+        );
+        do_lisp_env("(let ((n 100)) (inc!) (inc!))", &env);
+        assert_eq!(do_lisp_env("n", &env), "2");
+        assert_eq!(
+            do_lisp("(define (f v) (let-syntax ((get (syntax-rules () ((_) v)))) (let ((v 2)) (get)))) (f 10)"),
+            "10"
+        );
+
+        // the binding of let-values and receive
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-syntax swap! (syntax-rules () ((_ a b) (let-values (((x y) (values b a))) (set! a x) (set! b y)))))",
+            &env,
+        );
+        do_lisp_env("(define x 1)", &env);
+        do_lisp_env("(define y 2)", &env);
+        do_lisp_env("(swap! x y)", &env);
+        assert_eq!(do_lisp_env("(list x y)", &env), "(2 1)");
+        do_lisp_env(
+            "(define-syntax swap2! (syntax-rules () ((_ a b) (receive (x y) (values b a) (set! a x) (set! b y)))))",
+            &env,
+        );
+        do_lisp_env("(swap2! x y)", &env);
+        assert_eq!(do_lisp_env("(list x y)", &env), "(1 2)");
+
+        // the quoted identifier is the symbol
+        do_lisp_env(
+            "(define-syntax q (syntax-rules () ((_ v) (list 'a `(b ,v)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(q 1)", &env), "(a (b 1))");
+        assert_eq!(do_lisp_env("(eq? (car (q 1)) 'a)", &env), "#t");
+    }
+    #[test]
+    fn ellipsis() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-syntax my-let (syntax-rules () ((_ ((n v) ...) body ...) ((lambda (n ...) body ...) v ...))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(my-let ((a 1)(b 2)) (+ a b))", &env), "3");
+        assert_eq!(do_lisp_env("(my-let () 10)", &env), "10");
+
+        do_lisp_env(
+            "(define-syntax flat (syntax-rules () ((_ (a ...) ...) '(a ... ...))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(flat (1 2) (3) (4 5))", &env), "(1 2 3 4 5)");
+
+        do_lisp_env(
+            "(define-syntax tail (syntax-rules () ((_ a ... b c) (list b c))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(tail 1 2 3 4)", &env), "(3 4)");
+        assert_eq!(do_lisp_env("(tail 3 4)", &env), "(3 4)");
+
+        do_lisp_env(
+            "(define-syntax rest (syntax-rules () ((_ a . b) (quote b))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(rest 1 2 3)", &env), "(2 3)");
+
+        do_lisp_env(
+            "(define-syntax esc (syntax-rules () ((_ a) '(a (... ...)))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(esc 1)", &env), "(1 ...)");
+
+        do_lisp_env(
+            "(define-syntax my-list (syntax-rules ::: () ((_ a :::) (list a :::))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(my-list 1 2 3)", &env), "(1 2 3)");
+    }
+    #[test]
+    fn literals() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-syntax for (syntax-rules (in from) ((_ x in l b ...) (map (lambda (x) b ...) l)) ((_ x from s b ...) (map (lambda (x) b ...) (iota 3 s)))))",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env("(for x in (list 1 2) (* x 10))", &env),
+            "(10 20)"
+        );
+        assert_eq!(do_lisp_env("(for x from 5 x)", &env), "(5 6 7)");
+        assert_eq!(do_lisp_env("(for x to 5 x)", &env), "E1025");
+
+        do_lisp_env(
+            "(define-syntax vec (syntax-rules () ((_ #(a ...)) (list a ...))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(vec #(1 2 3))", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("(vec (1 2 3))", &env), "E1025");
+
+        do_lisp_env(
+            "(define-syntax rev (syntax-rules () ((_ a b) #(b a))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(rev 1 (2 3))", &env), "#((2 3) 1)");
+
+        do_lisp_env(
+            "(define-syntax num (syntax-rules () ((_ 1) 'one) ((_ \"a\") 'a) ((_ x) 'other)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(num 1)", &env), "one");
+        assert_eq!(do_lisp_env("(num \"a\")", &env), "a");
+        assert_eq!(do_lisp_env("(num 2)", &env), "other");
+    }
+    #[test]
+    fn let_syntax() {
+        assert_eq!(
+            do_lisp("(let-syntax ((foo (syntax-rules () ((_ a) (* a 2))))) (foo 10))"),
+            "20"
+        );
+        assert_eq!(
+            do_lisp("(letrec-syntax ((ev? (syntax-rules () ((_) #t) ((_ a b ...) (od? b ...)))) (od? (syntax-rules () ((_) #f) ((_ a b ...) (ev? b ...))))) (ev? 1 2 3 4))"),
+            "#t"
+        );
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f x) (let-syntax ((double (syntax-rules () ((_ a) (+ a a))))) (double x)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 21)", &env), "42");
+        assert_eq!(do_lisp_env("double", &env), "E1008");
+    }
+    #[test]
+    fn syntax_rules() {
+        assert_eq!(do_lisp("(syntax-rules ())"), "Syntax");
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn define_syntax() {
+        assert_eq!(do_lisp("(define-syntax)"), "E1007");
+        assert_eq!(do_lisp("(define-syntax foo)"), "E1007");
+        assert_eq!(do_lisp("(define-syntax 10 (syntax-rules ()))"), "E1004");
+        assert_eq!(do_lisp("(define-syntax foo 10)"), "E1025");
+        assert_eq!(do_lisp("(define-syntax foo a)"), "E1008");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define-syntax foo (syntax-rules () ((_ a) a)))", &env);
+        assert_eq!(do_lisp_env("(foo)", &env), "E1025");
+        assert_eq!(do_lisp_env("(foo 1 2)", &env), "E1025");
+
+        do_lisp_env("(define-syntax bar (syntax-rules () ((_ a ...) a)))", &env);
+        assert_eq!(do_lisp_env("(bar 1 2)", &env), "E1025");
+    }
+    #[test]
+    fn let_syntax() {
+        assert_eq!(do_lisp("(let-syntax)"), "E1007");
+        assert_eq!(do_lisp("(let-syntax ())"), "E1007");
+        assert_eq!(do_lisp("(let-syntax 10 1)"), "E1005");
+        assert_eq!(do_lisp("(let-syntax (10) 1)"), "E1005");
+        assert_eq!(do_lisp("(let-syntax ((foo)) 1)"), "E1007");
+        assert_eq!(do_lisp("(let-syntax ((10 (syntax-rules ()))) 1)"), "E1004");
+        assert_eq!(do_lisp("(let-syntax ((foo 10)) 1)"), "E1025");
+        assert_eq!(do_lisp("(letrec-syntax ((foo 10)) 1)"), "E1025");
+    }
+    #[test]
+    fn syntax_rules() {
+        assert_eq!(do_lisp("(syntax-rules)"), "E1007");
+        assert_eq!(do_lisp("(syntax-rules :::)"), "E1007");
+        assert_eq!(do_lisp("(syntax-rules 10)"), "E1005");
+        assert_eq!(do_lisp("(syntax-rules (10))"), "E1004");
+        assert_eq!(do_lisp("(syntax-rules () 10)"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules () (10 20))"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules () ((... a) 1))"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules () ((_ ... a) 1))"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules () ((_ a (... b)) 1))"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules () ((_ #(... a)) 1))"), "E1025");
+        assert_eq!(do_lisp("(syntax-rules ::: () ((_ ::: a) 1))"), "E1025");
+    }
+}
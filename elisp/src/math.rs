@@ -17,7 +17,12 @@ use crate::buildin::BuildInTable;
 use crate::lisp::eval;
 use crate::lisp::{Environment, Expression, Int, ResultExpression};
 use crate::lisp::{ErrCode, Error};
-use crate::number::Rat;
+use crate::number::{Number, Rat};
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use std::convert::TryFrom;
 
 const SAMPLE_INT: Int = 10_000_000_000_000;
 
@@ -25,9 +30,7 @@ pub fn create_function<T>(b: &mut T)
 where
     T: BuildInTable + ?Sized,
 {
-    b.regist("sqrt", |exp, env| {
-        Ok(Expression::Float(to_f64(exp, env)?.sqrt()))
-    });
+    b.regist("sqrt", sqrt);
     b.regist("sin", |exp, env| {
         Ok(Expression::Float(to_f64(exp, env)?.sin()))
     });
@@ -53,45 +56,273 @@ where
         Ok(Expression::Float(to_f64(exp, env)?.log((1.0_f64).exp())))
     });
     b.regist("truncate", |exp, env| {
-        Ok(Expression::Float(to_f64(exp, env)?.trunc()))
+        rounding(exp, env, f64::trunc, |n, d| n / d)
     });
     b.regist("floor", |exp, env| {
-        Ok(Expression::Float(to_f64(exp, env)?.floor()))
+        rounding(exp, env, f64::floor, |n, d| n.div_floor(&d))
     });
     b.regist("ceiling", |exp, env| {
-        Ok(Expression::Float(to_f64(exp, env)?.ceil()))
+        rounding(exp, env, f64::ceil, |n, d| -(-n).div_floor(&d))
     });
     b.regist("round", |exp, env| {
-        Ok(Expression::Float(to_f64(exp, env)?.round()))
+        rounding(exp, env, f64::round_ties_even, round_even)
     });
     b.regist("abs", abs);
+    b.regist("square", |exp, env| {
+        let v = to_number(exp, env)?;
+        Ok((v.clone() * v).to_expression())
+    });
+    b.regist("exact-integer-sqrt", exact_integer_sqrt);
+
+    b.regist("exact", exact);
+    b.regist("inexact", inexact);
+    b.regist("inexact->exact", exact);
+    b.regist("exact->inexact", inexact);
+    b.regist("numerator", |exp, env| fraction(exp, env, |r| r.numer));
+    b.regist("denominator", |exp, env| fraction(exp, env, |r| r.denom));
+    b.regist("rationalize", rationalize);
+
+    b.regist("make-rectangular", |exp, env| {
+        make_complex(exp, env, Number::complex)
+    });
+    b.regist("make-polar", |exp, env| {
+        make_complex(exp, env, Number::polar)
+    });
+    b.regist("real-part", |exp, env| {
+        Ok(match to_number(exp, env)? {
+            Number::Complex(a, _) => Expression::Float(a),
+            v => v.to_expression(),
+        })
+    });
+    b.regist("imag-part", |exp, env| {
+        Ok(match to_number(exp, env)? {
+            Number::Complex(_, b) => Expression::Float(b),
+            _ => Expression::Integer(0),
+        })
+    });
+    b.regist("magnitude", |exp, env| {
+        Ok(match to_number(exp, env)? {
+            Number::Complex(a, b) => Expression::Float(a.hypot(b)),
+            v => abs_number(v).to_expression(),
+        })
+    });
+    b.regist("angle", |exp, env| {
+        Ok(match to_number(exp, env)? {
+            Number::Complex(a, b) => Expression::Float(b.atan2(a)),
+            v if v < Number::Integer(0) => Expression::Float(std::f64::consts::PI),
+            v if v.is_exact() => Expression::Integer(0),
+            _ => Expression::Float(0.0),
+        })
+    });
 
     b.regist("rand-integer", rand_integer);
     b.regist("rand-list", rand_list);
     b.regist("expt", expt);
 }
-fn to_f64(exp: &[Expression], env: &Environment) -> Result<f64, Error> {
+fn to_number(exp: &[Expression], env: &Environment) -> Result<Number, Error> {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::Float(f) => Ok(f),
-        Expression::Integer(i) => Ok(i as f64),
-        Expression::Rational(r) => Ok(r.div_float()),
-        e => Err(create_error_value!(ErrCode::E1003, e)),
+    Expression::to_number(&eval(&exp[1], env)?)
+}
+fn to_real(e: Expression) -> Result<Number, Error> {
+    match Expression::to_number(&e)? {
+        v if v.is_real() => Ok(v),
+        _ => Err(create_error_value!(ErrCode::E1031, e)),
     }
 }
+fn to_f64(exp: &[Expression], env: &Environment) -> Result<f64, Error> {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(to_real(eval(&exp[1], env)?)?.to_f64())
+}
 fn abs(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 2 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    Ok(match eval(&exp[1], env)? {
-        Expression::Float(v) => Expression::Float(v.abs()),
-        Expression::Integer(v) => Expression::Integer(v.abs()),
-        Expression::Rational(v) => Expression::Rational(v.abs()),
-        e => return Err(create_error_value!(ErrCode::E1003, e)),
+    Ok(abs_number(to_real(eval(&exp[1], env)?)?).to_expression())
+}
+fn abs_number(v: Number) -> Number {
+    match v {
+        Number::Integer(v) => match v.checked_abs() {
+            Some(v) => Number::Integer(v),
+            None => Number::BigInt(BigInt::from(v).abs()),
+        },
+        Number::BigInt(v) => Number::BigInt(v.abs()),
+        Number::Float(v) => Number::Float(v.abs()),
+        Number::Rational(v) => Number::Rational(v.abs()),
+        Number::Complex(a, b) => Number::Float(a.hypot(b)),
+    }
+}
+// The exact number is rounded to the exact integer.
+fn rounding(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(f64) -> f64,
+    rfunc: fn(BigInt, BigInt) -> BigInt,
+) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(match to_real(eval(&exp[1], env)?)? {
+        Number::Float(v) => Expression::Float(func(v)),
+        Number::Rational(r) => Number::from_big(rfunc(r.numer, r.denom)).to_expression(),
+        v => v.to_expression(),
     })
 }
+// ex. 5/2 => 2, 7/2 => 4
+fn round_even(n: BigInt, d: BigInt) -> BigInt {
+    let two = BigInt::from(2);
+    let (q, r) = (n * &two + &d).div_mod_floor(&(d * two));
+    if r.is_zero() && q.is_odd() {
+        q - 1
+    } else {
+        q
+    }
+}
+// The square root of the exact square number is exact, ex. (sqrt 1/4) => 1/2
+fn sqrt(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let v = match to_number(exp, env)? {
+        Number::Complex(a, b) => {
+            let m = a.hypot(b);
+            let (re, im) = (((m + a) / 2.0).sqrt(), ((m - a) / 2.0).sqrt());
+            return Ok(Number::complex(re, im.copysign(b)).to_expression());
+        }
+        v => v,
+    };
+    if v.is_exact() {
+        let r = v.to_rat();
+        if !r.numer.is_negative() {
+            let (n, d) = (r.numer.sqrt(), r.denom.sqrt());
+            if &n * &n == r.numer && &d * &d == r.denom {
+                return Ok(Number::from_rat(Rat::from_big(n, d)).to_expression());
+            }
+        }
+    }
+    let v = v.to_f64();
+    Ok(if v < 0.0 {
+        Number::complex(0.0, (-v).sqrt())
+    } else {
+        Number::Float(v.sqrt())
+    }
+    .to_expression())
+}
+fn exact_integer_sqrt(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let n = match eval(&exp[1], env)? {
+        Expression::Integer(v) => BigInt::from(v),
+        Expression::BigInt(v) => *v,
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
+    };
+    if n.is_negative() {
+        return Err(create_error_value!(ErrCode::E1021, Number::from_big(n)));
+    }
+    let s = n.sqrt();
+    let r = &n - &s * &s;
+    Ok(Environment::create_values(vec![
+        Number::from_big(s).to_expression(),
+        Number::from_big(r).to_expression(),
+    ]))
+}
+fn exact(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let e = eval(&exp[1], env)?;
+    match Expression::to_number(&e)?.exact() {
+        Ok(v) => Ok(v.to_expression()),
+        Err(c) => Err(create_error_value!(c, e)),
+    }
+}
+fn inexact(exp: &[Expression], env: &Environment) -> ResultExpression {
+    Ok(to_number(exp, env)?.inexact().to_expression())
+}
+// The inexact number is done by its exact value, ex. (denominator 0.5) => 2.0
+fn fraction(exp: &[Expression], env: &Environment, func: fn(Rat) -> BigInt) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let e = eval(&exp[1], env)?;
+    let v = to_real(e.clone())?;
+    let r = match v.clone().exact() {
+        Ok(n) => n.to_rat(),
+        Err(c) => return Err(create_error_value!(c, e)),
+    };
+    let n = Number::from_big(func(r));
+    Ok(if v.is_exact() { n } else { n.inexact() }.to_expression())
+}
+// The simplest rational within y of x, ex. (rationalize 3/10 1/10) => 1/3
+fn rationalize(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let x = to_real(eval(&exp[1], env)?)?;
+    let y = to_real(eval(&exp[2], env)?)?;
+    let exact = x.is_exact() && y.is_exact();
+
+    let (a, b) = match (x.clone().exact(), y.clone().exact()) {
+        (Ok(a), Ok(b)) => (a.to_rat(), b.to_rat().abs()),
+        (_, _) => {
+            let (a, b) = (x.to_f64(), y.to_f64());
+            let v = if b.is_finite() {
+                a
+            } else if a.is_finite() {
+                0.0
+            } else {
+                f64::NAN
+            };
+            return Ok(Expression::Float(v));
+        }
+    };
+    let (lo, hi) = (a.clone() - b.clone(), a + b);
+    let v = if lo.numer.is_positive() {
+        simplest(lo, hi)
+    } else if hi.numer.is_negative() {
+        let v = simplest(
+            Rat::from_big(-hi.numer, hi.denom),
+            Rat::from_big(-lo.numer, lo.denom),
+        );
+        Rat::from_big(-v.numer, v.denom)
+    } else {
+        Rat::new(0, 1)
+    };
+    let v = Number::from_rat(v);
+    Ok(if exact { v } else { v.inexact() }.to_expression())
+}
+// 0 < x <= y
+fn simplest(x: Rat, y: Rat) -> Rat {
+    let n = x.numer.div_floor(&x.denom);
+    let f = Rat::from_big(n.clone(), BigInt::one());
+    if f == x {
+        f
+    } else if n < y.numer.div_floor(&y.denom) {
+        f + Rat::new(1, 1)
+    } else {
+        let (a, b) = (
+            Rat::new(1, 1) / (y - f.clone()),
+            Rat::new(1, 1) / (x - f.clone()),
+        );
+        f + Rat::new(1, 1) / simplest(a, b)
+    }
+}
+fn make_complex(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(f64, f64) -> Number,
+) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let x = to_real(eval(&exp[1], env)?)?;
+    let y = to_real(eval(&exp[2], env)?)?;
+    if y.is_exact() && y == Number::Integer(0) {
+        return Ok(x.to_expression());
+    }
+    Ok(func(x.to_f64(), y.to_f64()).to_expression())
+}
 fn rand_integer(exp: &[Expression], _env: &Environment) -> ResultExpression {
     if 1 < exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
@@ -120,27 +351,41 @@ fn expt(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    match eval(&exp[1], env)? {
-        Expression::Float(x) => match eval(&exp[2], env)? {
-            Expression::Float(y) => Ok(Expression::Float(x.powf(y))),
-            Expression::Integer(y) => Ok(Expression::Float(x.powf(y as f64))),
-            e => Err(create_error_value!(ErrCode::E1003, e)),
-        },
-        Expression::Integer(x) => match eval(&exp[2], env)? {
-            Expression::Float(y) => Ok(Expression::Float((x as f64).powf(y))),
-            Expression::Integer(y) => {
-                if y >= 0 {
-                    Ok(Expression::Integer(x.pow(y as u32)))
-                } else {
-                    Ok(Expression::Rational(Rat::new(
-                        1,
-                        x.pow(y.unsigned_abs() as u32),
-                    )))
+    let x = Expression::to_number(&eval(&exp[1], env)?)?;
+    let y = Expression::to_number(&eval(&exp[2], env)?)?;
+
+    match (x, y) {
+        (x, y) if !x.is_real() || !y.is_real() => {
+            // exp(y * log(x))
+            let ((a, b), (c, d)) = (x.to_complex(), y.to_complex());
+            let (re, im) = (a.hypot(b).ln(), b.atan2(a));
+            Ok(Number::polar((c * re - d * im).exp(), c * im + d * re).to_expression())
+        }
+        (x, Number::Integer(y)) if x.is_exact() => {
+            let n = match u32::try_from(y.unsigned_abs()) {
+                Ok(n) => n,
+                Err(_) => return Err(create_error_value!(ErrCode::E1021, y)),
+            };
+            if let (Number::Integer(x), true) = (&x, y >= 0) {
+                if let Some(v) = x.checked_pow(n) {
+                    return Ok(Expression::Integer(v));
                 }
             }
-            e => Err(create_error_value!(ErrCode::E1003, e)),
-        },
-        e => Err(create_error_value!(ErrCode::E1003, e)),
+            let r = match x {
+                Number::Rational(r) => r,
+                x => Rat::from_big(x.to_big(), BigInt::one()),
+            };
+            let (numer, denom) = (r.numer.pow(n), r.denom.pow(n));
+            let v = if y >= 0 {
+                Rat::from_big(numer, denom)
+            } else if numer.is_zero() {
+                return Err(create_error!(ErrCode::E1013));
+            } else {
+                Rat::from_big(denom, numer)
+            };
+            Ok(Number::Rational(v).to_expression())
+        }
+        (x, y) => Ok(Expression::Float(x.to_f64().powf(y.to_f64()))),
     }
 }
 #[cfg(test)]
@@ -151,7 +396,11 @@ mod tests {
     #[test]
     fn sqrt() {
         assert_eq!(do_lisp("(sqrt 9)"), "3");
-        assert_eq!(do_lisp("(sqrt 25.0)"), "5");
+        assert_eq!(do_lisp("(sqrt 25.0)"), "5.0");
+        assert_eq!(do_lisp("(sqrt 1/4)"), "1/2");
+        assert_eq!(do_lisp("(sqrt 2)"), "1.4142135623730951");
+        assert_eq!(do_lisp("(sqrt -4)"), "+2.0i");
+        assert_eq!(do_lisp("(sqrt -2.0i)"), "1.0-1.0i");
 
         let env = lisp::Environment::new();
         do_lisp_env("(define a 16)", &env);
@@ -211,7 +460,7 @@ mod tests {
     fn asin() {
         assert_eq!(
             do_lisp("(round (/ (* (asin (/(sqrt 3) 2)) 180)(*(atan 1)4)))"),
-            "60"
+            "60.0"
         );
         assert_eq!(
             do_lisp("(sin (asin (/(* pi 30)180)))"),
@@ -226,7 +475,7 @@ mod tests {
     fn acos() {
         assert_eq!(
             do_lisp("(round (/ (* (acos (/ 1 2)) 180)(*(atan 1)4)))"),
-            "60"
+            "60.0"
         );
         assert_eq!(
             do_lisp("(cos (acos (/(* pi 30)180)))"),
@@ -239,7 +488,7 @@ mod tests {
     }
     #[test]
     fn atan() {
-        assert_eq!(do_lisp("(round (/(* (atan 1) 180)(*(atan 1)4)))"), "45");
+        assert_eq!(do_lisp("(round (/(* (atan 1) 180)(*(atan 1)4)))"), "45.0");
         assert_eq!(do_lisp("(* 4 (atan 1))"), "3.141592653589793");
         assert_eq!(do_lisp("(* 4 (atan 1.0))"), "3.141592653589793");
 
@@ -259,43 +508,53 @@ mod tests {
     }
     #[test]
     fn log() {
-        assert_eq!(do_lisp("(/(log 8)(log 2))"), "3");
-        assert_eq!(do_lisp("(/(log 9.0)(log 3.0))"), "2");
-        assert_eq!(do_lisp("(exp (/(log 8) 3))"), "2");
-        assert_eq!(do_lisp("(round (exp (* (log 2) 3)))"), "8");
+        assert_eq!(do_lisp("(/(log 8)(log 2))"), "3.0");
+        assert_eq!(do_lisp("(/(log 9.0)(log 3.0))"), "2.0");
+        assert_eq!(do_lisp("(exp (/(log 8) 3))"), "2.0");
+        assert_eq!(do_lisp("(round (exp (* (log 2) 3)))"), "8.0");
 
         let env = lisp::Environment::new();
         do_lisp_env("(define a 9)", &env);
         do_lisp_env("(define b 3)", &env);
-        assert_eq!(do_lisp_env("(/(log a)(log b))", &env), "2");
+        assert_eq!(do_lisp_env("(/(log a)(log b))", &env), "2.0");
     }
     #[test]
     fn truncate() {
-        assert_eq!(do_lisp("(truncate 3.7)"), "3");
-        assert_eq!(do_lisp("(truncate 3.1)"), "3");
-        assert_eq!(do_lisp("(truncate -3.1)"), "-3");
-        assert_eq!(do_lisp("(truncate -3.7)"), "-3");
+        assert_eq!(do_lisp("(truncate 3.7)"), "3.0");
+        assert_eq!(do_lisp("(truncate 3.1)"), "3.0");
+        assert_eq!(do_lisp("(truncate -3.1)"), "-3.0");
+        assert_eq!(do_lisp("(truncate -3.7)"), "-3.0");
+        assert_eq!(do_lisp("(truncate -7/2)"), "-3");
+        assert_eq!(do_lisp("(truncate 5)"), "5");
     }
     #[test]
     fn floor() {
-        assert_eq!(do_lisp("(floor 3.7)"), "3");
-        assert_eq!(do_lisp("(floor 3.1)"), "3");
-        assert_eq!(do_lisp("(floor -3.1)"), "-4");
-        assert_eq!(do_lisp("(floor -3.7)"), "-4");
+        assert_eq!(do_lisp("(floor 3.7)"), "3.0");
+        assert_eq!(do_lisp("(floor 3.1)"), "3.0");
+        assert_eq!(do_lisp("(floor -3.1)"), "-4.0");
+        assert_eq!(do_lisp("(floor -3.7)"), "-4.0");
+        assert_eq!(do_lisp("(floor -7/2)"), "-4");
+        assert_eq!(do_lisp("(floor 5)"), "5");
     }
     #[test]
     fn ceiling() {
-        assert_eq!(do_lisp("(ceiling 3.7)"), "4");
-        assert_eq!(do_lisp("(ceiling 3.1)"), "4");
-        assert_eq!(do_lisp("(ceiling -3.1)"), "-3");
-        assert_eq!(do_lisp("(ceiling -3.7)"), "-3");
+        assert_eq!(do_lisp("(ceiling 3.7)"), "4.0");
+        assert_eq!(do_lisp("(ceiling 3.1)"), "4.0");
+        assert_eq!(do_lisp("(ceiling -3.1)"), "-3.0");
+        assert_eq!(do_lisp("(ceiling -3.7)"), "-3.0");
+        assert_eq!(do_lisp("(ceiling 7/2)"), "4");
+        assert_eq!(do_lisp("(ceiling 5)"), "5");
     }
     #[test]
     fn round() {
-        assert_eq!(do_lisp("(round 3.7)"), "4");
-        assert_eq!(do_lisp("(round 3.1)"), "3");
-        assert_eq!(do_lisp("(round -3.1)"), "-3");
-        assert_eq!(do_lisp("(round -3.7)"), "-4");
+        assert_eq!(do_lisp("(round 3.7)"), "4.0");
+        assert_eq!(do_lisp("(round 3.1)"), "3.0");
+        assert_eq!(do_lisp("(round -3.1)"), "-3.0");
+        assert_eq!(do_lisp("(round -3.7)"), "-4.0");
+        assert_eq!(do_lisp("(round 2.5)"), "2.0");
+        assert_eq!(do_lisp("(round 7/2)"), "4");
+        assert_eq!(do_lisp("(round -5/2)"), "-2");
+        assert_eq!(do_lisp("(round 5)"), "5");
     }
     #[test]
     fn abs() {
@@ -330,9 +589,127 @@ mod tests {
         assert_eq!(do_lisp("(expt 2 (+ 1 2))"), "8");
         assert_eq!(do_lisp("(expt 2 -2)"), "1/4");
         assert_eq!(do_lisp("(expt 2 0)"), "1");
-        assert_eq!(do_lisp("(expt 2.0 3.0)"), "8");
-        assert_eq!(do_lisp("(expt 2.0 3)"), "8");
-        assert_eq!(do_lisp("(expt 2 3.0)"), "8");
+        assert_eq!(do_lisp("(expt 2.0 3.0)"), "8.0");
+        assert_eq!(do_lisp("(expt 2.0 3)"), "8.0");
+        assert_eq!(do_lisp("(expt 2 3.0)"), "8.0");
+        assert_eq!(do_lisp("(expt 1+i 2)"), "1.2246467991473532e-16+2.0i");
+    }
+    #[test]
+    fn bigint() {
+        assert_eq!(do_lisp("(expt 2 100)"), "1267650600228229401496703205376");
+        assert_eq!(
+            do_lisp("(expt 2 -100)"),
+            "1/1267650600228229401496703205376"
+        );
+        assert_eq!(
+            do_lisp("(expt (expt 2 50) 2)"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(do_lisp("(expt 2/3 3)"), "8/27");
+        assert_eq!(do_lisp("(expt 2/3 -3)"), "27/8");
+        assert_eq!(
+            do_lisp("(abs (- (expt 2 100)))"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(do_lisp("(sqrt (expt 2 100))"), "1125899906842624");
+    }
+    #[test]
+    fn square() {
+        assert_eq!(do_lisp("(square 3)"), "9");
+        assert_eq!(do_lisp("(square 1/2)"), "1/4");
+        assert_eq!(do_lisp("(square 1.5)"), "2.25");
+        assert_eq!(do_lisp("(square +i)"), "-1.0");
+
+        // the builtin name can be bound as the variable
+        assert_eq!(do_lisp("(let ((square 2)) square)"), "2");
+        assert_eq!(do_lisp("((lambda (square) (* square square)) 3)"), "9");
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (square x) (+ x x))", &env);
+        do_lisp_env("(define (f x) (square x))", &env);
+        assert_eq!(do_lisp_env("(f 3)", &env), "6");
+    }
+    #[test]
+    fn exact_integer_sqrt() {
+        assert_eq!(do_lisp("(exact-integer-sqrt 4)"), "2 0");
+        assert_eq!(do_lisp("(exact-integer-sqrt 17)"), "4 1");
+        assert_eq!(
+            do_lisp("(exact-integer-sqrt (+ (expt 2 100) 1))"),
+            "1125899906842624 1"
+        );
+    }
+    #[test]
+    fn exact() {
+        assert_eq!(do_lisp("(exact 2.0)"), "2");
+        assert_eq!(do_lisp("(exact 0.5)"), "1/2");
+        assert_eq!(do_lisp("(exact 1/3)"), "1/3");
+        assert_eq!(do_lisp("(exact 1e20)"), "100000000000000000000");
+        assert_eq!(do_lisp("(inexact->exact -0.25)"), "-1/4");
+    }
+    #[test]
+    fn inexact() {
+        assert_eq!(do_lisp("(inexact 2)"), "2.0");
+        assert_eq!(do_lisp("(inexact 1/3)"), "0.3333333333333333");
+        assert_eq!(do_lisp("(exact->inexact 1.5)"), "1.5");
+        assert_eq!(do_lisp("(inexact (expt 10 20))"), "1e20");
+    }
+    #[test]
+    fn numerator() {
+        assert_eq!(do_lisp("(numerator 6/4)"), "3");
+        assert_eq!(do_lisp("(numerator 5)"), "5");
+        assert_eq!(do_lisp("(numerator 0.75)"), "3.0");
+    }
+    #[test]
+    fn denominator() {
+        assert_eq!(do_lisp("(denominator 6/4)"), "2");
+        assert_eq!(do_lisp("(denominator 5)"), "1");
+        assert_eq!(do_lisp("(denominator 0)"), "1");
+        assert_eq!(do_lisp("(denominator 0.75)"), "4.0");
+    }
+    #[test]
+    fn rationalize() {
+        assert_eq!(do_lisp("(rationalize 3/10 1/10)"), "1/3");
+        assert_eq!(do_lisp("(rationalize -3/10 1/10)"), "-1/3");
+        assert_eq!(do_lisp("(rationalize 3/10 1)"), "0");
+        assert_eq!(do_lisp("(rationalize .3 1/10)"), "0.3333333333333333");
+        assert_eq!(do_lisp("(rationalize 5/2 0)"), "5/2");
+    }
+    #[test]
+    fn make_rectangular() {
+        assert_eq!(do_lisp("(make-rectangular 1 2)"), "1.0+2.0i");
+        assert_eq!(do_lisp("(make-rectangular 1.5 -2)"), "1.5-2.0i");
+        assert_eq!(do_lisp("(make-rectangular 1/2 0)"), "1/2");
+    }
+    #[test]
+    fn make_polar() {
+        assert_eq!(do_lisp("(make-polar 2 0)"), "2");
+        assert_eq!(
+            do_lisp("(make-polar 2 (* 2 (atan 1)))"),
+            "1.2246467991473532e-16+2.0i"
+        );
+    }
+    #[test]
+    fn real_part() {
+        assert_eq!(do_lisp("(real-part 1+2i)"), "1.0");
+        assert_eq!(do_lisp("(real-part 3/2)"), "3/2");
+        assert_eq!(do_lisp("(imag-part 1+2i)"), "2.0");
+        assert_eq!(do_lisp("(imag-part 3/2)"), "0");
+    }
+    #[test]
+    fn magnitude() {
+        assert_eq!(do_lisp("(magnitude 3+4i)"), "5.0");
+        assert_eq!(do_lisp("(magnitude -5)"), "5");
+        assert_eq!(do_lisp("(magnitude -1/2)"), "1/2");
+    }
+    #[test]
+    fn angle() {
+        assert_eq!(do_lisp("(angle +i)"), "1.5707963267948966");
+        assert_eq!(do_lisp("(angle -1)"), "3.141592653589793");
+        assert_eq!(do_lisp("(angle 1)"), "0");
+        assert_eq!(do_lisp("(angle 1.0)"), "0.0");
+        assert_eq!(do_lisp("(let ((angle 60)) (* angle 2))"), "120");
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (f len angle) (* len angle))", &env);
+        assert_eq!(do_lisp_env("(f 2 30)", &env), "60");
     }
 }
 #[cfg(test)]
@@ -455,4 +832,72 @@ mod error_tests {
         assert_eq!(do_lisp("(expt 10.5 #f)"), "E1003");
         assert_eq!(do_lisp("(expt #t 10)"), "E1003");
     }
+    #[test]
+    fn bigint() {
+        assert_eq!(do_lisp("(expt 0 -1)"), "E1013");
+        assert_eq!(do_lisp("(expt 2 (expt 2 40))"), "E1021");
+    }
+    #[test]
+    fn complex() {
+        assert_eq!(do_lisp("(sin +i)"), "E1031");
+        assert_eq!(do_lisp("(floor 1+i)"), "E1031");
+        assert_eq!(do_lisp("(abs 1+i)"), "E1031");
+    }
+    #[test]
+    fn square() {
+        assert_eq!(do_lisp("(square)"), "E1007");
+        assert_eq!(do_lisp("(square 1 2)"), "E1007");
+        assert_eq!(do_lisp("(square #t)"), "E1003");
+    }
+    #[test]
+    fn exact_integer_sqrt() {
+        assert_eq!(do_lisp("(exact-integer-sqrt)"), "E1007");
+        assert_eq!(do_lisp("(exact-integer-sqrt 1 2)"), "E1007");
+        assert_eq!(do_lisp("(exact-integer-sqrt 4.0)"), "E1002");
+        assert_eq!(do_lisp("(exact-integer-sqrt -4)"), "E1021");
+    }
+    #[test]
+    fn exact() {
+        assert_eq!(do_lisp("(exact)"), "E1007");
+        assert_eq!(do_lisp("(exact 1 2)"), "E1007");
+        assert_eq!(do_lisp("(exact #t)"), "E1003");
+        assert_eq!(do_lisp("(exact +inf.0)"), "E1021");
+        assert_eq!(do_lisp("(exact +nan.0)"), "E1021");
+        assert_eq!(do_lisp("(exact 1+i)"), "E1031");
+    }
+    #[test]
+    fn inexact() {
+        assert_eq!(do_lisp("(inexact)"), "E1007");
+        assert_eq!(do_lisp("(inexact 1 2)"), "E1007");
+        assert_eq!(do_lisp("(inexact #t)"), "E1003");
+    }
+    #[test]
+    fn numerator() {
+        assert_eq!(do_lisp("(numerator)"), "E1007");
+        assert_eq!(do_lisp("(numerator 1 2)"), "E1007");
+        assert_eq!(do_lisp("(numerator #t)"), "E1003");
+        assert_eq!(do_lisp("(numerator +inf.0)"), "E1021");
+        assert_eq!(do_lisp("(denominator 1+i)"), "E1031");
+    }
+    #[test]
+    fn rationalize() {
+        assert_eq!(do_lisp("(rationalize 1)"), "E1007");
+        assert_eq!(do_lisp("(rationalize 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(rationalize #t 1)"), "E1003");
+        assert_eq!(do_lisp("(rationalize 1 +i)"), "E1031");
+    }
+    #[test]
+    fn make_rectangular() {
+        assert_eq!(do_lisp("(make-rectangular 1)"), "E1007");
+        assert_eq!(do_lisp("(make-rectangular 1 #t)"), "E1003");
+        assert_eq!(do_lisp("(make-rectangular 1 +i)"), "E1031");
+        assert_eq!(do_lisp("(make-polar 1 2 3)"), "E1007");
+    }
+    #[test]
+    fn real_part() {
+        assert_eq!(do_lisp("(real-part)"), "E1007");
+        assert_eq!(do_lisp("(imag-part 1 2)"), "E1007");
+        assert_eq!(do_lisp("(magnitude #t)"), "E1003");
+        assert_eq!(do_lisp("(angle a)"), "E1008");
+    }
 }
@@ -7,13 +7,16 @@
 use std::cmp::Ordering;
 use std::cmp::PartialEq;
 use std::cmp::PartialOrd;
-use std::error::Error;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
 use std::ops::Sub;
 
+use num_bigint::BigInt;
+use num_traits::{Float, One, Signed, ToPrimitive, Zero};
+
 use crate::lisp::ErrCode;
 use crate::lisp::Expression;
 use crate::lisp::Int;
@@ -21,79 +24,227 @@ use crate::lisp::Int;
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 //========================================================================
-#[derive(Debug)]
-pub struct RatParseError {
-    pub code: ErrCode,
-}
-impl fmt::Display for RatParseError {
-    fn fmt(&self, format: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(format, "{}", self.code.as_str())
-    }
-}
-impl Error for RatParseError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
-    }
-}
-#[derive(Debug, Copy, Clone)]
+// ex. "-123", "+123" (BigInt::parse_bytes also accepts "1_000")
+pub fn parse_big(s: &str, r: u32) -> Option<BigInt> {
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(r)) {
+        return None;
+    }
+    BigInt::parse_bytes(s.as_bytes(), r)
+}
+// The numeric literal, ex. "#x1F", "#e1.5", "1/3", "1e10", "+inf.0", "1+2i", "1@2"
+pub fn parse_number(s: &str, radix: u32) -> Result<Option<Number>, ErrCode> {
+    let (mut s, mut radix, mut exact) = (s, radix, None);
+    while let Some(t) = s.strip_prefix('#') {
+        let mut c = t.chars();
+        match c.next().map(|c| c.to_ascii_lowercase()) {
+            Some('x') => radix = 16,
+            Some('d') => radix = 10,
+            Some('o') => radix = 8,
+            Some('b') => radix = 2,
+            Some('e') if exact.is_none() => exact = Some(true),
+            Some('i') if exact.is_none() => exact = Some(false),
+            _ => return Ok(None),
+        }
+        s = c.as_str();
+    }
+    let n = match parse_complex(s, radix, exact == Some(true))? {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+    match exact {
+        Some(true) => n.exact().map(Some),
+        Some(false) => Ok(Some(n.inexact())),
+        None => Ok(Some(n)),
+    }
+}
+fn parse_complex(s: &str, radix: u32, exact: bool) -> Result<Option<Number>, ErrCode> {
+    if let Some((m, a)) = s.split_once('@') {
+        return match (parse_real(m, radix, exact)?, parse_real(a, radix, exact)?) {
+            (Some(m), Some(a)) => Ok(Some(Number::polar(m.to_f64(), a.to_f64()))),
+            _ => Ok(None),
+        };
+    }
+    let s = match s.strip_suffix(['i', 'I']) {
+        Some(s) => s,
+        None => return parse_real(s, radix, exact),
+    };
+    // The sign of the imaginary part, which is not the one of the exponent.
+    let k = s.char_indices().rev().find(|(i, c)| {
+        (*c == '+' || *c == '-') && (*i == 0 || radix != 10 || !s[..*i].ends_with(['e', 'E']))
+    });
+    let k = match k {
+        Some((k, _)) => k,
+        None => return Ok(None),
+    };
+    let re = match k {
+        0 => Some(Number::Integer(0)),
+        _ => parse_real(&s[..k], radix, exact)?,
+    };
+    let im = match &s[k..] {
+        "+" => Some(Number::Integer(1)),
+        "-" => Some(Number::Integer(-1)),
+        t => parse_real(t, radix, exact)?,
+    };
+    match (re, im) {
+        (Some(re), Some(im)) => Ok(Some(Number::complex(re.to_f64(), im.to_f64()))),
+        _ => Ok(None),
+    }
+}
+fn parse_real(s: &str, radix: u32, exact: bool) -> Result<Option<Number>, ErrCode> {
+    match s {
+        "+inf.0" => return Ok(Some(Number::Float(f64::INFINITY))),
+        "-inf.0" => return Ok(Some(Number::Float(f64::NEG_INFINITY))),
+        "+nan.0" | "-nan.0" => return Ok(Some(Number::Float(f64::NAN))),
+        _ => {}
+    }
+    if let Some((n, d)) = s.split_once('/') {
+        if d.starts_with(['+', '-']) {
+            return Ok(None);
+        }
+        return match (parse_big(n, radix), parse_big(d, radix)) {
+            (Some(_), Some(d)) if d.is_zero() => Err(ErrCode::E1013),
+            (Some(n), Some(d)) => Ok(Some(Number::from_rat(Rat::from_big(n, d)))),
+            _ => Ok(None),
+        };
+    }
+    if let Some(n) = parse_big(s, radix) {
+        return Ok(Some(Number::from_big(n)));
+    }
+    if radix != 10 || !is_decimal(s) {
+        return Ok(None);
+    }
+    if exact {
+        return Ok(parse_exact_decimal(s).map(Number::from_rat));
+    }
+    Ok(s.parse::<f64>().ok().map(Number::Float))
+}
+// ex. "1.5", ".5", "1.", "1e10", "-1.5E-3"
+fn is_decimal(s: &str) -> bool {
+    let digits = |t: &str| t.chars().all(|c| c.is_ascii_digit());
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let (m, e) = match s.split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e)),
+        None => (s, None),
+    };
+    let (i, f) = m.split_once('.').unwrap_or((m, ""));
+    let e = e.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && digits(e)
+    });
+    !(i.is_empty() && f.is_empty()) && digits(i) && digits(f) && e
+}
+// ex. #e1.5 => 3/2
+fn parse_exact_decimal(s: &str) -> Option<Rat> {
+    let (m, e) = match s.split_once(['e', 'E']) {
+        Some((m, e)) => (m, e.parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+    let (i, f) = m.split_once('.').unwrap_or((m, ""));
+    let n = parse_big(&format!("{}{}0", i, f), 10)? / 10;
+    let e = e - f.len() as i32;
+    let p = num_traits::pow(
+        BigInt::from(10),
+        u16::try_from(e.unsigned_abs()).ok()? as usize,
+    );
+    Some(if e >= 0 {
+        Rat::from_big(n * p, BigInt::one())
+    } else {
+        Rat::from_big(n, p)
+    })
+}
+// The shortest representation, which is read back to the same value.
+pub fn float_to_string(v: f64) -> String {
+    if v.is_nan() {
+        String::from("+nan.0")
+    } else if v.is_infinite() {
+        String::from(if v > 0.0 { "+inf.0" } else { "-inf.0" })
+    } else {
+        format!("{:?}", v)
+    }
+}
+// ex. 1.0+2.0i, +2.0i
+pub fn complex_to_string(re: f64, im: f64) -> String {
+    let mut s = if re == 0.0 {
+        String::new()
+    } else {
+        float_to_string(re)
+    };
+    let i = float_to_string(im);
+    if !i.starts_with(['+', '-']) {
+        s.push('+');
+    }
+    s.push_str(&i);
+    s.push('i');
+    s
+}
+#[derive(Debug, Clone)]
 pub struct Rat {
-    pub numer: Int,
-    pub denom: Int,
+    pub numer: BigInt,
+    pub denom: BigInt,
 }
 impl Rat {
     pub fn new(n: Int, d: Int) -> Rat {
-        let l = gcm(n, d);
-        let sign = if n * d < 0 { -1 } else { 1 };
-
-        Rat {
-            numer: (n.wrapping_abs() / l) * sign,
-            denom: d.wrapping_abs() / l,
+        Rat::from_big(BigInt::from(n), BigInt::from(d))
+    }
+    pub fn from_big(n: BigInt, d: BigInt) -> Rat {
+        let l = gcm(&n, &d);
+        let (numer, denom) = (n / &l, d / &l);
+        if denom.is_negative() {
+            Rat {
+                numer: -numer,
+                denom: -denom,
+            }
+        } else {
+            Rat { numer, denom }
         }
     }
     pub fn div_float(&self) -> f64 {
-        self.numer as f64 / self.denom as f64
+        // The both are shifted, so that they are not overflowed to inf.
+        let bits = self.numer.bits().max(self.denom.bits());
+        let shift = bits.saturating_sub(f64::MAX_EXP as u64 - 1);
+        let (n, d) = (&self.numer >> shift, &self.denom >> shift);
+        n.to_f64().unwrap_or(f64::NAN) / d.to_f64().unwrap_or(f64::NAN)
     }
     pub fn abs(&self) -> Rat {
         Rat {
             numer: self.numer.abs(),
-            denom: self.denom,
+            denom: self.denom.clone(),
         }
     }
-    pub fn from(s: &str) -> Result<Rat, RatParseError> {
-        Rat::from_radix(s, 10)
+    pub fn is_zero(&self) -> bool {
+        self.numer.is_zero()
     }
-    pub fn from_radix(s: &str, r: u32) -> Result<Rat, RatParseError> {
-        let mut v = Vec::new();
-        for e in s.split('/') {
-            if let Ok(n) = Int::from_str_radix(e, r) {
-                v.push(n);
-            }
+    // The exact value of the finite float, ex. 0.5 => 1/2
+    pub fn from_f64(v: f64) -> Option<Rat> {
+        if !v.is_finite() {
+            return None;
         }
-        if v.len() == 2 {
-            if v[1] == 0 {
-                return Err(RatParseError {
-                    code: ErrCode::E1013,
-                });
-            }
+        let (mantissa, exponent, sign) = v.integer_decode();
+        let n = BigInt::from(mantissa) * sign;
+        Some(if exponent >= 0 {
+            Rat::from_big(n << exponent as usize, BigInt::one())
         } else {
-            return Err(RatParseError {
-                code: ErrCode::E1020,
-            });
-        }
-        Ok(Rat::new(v[0], v[1]))
+            Rat::from_big(n, BigInt::one() << -exponent as usize)
+        })
     }
 }
-fn gcm(n: Int, m: Int) -> Int {
-    match n % m {
-        0 => m.wrapping_abs(),
-        l => gcm(m, l),
+fn gcm(n: &BigInt, m: &BigInt) -> BigInt {
+    let (mut n, mut m) = (n.clone(), m.clone());
+    loop {
+        let l = &n % &m;
+        if l.is_zero() {
+            return m.abs();
+        }
+        n = m;
+        m = l;
     }
 }
 // ToString -> Display
 // https://rust-lang.github.io/rust-clippy/master/index.html#/to_string_trait_impl
 impl fmt::Display for Rat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.denom == 1 {
+        if self.denom.is_one() {
             write!(f, "{}", self.numer)
         } else {
             write!(f, "{}/{}", self.numer, self.denom)
@@ -103,8 +254,8 @@ impl fmt::Display for Rat {
 impl Add for Rat {
     type Output = Rat;
     fn add(self: Rat, other: Rat) -> Rat {
-        Rat::new(
-            (self.numer * other.denom) + (other.numer * self.denom),
+        Rat::from_big(
+            (&self.numer * &other.denom) + (&other.numer * &self.denom),
             self.denom * other.denom,
         )
     }
@@ -112,8 +263,8 @@ impl Add for Rat {
 impl Sub for Rat {
     type Output = Rat;
     fn sub(self: Rat, other: Rat) -> Rat {
-        Rat::new(
-            (self.numer * other.denom) - (other.numer * self.denom),
+        Rat::from_big(
+            (&self.numer * &other.denom) - (&other.numer * &self.denom),
             self.denom * other.denom,
         )
     }
@@ -121,13 +272,13 @@ impl Sub for Rat {
 impl Mul for Rat {
     type Output = Rat;
     fn mul(self: Rat, other: Rat) -> Rat {
-        Rat::new(self.numer * other.numer, self.denom * other.denom)
+        Rat::from_big(self.numer * other.numer, self.denom * other.denom)
     }
 }
 impl Div for Rat {
     type Output = Rat;
     fn div(self: Rat, other: Rat) -> Rat {
-        Rat::new(self.numer * other.denom, self.denom * other.numer)
+        Rat::from_big(self.numer * other.denom, self.denom * other.numer)
     }
 }
 impl PartialEq for Rat {
@@ -139,68 +290,166 @@ impl Eq for Rat {}
 
 impl Ord for Rat {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.lt(other) {
-            Ordering::Less
-        } else if self.gt(other) {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
+        (&self.numer * &other.denom).cmp(&(&other.numer * &self.denom))
     }
 }
 impl PartialOrd for Rat {
-    fn lt(&self, other: &Rat) -> bool {
-        (self.numer * other.denom) < (other.numer * self.denom)
-    }
-    fn le(&self, other: &Rat) -> bool {
-        (self.numer * other.denom) <= (other.numer * self.denom)
-    }
-    fn gt(&self, other: &Rat) -> bool {
-        (self.numer * other.denom) > (other.numer * self.denom)
-    }
-    fn ge(&self, other: &Rat) -> bool {
-        (self.numer * other.denom) >= (other.numer * self.denom)
-    }
     fn partial_cmp(&self, other: &Rat) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
-#[derive(Debug, Copy, Clone)]
+// The integer is promoted to BigInt on overflow, and demoted when it fits in Int.
+#[derive(Debug, Clone)]
 pub enum Number {
     Integer(Int),
+    BigInt(BigInt),
     Float(f64),
     Rational(Rat),
+    // The complex number is always inexact.
+    Complex(f64, f64),
 }
 impl Number {
-    fn calc<I, F, R, V>(self: Number, other: Number, icalc: I, fcalc: F, rcalc: R) -> V
+    pub fn from_big(n: BigInt) -> Number {
+        match Int::try_from(&n) {
+            Ok(i) => Number::Integer(i),
+            Err(_) => Number::BigInt(n),
+        }
+    }
+    pub fn from_rat(r: Rat) -> Number {
+        if r.denom.is_one() {
+            Number::from_big(r.numer)
+        } else {
+            Number::Rational(r)
+        }
+    }
+    // The imaginary part which is 0, is dropped.
+    pub fn complex(re: f64, im: f64) -> Number {
+        if im == 0.0 {
+            Number::Float(re)
+        } else {
+            Number::Complex(re, im)
+        }
+    }
+    pub fn polar(m: f64, a: f64) -> Number {
+        Number::complex(m * a.cos(), m * a.sin())
+    }
+    // The real part of the complex number
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(a) => *a as f64,
+            Number::BigInt(a) => a.to_f64().unwrap_or(f64::NAN),
+            Number::Float(a) => *a,
+            Number::Rational(a) => a.div_float(),
+            Number::Complex(a, _) => *a,
+        }
+    }
+    pub fn to_complex(&self) -> (f64, f64) {
+        match self {
+            Number::Complex(a, b) => (*a, *b),
+            _ => (self.to_f64(), 0.0),
+        }
+    }
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Number::Float(_) | Number::Complex(_, _))
+    }
+    pub fn is_real(&self) -> bool {
+        !matches!(self, Number::Complex(_, _))
+    }
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Number::Integer(_) | Number::BigInt(_) => true,
+            Number::Float(a) => a.is_finite() && a.fract() == 0.0,
+            _ => false,
+        }
+    }
+    pub fn exact(self) -> Result<Number, ErrCode> {
+        match self {
+            Number::Float(a) => match Rat::from_f64(a) {
+                Some(r) => Ok(Number::from_rat(r)),
+                None => Err(ErrCode::E1021),
+            },
+            Number::Complex(_, _) => Err(ErrCode::E1031),
+            n => Ok(n),
+        }
+    }
+    pub fn inexact(self) -> Number {
+        match self {
+            Number::Float(_) | Number::Complex(_, _) => self,
+            n => Number::Float(n.to_f64()),
+        }
+    }
+    pub(crate) fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(a) => *a == 0,
+            Number::BigInt(a) => a.is_zero(),
+            Number::Float(a) => *a == 0.0,
+            Number::Rational(a) => a.is_zero(),
+            Number::Complex(_, _) => false,
+        }
+    }
+    // Integer or BigInt only
+    pub(crate) fn to_big(&self) -> BigInt {
+        match self {
+            Number::Integer(a) => BigInt::from(*a),
+            Number::BigInt(a) => a.clone(),
+            _ => unreachable!(),
+        }
+    }
+    // Exact number only
+    pub(crate) fn to_rat(&self) -> Rat {
+        match self {
+            Number::Rational(a) => a.clone(),
+            _ => Rat::from_big(self.to_big(), BigInt::one()),
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn calc<I, B, F, R, C, V>(
+        self: Number,
+        other: Number,
+        icalc: I,
+        bcalc: B,
+        fcalc: F,
+        rcalc: R,
+        ccalc: C,
+    ) -> V
     where
         I: Fn(Int, Int) -> V,
+        B: Fn(BigInt, BigInt) -> V,
         F: Fn(f64, f64) -> V,
         R: Fn(Rat, Rat) -> V,
+        C: Fn((f64, f64), (f64, f64)) -> V,
     {
-        match self {
-            Number::Integer(a) => match other {
-                Number::Integer(b) => icalc(a, b),
-                Number::Float(b) => fcalc(a as f64, b),
-                Number::Rational(b) => rcalc(Rat::new(a, 1), b),
-            },
-            Number::Float(a) => match other {
-                Number::Integer(b) => fcalc(a, b as f64),
-                Number::Float(b) => fcalc(a, b),
-                Number::Rational(b) => fcalc(a, b.div_float()),
-            },
-            Number::Rational(a) => match other {
-                Number::Integer(b) => rcalc(a, Rat::new(b, 1)),
-                Number::Float(b) => fcalc(a.div_float(), b),
-                Number::Rational(b) => rcalc(a, b),
-            },
+        match (self, other) {
+            (Number::Complex(a, b), y) => ccalc((a, b), y.to_complex()),
+            (x, Number::Complex(c, d)) => ccalc(x.to_complex(), (c, d)),
+            (Number::Integer(a), Number::Integer(b)) => icalc(a, b),
+            (Number::Float(a), b) => fcalc(a, b.to_f64()),
+            (a, Number::Float(b)) => fcalc(a.to_f64(), b),
+            (Number::Rational(a), b) => rcalc(a, b.to_rat()),
+            (a, Number::Rational(b)) => rcalc(a.to_rat(), b),
+            (a, b) => bcalc(a.to_big(), b.to_big()),
         }
     }
+    fn compare(&self, other: &Number) -> Option<Ordering> {
+        self.clone().calc(
+            other.clone(),
+            |x, y| x.partial_cmp(&y),
+            |x, y| x.partial_cmp(&y),
+            |x, y| x.partial_cmp(&y),
+            |x, y| x.partial_cmp(&y),
+            |x, y| if x == y { Some(Ordering::Equal) } else { None },
+        )
+    }
     pub fn to_expression(self: Number) -> Expression {
         match self {
             Number::Integer(a) => Expression::Integer(a),
+            Number::BigInt(a) => Expression::BigInt(Box::new(a)),
             Number::Float(a) => Expression::Float(a),
-            Number::Rational(a) => Expression::Rational(a),
+            Number::Rational(a) => match Number::from_rat(a) {
+                Number::Rational(a) => Expression::Rational(Box::new(a)),
+                n => n.to_expression(),
+            },
+            Number::Complex(a, b) => Expression::Complex(a, b),
         }
     }
 }
@@ -209,117 +458,101 @@ impl Add for Number {
     type Output = Number;
 
     fn add(self, other: Number) -> Number {
-        self.calc::<fn(Int, Int) -> Number, fn(f64, f64) -> Number, fn(Rat, Rat) -> Number, Number>(
+        self.calc(
             other,
-            |x: Int, y: Int| Number::Integer(x + y),
-            |x: f64, y: f64| Number::Float(x + y),
-            |x: Rat, y: Rat| Number::Rational(x + y),
+            |x, y| match x.checked_add(y) {
+                Some(v) => Number::Integer(v),
+                None => Number::from_big(BigInt::from(x) + y),
+            },
+            |x, y| Number::from_big(x + y),
+            |x, y| Number::Float(x + y),
+            |x, y| Number::from_rat(x + y),
+            |(a, b), (c, d)| Number::complex(a + c, b + d),
         )
     }
 }
 impl Sub for Number {
     type Output = Number;
     fn sub(self, other: Number) -> Number {
-        self.calc::<fn(Int, Int) -> Number, fn(f64, f64) -> Number, fn(Rat, Rat) -> Number, Number>(
+        self.calc(
             other,
-            |x: Int, y: Int| Number::Integer(x - y),
-            |x: f64, y: f64| Number::Float(x - y),
-            |x: Rat, y: Rat| Number::Rational(x - y),
+            |x, y| match x.checked_sub(y) {
+                Some(v) => Number::Integer(v),
+                None => Number::from_big(BigInt::from(x) - y),
+            },
+            |x, y| Number::from_big(x - y),
+            |x, y| Number::Float(x - y),
+            |x, y| Number::from_rat(x - y),
+            |(a, b), (c, d)| Number::complex(a - c, b - d),
         )
     }
 }
 impl Mul for Number {
     type Output = Number;
     fn mul(self, other: Number) -> Number {
-        self.calc::<fn(Int, Int) -> Number, fn(f64, f64) -> Number, fn(Rat, Rat) -> Number, Number>(
+        self.calc(
             other,
-            |x: Int, y: Int| Number::Integer(x * y),
-            |x: f64, y: f64| Number::Float(x * y),
-            |x: Rat, y: Rat| Number::Rational(x * y),
+            |x, y| match x.checked_mul(y) {
+                Some(v) => Number::Integer(v),
+                None => Number::from_big(BigInt::from(x) * y),
+            },
+            |x, y| Number::from_big(x * y),
+            |x, y| Number::Float(x * y),
+            |x, y| Number::from_rat(x * y),
+            |(a, b), (c, d)| Number::complex(a * c - b * d, a * d + b * c),
         )
     }
 }
 impl Div for Number {
     type Output = Number;
     fn div(self, other: Number) -> Number {
-        if let (Number::Integer(x), Number::Integer(y)) = (self, other) {
-            if x == 0 && y == 0 {
+        if self.is_exact() && other.is_exact() && other.is_zero() {
+            if self.is_zero() {
                 return Number::Float(f64::NAN);
             }
-            if y == 0 {
-                return Number::Float(f64::INFINITY);
-            }
-            if 0 != (x % y) {
-                return self
-                    .calc::<fn(Int, Int) -> Number, fn(f64, f64) -> Number, fn(Rat, Rat) -> Number, Number>(
-                        Number::Rational(Rat::new(y, 1)),
-                        |x: Int, y: Int| Number::Integer(x / y),
-                        |x: f64, y: f64| Number::Float(x / y),
-                        |x: Rat, y: Rat| Number::Rational(x / y),
-                    );
-            }
+            return Number::Float(f64::INFINITY);
         }
-        self.calc::<fn(Int, Int) -> Number, fn(f64, f64) -> Number, fn(Rat, Rat) -> Number, Number>(
+        self.calc(
             other,
-            |x: Int, y: Int| Number::Integer(x / y),
-            |x: f64, y: f64| Number::Float(x / y),
-            |x: Rat, y: Rat| Number::Rational(x / y),
+            |x, y| match (x.checked_rem(y), x.checked_div(y)) {
+                (Some(0), Some(v)) => Number::Integer(v),
+                (_, _) => Number::from_rat(Rat::new(x, y)),
+            },
+            |x, y| Number::from_rat(Rat::from_big(x, y)),
+            |x, y| Number::Float(x / y),
+            |x, y| Number::from_rat(x / y),
+            |(a, b), (c, d)| {
+                let n = c * c + d * d;
+                Number::complex((a * c + b * d) / n, (b * c - a * d) / n)
+            },
         )
     }
 }
 impl PartialEq for Number {
     fn eq(&self, other: &Number) -> bool {
-        self.calc::<fn(Int, Int) -> bool, fn(f64, f64) -> bool, fn(Rat, Rat) -> bool, bool>(
-            *other,
-            |x: Int, y: Int| x == y,
-            |x: f64, y: f64| x == y,
-            |x: Rat, y: Rat| x == y,
-        )
+        self.compare(other) == Some(Ordering::Equal)
     }
 }
 impl Eq for Number {}
 impl Ord for Number {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.lt(other) {
-            Ordering::Less
-        } else if self.gt(other) {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
+        self.compare(other).unwrap_or(Ordering::Equal)
     }
 }
 impl PartialOrd for Number {
     fn lt(&self, other: &Number) -> bool {
-        self.calc::<fn(Int, Int) -> bool, fn(f64, f64) -> bool, fn(Rat, Rat) -> bool, bool>(
-            *other,
-            |x: Int, y: Int| x < y,
-            |x: f64, y: f64| x < y,
-            |x: Rat, y: Rat| x < y,
-        )
+        matches!(self.compare(other), Some(Ordering::Less))
     }
     fn le(&self, other: &Number) -> bool {
-        self.calc::<fn(Int, Int) -> bool, fn(f64, f64) -> bool, fn(Rat, Rat) -> bool, bool>(
-            *other,
-            |x: Int, y: Int| x <= y,
-            |x: f64, y: f64| x <= y,
-            |x: Rat, y: Rat| x <= y,
-        )
+        matches!(self.compare(other), Some(Ordering::Less | Ordering::Equal))
     }
     fn gt(&self, other: &Number) -> bool {
-        self.calc::<fn(Int, Int) -> bool, fn(f64, f64) -> bool, fn(Rat, Rat) -> bool, bool>(
-            *other,
-            |x: Int, y: Int| x > y,
-            |x: f64, y: f64| x > y,
-            |x: Rat, y: Rat| x > y,
-        )
+        matches!(self.compare(other), Some(Ordering::Greater))
     }
     fn ge(&self, other: &Number) -> bool {
-        self.calc::<fn(Int, Int) -> bool, fn(f64, f64) -> bool, fn(Rat, Rat) -> bool, bool>(
-            *other,
-            |x: Int, y: Int| x >= y,
-            |x: f64, y: f64| x >= y,
-            |x: Rat, y: Rat| x >= y,
+        matches!(
+            self.compare(other),
+            Some(Ordering::Greater | Ordering::Equal)
         )
     }
     fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
@@ -332,8 +565,10 @@ impl fmt::Display for Number {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Number::Integer(v) => write!(f, "{}", v),
-            Number::Float(v) => write!(f, "{}", v),
+            Number::BigInt(v) => write!(f, "{}", v),
+            Number::Float(v) => write!(f, "{}", float_to_string(*v)),
             Number::Rational(v) => write!(f, "{}", v),
+            Number::Complex(a, b) => write!(f, "{}", complex_to_string(*a, *b)),
         }
     }
 }
@@ -380,17 +615,35 @@ fn test_rat() {
     assert_eq!(Rat::new(1, 2).cmp(&Rat::new(1, 4)), Ordering::Greater);
 }
 #[test]
-fn test_rat_error() {
-    let _ = Rat::from("31.1").map_err(|e| assert_eq!(e.to_string(), "E1020"));
-    let _ = Rat::from("31.1").map_err(|e| assert!(e.source().is_none()));
+fn test_parse_number() {
+    let parse = |s| parse_number(s, 10).map(|n| n.map(|n| n.to_string()));
+    assert_eq!(parse("#x1F"), Ok(Some("31".into())));
+    assert_eq!(parse("#e#x10"), Ok(Some("16".into())));
+    assert_eq!(parse("#e1.25"), Ok(Some("5/4".into())));
+    assert_eq!(parse("#i1/4"), Ok(Some("0.25".into())));
+    assert_eq!(parse("-1.5e2"), Ok(Some("-150.0".into())));
+    assert_eq!(parse("+2i"), Ok(Some("+2.0i".into())));
+    assert_eq!(parse("1e2-i"), Ok(Some("100.0-1.0i".into())));
+    assert_eq!(parse("31.1.1"), Ok(None));
+    assert_eq!(parse("inf"), Ok(None));
+    assert_eq!(parse("1/-2"), Ok(None));
+    assert_eq!(parse("1/0"), Err(ErrCode::E1013));
+    assert_eq!(parse("#e+inf.0"), Err(ErrCode::E1021));
+}
+#[test]
+fn test_float_to_string() {
+    assert_eq!(float_to_string(1.0), "1.0");
+    assert_eq!(float_to_string(-0.5), "-0.5");
+    assert_eq!(float_to_string(f64::NAN), "+nan.0");
+    assert_eq!(float_to_string(f64::NEG_INFINITY), "-inf.0");
 }
 #[test]
 fn test_gcm() {
-    assert_eq!(gcm(17, 2), 1);
-    assert_eq!(gcm(36, 27), 9);
-    assert_eq!(gcm(27, 36), 9);
-    assert_eq!(gcm(-27, 36), 9);
-    assert_eq!(gcm(27, -36), 9);
+    assert_eq!(gcm(&BigInt::from(17), &BigInt::from(2)), BigInt::from(1));
+    assert_eq!(gcm(&BigInt::from(36), &BigInt::from(27)), BigInt::from(9));
+    assert_eq!(gcm(&BigInt::from(27), &BigInt::from(36)), BigInt::from(9));
+    assert_eq!(gcm(&BigInt::from(-27), &BigInt::from(36)), BigInt::from(9));
+    assert_eq!(gcm(&BigInt::from(27), &BigInt::from(-36)), BigInt::from(9));
 }
 #[test]
 fn test_add_integer() {
@@ -696,4 +949,70 @@ mod tests {
         assert_eq!(do_lisp("(>= 3/2 1.5)"), "#t");
         assert_eq!(do_lisp("(>= 4/8 2/4)"), "#t");
     }
+    #[test]
+    fn test_bigint() {
+        assert_eq!(do_lisp("9223372036854775808"), "9223372036854775808");
+        assert_eq!(do_lisp("-9223372036854775809"), "-9223372036854775809");
+        assert_eq!(do_lisp("(+ 9223372036854775807 1)"), "9223372036854775808");
+        assert_eq!(
+            do_lisp("(- -9223372036854775808 1)"),
+            "-9223372036854775809"
+        );
+        assert_eq!(do_lisp("(* 4294967296 4294967296)"), "18446744073709551616");
+        assert_eq!(do_lisp("(- 9223372036854775808 1)"), "9223372036854775807");
+        assert_eq!(do_lisp("(integer? (- 9223372036854775808 1))"), "#t");
+        assert_eq!(do_lisp("(integer? 9223372036854775808)"), "#t");
+        assert_eq!(do_lisp("(number? 9223372036854775808)"), "#t");
+        assert_eq!(
+            do_lisp("(let loop ((i 1)(n 1)) (if (< 30 i) n (loop (+ i 1)(* n i))))"),
+            "265252859812191058636308480000000"
+        );
+        assert_eq!(
+            do_lisp("(let loop ((i 1)(n 1)) (if (< 50 i) n (loop (+ i 1)(* n i))))"),
+            "30414093201713378043612608166064768844377641568960512000000000000"
+        );
+        assert_eq!(do_lisp("(/ 18446744073709551616 4294967296)"), "4294967296");
+        assert_eq!(do_lisp("(/ 18446744073709551616 0)"), "E1013");
+        assert_eq!(
+            do_lisp("(= 18446744073709551616 (* 4294967296 4294967296))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(eqv? 18446744073709551616 (* 4294967296 4294967296))"),
+            "#t"
+        );
+        assert_eq!(do_lisp("(< 9223372036854775807 9223372036854775808)"), "#t");
+        assert_eq!(
+            do_lisp("(max 1 18446744073709551616 2)"),
+            "18446744073709551616"
+        );
+        assert_eq!(
+            do_lisp("(+ 0.5 18446744073709551616)"),
+            "1.8446744073709552e19"
+        );
+    }
+    #[test]
+    fn test_big_rational() {
+        assert_eq!(
+            do_lisp("(/ 18446744073709551616 3)"),
+            "18446744073709551616/3"
+        );
+        assert_eq!(
+            do_lisp("(* 18446744073709551616/3 3)"),
+            "18446744073709551616"
+        );
+        assert_eq!(do_lisp("(integer? (* 18446744073709551616/3 3))"), "#t");
+        assert_eq!(
+            do_lisp("(+ 1/3 1267650600228229401496703205376)"),
+            "3802951800684688204490109616129/3"
+        );
+        assert_eq!(
+            do_lisp("(- 3802951800684688204490109616129/3 1/3)"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(do_lisp("(< 1/1267650600228229401496703205376 1/3)"), "#t");
+        assert_eq!(do_lisp("6/2"), "3");
+        assert_eq!(do_lisp("(integer? 6/2)"), "#t");
+        assert_eq!(do_lisp("(/ 1/2 0)"), "E1013");
+    }
 }
@@ -17,6 +17,11 @@ use crate::lisp::{ErrCode, Error};
 
 use crate::number::Number;
 
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+use std::convert::TryFrom;
+
 pub fn create_function<T>(b: &mut T)
 where
     T: BuildInTable + ?Sized,
@@ -24,7 +29,7 @@ where
     b.regist("+", |exp, env| calc(exp, env, |x, y| x + y, 0));
     b.regist("-", |exp, env| calc(exp, env, |x, y| x - y, 0));
     b.regist("*", |exp, env| calc(exp, env, |x, y| x * y, 1));
-    b.regist("/", |exp, env| calc(exp, env, |x, y| x / y, 1));
+    b.regist("/", division);
     b.regist("max", |exp, env| {
         select_one(exp, env, |x, y| if x > y { x } else { y })
     });
@@ -43,14 +48,52 @@ where
     b.regist("logxor", |exp, env| bit(exp, env, |x, y| x ^ y));
     b.regist("lognot", lognot);
     b.regist("logcount", |exp, env| {
-        bitcount(exp, env, |v, i| (1 & (v >> i)) > 0)
+        bitcount(exp, env, |v| v.magnitude().count_ones())
     });
     b.regist("integer-length", |exp, env| {
-        bitcount(exp, env, |v, i| (v >> i) > 0)
+        bitcount(exp, env, |v| v.bits())
     });
 
-    b.regist("modulo", |exp, env| divide(exp, env, |x, y| x % y));
-    b.regist("quotient", |exp, env| divide(exp, env, |x, y| x / y));
+    b.regist("modulo", |exp, env| {
+        divide(
+            exp,
+            env,
+            |x, y| x.checked_rem(y).map(|_| x.mod_floor(&y)),
+            |x, y| x.mod_floor(&y),
+        )
+    });
+    b.regist("remainder", |exp, env| {
+        divide(exp, env, |x, y| x.checked_rem(y), |x, y| x % y)
+    });
+    b.regist("quotient", |exp, env| {
+        divide(exp, env, |x, y| x.checked_div(y), |x, y| x / y)
+    });
+    b.regist("floor/", |exp, env| {
+        let (q, r) = integer_divide(exp, env, |x, y| x.div_mod_floor(y))?;
+        Ok(Environment::create_values(vec![q, r]))
+    });
+    b.regist("floor-quotient", |exp, env| {
+        Ok(integer_divide(exp, env, |x, y| x.div_mod_floor(y))?.0)
+    });
+    b.regist("floor-remainder", |exp, env| {
+        Ok(integer_divide(exp, env, |x, y| x.div_mod_floor(y))?.1)
+    });
+    b.regist("truncate/", |exp, env| {
+        let (q, r) = integer_divide(exp, env, |x, y| x.div_rem(y))?;
+        Ok(Environment::create_values(vec![q, r]))
+    });
+    b.regist("truncate-quotient", |exp, env| {
+        Ok(integer_divide(exp, env, |x, y| x.div_rem(y))?.0)
+    });
+    b.regist("truncate-remainder", |exp, env| {
+        Ok(integer_divide(exp, env, |x, y| x.div_rem(y))?.1)
+    });
+    b.regist("gcd", |exp, env| {
+        fold_integer(exp, env, |x, y| x.gcd(&y), 0)
+    });
+    b.regist("lcm", |exp, env| {
+        fold_integer(exp, env, |x, y| x.lcm(&y), 1)
+    });
     b.regist("twos-exponent", twos_exponent);
 }
 fn calc(
@@ -74,6 +117,30 @@ fn calc(
     }
     Ok(Number::to_expression(result))
 }
+// The division by the exact zero is an error, ex. (/ 9 0)
+fn division(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if 1 >= exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let div = |x: Number, y: Number| {
+        if x.is_exact() && y.is_exact() && y.is_zero() {
+            Err(create_error!(ErrCode::E1013))
+        } else {
+            Ok(x / y)
+        }
+    };
+    let mut result = Expression::to_number(&eval(&exp[1], env)?)?;
+
+    if 2 == exp.len() {
+        result = div(Number::Integer(1), result)?;
+    } else {
+        for e in &exp[2..] {
+            let param = Expression::to_number(&eval(e, env)?)?;
+            result = div(result, param)?;
+        }
+    }
+    Ok(Number::to_expression(result))
+}
 fn select_one(
     exp: &[Expression],
     env: &Environment,
@@ -98,28 +165,99 @@ fn cmp(
     if 3 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let mut v: [Number; 2] = [Number::Integer(0); 2];
+    let mut v: [Number; 2] = [Number::Integer(0), Number::Integer(0)];
 
     for (i, e) in exp[1..].iter().enumerate() {
         v[i] = Expression::to_number(&eval(e, env)?)?;
     }
     Ok(Expression::Boolean(func(&v[0], &v[1])))
 }
+// The exact integer, which is promoted to BigInt.
+fn to_integer(e: Expression) -> Result<BigInt, Error> {
+    match e {
+        Expression::Integer(v) => Ok(BigInt::from(v)),
+        Expression::BigInt(v) => Ok(*v),
+        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+}
+fn from_big(v: BigInt) -> Expression {
+    Number::from_big(v).to_expression()
+}
+// The integer which may be inexact, ex. 2.0
+fn to_integer_number(e: Expression) -> Result<(BigInt, bool), Error> {
+    match Expression::to_number(&e)? {
+        n if n.is_integer() => match n.clone().exact() {
+            Ok(v) => Ok((v.to_big(), n.is_exact())),
+            Err(c) => Err(create_error_value!(c, e)),
+        },
+        _ => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+}
+fn from_integer_number(v: BigInt, exact: bool) -> Expression {
+    let n = Number::from_big(v);
+    if exact {
+        n.to_expression()
+    } else {
+        n.inexact().to_expression()
+    }
+}
+fn integer_divide(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(x: &BigInt, y: &BigInt) -> (BigInt, BigInt),
+) -> Result<(Expression, Expression), Error> {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let (x, a) = to_integer_number(eval(&exp[1], env)?)?;
+    let (y, b) = to_integer_number(eval(&exp[2], env)?)?;
+    if y.is_zero() {
+        return Err(create_error!(ErrCode::E1013));
+    }
+    let (q, r) = func(&x, &y);
+    Ok((
+        from_integer_number(q, a && b),
+        from_integer_number(r, a && b),
+    ))
+}
+fn fold_integer(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(x: BigInt, y: BigInt) -> BigInt,
+    x: Int,
+) -> ResultExpression {
+    let (mut result, mut exact) = (BigInt::from(x), true);
+    for e in &exp[1..] {
+        let (v, b) = to_integer_number(eval(e, env)?)?;
+        result = func(result, v);
+        exact &= b;
+    }
+    Ok(from_integer_number(result, exact))
+}
 fn divide(
     exp: &[Expression],
     env: &Environment,
-    func: fn(x: &Int, y: &Int) -> Int,
+    func: fn(x: Int, y: Int) -> Option<Int>,
+    bfunc: fn(x: BigInt, y: BigInt) -> BigInt,
 ) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     let (a, b) = (eval(&exp[1], env)?, eval(&exp[2], env)?);
-    match (a, b) {
-        (Expression::Integer(x), Expression::Integer(y)) => {
-            if y == 0 {
+    if let (Expression::Integer(x), Expression::Integer(y)) = (&a, &b) {
+        if *y == 0 {
+            return Err(create_error!(ErrCode::E1013));
+        }
+        if let Some(v) = func(*x, *y) {
+            return Ok(Expression::Integer(v));
+        }
+    }
+    match (to_integer(a), to_integer(b)) {
+        (Ok(x), Ok(y)) => {
+            if y.is_zero() {
                 Err(create_error!(ErrCode::E1013))
             } else {
-                Ok(Expression::Integer(func(&x, &y)))
+                Ok(from_big(bfunc(x, y)))
             }
         }
         (_, _) => Err(create_error!(ErrCode::E1002)),
@@ -129,39 +267,32 @@ fn shift(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let mut x: [Int; 2] = [0; 2];
-    for (i, e) in exp[1..].iter().enumerate() {
-        x[i] = match eval(e, env)? {
-            Expression::Integer(v) => v,
-            e => return Err(create_error_value!(ErrCode::E1002, e)),
-        };
-    }
-    Ok(Expression::Integer(if x[1] >= 0 {
-        x[0] << x[1]
-    } else {
-        x[0] >> x[1].abs()
-    }))
+    let x = to_integer(eval(&exp[1], env)?)?;
+    let n = match eval(&exp[2], env)? {
+        Expression::Integer(v) => v,
+        e => return Err(create_error_value!(ErrCode::E1002, e)),
+    };
+    // ex. (ash 1 (ash 1 100)) with i128, the shift is too large
+    let bits = match usize::try_from(n.unsigned_abs()) {
+        Ok(bits) => bits,
+        Err(_) => return Err(create_error_value!(ErrCode::E1021, n)),
+    };
+    Ok(from_big(if n >= 0 { x << bits } else { x >> bits }))
 }
-fn bit(exp: &[Expression], env: &Environment, func: fn(x: Int, y: Int) -> Int) -> ResultExpression {
-    let mut result: Int = 0;
-    let mut first: bool = true;
-
+fn bit(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(x: BigInt, y: BigInt) -> BigInt,
+) -> ResultExpression {
     if 1 >= exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    for e in &exp[1..] {
-        let param = match eval(e, env)? {
-            Expression::Integer(v) => v,
-            e => return Err(create_error_value!(ErrCode::E1002, e)),
-        };
-        if first {
-            result = param;
-            first = false;
-            continue;
-        }
+    let mut result = to_integer(eval(&exp[1], env)?)?;
+    for e in &exp[2..] {
+        let param = to_integer(eval(e, env)?)?;
         result = func(result, param);
     }
-    Ok(Expression::Integer(result))
+    Ok(from_big(result))
 }
 fn lognot(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
@@ -169,6 +300,7 @@ fn lognot(exp: &[Expression], env: &Environment) -> ResultExpression {
     } else {
         match eval(&exp[1], env)? {
             Expression::Integer(v) => Ok(Expression::Integer(!v)),
+            Expression::BigInt(v) => Ok(from_big(!*v)),
             e => Err(create_error_value!(ErrCode::E1002, e)),
         }
     }
@@ -176,49 +308,25 @@ fn lognot(exp: &[Expression], env: &Environment) -> ResultExpression {
 fn bitcount(
     exp: &[Expression],
     env: &Environment,
-    func: fn(x: Int, y: Int) -> bool,
+    func: fn(x: &BigInt) -> u64,
 ) -> ResultExpression {
     if exp.len() != 2 {
-        Err(create_error_value!(ErrCode::E1007, exp.len()))
-    } else {
-        match eval(&exp[1], env)? {
-            Expression::Integer(v) => {
-                // https://practical-scheme.net/gauche/man/gauche-refe/Numbers.html
-                // (If n is negative, returns the number of 0’s in the bits of 2’s complement)
-                let x = if v >= 0 { v } else { !v };
-
-                let mut n = 0;
-                for i in 0..64 {
-                    if func(x, i) {
-                        n += 1;
-                    }
-                }
-
-                Ok(Expression::Integer(n))
-            }
-            e => Err(create_error_value!(ErrCode::E1002, e)),
-        }
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
+    let v = to_integer(eval(&exp[1], env)?)?;
+
+    // https://practical-scheme.net/gauche/man/gauche-refe/Numbers.html
+    // (If n is negative, returns the number of 0’s in the bits of 2’s complement)
+    let x = if v.is_negative() { !v } else { v };
+    Ok(Expression::Integer(func(&x) as Int))
 }
 fn twos_exponent(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let v = match eval(&exp[1], env)? {
-        Expression::Integer(v) => v,
-        e => return Err(create_error_value!(ErrCode::E1002, e)),
-    };
-    if 0 >= v {
-        return Ok(Expression::Boolean(false));
-    }
-    let m = 1;
-    for i in 0..63 {
-        if v == (m << i) {
-            return Ok(Expression::Integer(i));
-        }
-        if v < (m << i) {
-            break;
-        }
+    let v = to_integer(eval(&exp[1], env)?)?;
+    if v.is_positive() && v.magnitude().count_ones() == 1 {
+        return Ok(Expression::Integer((v.bits() - 1) as Int));
     }
     Ok(Expression::Boolean(false))
 }
@@ -250,8 +358,8 @@ mod tests {
     fn multi() {
         assert_eq!(do_lisp("(* 3 6)"), "18");
         assert_eq!(do_lisp("(* 0.5 5.75)"), "2.875");
-        assert_eq!(do_lisp("(* 3.5 6)"), "21");
-        assert_eq!(do_lisp("(* 6 3.5)"), "21");
+        assert_eq!(do_lisp("(* 3.5 6)"), "21.0");
+        assert_eq!(do_lisp("(* 6 3.5)"), "21.0");
         assert_eq!(do_lisp("(* (+ 3 4)(+ 1 2))"), "21");
         assert_eq!(do_lisp("(* 1/2 1)"), "1/2");
         assert_eq!(do_lisp("(* 10)"), "10");
@@ -261,18 +369,17 @@ mod tests {
         assert_eq!(do_lisp("(/ 4 3)"), "4/3");
         assert_eq!(do_lisp("(/ 1 2)"), "1/2");
         assert_eq!(do_lisp("(/ 9 3)"), "3");
-        assert_eq!(do_lisp("(/ 0.75 0.25)"), "3");
+        assert_eq!(do_lisp("(/ 0.75 0.25)"), "3.0");
         assert_eq!(do_lisp("(/ 9.5 5)"), "1.9");
         assert_eq!(do_lisp("(/ 6 2.5)"), "2.4");
-        assert_eq!(do_lisp("(/ 0 0)"), "NaN");
-        assert_eq!(do_lisp("(/ 9 0)"), "inf");
-        assert_eq!(do_lisp("(/ 10 0.0)"), "inf");
-        assert_eq!(do_lisp("(+ 10 (/ 0 0))"), "NaN");
-        assert_eq!(do_lisp("(+ 10 (/ 9 0))"), "inf");
+        assert_eq!(do_lisp("(/ 2)"), "1/2");
+        assert_eq!(do_lisp("(/ 10 0.0)"), "+inf.0");
+        assert_eq!(do_lisp("(/ 10.0 0)"), "+inf.0");
         assert_eq!(do_lisp("(/ 0 9)"), "0");
-        assert_eq!(do_lisp("(/ 0.0 9)"), "0");
+        assert_eq!(do_lisp("(/ 0.0 9)"), "0.0");
         assert_eq!(do_lisp("(/ (+ 4 4)(+ 2 2))"), "2");
         assert_eq!(do_lisp("(/ 10)"), "1/10");
+        assert_eq!(do_lisp("(/ 1+2i 3+4i)"), "0.44+0.08i");
     }
     #[test]
     fn max_f() {
@@ -407,6 +514,17 @@ mod tests {
         assert_eq!(do_lisp("(modulo 11 3)"), "2");
         assert_eq!(do_lisp("(modulo 11 (+ 1 2))"), "2");
         assert_eq!(do_lisp("(modulo  3 5)"), "3");
+        assert_eq!(do_lisp("(modulo -8 7)"), "6");
+        assert_eq!(do_lisp("(modulo 8 -7)"), "-6");
+        assert_eq!(do_lisp("(modulo -8 -7)"), "-1");
+        assert_eq!(do_lisp("(modulo (- (ash 1 100)) 7)"), "5");
+    }
+    #[test]
+    fn remainder() {
+        assert_eq!(do_lisp("(remainder 11 3)"), "2");
+        assert_eq!(do_lisp("(remainder -8 7)"), "-1");
+        assert_eq!(do_lisp("(remainder 8 -7)"), "1");
+        assert_eq!(do_lisp("(remainder (- (ash 1 100)) 7)"), "-2");
     }
     #[test]
     fn quotient() {
@@ -415,6 +533,41 @@ mod tests {
         assert_eq!(do_lisp("(quotient 3 5)"), "0");
     }
     #[test]
+    fn floor_divide() {
+        assert_eq!(do_lisp("(floor/ 7 2)"), "3 1");
+        assert_eq!(do_lisp("(floor/ -7 2)"), "-4 1");
+        assert_eq!(do_lisp("(floor/ 7 -2)"), "-4 -1");
+        assert_eq!(do_lisp("(floor/ 7.0 2)"), "3.0 1.0");
+        assert_eq!(do_lisp("(floor-quotient -7 2)"), "-4");
+        assert_eq!(do_lisp("(floor-remainder -7 2)"), "1");
+    }
+    #[test]
+    fn truncate_divide() {
+        assert_eq!(do_lisp("(truncate/ 7 2)"), "3 1");
+        assert_eq!(do_lisp("(truncate/ -7 2)"), "-3 -1");
+        assert_eq!(do_lisp("(truncate/ 7 -2.0)"), "-3.0 1.0");
+        assert_eq!(do_lisp("(truncate-quotient -7 2)"), "-3");
+        assert_eq!(do_lisp("(truncate-remainder -7 2)"), "-1");
+    }
+    #[test]
+    fn gcd() {
+        assert_eq!(do_lisp("(gcd)"), "0");
+        assert_eq!(do_lisp("(gcd 32 -36)"), "4");
+        assert_eq!(do_lisp("(gcd 32 -36 6)"), "2");
+        assert_eq!(do_lisp("(gcd 4.0 6)"), "2.0");
+        assert_eq!(
+            do_lisp("(gcd (ash 1 100) (ash 1 70))"),
+            "1180591620717411303424"
+        );
+    }
+    #[test]
+    fn lcm() {
+        assert_eq!(do_lisp("(lcm)"), "1");
+        assert_eq!(do_lisp("(lcm 32 -36)"), "288");
+        assert_eq!(do_lisp("(lcm 32 -36 0)"), "0");
+        assert_eq!(do_lisp("(lcm 4 6.0)"), "12.0");
+    }
+    #[test]
     fn twos_exponent() {
         assert_eq!(do_lisp("(twos-exponent -1)"), "#f");
         assert_eq!(do_lisp("(twos-exponent 0)"), "#f");
@@ -425,6 +578,33 @@ mod tests {
         assert_eq!(do_lisp("(twos-exponent 16)"), "4");
         assert_eq!(do_lisp("(twos-exponent 9223372036854775807)"), "#f");
     }
+    #[test]
+    fn bigint() {
+        assert_eq!(do_lisp("(ash 1 100)"), "1267650600228229401496703205376");
+        assert_eq!(do_lisp("(ash (ash 1 100) -99)"), "2");
+        assert_eq!(
+            do_lisp("(logand (ash 1 100) (- (ash 1 101) 1))"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(
+            do_lisp("(logior (ash 1 100) 1)"),
+            "1267650600228229401496703205377"
+        );
+        assert_eq!(do_lisp("(logxor (ash 1 100) (ash 1 100))"), "0");
+        assert_eq!(
+            do_lisp("(lognot (ash 1 100))"),
+            "-1267650600228229401496703205377"
+        );
+        assert_eq!(do_lisp("(logcount (- (ash 1 100) 1))"), "100");
+        assert_eq!(do_lisp("(integer-length (ash 1 100))"), "101");
+        assert_eq!(do_lisp("(twos-exponent (ash 1 100))"), "100");
+        assert_eq!(do_lisp("(modulo (ash 1 100) 7)"), "2");
+        assert_eq!(do_lisp("(quotient (ash 1 100) (ash 1 98))"), "4");
+        assert_eq!(
+            do_lisp("(quotient -9223372036854775808 -1)"),
+            "9223372036854775808"
+        );
+    }
 }
 #[cfg(test)]
 mod error_tests {
@@ -453,6 +633,11 @@ mod error_tests {
         assert_eq!(do_lisp("(/ 9 a)"), "E1008");
         assert_eq!(do_lisp("(/ 1 3.4 #t)"), "E1003");
         assert_eq!(do_lisp("(/)"), "E1007");
+        assert_eq!(do_lisp("(/ 9 0)"), "E1013");
+        assert_eq!(do_lisp("(/ 0 0)"), "E1013");
+        assert_eq!(do_lisp("(/ 0)"), "E1013");
+        assert_eq!(do_lisp("(/ 9 1/2 0)"), "E1013");
+        assert_eq!(do_lisp("(+ 10 (/ 9 0))"), "E1013");
     }
     #[test]
     fn max_f() {
@@ -559,6 +744,13 @@ mod error_tests {
         assert_eq!(do_lisp("(modulo 10 a)"), "E1008");
     }
     #[test]
+    fn remainder() {
+        assert_eq!(do_lisp("(remainder 10)"), "E1007");
+        assert_eq!(do_lisp("(remainder 10 0)"), "E1013");
+        assert_eq!(do_lisp("(remainder 13 5.5)"), "E1002");
+        assert_eq!(do_lisp("(remainder 10 a)"), "E1008");
+    }
+    #[test]
     fn quotient() {
         assert_eq!(do_lisp("(quotient 10)"), "E1007");
         assert_eq!(do_lisp("(quotient 10 0)"), "E1013");
@@ -566,9 +758,38 @@ mod error_tests {
         assert_eq!(do_lisp("(quotient 10 a)"), "E1008");
     }
     #[test]
+    fn floor_divide() {
+        assert_eq!(do_lisp("(floor/ 10)"), "E1007");
+        assert_eq!(do_lisp("(floor/ 10 0)"), "E1013");
+        assert_eq!(do_lisp("(floor-quotient 13 5.5)"), "E1002");
+        assert_eq!(do_lisp("(floor-remainder 10 #t)"), "E1003");
+        assert_eq!(do_lisp("(floor/ 10 a)"), "E1008");
+    }
+    #[test]
+    fn truncate_divide() {
+        assert_eq!(do_lisp("(truncate/ 10 1 2)"), "E1007");
+        assert_eq!(do_lisp("(truncate/ 10 0.0)"), "E1013");
+        assert_eq!(do_lisp("(truncate-quotient 1/2 5)"), "E1002");
+        assert_eq!(do_lisp("(truncate-remainder +inf.0 5)"), "E1002");
+        assert_eq!(do_lisp("(truncate/ a 10)"), "E1008");
+    }
+    #[test]
+    fn gcd() {
+        assert_eq!(do_lisp("(gcd 10 1.5)"), "E1002");
+        assert_eq!(do_lisp("(gcd 10 #t)"), "E1003");
+        assert_eq!(do_lisp("(lcm 10 1+i)"), "E1002");
+        assert_eq!(do_lisp("(lcm a)"), "E1008");
+    }
+    #[test]
     fn twos_exponent() {
         assert_eq!(do_lisp("(twos-exponent)"), "E1007");
         assert_eq!(do_lisp("(twos-exponent #f)"), "E1002");
         assert_eq!(do_lisp("(twos-exponent a)"), "E1008");
     }
+    #[test]
+    fn bigint() {
+        assert_eq!(do_lisp("(modulo (ash 1 100) 0)"), "E1013");
+        assert_eq!(do_lisp("(quotient (ash 1 100) 1.5)"), "E1002");
+        assert_eq!(do_lisp("(ash 1 (ash 1 200))"), "E1002");
+    }
 }
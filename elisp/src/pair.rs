@@ -0,0 +1,207 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::create_error_value;
+use crate::get_ptr;
+use crate::lisp::{Environment, ErrCode, Error, Expression, PairRc};
+use crate::mut_obj;
+use crate::reference_obj;
+use crate::symbol::Symbol;
+
+const DOT: &str = ".";
+
+// The cons cell, the list is the chain of the pairs which ends with ().
+pub struct Pair {
+    pub car: Expression,
+    pub cdr: Expression,
+}
+impl Pair {
+    pub fn new(car: Expression, cdr: Expression) -> Self {
+        Pair { car, cdr }
+    }
+}
+// The long list is released one by one, not to overflow the stack.
+impl Drop for Pair {
+    fn drop(&mut self) {
+        let mut next = std::mem::replace(&mut self.cdr, Expression::Null());
+        while let Expression::Pair(p) = next {
+            next = match Environment::unique_pair(p) {
+                Some(mut p) => std::mem::replace(&mut p.cdr, Expression::Null()),
+                None => break,
+            };
+        }
+    }
+}
+// The pairs of the list from the head, ex. (1 2 . 3) => (1 2 . 3), (2 . 3)
+// The cyclic list is stopped, when the pair which has been visited comes again.
+pub struct Pairs {
+    next: Expression,
+    slow: Expression,
+    count: usize,
+}
+impl Pairs {
+    // ex. (1 2 . 3) => 3, it's the pair for the cyclic list.
+    pub fn tail(&self) -> &Expression {
+        &self.next
+    }
+}
+impl Iterator for Pairs {
+    type Item = PairRc;
+    fn next(&mut self) -> Option<PairRc> {
+        let p = match &self.next {
+            Expression::Pair(p) => p.clone(),
+            _ => return None,
+        };
+        if let Expression::Pair(s) = &self.slow {
+            if self.count > 0 && get_ptr!(s) == get_ptr!(&p) {
+                return None;
+            }
+        }
+        self.next = reference_obj!(p).cdr.clone();
+        self.count += 1;
+        if self.count.is_multiple_of(2) {
+            self.slow = cdr(&self.slow);
+        }
+        Some(p)
+    }
+}
+pub fn iter(exp: &Expression) -> Pairs {
+    Pairs {
+        next: exp.clone(),
+        slow: exp.clone(),
+        count: 0,
+    }
+}
+pub fn car(exp: &Expression) -> Expression {
+    match exp {
+        Expression::Pair(p) => reference_obj!(p).car.clone(),
+        _ => Expression::Null(),
+    }
+}
+pub fn cdr(exp: &Expression) -> Expression {
+    match exp {
+        Expression::Pair(p) => reference_obj!(p).cdr.clone(),
+        _ => Expression::Null(),
+    }
+}
+// The elements and the tail, ex. (1 2 . 3) => ([1, 2], 3), (1 2) => ([1, 2], ())
+pub fn elements(exp: &Expression) -> (Vec<Expression>, Expression) {
+    let mut it = iter(exp);
+    let v = it.by_ref().map(|p| reference_obj!(p).car.clone()).collect();
+    (v, it.tail().clone())
+}
+// The elements of the proper list, ex. (1 2 3) => Some([1, 2, 3]), (1 . 2) => None
+pub fn to_vec(exp: &Expression) -> Option<Vec<Expression>> {
+    match elements(exp) {
+        (v, Expression::Null()) => Some(v),
+        _ => None,
+    }
+}
+// ex. (1 2 3) => Some(3), (1 2 . 3) => None
+pub fn length(exp: &Expression) -> Option<usize> {
+    let mut it = iter(exp);
+    let n = it.by_ref().count();
+    match it.tail() {
+        Expression::Null() => Some(n),
+        _ => None,
+    }
+}
+pub fn is_list(exp: &Expression) -> bool {
+    length(exp).is_some()
+}
+// The elements of the list argument, ex. (length 10) => E1005
+pub(crate) fn get_list(exp: Expression) -> Result<Vec<Expression>, Error> {
+    match to_vec(&exp) {
+        Some(v) => Ok(v),
+        None => Err(create_error_value!(ErrCode::E1005, exp)),
+    }
+}
+// The quoted list of the source is converted to the pairs, ex. '(1 (2) . 3)
+// The lists which are shared(or cyclic) by the datum labels, are kept so.
+pub fn datum(exp: &Expression) -> Expression {
+    datum_iter(exp, &mut HashMap::new())
+}
+fn datum_iter(exp: &Expression, memo: &mut HashMap<usize, Expression>) -> Expression {
+    let l = match exp {
+        Expression::List(l) => l,
+        // the renamed identifier of the macro template
+        Expression::Symbol(s) if s.origin().is_some() => return Expression::Symbol(s.strip()),
+        _ => return exp.clone(),
+    };
+    let ptr = get_ptr!(l) as *const u8 as usize;
+    if let Some(e) = memo.get(&ptr) {
+        return e.clone();
+    }
+    let v = reference_obj!(l).to_vec();
+    let (v, tail) = match &v[..] {
+        [x @ .., Expression::Symbol(s), t] if s == DOT && !x.is_empty() => (x, Some(t)),
+        _ => (&v[..], None),
+    };
+    if v.is_empty() {
+        return Expression::Null();
+    }
+    // the pairs are registered before their elements, for the cyclic list.
+    let pairs: Vec<Expression> = v
+        .iter()
+        .map(|_| Environment::create_pair(Expression::Null(), Expression::Null()))
+        .collect();
+    memo.insert(ptr, pairs[0].clone());
+
+    for (i, e) in v.iter().enumerate() {
+        let car = datum_iter(e, memo);
+        let cdr = match (pairs.get(i + 1), tail) {
+            (Some(p), _) => p.clone(),
+            (None, Some(t)) => datum_iter(t, memo),
+            (None, None) => Expression::Null(),
+        };
+        if let Expression::Pair(p) = &pairs[i] {
+            let mut p = mut_obj!(p);
+            p.car = car;
+            p.cdr = cdr;
+        }
+    }
+    pairs[0].clone()
+}
+// The list is converted to the form to be evaluated, ex. (eval (list '+ 1 2) env)
+// The quoted datum is kept as it is.
+pub fn form(exp: &Expression) -> Expression {
+    form_iter(exp, &mut HashSet::new())
+}
+fn form_iter(exp: &Expression, path: &mut HashSet<usize>) -> Expression {
+    let ptr = match exp {
+        Expression::Pair(p) => get_ptr!(p) as *const u8 as usize,
+        Expression::Null() => return Environment::create_form(Vec::new()),
+        _ => return exp.clone(),
+    };
+    // the cyclic list can't be evaluated, it's left as it is.
+    if !path.insert(ptr) {
+        return exp.clone();
+    }
+    let (v, tail) = elements(exp);
+    let mut l: Vec<Expression> = match v.first() {
+        Some(Expression::BuildInFunction(s, _)) if *s == "quote" => v,
+        Some(Expression::Symbol(s)) if s == "quote" => v,
+        _ => v.iter().map(|e| form_iter(e, path)).collect(),
+    };
+    match tail {
+        Expression::Null() => {}
+        Expression::Pair(_) => {
+            l.push(Expression::Symbol(Symbol::from(DOT)));
+            l.push(tail);
+        }
+        _ => {
+            l.push(Expression::Symbol(Symbol::from(DOT)));
+            l.push(form_iter(&tail, path));
+        }
+    }
+    path.remove(&ptr);
+    Environment::create_form(l)
+}
@@ -0,0 +1,227 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::vec::Vec;
+
+use crate::create_error_value;
+use crate::get_ptr;
+use crate::mut_obj;
+use crate::reference_obj;
+
+use crate::buildin::BuildInTable;
+use crate::io::{default_port, to_port};
+use crate::lisp::eval;
+use crate::lisp::{Environment, Expression, ListRc, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::machine;
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("make-parameter", make_parameter);
+    b.regist("parameterize", parameterize);
+}
+// The value is shared by the copies, so that parameterize changes all of them.
+#[derive(Clone)]
+pub struct Parameter {
+    value: ListRc,
+    converter: Option<Expression>,
+}
+impl Parameter {
+    pub(crate) fn create(v: Expression, converter: Option<Expression>) -> Expression {
+        let value = match Environment::create_form(vec![v]) {
+            Expression::List(l) => l,
+            _ => unreachable!(),
+        };
+        Expression::Parameter(Box::new(Parameter { value, converter }))
+    }
+    pub(crate) fn get(&self) -> Expression {
+        reference_obj!(self.value)[0].clone()
+    }
+    pub(crate) fn converter(&self) -> Option<&Expression> {
+        self.converter.as_ref()
+    }
+    pub(crate) fn ptr_eq(&self, other: &Parameter) -> bool {
+        get_ptr!(&self.value) == get_ptr!(&other.value)
+    }
+}
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<parameter {}>", self.get())
+    }
+}
+// The parameter of parameterize, current-output-port and current-input-port are also accepted.
+pub(crate) fn check(p: &Expression, v: Expression) -> ResultExpression {
+    match p {
+        Expression::Parameter(_) => Ok(v),
+        Expression::BuildInFunction(name @ ("current-input-port" | "current-output-port"), _) => {
+            to_port(v.clone(), *name == "current-input-port", false)?;
+            Ok(v)
+        }
+        e => Err(create_error_value!(ErrCode::E1006, e)),
+    }
+}
+// The values of the parameters and the saved values are exchanged,
+// it is called at the entry and the exit of parameterize.
+pub(crate) fn swap(params: &[Expression], saved: &ListRc, env: &Environment) {
+    let mut saved = mut_obj!(saved);
+    for (p, slot) in params.iter().zip(saved.iter_mut()) {
+        let v = std::mem::replace(slot, Expression::Nil());
+        let old = match p {
+            Expression::Parameter(p) => std::mem::replace(&mut mut_obj!(p.value)[0], v),
+            Expression::BuildInFunction(name, _) if *name == "current-input-port" => {
+                let old = default_port(env, true);
+                env.set_input_port(Some(v));
+                old
+            }
+            _ => {
+                let old = default_port(env, false);
+                env.set_output_port(Some(v));
+                old
+            }
+        };
+        *slot = old;
+    }
+}
+// ex. (make-parameter 10), (make-parameter 10 (lambda (x) (* x 2)))
+fn make_parameter(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 && exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    if exp.len() == 2 {
+        return Ok(Parameter::create(v, None));
+    }
+    let converter = eval(&exp[2], env)?;
+    if !Expression::is_procedure(&converter) {
+        return Err(create_error_value!(ErrCode::E1006, converter));
+    }
+    let make_parameter = Expression::BuildInFunction("make-parameter", make_parameter);
+    machine::apply(&make_parameter, vec![v, converter], env)
+}
+// ex. (parameterize ((p 1) (current-output-port port)) (p))
+fn parameterize(exp: &[Expression], env: &Environment) -> ResultExpression {
+    machine::run(&Environment::create_form(exp.to_vec()), env)
+}
+// The bindings of parameterize, ex. ((p 1) (q 2)) => [p, 1, q, 2]
+pub(crate) fn bindings(exp: &Expression) -> Result<Vec<Expression>, Error> {
+    let l = match exp {
+        Expression::List(l) => l,
+        e => return Err(create_error_value!(ErrCode::E1005, e)),
+    };
+    let mut v = Vec::new();
+    for e in &*(reference_obj!(l)) {
+        match e {
+            Expression::List(b) if reference_obj!(b).len() == 2 => {
+                v.extend(reference_obj!(b).iter().cloned());
+            }
+            Expression::List(b) => {
+                return Err(create_error_value!(ErrCode::E1007, reference_obj!(b).len()))
+            }
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        }
+    }
+    Ok(v)
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn make_parameter() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (make-parameter 10))", &env);
+        assert_eq!(do_lisp_env("(p)", &env), "10");
+        assert_eq!(do_lisp_env("p", &env), "#<parameter 10>");
+        assert_eq!(do_lisp_env("(procedure? p)", &env), "#t");
+        assert_eq!(
+            do_lisp_env("(map (lambda (f) (f)) (list p p))", &env),
+            "(10 10)"
+        );
+        assert_eq!(do_lisp("((make-parameter 10 (lambda (x) (* x 2))))"), "20");
+    }
+    #[test]
+    fn parameterize() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define p (make-parameter 10 (lambda (x) (* x 2))))", &env);
+        do_lisp_env("(define q (make-parameter 1))", &env);
+        do_lisp_env("(define (f) (list (p) (q)))", &env);
+        assert_eq!(
+            do_lisp_env("(parameterize ((p 3) (q 4)) (f))", &env),
+            "(6 4)"
+        );
+        assert_eq!(do_lisp_env("(f)", &env), "(20 1)");
+        assert_eq!(
+            do_lisp_env("(parameterize ((q 2)) (parameterize ((q 3)) (q)))", &env),
+            "3"
+        );
+        assert_eq!(do_lisp_env("(parameterize () 1 2)", &env), "2");
+
+        // the value is restored on the escape
+        assert_eq!(
+            do_lisp_env(
+                "(guard (e (#t (q))) (parameterize ((q 5)) (raise 'a)))",
+                &env
+            ),
+            "1"
+        );
+        do_lisp_env("(define k #f)", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(+ 100 (parameterize ((q 2)) (call/cc (lambda (c) (set! k c) (q)))))",
+                &env
+            ),
+            "102"
+        );
+        assert_eq!(do_lisp_env("(q)", &env), "1");
+
+        do_lisp_env("(define s (open-output-string))", &env);
+        do_lisp_env(
+            "(parameterize ((current-output-port s)) (display \"abc\"))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(get-output-string s)", &env), "\"abc\"");
+        assert_eq!(
+            do_lisp_env("(current-output-port)", &env),
+            "#<output-port stdout>"
+        );
+        assert_eq!(
+            do_lisp("(parameterize ((current-input-port (open-input-string \"(1 2)\"))) (read))"),
+            "(1 2)"
+        );
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp;
+
+    #[test]
+    fn make_parameter() {
+        assert_eq!(do_lisp("(make-parameter)"), "E1007");
+        assert_eq!(do_lisp("(make-parameter 1 2 3)"), "E1007");
+        assert_eq!(do_lisp("(make-parameter 1 2)"), "E1006");
+        assert_eq!(do_lisp("((make-parameter 1) 2)"), "E1007");
+        assert_eq!(do_lisp("(make-parameter 1 car)"), "E1005");
+    }
+    #[test]
+    fn parameterize() {
+        assert_eq!(do_lisp("(parameterize)"), "E1007");
+        assert_eq!(do_lisp("(parameterize 1 2)"), "E1005");
+        assert_eq!(do_lisp("(parameterize (1) 2)"), "E1005");
+        assert_eq!(do_lisp("(parameterize ((car)) 2)"), "E1007");
+        assert_eq!(do_lisp("(parameterize ((car 1)) 2)"), "E1006");
+        assert_eq!(do_lisp("(parameterize ((a 1)) 2)"), "E1008");
+        assert_eq!(
+            do_lisp("(parameterize ((current-output-port 1)) 2)"),
+            "E1032"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Cursor;
+use std::io::Write;
+
+// The stream is Sync, so that the port is shared by the threads.
+enum Stream {
+    Input(Box<dyn BufRead + Send + Sync>),
+    Output(Box<dyn Write + Send + Sync>),
+    StringOutput(Vec<u8>),
+    Console,
+    Closed,
+}
+pub struct Port {
+    name: String,
+    input: bool,
+    binary: bool,
+    stream: Stream,
+    // The character which is read by peek-char
+    peeked: Option<char>,
+}
+impl Port {
+    pub fn input<R>(name: &str, r: R, binary: bool) -> Port
+    where
+        R: BufRead + Send + Sync + 'static,
+    {
+        Port::new(name, true, binary, Stream::Input(Box::new(r)))
+    }
+    pub fn output<W>(name: &str, w: W, binary: bool) -> Port
+    where
+        W: Write + Send + Sync + 'static,
+    {
+        Port::new(name, false, binary, Stream::Output(Box::new(w)))
+    }
+    pub fn string_input(s: &str) -> Port {
+        Port::input("string", Cursor::new(s.as_bytes().to_vec()), false)
+    }
+    pub fn string_output() -> Port {
+        Port::new("string", false, false, Stream::StringOutput(Vec::new()))
+    }
+    pub fn bytes_input(v: Vec<u8>) -> Port {
+        Port::input("bytevector", Cursor::new(v), true)
+    }
+    pub fn bytes_output() -> Port {
+        Port::new("bytevector", false, true, Stream::StringOutput(Vec::new()))
+    }
+    pub fn stdin() -> Port {
+        Port::input("stdin", BufReader::new(io::stdin()), false)
+    }
+    pub fn stdout() -> Port {
+        Port::new("stdout", false, false, Stream::Console)
+    }
+    fn new(name: &str, input: bool, binary: bool, stream: Stream) -> Port {
+        Port {
+            name: name.to_string(),
+            input,
+            binary,
+            stream,
+            peeked: None,
+        }
+    }
+    pub fn is_input(&self) -> bool {
+        self.input
+    }
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+    pub fn is_open(&self) -> bool {
+        !matches!(self.stream, Stream::Closed)
+    }
+    pub fn close(&mut self) -> io::Result<()> {
+        let r = self.flush();
+        self.stream = Stream::Closed;
+        r
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stream {
+            Stream::Output(w) => w.flush(),
+            Stream::Console => io::stdout().flush(),
+            _ => Ok(()),
+        }
+    }
+    fn reader(&mut self) -> io::Result<&mut Box<dyn BufRead + Send + Sync>> {
+        match &mut self.stream {
+            Stream::Input(r) => Ok(r),
+            _ => Err(io::Error::from(io::ErrorKind::Unsupported)),
+        }
+    }
+    pub fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        let v = self.peek_u8()?;
+        if v.is_some() {
+            self.reader()?.consume(1);
+        }
+        Ok(v)
+    }
+    pub fn peek_u8(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader()?.fill_buf()?.first().copied())
+    }
+    pub fn read_char(&mut self) -> io::Result<Option<char>> {
+        if let Some(c) = self.peeked.take() {
+            return Ok(Some(c));
+        }
+        let b = match self.read_u8()? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        let n = match b {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        let mut buf = vec![b];
+        for _ in 1..n {
+            match self.read_u8()? {
+                Some(b) => buf.push(b),
+                None => break,
+            }
+        }
+        match std::str::from_utf8(&buf) {
+            Ok(s) => Ok(s.chars().next()),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+    pub fn peek_char(&mut self) -> io::Result<Option<char>> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_char()?;
+        }
+        Ok(self.peeked)
+    }
+    // The newline is not included.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut s = String::new();
+        match self.peeked.take() {
+            Some('\n') => return Ok(Some(s)),
+            Some(c) => s.push(c),
+            None => {}
+        }
+        if self.reader()?.read_line(&mut s)? == 0 && s.is_empty() {
+            return Ok(None);
+        }
+        if s.ends_with('\n') {
+            s.pop();
+            if s.ends_with('\r') {
+                s.pop();
+            }
+        }
+        Ok(Some(s))
+    }
+    pub fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.write_bytes(s.as_bytes())
+    }
+    pub fn write_bytes(&mut self, b: &[u8]) -> io::Result<()> {
+        match &mut self.stream {
+            Stream::Output(w) => w.write_all(b),
+            Stream::StringOutput(v) => {
+                v.extend_from_slice(b);
+                Ok(())
+            }
+            Stream::Console => {
+                let mut out = io::stdout();
+                out.write_all(b)?;
+                out.flush()
+            }
+            _ => Err(io::Error::from(io::ErrorKind::Unsupported)),
+        }
+    }
+    // The output of open-output-string
+    pub fn get_string(&self) -> Option<String> {
+        match &self.stream {
+            Stream::StringOutput(v) if !self.binary => {
+                Some(String::from_utf8_lossy(v).into_owned())
+            }
+            _ => None,
+        }
+    }
+    // The output of open-output-bytevector
+    pub fn get_bytes(&self) -> Option<Vec<u8>> {
+        match &self.stream {
+            Stream::StringOutput(v) if self.binary => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.input { "input" } else { "output" };
+        write!(f, "#<{}-port {}>", kind, self.name)
+    }
+}
@@ -0,0 +1,293 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+
+use crate::get_ptr;
+use crate::lisp::CHAR_NAMES;
+use crate::lisp::{Expression, ListRc};
+use crate::reference_obj;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    // display, the strings and the characters are printed as they are.
+    Display,
+    // write, the datum labels are used for the cycles.
+    Write,
+    // write-simple, the datum labels are not used.
+    Simple,
+    // write-shared, the datum labels are used for all shared structures.
+    Shared,
+}
+struct Printer {
+    style: Style,
+    // The objects which need the label, and the number if it's printed.
+    labels: HashMap<usize, Option<usize>>,
+    count: usize,
+}
+pub fn to_string(exp: &Expression, style: Style) -> String {
+    let mut p = Printer {
+        style,
+        labels: HashMap::new(),
+        count: 0,
+    };
+    if style != Style::Simple {
+        p.scan(exp, &mut HashMap::new());
+    }
+    let mut s = String::new();
+    p.print(exp, &mut s);
+    s
+}
+// ex. "a\"b" => "\"a\\\"b\""
+pub fn escape_string(v: &str) -> String {
+    let mut s = String::from("\"");
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\t' => s.push_str("\\t"),
+            '\r' => s.push_str("\\r"),
+            c if c.is_control() => s.push_str(&format!("\\x{:x};", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+// ex. 'a' => "#\\a", ' ' => "#\\space"
+pub fn char_name(c: char) -> String {
+    if let Some((name, _)) = CHAR_NAMES.iter().find(|(_, v)| *v == c) {
+        format!("#\\{}", name)
+    } else if c.is_control() || c.is_whitespace() {
+        format!("#\\x{:x}", c as u32)
+    } else {
+        format!("#\\{}", c)
+    }
+}
+// ex. "a b" => "|a b|"
+pub fn symbol_name(v: &str) -> String {
+    let bar = v.is_empty()
+        || v.chars()
+            .any(|c| c.is_whitespace() || "()|\"';`,".contains(c));
+    if !bar {
+        return v.to_string();
+    }
+    let mut s = String::from("|");
+    for c in v.chars() {
+        match c {
+            '|' => s.push_str("\\|"),
+            '\\' => s.push_str("\\\\"),
+            c if c.is_control() => s.push_str(&format!("\\x{:x};", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('|');
+    s
+}
+impl Printer {
+    // Find the objects which are reachable from themselves(or shared).
+    fn scan(&mut self, exp: &Expression, visited: &mut HashMap<usize, bool>) {
+        match exp {
+            Expression::List(l) | Expression::Vector(l) => self.scan_elements(exp, l, visited),
+            // ex. (set-point-x! p p)
+            Expression::Record(r) => self.scan_elements(exp, r.values(), visited),
+            // the cdr is followed in the loop, for the long list.
+            Expression::Pair(_) => {
+                let mut scanned = Vec::new();
+                let mut next = exp.clone();
+                while let Expression::Pair(p) = &next {
+                    let ptr = ptr(&next);
+                    match visited.get(&ptr) {
+                        Some(true) => {
+                            self.labels.insert(ptr, None);
+                            break;
+                        }
+                        Some(false) => {
+                            if self.style == Style::Shared {
+                                self.labels.insert(ptr, None);
+                            }
+                            break;
+                        }
+                        None => {}
+                    }
+                    visited.insert(ptr, true);
+                    scanned.push(ptr);
+                    let (car, cdr) = {
+                        let p = reference_obj!(p);
+                        (p.car.clone(), p.cdr.clone())
+                    };
+                    self.scan(&car, visited);
+                    next = cdr;
+                }
+                if !Expression::is_pair(&next) {
+                    self.scan(&next, visited);
+                }
+                for ptr in scanned {
+                    visited.insert(ptr, false);
+                }
+            }
+            Expression::Values(l) => {
+                for e in reference_obj!(l).iter() {
+                    self.scan(e, visited);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn scan_elements(&mut self, exp: &Expression, l: &ListRc, visited: &mut HashMap<usize, bool>) {
+        let ptr = ptr(exp);
+        match visited.get(&ptr) {
+            Some(true) => {
+                self.labels.insert(ptr, None);
+                return;
+            }
+            Some(false) => {
+                if self.style == Style::Shared {
+                    self.labels.insert(ptr, None);
+                }
+                return;
+            }
+            None => {}
+        }
+        let l = &*(reference_obj!(l));
+        if l.is_empty() {
+            return;
+        }
+        visited.insert(ptr, true);
+        for e in l.iter() {
+            self.scan(e, visited);
+        }
+        visited.insert(ptr, false);
+    }
+    fn print(&mut self, exp: &Expression, s: &mut String) {
+        match exp {
+            Expression::List(l) | Expression::Vector(l) => {
+                if self.label(exp, s) {
+                    return;
+                }
+                if let Expression::Vector(_) = exp {
+                    s.push('#');
+                }
+                s.push('(');
+                for (i, e) in reference_obj!(l).iter().enumerate() {
+                    if i > 0 {
+                        s.push(' ');
+                    }
+                    self.print(e, s);
+                }
+                s.push(')');
+            }
+            // ex. (1 2 . 3), the labeled pair in the cdr is printed after the dot.
+            Expression::Pair(_) => {
+                if self.label(exp, s) {
+                    return;
+                }
+                s.push('(');
+                let mut next = exp.clone();
+                loop {
+                    let (car, cdr) = match &next {
+                        Expression::Pair(p) => {
+                            let p = reference_obj!(p);
+                            (p.car.clone(), p.cdr.clone())
+                        }
+                        _ => unreachable!(),
+                    };
+                    self.print(&car, s);
+                    match cdr {
+                        Expression::Null() => break,
+                        Expression::Pair(_) if !self.labels.contains_key(&ptr(&cdr)) => {
+                            s.push(' ');
+                            next = cdr;
+                        }
+                        _ => {
+                            s.push_str(" . ");
+                            self.print(&cdr, s);
+                            break;
+                        }
+                    }
+                }
+                s.push(')');
+            }
+            // ex. #<point x=1 y=2>
+            Expression::Record(r) => {
+                if self.label(exp, s) {
+                    return;
+                }
+                s.push_str(&format!("#<{}", r.name()));
+                for (f, v) in r.fields().iter().zip(reference_obj!(r.values()).iter()) {
+                    s.push_str(&format!(" {}=", f.as_str()));
+                    self.print(v, s);
+                }
+                s.push('>');
+            }
+            Expression::Values(l) => {
+                for (i, e) in reference_obj!(l).iter().enumerate() {
+                    if i > 0 {
+                        s.push(' ');
+                    }
+                    self.print(e, s);
+                }
+            }
+            Expression::String(v) if self.style == Style::Display => s.push_str(v),
+            Expression::String(v) => s.push_str(&escape_string(v)),
+            Expression::Char(c) if self.style == Style::Display => s.push(*c),
+            Expression::Char(c) => s.push_str(&char_name(*c)),
+            Expression::Symbol(v) if self.style == Style::Display => s.push_str(v),
+            e => s.push_str(&e.to_string()),
+        }
+    }
+    // ex. #0=, true if the reference is printed, ex. #0#
+    fn label(&mut self, exp: &Expression, s: &mut String) -> bool {
+        if let Some(label) = self.labels.get_mut(&ptr(exp)) {
+            if let Some(n) = label {
+                s.push_str(&format!("#{}#", n));
+                return true;
+            }
+            *label = Some(self.count);
+            s.push_str(&format!("#{}=", self.count));
+            self.count += 1;
+        }
+        false
+    }
+}
+fn ptr(exp: &Expression) -> usize {
+    match exp {
+        Expression::List(l) | Expression::Vector(l) => get_ptr!(l) as *const u8 as usize,
+        Expression::Pair(p) => get_ptr!(p) as *const u8 as usize,
+        Expression::Record(r) => get_ptr!(r.values()) as *const u8 as usize,
+        _ => 0,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_string() {
+        assert_eq!(escape_string("abc"), "\"abc\"");
+        assert_eq!(escape_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(escape_string("\n\t\r\u{1}"), "\"\\n\\t\\r\\x1;\"");
+    }
+    #[test]
+    fn test_char_name() {
+        assert_eq!(char_name('a'), "#\\a");
+        assert_eq!(char_name(' '), "#\\space");
+        assert_eq!(char_name('\0'), "#\\null");
+        assert_eq!(char_name('\u{7f}'), "#\\delete");
+        assert_eq!(char_name('\u{1}'), "#\\x1");
+        assert_eq!(char_name('\u{3000}'), "#\\x3000");
+    }
+    #[test]
+    fn test_symbol_name() {
+        assert_eq!(symbol_name("abc"), "abc");
+        assert_eq!(symbol_name("a b"), "|a b|");
+        assert_eq!(symbol_name(""), "||");
+        assert_eq!(symbol_name("a|b\\"), "|a\\|b\\\\|");
+    }
+}
@@ -0,0 +1,385 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::create_error;
+use crate::create_error_value;
+use crate::lisp::atom;
+use crate::lisp::{Environment, Expression, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::mut_obj;
+use crate::pair;
+use crate::source::Position;
+
+// The byte range of the datum in the program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+// ex. let mut reader = Reader::new("(+ 1 2) (* 3 4)");
+//     while let Some((exp, span)) = reader.read(&env)? { ... }
+pub struct Reader<'a> {
+    program: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    start: Option<Position>,
+    // (line, column) from the start of the program
+    at: (u32, u32),
+    labels: HashMap<String, Expression>,
+}
+impl<'a> Reader<'a> {
+    pub fn new(program: &'a str) -> Self {
+        Reader {
+            program,
+            chars: program.char_indices().peekable(),
+            start: None,
+            at: (0, 0),
+            labels: HashMap::new(),
+        }
+    }
+    // The errors are reported with the positions in the file.
+    pub fn with_position(program: &'a str, start: &Position) -> Self {
+        let mut reader = Reader::new(program);
+        reader.start = Some(start.clone());
+        reader
+    }
+    // The next datum and its span, None at the end of the program.
+    pub fn read(&mut self, env: &Environment) -> Result<Option<(Expression, Span)>, Error> {
+        self.labels.clear();
+        self.skip(env)?;
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        let start = self.offset();
+        let exp = self.datum(env)?;
+        let end = self.offset();
+        Ok(Some((exp, Span { start, end })))
+    }
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+    // The character after the next one, ex. '|' of "#|"
+    fn peek2(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next().map(|(_, c)| c)
+    }
+    // ex. "#u8(" of "#u8(1 2)"
+    fn lookahead(&self, s: &str) -> bool {
+        let i = self
+            .chars
+            .clone()
+            .next()
+            .map_or(self.program.len(), |(i, _)| i);
+        self.program[i..].starts_with(s)
+    }
+    fn next(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.at = (self.at.0 + 1, 0);
+        } else {
+            self.at.1 += 1;
+        }
+        Some(c)
+    }
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.program.len(), |(i, _)| *i)
+    }
+    fn pos(&self) -> Option<Position> {
+        self.start.as_ref().map(|p| p.offset(self.at.0, self.at.1))
+    }
+    // The whitespaces and the comments are skipped.
+    fn skip(&mut self, env: &Environment) -> Result<(), Error> {
+        while let Some(c) = self.peek() {
+            match c {
+                ';' => while !matches!(self.next(), Some('\n') | None) {},
+                '#' if self.peek2() == Some('|') => self.block_comment()?,
+                // ex. (list 1 #;(+ 1 2) 3)
+                '#' if self.peek2() == Some(';') => {
+                    self.next();
+                    self.next();
+                    self.skip(env)?;
+                    self.datum(env)?;
+                }
+                c if c.is_whitespace() => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+    // ex. #| ... #| ... |# ... |#
+    fn block_comment(&mut self) -> Result<(), Error> {
+        let pos = self.pos();
+        self.next();
+        self.next();
+        let mut level = 1;
+        while level > 0 {
+            match self.next() {
+                Some('|') if self.peek() == Some('#') => {
+                    self.next();
+                    level -= 1;
+                }
+                Some('#') if self.peek() == Some('|') => {
+                    self.next();
+                    level += 1;
+                }
+                Some(_) => {}
+                None => return Err(create_error!(ErrCode::E0001).with_pos(&pos)),
+            }
+        }
+        Ok(())
+    }
+    fn datum(&mut self, env: &Environment) -> ResultExpression {
+        let pos = self.pos();
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(create_error!(ErrCode::E0001)),
+        };
+        match c {
+            '(' => {
+                self.next();
+                let v = self.elements(env, &pos, Vec::new())?;
+                Ok(list(v, &pos, env))
+            }
+            ')' => {
+                self.next();
+                Err(create_error!(ErrCode::E0003).with_pos(&pos))
+            }
+            '\'' => self.quote("quote", &pos, env),
+            '`' => self.quote("quasiquote", &pos, env),
+            ',' if self.peek2() == Some('@') => {
+                self.next();
+                self.quote("unquote-splicing", &pos, env)
+            }
+            ',' => self.quote("unquote", &pos, env),
+            '"' | '|' => self.string(c),
+            '#' if self.peek2() == Some('(') => {
+                self.next();
+                self.next();
+                let v = self.elements(env, &pos, Vec::new())?;
+                Ok(Environment::create_vector(
+                    v.iter().map(pair::datum).collect(),
+                ))
+            }
+            '#' if self.lookahead("#u8(") => self.bytevector(&pos, env),
+            _ => self.token(env),
+        }
+    }
+    fn elements(
+        &mut self,
+        env: &Environment,
+        pos: &Option<Position>,
+        mut v: Vec<Expression>,
+    ) -> Result<Vec<Expression>, Error> {
+        loop {
+            self.skip(env)?;
+            match self.peek() {
+                Some(')') => {
+                    self.next();
+                    return Ok(v);
+                }
+                Some(_) => v.push(self.datum(env)?),
+                None if v.is_empty() => return Err(create_error!(ErrCode::E0001)),
+                None => return Err(create_error!(ErrCode::E0002).with_pos(pos)),
+            }
+        }
+    }
+    // ex. #u8(0 255), the elements are the bytes.
+    fn bytevector(&mut self, pos: &Option<Position>, env: &Environment) -> ResultExpression {
+        for _ in 0.."#u8(".len() {
+            self.next();
+        }
+        let mut v = Vec::new();
+        for e in self.elements(env, pos, Vec::new())? {
+            match e {
+                Expression::Integer(i) => match u8::try_from(i) {
+                    Ok(b) => v.push(b),
+                    Err(_) => return Err(create_error_value!(ErrCode::E1021, i).with_pos(pos)),
+                },
+                e => return Err(create_error_value!(ErrCode::E1002, e).with_pos(pos)),
+            }
+        }
+        Ok(Environment::create_bytevector(v))
+    }
+    // ex. 'a => (quote a), `(a ,b) => (quasiquote (a (unquote b)))
+    fn quote(
+        &mut self,
+        keyword: &str,
+        pos: &Option<Position>,
+        env: &Environment,
+    ) -> ResultExpression {
+        self.next();
+        self.skip(env)?;
+        let v = vec![atom(keyword)?, self.datum(env)?];
+        Ok(list(v, pos, env))
+    }
+    // ex. "abc", |a b|
+    fn string(&mut self, end: char) -> ResultExpression {
+        let pos = self.pos();
+        let start = self.offset();
+        self.next();
+        let mut escape = false;
+        loop {
+            match self.next() {
+                None => return Err(create_error!(ErrCode::E0004).with_pos(&pos)),
+                Some('\\') if !escape => escape = true,
+                Some(c) if c == end && !escape => break,
+                Some(_) => escape = false,
+            }
+        }
+        let token = &self.program[start..self.offset()];
+        atom(token).map_err(|e| e.with_pos(&pos))
+    }
+    // The number, the symbol, the character and the datum label.
+    fn token(&mut self, env: &Environment) -> ResultExpression {
+        let pos = self.pos();
+        let start = self.offset();
+        // ex. #\(
+        if self.peek() == Some('#') && self.peek2() == Some('\\') {
+            self.next();
+            self.next();
+            self.next();
+        }
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "()\";".contains(c) {
+                break;
+            }
+            self.next();
+            // ex. #0=abc
+            if c == '=' && datum_label(&self.program[start..self.offset()], '=').is_some() {
+                break;
+            }
+        }
+        let token = &self.program[start..self.offset()];
+        if let Some(n) = datum_label(token, '=') {
+            return self.label(n.to_string(), env);
+        }
+        if let Some(n) = datum_label(token, '#') {
+            return match self.labels.get(n) {
+                Some(v) => Ok(v.clone()),
+                None => Err(create_error_value!(ErrCode::E0005, token).with_pos(&pos)),
+            };
+        }
+        // ex. abc"
+        if self.peek() == Some('"') {
+            return Err(create_error!(ErrCode::E0004).with_pos(&pos));
+        }
+        atom(token).map_err(|e| e.with_pos(&pos))
+    }
+    // ex. #0=(a #0#), the list is registered before its elements.
+    fn label(&mut self, n: String, env: &Environment) -> ResultExpression {
+        self.skip(env)?;
+        if self.peek() != Some('(') {
+            let v = self.datum(env)?;
+            self.labels.insert(n, v.clone());
+            return Ok(v);
+        }
+        let pos = self.pos();
+        let label = list(Vec::new(), &pos, env);
+        self.labels.insert(n, label.clone());
+
+        self.next();
+        let v = self.elements(env, &pos, Vec::new())?;
+        if let Expression::List(l) = &label {
+            *mut_obj!(l) = v;
+        }
+        Ok(label)
+    }
+}
+// The position of the list is recorded, it's used for the error report.
+fn list(v: Vec<Expression>, pos: &Option<Position>, env: &Environment) -> Expression {
+    let list = Environment::create_form(v);
+    if let (Expression::List(l), Some(p)) = (&list, pos) {
+        env.set_position(l, p.clone());
+    }
+    list
+}
+// ex. "#0=" => Some("0"), "#0#" => Some("0")
+pub(crate) fn datum_label(token: &str, end: char) -> Option<&str> {
+    let n = token.strip_prefix('#')?.strip_suffix(end)?;
+    if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) {
+        Some(n)
+    } else {
+        None
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lisp::Environment;
+
+    fn read_all(program: &str) -> Vec<(String, Span)> {
+        let env = Environment::new();
+        let mut reader = Reader::new(program);
+        let mut v = Vec::new();
+        while let Ok(Some((exp, span))) = reader.read(&env) {
+            v.push((exp.to_string(), span));
+        }
+        v
+    }
+    #[test]
+    fn test_read() {
+        let v = read_all(" 10 (a \"b\") ; c\n #| d |# #;e #\\( |f g|");
+        assert_eq!(v.len(), 4);
+        assert_eq!(v[0], ("10".into(), Span { start: 1, end: 3 }));
+        assert_eq!(v[1], ("(a \"b\")".into(), Span { start: 4, end: 11 }));
+        assert_eq!(v[2], ("#\\(".into(), Span { start: 29, end: 32 }));
+        assert_eq!(v[3], ("|f g|".into(), Span { start: 33, end: 38 }));
+
+        let v = read_all("あ 'い");
+        assert_eq!(v[0].1, Span { start: 0, end: 3 });
+        assert_eq!(v[1].1, Span { start: 4, end: 8 });
+        assert!(read_all("").is_empty());
+        assert!(read_all(" ; comment").is_empty());
+
+        let v = read_all("#u8(0 #xff) #u8()");
+        assert_eq!(v[0], ("#u8(0 255)".into(), Span { start: 0, end: 11 }));
+        assert_eq!(v[1].0, "#u8()");
+    }
+    #[test]
+    fn test_read_position() {
+        let env = Environment::new();
+        let mut reader = Reader::with_position("(a)\n  (b", &Position::new("test.scm", 1, 1));
+        assert!(reader.read(&env).is_ok());
+        match reader.read(&env) {
+            Ok(_) => panic!("no error"),
+            Err(e) => assert_eq!(e.get_msg(), "Unexpected ')' while reading (test.scm:2:3)"),
+        }
+    }
+    #[test]
+    fn test_read_error() {
+        let env = Environment::new();
+        let error = |program: &str| match Reader::new(program).read(&env) {
+            Ok(_) => panic!("no error"),
+            Err(e) => e.get_code(),
+        };
+        assert_eq!(error("("), "E0001");
+        assert_eq!(error("(a"), "E0002");
+        assert_eq!(error(")"), "E0003");
+        assert_eq!(error("\"abc"), "E0004");
+        assert_eq!(error("#0#"), "E0005");
+        assert_eq!(error("#| abc"), "E0001");
+        assert_eq!(error("'"), "E0001");
+        assert_eq!(error("#u8(1"), "E0002");
+        assert_eq!(error("#u8(256)"), "E1021");
+        assert_eq!(error("#u8(a)"), "E1002");
+    }
+    #[test]
+    fn test_datum_label() {
+        assert_eq!(datum_label("#0=", '='), Some("0"));
+        assert_eq!(datum_label("#12#", '#'), Some("12"));
+        assert_eq!(datum_label("#=", '='), None);
+        assert_eq!(datum_label("#a=", '='), None);
+    }
+}
@@ -0,0 +1,395 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use crate::create_error_value;
+use crate::get_ptr;
+use crate::mut_obj;
+use crate::reference_obj;
+
+use crate::buildin::BuildInTable;
+use crate::lisp::eval;
+use crate::lisp::{Environment, Expression, ListRc, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::symbol::Symbol;
+
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("define-record-type", define_record_type);
+}
+// The type which is defined by define-record-type, ex. <point> => point
+pub struct RecordType {
+    name: String,
+    fields: Vec<Symbol>,
+}
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<record-type {}>", self.name)
+    }
+}
+// The fields are shared by the copies, so that the modifier changes all of them.
+#[derive(Clone)]
+pub struct Record {
+    rtd: Arc<RecordType>,
+    values: ListRc,
+}
+impl Record {
+    fn new(rtd: &Arc<RecordType>, values: Vec<Expression>) -> Self {
+        let values = match Environment::create_form(values) {
+            Expression::List(l) => l,
+            _ => unreachable!(),
+        };
+        Record {
+            rtd: rtd.clone(),
+            values,
+        }
+    }
+    pub fn is_a(&self, rtd: &Arc<RecordType>) -> bool {
+        Arc::ptr_eq(&self.rtd, rtd)
+    }
+    pub(crate) fn is_same_type(&self, other: &Record) -> bool {
+        Arc::ptr_eq(&self.rtd, &other.rtd)
+    }
+    pub(crate) fn values(&self) -> &ListRc {
+        &self.values
+    }
+    pub(crate) fn name(&self) -> &str {
+        &self.rtd.name
+    }
+    pub(crate) fn fields(&self) -> &[Symbol] {
+        &self.rtd.fields
+    }
+    pub(crate) fn ptr_eq(&self, other: &Record) -> bool {
+        get_ptr!(&self.values) == get_ptr!(&other.values)
+    }
+}
+// ex. (field accessor modifier), (field accessor) or field
+struct FieldSpec {
+    name: Symbol,
+    accessor: Option<Symbol>,
+    modifier: Option<Symbol>,
+}
+fn field_spec(exp: &Expression) -> Result<FieldSpec, Error> {
+    let spec = |name: &Symbol, accessor: Option<&Symbol>, modifier: Option<&Symbol>| FieldSpec {
+        name: name.clone(),
+        accessor: accessor.cloned(),
+        modifier: modifier.cloned(),
+    };
+    match exp {
+        Expression::Symbol(s) => Ok(spec(s, None, None)),
+        Expression::List(l) => match &reference_obj!(l)[..] {
+            [Expression::Symbol(s)] => Ok(spec(s, None, None)),
+            [Expression::Symbol(s), Expression::Symbol(a)] => Ok(spec(s, Some(a), None)),
+            [Expression::Symbol(s), Expression::Symbol(a), Expression::Symbol(m)] => {
+                Ok(spec(s, Some(a), Some(m)))
+            }
+            _ => Err(create_error_value!(ErrCode::E1004, exp)),
+        },
+        e => Err(create_error_value!(ErrCode::E1004, e)),
+    }
+}
+fn get_record(exp: &Expression, env: &Environment, rtd: &Arc<RecordType>) -> Result<Record, Error> {
+    match eval(exp, env)? {
+        Expression::Record(r) if r.is_a(rtd) => Ok(*r),
+        e => Err(create_error_value!(ErrCode::E1030, e)),
+    }
+}
+// ex. (define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))
+fn define_record_type(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let type_name = match &exp[1] {
+        Expression::Symbol(s) => s.clone(),
+        e => return Err(create_error_value!(ErrCode::E1004, e)),
+    };
+    let mut specs: Vec<FieldSpec> = Vec::new();
+    for e in &exp[4..] {
+        let spec = field_spec(e)?;
+        if specs.iter().any(|f| f.name == spec.name) {
+            return Err(create_error_value!(ErrCode::E1004, e));
+        }
+        specs.push(spec);
+    }
+    let name = type_name.as_str();
+    let name = name.strip_prefix('<').unwrap_or(name);
+    let name = name.strip_suffix('>').unwrap_or(name);
+    let rtd = Arc::new(RecordType {
+        name: name.to_string(),
+        fields: specs.iter().map(|f| f.name.clone()).collect(),
+    });
+    let index = |s: &Symbol| rtd.fields.iter().position(|f| f == s);
+
+    // (make-point x y) or make-point (all the fields) or #f
+    let constructor = match &exp[2] {
+        Expression::List(l) => {
+            let l = &*(reference_obj!(l));
+            let ctor = match l.first() {
+                Some(Expression::Symbol(s)) => s.clone(),
+                _ => return Err(create_error_value!(ErrCode::E1004, exp[2])),
+            };
+            let mut args = Vec::new();
+            for e in &l[1..] {
+                match e {
+                    Expression::Symbol(s) if index(s).is_some() && !args.contains(&index(s)) => {
+                        args.push(index(s))
+                    }
+                    e => return Err(create_error_value!(ErrCode::E1004, e)),
+                }
+            }
+            Some((ctor, args.into_iter().flatten().collect::<Vec<usize>>()))
+        }
+        Expression::Symbol(s) => Some((s.clone(), (0..rtd.fields.len()).collect())),
+        Expression::Boolean(false) => None,
+        e => return Err(create_error_value!(ErrCode::E1004, e)),
+    };
+    let predicate = match &exp[3] {
+        Expression::Symbol(s) => Some(s.clone()),
+        Expression::Boolean(false) => None,
+        e => return Err(create_error_value!(ErrCode::E1004, e)),
+    };
+
+    if let Some((ctor, args)) = constructor {
+        let rtd = rtd.clone();
+        let f = Environment::create_func_ext(move |exp, env| {
+            if exp.len() - 1 != args.len() {
+                return Err(create_error_value!(ErrCode::E1007, exp.len()));
+            }
+            let mut values = vec![Expression::Nil(); rtd.fields.len()];
+            for (i, e) in args.iter().zip(&exp[1..]) {
+                values[*i] = eval(e, env)?;
+            }
+            Ok(Expression::Record(Box::new(Record::new(&rtd, values))))
+        });
+        env.regist(ctor, f);
+    }
+    if let Some(pred) = predicate {
+        let rtd = rtd.clone();
+        let f = Environment::create_func_ext(move |exp, env| {
+            if exp.len() != 2 {
+                return Err(create_error_value!(ErrCode::E1007, exp.len()));
+            }
+            Ok(Expression::Boolean(matches!(
+                eval(&exp[1], env)?,
+                Expression::Record(r) if r.is_a(&rtd)
+            )))
+        });
+        env.regist(pred, f);
+    }
+    for (i, spec) in specs.into_iter().enumerate() {
+        if let Some(accessor) = spec.accessor {
+            let rtd = rtd.clone();
+            let f = Environment::create_func_ext(move |exp, env| {
+                if exp.len() != 2 {
+                    return Err(create_error_value!(ErrCode::E1007, exp.len()));
+                }
+                let r = get_record(&exp[1], env, &rtd)?;
+                let v = reference_obj!(r.values)[i].clone();
+                Ok(v)
+            });
+            env.regist(accessor, f);
+        }
+        if let Some(modifier) = spec.modifier {
+            let rtd = rtd.clone();
+            let f = Environment::create_func_ext(move |exp, env| {
+                if exp.len() != 3 {
+                    return Err(create_error_value!(ErrCode::E1007, exp.len()));
+                }
+                let r = get_record(&exp[1], env, &rtd)?;
+                let v = eval(&exp[2], env)?;
+                mut_obj!(r.values)[i] = v;
+                Ok(Expression::Nil())
+            });
+            env.regist(modifier, f);
+        }
+    }
+    env.regist(type_name.clone(), Expression::RecordType(rtd));
+    Ok(Expression::Symbol(type_name))
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    fn point_env() -> lisp::Environment {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))",
+            &env,
+        );
+        env
+    }
+    #[test]
+    fn define_record_type() {
+        let env = point_env();
+        do_lisp_env("(define p (make-point 1 2))", &env);
+        assert_eq!(do_lisp_env("p", &env), "#<point x=1 y=2>");
+        assert_eq!(do_lisp_env("<point>", &env), "#<record-type point>");
+        assert_eq!(do_lisp_env("(point? p)", &env), "#t");
+        assert_eq!(do_lisp_env("(point? 10)", &env), "#f");
+        assert_eq!(do_lisp_env("(point-x p)", &env), "1");
+        assert_eq!(do_lisp_env("(point-y p)", &env), "2");
+        do_lisp_env("(define q p)", &env);
+        do_lisp_env("(set-point-x! p 10)", &env);
+        assert_eq!(do_lisp_env("(point-x q)", &env), "10");
+        assert_eq!(
+            do_lisp_env("(make-point \"a\" (list 1 2))", &env),
+            "#<point x=\"a\" y=(1 2)>"
+        );
+
+        do_lisp_env(
+            "(define-record-type <line> (make-line) line? a (b line-b))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(make-line)", &env), "#<line a=nil b=nil>");
+        assert_eq!(do_lisp_env("(line? (make-point 1 2))", &env), "#f");
+        do_lisp_env("(define-record-type pare kons #f (x kar) (y kdr))", &env);
+        assert_eq!(do_lisp_env("(kdr (kons 1 2))", &env), "2");
+        do_lisp_env("(define-record-type <node> #f node? (v node-v))", &env);
+        assert_eq!(do_lisp_env("(node? 1)", &env), "#f");
+        assert_eq!(
+            do_lisp("(let () (define-record-type t (mk) t?) (t? (mk)))"),
+            "#t"
+        );
+    }
+    #[test]
+    fn equal() {
+        let env = point_env();
+        do_lisp_env("(define p (make-point 1 (list 2)))", &env);
+        assert_eq!(do_lisp_env("(eq? p p)", &env), "#t");
+        assert_eq!(do_lisp_env("(eqv? p p)", &env), "#t");
+        assert_eq!(do_lisp_env("(eqv? p (make-point 1 (list 2)))", &env), "#f");
+        assert_eq!(
+            do_lisp_env("(equal? p (make-point 1 (list 2)))", &env),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp_env("(equal? p (make-point 1 (list 3)))", &env),
+            "#f"
+        );
+        do_lisp_env(
+            "(define-record-type <point2> (make-point2 x y) point2? (x point2-x) y)",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env("(equal? (make-point 1 2) (make-point2 1 2))", &env),
+            "#f"
+        );
+        assert_eq!(
+            do_lisp_env("(member (make-point 1 (list 2)) (list 1 p))", &env),
+            "(#<point x=1 y=(2)>)"
+        );
+        do_lisp_env("(set-point-x! p p)", &env);
+        do_lisp_env("(define q (make-point 1 (list 2)))", &env);
+        do_lisp_env("(set-point-x! q q)", &env);
+        assert_eq!(do_lisp_env("(equal? p q)", &env), "#t");
+    }
+    #[test]
+    fn display() {
+        let env = point_env();
+        do_lisp_env("(define p (make-point 1 \"a\"))", &env);
+        do_lisp_env("(set-point-x! p p)", &env);
+        assert_eq!(do_lisp_env("p", &env), "#0=#<point x=#0# y=\"a\">");
+        assert_eq!(
+            do_lisp_env(
+                "(let ((out (open-output-string))) (display p out) (get-output-string out))",
+                &env
+            ),
+            "\"#0=#<point x=#0# y=a>\""
+        );
+        do_lisp_env("(define q (make-point 1 2))", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(let ((out (open-output-string))) (write-shared (list q q) out) (get-output-string out))",
+                &env
+            ),
+            "\"(#0=#<point x=1 y=2> #0#)\""
+        );
+    }
+    #[test]
+    fn sort_hash_table() {
+        let env = point_env();
+        do_lisp_env(
+            "(define l (list (make-point 3 0) (make-point 1 0) (make-point 2 0)))",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(map point-x (sort l (lambda (a b) (< (point-x a) (point-x b)))))",
+                &env
+            ),
+            "(1 2 3)"
+        );
+        do_lisp_env("(define h (make-hash-table))", &env);
+        do_lisp_env("(hash-table-put! h 'a (make-point 1 2))", &env);
+        assert_eq!(do_lisp_env("(point-y (hash-table-get h 'a))", &env), "2");
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn define_record_type() {
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point))"),
+            "E1007"
+        );
+        assert_eq!(
+            do_lisp("(define-record-type 1 (make-point) point?)"),
+            "E1004"
+        );
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point z) point? x)"),
+            "E1004"
+        );
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point x x) point? x)"),
+            "E1004"
+        );
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point) point? x x)"),
+            "E1004"
+        );
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point) point? (x 1))"),
+            "E1004"
+        );
+        assert_eq!(do_lisp("(define-record-type <point> 10 point? x)"), "E1004");
+        assert_eq!(
+            do_lisp("(define-record-type <point> (make-point) 10 x)"),
+            "E1004"
+        );
+    }
+    #[test]
+    fn record_procedures() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))",
+            &env,
+        );
+        do_lisp_env("(define-record-type <line> (make-line) line?)", &env);
+        assert_eq!(do_lisp_env("(make-point 1)", &env), "E1007");
+        assert_eq!(do_lisp_env("(make-point 1 a)", &env), "E1008");
+        assert_eq!(do_lisp_env("(point?)", &env), "E1007");
+        assert_eq!(do_lisp_env("(point-x)", &env), "E1007");
+        assert_eq!(do_lisp_env("(point-x 1)", &env), "E1030");
+        assert_eq!(do_lisp_env("(point-x (make-line))", &env), "E1030");
+        assert_eq!(
+            do_lisp_env("(set-point-x! (make-point 1 2))", &env),
+            "E1007"
+        );
+        assert_eq!(do_lisp_env("(set-point-x! (make-line) 1)", &env), "E1030");
+    }
+}
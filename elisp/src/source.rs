@@ -0,0 +1,142 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::get_ptr;
+use crate::lisp::ListRc;
+
+#[cfg(feature = "thread")]
+use crate::env_thread::ListWeak;
+
+#[cfg(not(feature = "thread"))]
+use crate::env_single::ListWeak;
+
+// The number of the entries, which the dead lists are removed at.
+const PRUNE_LIMIT: usize = 1024;
+
+// The location in the lisp program. (line and column start at 1)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    pub file: Arc<str>,
+    pub line: u32,
+    pub column: u32,
+}
+impl Position {
+    pub fn new(file: &str, line: u32, column: u32) -> Self {
+        Position {
+            file: file.into(),
+            line,
+            column,
+        }
+    }
+    // ex. the token at (1, 4) from "test.scm:10:1" => "test.scm:11:5"
+    pub(crate) fn offset(&self, line: u32, column: u32) -> Self {
+        Position {
+            file: self.file.clone(),
+            line: self.line + line,
+            column: if line == 0 {
+                self.column + column
+            } else {
+                column + 1
+            },
+        }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+// The lisp procedure which is active, when the error occurs.
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub name: String,
+    pub pos: Option<Position>,
+}
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pos {
+            Some(p) => write!(f, "at {} ({})", self.name, p),
+            None => write!(f, "at {}", self.name),
+        }
+    }
+}
+// The positions of the parsed lists.
+// The weak reference keeps the address from being reused, so it is the key.
+pub(crate) struct SourceTbl {
+    tbl: HashMap<usize, (ListWeak, Position)>,
+    limit: usize,
+}
+impl SourceTbl {
+    pub(crate) fn new() -> Self {
+        SourceTbl {
+            tbl: HashMap::new(),
+            limit: PRUNE_LIMIT,
+        }
+    }
+    pub(crate) fn insert(&mut self, l: &ListRc, pos: Position) {
+        if self.tbl.len() >= self.limit {
+            self.tbl.retain(|_, (w, _)| w.strong_count() > 0);
+            self.limit = PRUNE_LIMIT.max(self.tbl.len() * 2);
+        }
+        self.tbl
+            .insert(get_ptr!(l) as usize, (ListRc::downgrade(l), pos));
+    }
+    pub(crate) fn get(&self, l: &ListRc) -> Option<Position> {
+        self.tbl
+            .get(&(get_ptr!(l) as usize))
+            .map(|(_, p)| p.clone())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lisp::{Environment, Expression};
+
+    fn list_rc() -> ListRc {
+        match Environment::create_form(vec![]) {
+            Expression::List(l) => l,
+            _ => unreachable!(),
+        }
+    }
+    #[test]
+    fn position() {
+        let p = Position::new("test.scm", 10, 3);
+        assert_eq!(p.to_string(), "test.scm:10:3");
+        assert_eq!(p.offset(0, 4).to_string(), "test.scm:10:7");
+        assert_eq!(p.offset(1, 4).to_string(), "test.scm:11:5");
+    }
+    #[test]
+    fn call() {
+        let c = Call {
+            name: String::from("foo"),
+            pos: Some(Position::new("test.scm", 1, 2)),
+        };
+        assert_eq!(c.to_string(), "at foo (test.scm:1:2)");
+        let c = Call {
+            name: String::from("foo"),
+            pos: None,
+        };
+        assert_eq!(c.to_string(), "at foo");
+    }
+    #[test]
+    fn source_tbl() {
+        let mut tbl = SourceTbl::new();
+        let l = list_rc();
+        tbl.insert(&l, Position::new("test.scm", 1, 1));
+        assert_eq!(tbl.get(&l), Some(Position::new("test.scm", 1, 1)));
+        assert_eq!(tbl.get(&list_rc()), None);
+
+        for _ in 0..PRUNE_LIMIT {
+            tbl.insert(&list_rc(), Position::new("test.scm", 2, 1));
+        }
+        assert!(tbl.tbl.len() < PRUNE_LIMIT);
+        assert_eq!(tbl.get(&l), Some(Position::new("test.scm", 1, 1)));
+    }
+}
@@ -0,0 +1,1877 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use std::vec::IntoIter;
+
+use crate::create_error;
+use crate::create_error_value;
+use crate::mut_obj;
+use crate::reference_obj;
+
+use crate::buildin::BuildInTable;
+use crate::lisp::eval;
+use crate::lisp::{BasicBuiltIn, Environment, Expression, Int, PairRc, ResultExpression};
+use crate::lisp::{ErrCode, Error};
+use crate::list::{drop_list, make_evaled_list, relink, take_list};
+use crate::machine::{self, Iterate, IterateBox, Start, Step};
+use crate::pair;
+
+// SRFI-1 List Library, ex. (fold + 0 '(1 2 3))
+// The circular lists are not supported.
+pub fn create_function<T>(b: &mut T)
+where
+    T: BuildInTable + ?Sized,
+{
+    b.regist("xcons", xcons);
+    b.regist("cons*", cons_star);
+    b.regist("list-tabulate", machine::native);
+    b.regist("list-copy", list_copy);
+
+    b.regist("proper-list?", |exp, env| {
+        is_kind(exp, env, Expression::is_list)
+    });
+    b.regist("dotted-list?", |exp, env| is_kind(exp, env, is_dotted));
+    b.regist("not-pair?", |exp, env| is_kind(exp, env, is_not_pair));
+    b.regist("null-list?", null_list);
+    b.regist("list=", machine::native);
+
+    b.regist("first", |exp, env| nth(exp, env, 0));
+    b.regist("second", |exp, env| nth(exp, env, 1));
+    b.regist("third", |exp, env| nth(exp, env, 2));
+    b.regist("fourth", |exp, env| nth(exp, env, 3));
+    b.regist("fifth", |exp, env| nth(exp, env, 4));
+    b.regist("sixth", |exp, env| nth(exp, env, 5));
+    b.regist("seventh", |exp, env| nth(exp, env, 6));
+    b.regist("eighth", |exp, env| nth(exp, env, 7));
+    b.regist("ninth", |exp, env| nth(exp, env, 8));
+    b.regist("tenth", |exp, env| nth(exp, env, 9));
+    b.regist("car+cdr", car_cdr);
+    b.regist("take!", |exp, env| linear_update(exp, env, take, 1));
+    b.regist("take-right", |exp, env| split_at(exp, env, take_right_f));
+    b.regist("drop-right", drop_right);
+    b.regist("drop-right!", |exp, env| {
+        linear_update(exp, env, drop_right, 1)
+    });
+    b.regist("split-at", |exp, env| split_at(exp, env, split_at_f));
+    b.regist("split-at!", |exp, env| split_at(exp, env, split_at_f));
+    b.regist("list-tail", |exp, env| split_at(exp, env, list_tail_f));
+    b.regist("last-pair", last_pair);
+
+    b.regist("length+", length_plus);
+    b.regist("concatenate", concatenate);
+    b.regist("concatenate!", concatenate);
+    b.regist("append-reverse", append_reverse);
+    b.regist("append-reverse!", append_reverse);
+    b.regist("zip", zip);
+    b.regist("unzip1", |exp, env| unzip(exp, env, 1));
+    b.regist("unzip2", |exp, env| unzip(exp, env, 2));
+    b.regist("unzip3", |exp, env| unzip(exp, env, 3));
+    b.regist("unzip4", |exp, env| unzip(exp, env, 4));
+    b.regist("unzip5", |exp, env| unzip(exp, env, 5));
+    b.regist("count", machine::native);
+
+    b.regist("fold", machine::native);
+    b.regist("fold-left", machine::native);
+    b.regist("fold-right", machine::native);
+    b.regist("pair-fold", machine::native);
+    b.regist("pair-fold-right", machine::native);
+    b.regist("reduce-right", machine::native);
+    b.regist("unfold", machine::native);
+    b.regist("unfold-right", machine::native);
+    b.regist("append-map", machine::native);
+    b.regist("append-map!", machine::native);
+    b.regist("map-in-order", machine::native);
+    b.regist("pair-for-each", machine::native);
+    b.regist("filter-map", machine::native);
+
+    b.regist("filter!", machine::native);
+    b.regist("remove", machine::native);
+    b.regist("remove!", machine::native);
+    b.regist("partition", machine::native);
+    b.regist("partition!", machine::native);
+
+    b.regist("find", machine::native);
+    b.regist("find-tail", machine::native);
+    b.regist("take-while", machine::native);
+    b.regist("take-while!", machine::native);
+    b.regist("drop-while", machine::native);
+    b.regist("span", machine::native);
+    b.regist("span!", machine::native);
+    b.regist("break", machine::native);
+    b.regist("break!", machine::native);
+    b.regist("any", machine::native);
+    b.regist("every", machine::native);
+    b.regist("list-index", machine::native);
+
+    b.regist("delete-duplicates", machine::native);
+    b.regist("delete-duplicates!", machine::native);
+
+    b.regist("alist-cons", alist_cons);
+    b.regist("alist-copy", alist_copy);
+    b.regist("alist-delete", machine::native);
+    b.regist("alist-delete!", machine::native);
+
+    b.regist("lset<=", machine::native);
+    b.regist("lset=", machine::native);
+    b.regist("lset-adjoin", machine::native);
+    b.regist("lset-union", machine::native);
+    b.regist("lset-union!", machine::native);
+    b.regist("lset-intersection", machine::native);
+    b.regist("lset-intersection!", machine::native);
+    b.regist("lset-difference", machine::native);
+    b.regist("lset-difference!", machine::native);
+    b.regist("lset-xor", machine::native);
+    b.regist("lset-xor!", machine::native);
+    b.regist("lset-diff+intersection", machine::native);
+    b.regist("lset-diff+intersection!", machine::native);
+}
+// The builtin functions which apply the procedures, they are run by the machine.
+pub(crate) fn start(name: &str) -> Option<Start> {
+    let f: Start = match name {
+        "list-tabulate" => list_tabulate,
+        "list=" => list_eq,
+        "count" => |exp| each(exp, EachKind::Count),
+        "fold" => |exp| fold(exp, FoldKind::Left, false),
+        "fold-left" => |exp| fold(exp, FoldKind::Left, true),
+        "fold-right" => |exp| fold(exp, FoldKind::Right, false),
+        "pair-fold" => |exp| fold(exp, FoldKind::PairLeft, false),
+        "pair-fold-right" => |exp| fold(exp, FoldKind::PairRight, false),
+        "reduce-right" => reduce_right,
+        "unfold" => |exp| unfold(exp, false),
+        "unfold-right" => |exp| unfold(exp, true),
+        "append-map" | "append-map!" => |exp| each(exp, EachKind::AppendMap),
+        "map-in-order" => |exp| map_lists(exp, true),
+        "pair-for-each" => pair_for_each,
+        "filter-map" => |exp| each(exp, EachKind::FilterMap),
+        "filter!" => |exp| update(exp, 2, |exp| divide(exp, EachKind::Filter(true))),
+        "remove" => |exp| divide(exp, EachKind::Filter(false)),
+        "remove!" => |exp| update(exp, 2, |exp| divide(exp, EachKind::Filter(false))),
+        "partition" | "partition!" => |exp| divide(exp, EachKind::Partition),
+        "find" => |exp| find(exp, false),
+        "find-tail" => |exp| find(exp, true),
+        "take-while" => |exp| divide(exp, EachKind::Span(SpanKind::Take)),
+        "take-while!" => |exp| update(exp, 2, |exp| divide(exp, EachKind::Span(SpanKind::Take))),
+        "drop-while" => |exp| divide(exp, EachKind::Span(SpanKind::Drop)),
+        "span" | "span!" => |exp| divide(exp, EachKind::Span(SpanKind::Span)),
+        "break" | "break!" => |exp| divide(exp, EachKind::Span(SpanKind::Break)),
+        "any" => |exp| each(exp, EachKind::Any),
+        "every" => |exp| each(exp, EachKind::Every),
+        "list-index" => |exp| each(exp, EachKind::Index),
+        "delete-duplicates" => delete_duplicates,
+        "delete-duplicates!" => |exp| update(exp, 1, delete_duplicates),
+        "alist-delete" => alist_delete,
+        "alist-delete!" => |exp| update(exp, 2, alist_delete),
+        "lset<=" => |exp| lset_compare(exp, false),
+        "lset=" => |exp| lset_compare(exp, true),
+        "lset-adjoin" => lset_adjoin,
+        "lset-union" => lset_union,
+        "lset-union!" => |exp| update(exp, 2, lset_union),
+        "lset-intersection" => |exp| lset_filter(exp, true),
+        "lset-intersection!" => |exp| update(exp, 2, |exp| lset_filter(exp, true)),
+        "lset-difference" => |exp| lset_filter(exp, false),
+        "lset-difference!" => |exp| update(exp, 2, |exp| lset_filter(exp, false)),
+        "lset-xor" => lset_xor,
+        "lset-xor!" => |exp| update(exp, 2, lset_xor),
+        "lset-diff+intersection" | "lset-diff+intersection!" => lset_diff_intersection,
+        _ => return None,
+    };
+    Some(f)
+}
+enum FoldKind {
+    Left,
+    Right,
+    PairLeft,
+    PairRight,
+}
+#[derive(Clone, Copy)]
+enum SpanKind {
+    Take,
+    Drop,
+    Span,
+    Break,
+}
+#[derive(Clone, Copy)]
+enum EachKind {
+    Count,
+    Map,
+    ForEach,
+    AppendMap,
+    FilterMap,
+    Any,
+    Every,
+    // #f if a result is #f, ex. list=
+    All,
+    Index,
+    // the elements whose results are true(or false) are kept
+    Filter(bool),
+    Partition,
+    Find,
+    Span(SpanKind),
+}
+// The procedure is applied to the rows in order, ex. (count even? '(1 2 3))
+// equal? is used when the procedure is omitted, ex. (alist-delete 'a alist)
+#[derive(Clone)]
+struct Each {
+    kind: EachKind,
+    func: Option<Expression>,
+    rows: IntoIter<Vec<Expression>>,
+    // the element of each row, ex. the pair for find-tail
+    items: Vec<Expression>,
+    idx: usize,
+    acc: Vec<Expression>,
+    rest: Vec<Expression>,
+    value: Expression,
+}
+impl Each {
+    fn new(kind: EachKind, func: Option<Expression>, rows: Vec<Vec<Expression>>) -> Self {
+        Each {
+            kind,
+            func,
+            rows: rows.into_iter(),
+            items: Vec::new(),
+            idx: 0,
+            acc: Vec::new(),
+            rest: Vec::new(),
+            value: Expression::Boolean(true),
+        }
+    }
+    // The result of the procedure, the value is returned if the iteration ends.
+    fn record(&mut self, v: Expression) -> Result<Option<Expression>, Error> {
+        let i = self.idx;
+        self.idx += 1;
+        match self.kind {
+            EachKind::Count | EachKind::FilterMap => {
+                if is_true(&v) {
+                    self.acc.push(v);
+                }
+            }
+            EachKind::Map => self.acc.push(v),
+            EachKind::ForEach => {}
+            EachKind::AppendMap => self.acc.extend(to_list(v)?),
+            EachKind::Any if is_true(&v) => return Ok(Some(v)),
+            EachKind::Any => {}
+            EachKind::Every if is_true(&v) => self.value = v,
+            EachKind::Every => return Ok(Some(v)),
+            EachKind::All if is_true(&v) => {}
+            EachKind::All => return Ok(Some(Expression::Boolean(false))),
+            EachKind::Index if is_true(&v) => return Ok(Some(Expression::Integer(i as Int))),
+            EachKind::Index => {}
+            EachKind::Filter(keep) => {
+                if is_true(&v) == keep {
+                    self.acc.push(self.items[i].clone());
+                }
+            }
+            EachKind::Partition => {
+                let e = self.items[i].clone();
+                if is_true(&v) {
+                    self.acc.push(e);
+                } else {
+                    self.rest.push(e);
+                }
+            }
+            EachKind::Find if is_true(&v) => return Ok(Some(self.items[i].clone())),
+            EachKind::Find => {}
+            EachKind::Span(kind) => {
+                if is_true(&v) == matches!(kind, SpanKind::Break) {
+                    return Ok(Some(self.span(kind, i)));
+                }
+            }
+        }
+        Ok(None)
+    }
+    fn finish(&mut self) -> Expression {
+        let acc = std::mem::take(&mut self.acc);
+        match self.kind {
+            EachKind::Count => Expression::Integer(acc.len() as Int),
+            EachKind::Map | EachKind::AppendMap | EachKind::FilterMap | EachKind::Filter(_) => {
+                Environment::create_list(acc)
+            }
+            EachKind::ForEach => Expression::Nil(),
+            EachKind::Any | EachKind::Index | EachKind::Find => Expression::Boolean(false),
+            EachKind::Every | EachKind::All => self.value.clone(),
+            EachKind::Partition => Environment::create_values(vec![
+                Environment::create_list(acc),
+                Environment::create_list(std::mem::take(&mut self.rest)),
+            ]),
+            EachKind::Span(kind) => self.span(kind, self.items.len()),
+        }
+    }
+    // ex. (span even? '(2 4 5 6)) => (2 4) (5 6)
+    fn span(&mut self, kind: SpanKind, n: usize) -> Expression {
+        let mut l = std::mem::take(&mut self.items);
+        let r = l.split_off(n);
+        match kind {
+            SpanKind::Take => Environment::create_list(l),
+            SpanKind::Drop => Environment::create_list(r),
+            SpanKind::Span | SpanKind::Break => Environment::create_values(vec![
+                Environment::create_list(l),
+                Environment::create_list(r),
+            ]),
+        }
+    }
+}
+impl Iterate for Each {
+    fn next(&mut self, mut v: Option<Expression>) -> Result<Step, Error> {
+        loop {
+            if let Some(v) = v.take() {
+                if let Some(r) = self.record(v)? {
+                    return Ok(Step::Return(r));
+                }
+            }
+            let args = match self.rows.next() {
+                Some(args) => args,
+                None => return Ok(Step::Return(self.finish())),
+            };
+            match &self.func {
+                Some(f) => return Ok(Step::Apply(f.clone(), args)),
+                None => v = Some(equal(&args)),
+            }
+        }
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+// ex. (fold kons knil l ...) calls (kons e ... acc), (fold-left f init l ...) calls (f acc e ...)
+#[derive(Clone)]
+struct Fold {
+    func: Expression,
+    rows: IntoIter<Vec<Expression>>,
+    acc: Expression,
+    acc_first: bool,
+}
+impl Iterate for Fold {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        if let Some(v) = v {
+            self.acc = v;
+        }
+        let acc = std::mem::replace(&mut self.acc, Expression::Nil());
+        match self.rows.next() {
+            Some(mut args) => {
+                if self.acc_first {
+                    args.insert(0, acc);
+                } else {
+                    args.push(acc);
+                }
+                Ok(Step::Apply(self.func.clone(), args))
+            }
+            None => Ok(Step::Return(acc)),
+        }
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+// ex. (unfold stop? mapper successor seed), the elements are generated until stop? is true.
+#[derive(Clone)]
+struct Unfold {
+    // stop?, mapper, successor
+    procs: Vec<Expression>,
+    // the tail generator of unfold, or the tail of unfold-right
+    tail: Option<Expression>,
+    right: bool,
+    seed: Expression,
+    acc: Vec<Expression>,
+    phase: usize,
+}
+impl Unfold {
+    fn apply(&mut self, phase: usize) -> Step {
+        self.phase = phase;
+        Step::Apply(self.procs[phase].clone(), vec![self.seed.clone()])
+    }
+    fn finish(&mut self) -> Step {
+        if let (Some(g), false) = (&self.tail, self.right) {
+            self.phase = self.procs.len();
+            return Step::Apply(g.clone(), vec![self.seed.clone()]);
+        }
+        let mut v = std::mem::take(&mut self.acc);
+        let tail = match &self.tail {
+            Some(e) => e.clone(),
+            None => Environment::create_list(Vec::new()),
+        };
+        if self.right {
+            v.reverse();
+        }
+        Step::Return(Environment::create_dotted_list(v, tail))
+    }
+}
+impl Iterate for Unfold {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        let v = match v {
+            Some(v) => v,
+            None => return Ok(self.apply(0)),
+        };
+        Ok(match self.phase {
+            0 if is_true(&v) => self.finish(),
+            0 => self.apply(1),
+            1 => {
+                self.acc.push(v);
+                self.apply(2)
+            }
+            2 => {
+                self.seed = v;
+                self.apply(0)
+            }
+            _ => Step::Return(Environment::create_dotted_list(
+                std::mem::take(&mut self.acc),
+                v,
+            )),
+        })
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+// ex. (filter! pred l), the pairs of the argument are reused for the result.
+#[derive(Clone)]
+struct Update {
+    it: IterateBox,
+    list: Expression,
+}
+impl Iterate for Update {
+    fn next(&mut self, v: Option<Expression>) -> Result<Step, Error> {
+        match self.it.next(v)? {
+            Step::Return(r) => Ok(Step::Return(reuse(&self.list, r))),
+            s => Ok(s),
+        }
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+fn update(exp: &[Expression], i: usize, start: Start) -> Result<IterateBox, Error> {
+    let it = start(exp)?;
+    let list = exp.get(i).cloned().unwrap_or(Expression::Nil());
+    Ok(Box::new(Update { it, list }))
+}
+#[derive(Clone, Copy)]
+enum Grow {
+    No,
+    Front,
+    Back,
+}
+// The items are divided by the membership of the targets, (= x y) is called for y of the target.
+#[derive(Clone)]
+struct Search {
+    items: Vec<Expression>,
+    targets: Vec<Vec<Expression>>,
+    // found in all targets(or any target)
+    all: bool,
+    // (= y x) is called, ex. lset-adjoin
+    flip: bool,
+    // the missing item is added to the first target
+    grow: Grow,
+    // stop at the first missing item, ex. lset<=
+    stop: bool,
+    idx: usize,
+    target: usize,
+    pos: usize,
+    found: Vec<Expression>,
+    missing: Vec<Expression>,
+}
+impl Search {
+    fn new(items: Vec<Expression>, targets: Vec<Vec<Expression>>) -> Self {
+        Search {
+            items,
+            targets,
+            all: false,
+            flip: false,
+            grow: Grow::No,
+            stop: false,
+            idx: 0,
+            target: 0,
+            pos: 0,
+            found: Vec::new(),
+            missing: Vec::new(),
+        }
+    }
+    // The arguments of the next call, None if all items are divided.
+    fn next(&mut self, v: Option<Expression>) -> Option<Vec<Expression>> {
+        let more = |s: &Search| s.target + 1 < s.targets.len();
+        match v {
+            Some(v) if is_true(&v) && self.all && more(self) => {
+                self.target += 1;
+                self.pos = 0;
+            }
+            Some(v) if is_true(&v) => self.divide(true),
+            Some(_) => self.pos += 1,
+            None => {}
+        }
+        loop {
+            let x = self.items.get(self.idx)?;
+            match self.targets.get(self.target).and_then(|t| t.get(self.pos)) {
+                Some(y) if self.flip => return Some(vec![y.clone(), x.clone()]),
+                Some(y) => return Some(vec![x.clone(), y.clone()]),
+                None if !self.all && more(self) => {
+                    self.target += 1;
+                    self.pos = 0;
+                }
+                // ex. (lset-intersection eq? '(a b))
+                None if self.targets.is_empty() => self.divide(self.all),
+                None => self.divide(false),
+            }
+        }
+    }
+    fn divide(&mut self, found: bool) {
+        let x = self.items[self.idx].clone();
+        self.idx += 1;
+        self.target = 0;
+        self.pos = 0;
+        if found {
+            self.found.push(x);
+            return;
+        }
+        match self.grow {
+            Grow::Front => self.targets[0].insert(0, x.clone()),
+            Grow::Back => self.targets[0].push(x.clone()),
+            Grow::No => {}
+        }
+        if self.stop {
+            self.idx = self.items.len();
+        }
+        self.missing.push(x);
+    }
+}
+#[derive(Clone, Copy)]
+enum LsetKind {
+    Compare,
+    Adjoin,
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+    DiffIntersection,
+    Dedup,
+}
+// The searches are run in order, the next one of union and xor is made by the last result.
+#[derive(Clone)]
+struct Lset {
+    kind: LsetKind,
+    func: Option<Expression>,
+    searches: IntoIter<Search>,
+    lists: IntoIter<Vec<Expression>>,
+    search: Option<Search>,
+    v: Vec<Expression>,
+    // the search of xor, 0: v by the list, 1: the list by v, 2: the list by the intersection
+    phase: usize,
+}
+impl Lset {
+    fn create(
+        kind: LsetKind,
+        func: Option<Expression>,
+        searches: Vec<Search>,
+        lists: Vec<Vec<Expression>>,
+    ) -> IterateBox {
+        Box::new(Lset {
+            kind,
+            func,
+            searches: searches.into_iter(),
+            lists: lists.into_iter(),
+            search: None,
+            v: Vec::new(),
+            phase: 0,
+        })
+    }
+    // The next search is set, the value is returned if all searches end.
+    fn advance(&mut self, done: Option<Search>) -> Option<Expression> {
+        match self.kind {
+            LsetKind::Compare => {
+                if matches!(&done, Some(s) if !s.missing.is_empty()) {
+                    return Some(Expression::Boolean(false));
+                }
+                self.search = self.searches.next();
+                if self.search.is_none() {
+                    return Some(Expression::Boolean(true));
+                }
+                None
+            }
+            LsetKind::Union => {
+                if let Some(mut s) = done {
+                    self.v = s.targets.remove(0);
+                }
+                for l in self.lists.by_ref() {
+                    if self.v.is_empty() {
+                        self.v = l;
+                        continue;
+                    }
+                    let mut s = Search::new(l, vec![std::mem::take(&mut self.v)]);
+                    s.flip = true;
+                    s.grow = Grow::Front;
+                    self.search = Some(s);
+                    return None;
+                }
+                Some(Environment::create_list(std::mem::take(&mut self.v)))
+            }
+            LsetKind::Xor => self.xor(done),
+            _ => {
+                let mut s = match done {
+                    Some(s) => s,
+                    None => {
+                        self.search = self.searches.next();
+                        return None;
+                    }
+                };
+                Some(match self.kind {
+                    LsetKind::Adjoin => Environment::create_list(s.targets.remove(0)),
+                    LsetKind::Intersection => Environment::create_list(s.found),
+                    LsetKind::Difference | LsetKind::Dedup => Environment::create_list(s.missing),
+                    _ => Environment::create_values(vec![
+                        Environment::create_list(s.missing),
+                        Environment::create_list(s.found),
+                    ]),
+                })
+            }
+        }
+    }
+    // ex. (lset-xor eq? '(a b c d e) '(a e i o u)) => (u o i b c d)
+    fn xor(&mut self, done: Option<Search>) -> Option<Expression> {
+        if let Some(mut s) = done {
+            match self.phase {
+                0 => {
+                    let l = s.targets.remove(0);
+                    if s.missing.is_empty() {
+                        self.phase = 1;
+                        self.search = Some(Search::new(l, vec![s.found]));
+                        return None;
+                    } else if s.found.is_empty() {
+                        self.v = l.into_iter().chain(s.missing).collect();
+                    } else {
+                        self.v = s.missing;
+                        self.phase = 2;
+                        self.search = Some(Search::new(l, vec![s.found]));
+                        return None;
+                    }
+                }
+                1 => self.v = s.missing,
+                _ => {
+                    let mut r = s.missing;
+                    r.reverse();
+                    r.append(&mut self.v);
+                    self.v = r;
+                }
+            }
+        }
+        match self.lists.next() {
+            Some(l) => {
+                self.phase = 0;
+                self.search = Some(Search::new(std::mem::take(&mut self.v), vec![l]));
+                None
+            }
+            None => Some(Environment::create_list(std::mem::take(&mut self.v))),
+        }
+    }
+}
+impl Iterate for Lset {
+    fn next(&mut self, mut v: Option<Expression>) -> Result<Step, Error> {
+        loop {
+            let args = match self.search.as_mut().and_then(|s| s.next(v.take())) {
+                Some(args) => args,
+                None => {
+                    let done = self.search.take();
+                    match self.advance(done) {
+                        Some(r) => return Ok(Step::Return(r)),
+                        None => continue,
+                    }
+                }
+            };
+            match &self.func {
+                Some(f) => return Ok(Step::Apply(f.clone(), args)),
+                None => v = Some(equal(&args)),
+            }
+        }
+    }
+    fn box_clone(&self) -> IterateBox {
+        Box::new(self.clone())
+    }
+}
+fn to_list(e: Expression) -> Result<Vec<Expression>, Error> {
+    pair::get_list(e)
+}
+fn get_list(e: &Expression, env: &Environment) -> Result<Vec<Expression>, Error> {
+    to_list(eval(e, env)?)
+}
+pub(crate) fn to_proc(e: &Expression) -> ResultExpression {
+    if Expression::is_procedure(e) {
+        Ok(e.clone())
+    } else {
+        Err(create_error_value!(ErrCode::E1006, e))
+    }
+}
+// ex. (delete-duplicates l =), equal? is used when = is omitted.
+fn to_equal(exp: &[Expression], i: usize) -> Result<Option<Expression>, Error> {
+    match exp.get(i) {
+        Some(e) => Ok(Some(to_proc(e)?)),
+        None => Ok(None),
+    }
+}
+fn to_index(e: &Expression) -> Result<usize, Error> {
+    match e {
+        Expression::Integer(n) if *n < 0 => Err(create_error!(ErrCode::E1011)),
+        Expression::Integer(n) => Ok(*n as usize),
+        e => Err(create_error_value!(ErrCode::E1002, e)),
+    }
+}
+fn get_index(e: &Expression, env: &Environment) -> Result<usize, Error> {
+    to_index(&eval(e, env)?)
+}
+fn is_true(e: &Expression) -> bool {
+    !matches!(e, Expression::Boolean(false))
+}
+fn equal(args: &[Expression]) -> Expression {
+    Expression::Boolean(Expression::equal(&args[0], &args[1]))
+}
+fn is_not_pair(e: &Expression) -> bool {
+    !Expression::is_pair(e)
+}
+// ex. (1 2 . 3) or 1, the cyclic list is not dotted.
+fn is_dotted(e: &Expression) -> bool {
+    !matches!(
+        pair::elements(e).1,
+        Expression::Null() | Expression::Pair(_)
+    )
+}
+// ex. (map f '(1 2 3) '(a b)), the arguments are taken until the shortest list ends.
+fn columns(l: &[Expression]) -> Result<Vec<Vec<Expression>>, Error> {
+    let mut lists = Vec::with_capacity(l.len());
+    for e in l {
+        lists.push(to_list(e.clone())?);
+    }
+    let n = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+    Ok((0..n)
+        .map(|i| lists.iter().map(|l| l[i].clone()).collect())
+        .collect())
+}
+// ex. (pair-for-each f '(1 2 3)), f is called with (1 2 3), (2 3), (3)
+fn tails(l: &[Expression]) -> Result<Vec<Vec<Expression>>, Error> {
+    let mut lists = Vec::with_capacity(l.len());
+    let mut n = usize::MAX;
+    for e in l {
+        match pair::length(e) {
+            Some(len) => n = n.min(len),
+            None => return Err(create_error_value!(ErrCode::E1005, e)),
+        }
+        lists.push(e.clone());
+    }
+    let mut rows = Vec::new();
+    for _ in 0..n {
+        let next = lists.iter().map(pair::cdr).collect();
+        rows.push(std::mem::replace(&mut lists, next));
+    }
+    Ok(rows)
+}
+// The pairs of the list are reused for the result, ex. (take! l 2)
+fn reuse(l: &Expression, result: Expression) -> Expression {
+    let v = match (l, pair::to_vec(&result)) {
+        (Expression::Pair(_), Some(v)) if !v.is_empty() => v,
+        _ => return result,
+    };
+    let mut v = v.into_iter();
+    let mut pairs: Vec<PairRc> = Vec::new();
+    for (p, e) in pair::iter(l).zip(v.by_ref()) {
+        mut_obj!(p).car = e;
+        pairs.push(p);
+    }
+    relink(pairs, Environment::create_list(v.collect()))
+}
+fn linear_update(
+    exp: &[Expression],
+    env: &Environment,
+    func: BasicBuiltIn,
+    i: usize,
+) -> ResultExpression {
+    let mut args = Vec::with_capacity(exp.len());
+    for e in &exp[1..] {
+        args.push(eval(e, env)?);
+    }
+    let result = func(&make_evaled_list(&exp[0], &args, &None), env)?;
+    Ok(match args.get(i - 1) {
+        Some(l) => reuse(l, result),
+        None => result,
+    })
+}
+// ex. (map-in-order f '(1 2) '(3 4)), map and for-each of the lists.
+pub(crate) fn map_lists(exp: &[Expression], collect: bool) -> Result<IterateBox, Error> {
+    each(
+        exp,
+        if collect {
+            EachKind::Map
+        } else {
+            EachKind::ForEach
+        },
+    )
+}
+fn each(exp: &[Expression], kind: EachKind) -> Result<IterateBox, Error> {
+    if exp.len() < 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    Ok(Box::new(Each::new(kind, Some(f), columns(&exp[2..])?)))
+}
+fn xcons(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let cdr = eval(&exp[1], env)?;
+    let car = eval(&exp[2], env)?;
+    Ok(Environment::create_dotted_list(vec![car], cdr))
+}
+fn cons_star(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v = Vec::with_capacity(exp.len());
+    for e in &exp[1..] {
+        v.push(eval(e, env)?);
+    }
+    let tail = v.pop().unwrap();
+    Ok(Environment::create_dotted_list(v, tail))
+}
+fn list_tabulate(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let n = to_index(&exp[1])?;
+    let f = to_proc(&exp[2])?;
+    let rows = (0..n)
+        .map(|i| vec![Expression::Integer(i as Int)])
+        .collect();
+    Ok(Box::new(Each::new(EachKind::Map, Some(f), rows)))
+}
+fn list_copy(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let (v, tail) = pair::elements(&eval(&exp[1], env)?);
+    Ok(Environment::create_dotted_list(v, tail))
+}
+fn is_kind(exp: &[Expression], env: &Environment, f: fn(&Expression) -> bool) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Expression::Boolean(f(&eval(&exp[1], env)?)))
+}
+fn null_list(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Null() => Ok(Expression::Boolean(true)),
+        Expression::Pair(_) => Ok(Expression::Boolean(false)),
+        e => Err(create_error_value!(ErrCode::E1005, e)),
+    }
+}
+// ex. (list= eq? '(a b) '(a b) '(a b))
+fn list_eq(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    let mut lists = Vec::new();
+    for e in &exp[2..] {
+        lists.push(to_list(e.clone())?);
+    }
+    let mut rows = Vec::new();
+    let mut same = true;
+    for w in lists.windows(2) {
+        if w[0].len() != w[1].len() {
+            same = false;
+            break;
+        }
+        for (x, y) in w[0].iter().zip(w[1].iter()) {
+            rows.push(vec![x.clone(), y.clone()]);
+        }
+    }
+    let mut it = Each::new(EachKind::All, Some(f), rows);
+    it.value = Expression::Boolean(same);
+    Ok(Box::new(it))
+}
+fn nth(exp: &[Expression], env: &Environment, n: usize) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match get_list(&exp[1], env)?.get(n) {
+        Some(e) => Ok(e.clone()),
+        None => Err(create_error!(ErrCode::E1011)),
+    }
+}
+fn car_cdr(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Pair(p) => {
+            let p = reference_obj!(p);
+            Ok(Environment::create_values(vec![
+                p.car.clone(),
+                p.cdr.clone(),
+            ]))
+        }
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
+        e => Err(create_error_value!(ErrCode::E1005, e)),
+    }
+}
+// ex. (split-at l n), n must be less than or equal to the length of l.
+fn split_at(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(&Expression, usize, usize) -> Expression,
+) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = eval(&exp[1], env)?;
+    let len = match pair::length(&l) {
+        Some(len) => len,
+        None => return Err(create_error_value!(ErrCode::E1005, l)),
+    };
+    let n = get_index(&exp[2], env)?;
+    if len < n {
+        return Err(create_error!(ErrCode::E1011));
+    }
+    Ok(func(&l, len, n))
+}
+fn take(exp: &[Expression], env: &Environment) -> ResultExpression {
+    split_at(exp, env, |l, _, n| take_list(l, n))
+}
+fn drop_right(exp: &[Expression], env: &Environment) -> ResultExpression {
+    split_at(exp, env, |l, len, n| take_list(l, len - n))
+}
+fn take_right_f(l: &Expression, len: usize, n: usize) -> Expression {
+    drop_list(l, len - n)
+}
+fn split_at_f(l: &Expression, _: usize, n: usize) -> Expression {
+    Environment::create_values(vec![take_list(l, n), drop_list(l, n)])
+}
+fn list_tail_f(l: &Expression, _: usize, n: usize) -> Expression {
+    drop_list(l, n)
+}
+fn last_pair(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Null() => Err(create_error!(ErrCode::E1011)),
+        e @ Expression::Pair(_) => match pair::iter(&e).last() {
+            Some(p) => Ok(Expression::Pair(p)),
+            None => Err(create_error!(ErrCode::E1011)),
+        },
+        e => Err(create_error_value!(ErrCode::E1005, e)),
+    }
+}
+fn length_plus(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    // ex. (length+ l) => #f, l is the cyclic list.
+    let l = eval(&exp[1], env)?;
+    let mut it = pair::iter(&l);
+    let n = it.by_ref().count();
+    match it.tail() {
+        Expression::Null() => Ok(Expression::Integer(n as Int)),
+        Expression::Pair(_) => Ok(Expression::Boolean(false)),
+        _ => Err(create_error_value!(ErrCode::E1005, l)),
+    }
+}
+// ex. (concatenate '((1) (2 3))) => (1 2 3)
+fn concatenate(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v = Vec::new();
+    for e in get_list(&exp[1], env)? {
+        v.extend(to_list(e)?);
+    }
+    Ok(Environment::create_list(v))
+}
+fn append_reverse(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v = get_list(&exp[1], env)?;
+    v.reverse();
+    Ok(Environment::create_dotted_list(v, eval(&exp[2], env)?))
+}
+fn zip(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() < 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut lists = Vec::with_capacity(exp.len());
+    for e in &exp[1..] {
+        lists.push(eval(e, env)?);
+    }
+    Ok(Environment::create_list(
+        columns(&lists)?
+            .into_iter()
+            .map(Environment::create_list)
+            .collect(),
+    ))
+}
+// ex. (unzip2 '((1 a) (2 b))) => (1 2) (a b)
+fn unzip(exp: &[Expression], env: &Environment, n: usize) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut result = vec![Vec::new(); n];
+    for e in get_list(&exp[1], env)? {
+        let l = to_list(e)?;
+        if l.len() < n {
+            return Err(create_error!(ErrCode::E1011));
+        }
+        for (r, e) in result.iter_mut().zip(l) {
+            r.push(e);
+        }
+    }
+    let mut result: Vec<Expression> = result.into_iter().map(Environment::create_list).collect();
+    if n == 1 {
+        Ok(result.remove(0))
+    } else {
+        Ok(Environment::create_values(result))
+    }
+}
+fn fold(exp: &[Expression], kind: FoldKind, acc_first: bool) -> Result<IterateBox, Error> {
+    if exp.len() < 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let func = to_proc(&exp[1])?;
+    let rows = match kind {
+        FoldKind::Left => columns(&exp[3..])?,
+        FoldKind::Right => columns(&exp[3..])?.into_iter().rev().collect(),
+        FoldKind::PairLeft => tails(&exp[3..])?,
+        FoldKind::PairRight => tails(&exp[3..])?.into_iter().rev().collect(),
+    };
+    Ok(Box::new(Fold {
+        func,
+        rows: rows.into_iter(),
+        acc: exp[2].clone(),
+        acc_first,
+    }))
+}
+// ex. (reduce-right append '() '((1) (2))) => (append '(1) '(2))
+fn reduce_right(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let func = to_proc(&exp[1])?;
+    let mut l = to_list(exp[3].clone())?;
+    let acc = l.pop().unwrap_or_else(|| exp[2].clone());
+    let rows: Vec<Vec<Expression>> = l.into_iter().rev().map(|e| vec![e]).collect();
+    Ok(Box::new(Fold {
+        func,
+        rows: rows.into_iter(),
+        acc,
+        acc_first: false,
+    }))
+}
+fn unfold(exp: &[Expression], right: bool) -> Result<IterateBox, Error> {
+    if exp.len() < 5 || 6 < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut procs = Vec::with_capacity(3);
+    for e in &exp[1..4] {
+        procs.push(to_proc(e)?);
+    }
+    let tail = match exp.get(5) {
+        Some(e) if !right => Some(to_proc(e)?),
+        e => e.cloned(),
+    };
+    Ok(Box::new(Unfold {
+        procs,
+        tail,
+        right,
+        seed: exp[4].clone(),
+        acc: Vec::new(),
+        phase: 0,
+    }))
+}
+fn pair_for_each(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() < 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    Ok(Box::new(Each::new(
+        EachKind::ForEach,
+        Some(f),
+        tails(&exp[2..])?,
+    )))
+}
+// ex. (pred e), the elements are divided by the result.
+fn divide(exp: &[Expression], kind: EachKind) -> Result<IterateBox, Error> {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    let l = to_list(exp[2].clone())?;
+    let mut it = Each::new(kind, Some(f), l.iter().map(|e| vec![e.clone()]).collect());
+    it.items = l;
+    Ok(Box::new(it))
+}
+fn find(exp: &[Expression], tail: bool) -> Result<IterateBox, Error> {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    let l = &exp[2];
+    if !Expression::is_list(l) {
+        return Err(create_error_value!(ErrCode::E1005, l));
+    }
+    let mut rows = Vec::new();
+    let mut items = Vec::new();
+    for p in pair::iter(l) {
+        let e = reference_obj!(p).car.clone();
+        rows.push(vec![e.clone()]);
+        items.push(if tail { Expression::Pair(p) } else { e });
+    }
+    let mut it = Each::new(EachKind::Find, Some(f), rows);
+    it.items = items;
+    Ok(Box::new(it))
+}
+fn delete_duplicates(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() < 2 || 3 < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = to_list(exp[1].clone())?;
+    let f = to_equal(exp, 2)?;
+    let mut s = Search::new(l, vec![Vec::new()]);
+    s.flip = true;
+    s.grow = Grow::Back;
+    Ok(Lset::create(LsetKind::Dedup, f, vec![s], Vec::new()))
+}
+fn alist_cons(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let key = eval(&exp[1], env)?;
+    let value = eval(&exp[2], env)?;
+    let entry = Environment::create_pair(key, value);
+    Ok(Environment::create_dotted_list(
+        vec![entry],
+        eval(&exp[3], env)?,
+    ))
+}
+fn alist_copy(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut v = Vec::new();
+    for e in get_list(&exp[1], env)? {
+        v.push(match e {
+            Expression::Pair(p) => {
+                let p = reference_obj!(p);
+                Environment::create_pair(p.car.clone(), p.cdr.clone())
+            }
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        });
+    }
+    Ok(Environment::create_list(v))
+}
+// ex. (alist-delete key alist =), (= key (car entry)) is called.
+fn alist_delete(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() < 3 || 4 < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let key = &exp[1];
+    let l = to_list(exp[2].clone())?;
+    let f = to_equal(exp, 3)?;
+    let mut rows = Vec::with_capacity(l.len());
+    for e in &l {
+        match e {
+            Expression::Pair(p) => rows.push(vec![key.clone(), reference_obj!(p).car.clone()]),
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        }
+    }
+    let mut it = Each::new(EachKind::Filter(false), f, rows);
+    it.items = l;
+    Ok(Box::new(it))
+}
+// ex. (lset-union = list1 list2 ...)
+fn lset_args(exp: &[Expression], min: usize) -> Result<(Expression, Vec<Vec<Expression>>), Error> {
+    if exp.len() < min {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    let mut lists = Vec::with_capacity(exp.len());
+    for e in &exp[2..] {
+        lists.push(to_list(e.clone())?);
+    }
+    Ok((f, lists))
+}
+fn lset_compare(exp: &[Expression], both: bool) -> Result<IterateBox, Error> {
+    let (f, lists) = lset_args(exp, 2)?;
+    let mut searches = Vec::new();
+    for w in lists.windows(2) {
+        let mut s = Search::new(w[0].clone(), vec![w[1].clone()]);
+        s.stop = true;
+        searches.push(s);
+        if both {
+            let mut s = Search::new(w[1].clone(), vec![w[0].clone()]);
+            s.flip = true;
+            s.stop = true;
+            searches.push(s);
+        }
+    }
+    Ok(Lset::create(
+        LsetKind::Compare,
+        Some(f),
+        searches,
+        Vec::new(),
+    ))
+}
+// ex. (lset-adjoin eq? '(a b) 'c 'a) => (c a b)
+fn lset_adjoin(exp: &[Expression]) -> Result<IterateBox, Error> {
+    if exp.len() < 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let f = to_proc(&exp[1])?;
+    let v = to_list(exp[2].clone())?;
+    let mut s = Search::new(exp[3..].to_vec(), vec![v]);
+    s.flip = true;
+    s.grow = Grow::Front;
+    Ok(Lset::create(LsetKind::Adjoin, Some(f), vec![s], Vec::new()))
+}
+// ex. (lset-union eq? '(a b c) '(a d e)) => (e d a b c)
+fn lset_union(exp: &[Expression]) -> Result<IterateBox, Error> {
+    let (f, lists) = lset_args(exp, 2)?;
+    Ok(Lset::create(LsetKind::Union, Some(f), Vec::new(), lists))
+}
+// ex. (lset-intersection = list1 list2 ...) or (lset-difference = list1 list2 ...)
+fn lset_filter(exp: &[Expression], intersection: bool) -> Result<IterateBox, Error> {
+    let (f, mut lists) = lset_args(exp, 3)?;
+    let others = lists.split_off(1);
+    let mut s = Search::new(lists.remove(0), others);
+    s.all = intersection;
+    let kind = if intersection {
+        LsetKind::Intersection
+    } else {
+        LsetKind::Difference
+    };
+    Ok(Lset::create(kind, Some(f), vec![s], Vec::new()))
+}
+// ex. (lset-xor eq? '(a b c d e) '(a e i o u)) => (u o i b c d)
+fn lset_xor(exp: &[Expression]) -> Result<IterateBox, Error> {
+    let (f, lists) = lset_args(exp, 2)?;
+    Ok(Lset::create(LsetKind::Xor, Some(f), Vec::new(), lists))
+}
+fn lset_diff_intersection(exp: &[Expression]) -> Result<IterateBox, Error> {
+    let (f, mut lists) = lset_args(exp, 3)?;
+    let others = lists.split_off(1);
+    let s = Search::new(lists.remove(0), others);
+    Ok(Lset::create(
+        LsetKind::DiffIntersection,
+        Some(f),
+        vec![s],
+        Vec::new(),
+    ))
+}
+#[cfg(test)]
+mod tests {
+    use crate::lisp;
+    use crate::{do_lisp, do_lisp_env};
+
+    #[test]
+    fn constructors() {
+        assert_eq!(do_lisp("(xcons '(b c) 'a)"), "(a b c)");
+        assert_eq!(do_lisp("(xcons 2 1)"), "(1 . 2)");
+        assert_eq!(do_lisp("(cons* 1 2 '(3 4))"), "(1 2 3 4)");
+        assert_eq!(do_lisp("(cons* 1 2 3)"), "(1 2 . 3)");
+        assert_eq!(do_lisp("(cons* 1)"), "1");
+        assert_eq!(
+            do_lisp("(list-tabulate 4 (lambda (i) (* i i)))"),
+            "(0 1 4 9)"
+        );
+        assert_eq!(do_lisp("(list-tabulate 0 (lambda (i) i))"), "()");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3))", &env);
+        do_lisp_env("(define b (list-copy a))", &env);
+        do_lisp_env("(set-car! b 10)", &env);
+        assert_eq!(do_lisp_env("a", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("b", &env), "(10 2 3)");
+    }
+    #[test]
+    fn predicates() {
+        assert_eq!(do_lisp("(proper-list? '(1 2))"), "#t");
+        assert_eq!(do_lisp("(proper-list? (cons 1 2))"), "#f");
+        assert_eq!(do_lisp("(dotted-list? (cons 1 2))"), "#t");
+        assert_eq!(do_lisp("(dotted-list? 1)"), "#t");
+        assert_eq!(do_lisp("(dotted-list? '())"), "#f");
+        assert_eq!(do_lisp("(not-pair? '())"), "#t");
+        assert_eq!(do_lisp("(not-pair? 10)"), "#t");
+        assert_eq!(do_lisp("(not-pair? '(1))"), "#f");
+        assert_eq!(do_lisp("(null-list? '())"), "#t");
+        assert_eq!(do_lisp("(null-list? '(1))"), "#f");
+        assert_eq!(do_lisp("(null-list? (cons 1 2))"), "#f");
+        assert_eq!(do_lisp("(list= eq?)"), "#t");
+        assert_eq!(do_lisp("(list= = '(1 2) '(1 2) '(1 2))"), "#t");
+        assert_eq!(do_lisp("(list= = '(1 2) '(1 2 3))"), "#f");
+        assert_eq!(do_lisp("(list= = '(1 2) '(1 3))"), "#f");
+    }
+    #[test]
+    fn selectors() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (iota 10 1))", &env);
+        assert_eq!(do_lisp_env("(first a)", &env), "1");
+        assert_eq!(do_lisp_env("(second a)", &env), "2");
+        assert_eq!(do_lisp_env("(third a)", &env), "3");
+        assert_eq!(do_lisp_env("(fourth a)", &env), "4");
+        assert_eq!(do_lisp_env("(fifth a)", &env), "5");
+        assert_eq!(do_lisp_env("(sixth a)", &env), "6");
+        assert_eq!(do_lisp_env("(seventh a)", &env), "7");
+        assert_eq!(do_lisp_env("(eighth a)", &env), "8");
+        assert_eq!(do_lisp_env("(ninth a)", &env), "9");
+        assert_eq!(do_lisp_env("(tenth a)", &env), "10");
+        assert_eq!(do_lisp("(car+cdr '(1 2 3))"), "1 (2 3)");
+        assert_eq!(do_lisp("(car+cdr (cons 1 2))"), "1 2");
+        assert_eq!(do_lisp("(take-right '(1 2 3 4) 2)"), "(3 4)");
+        assert_eq!(do_lisp("(drop-right '(1 2 3 4) 2)"), "(1 2)");
+        assert_eq!(do_lisp("(drop-right '(1 2 3 4) 4)"), "()");
+        assert_eq!(do_lisp("(split-at '(1 2 3 4) 1)"), "(1) (2 3 4)");
+        assert_eq!(
+            do_lisp("(receive (a b) (split-at! '(1 2 3 4) 3) (list a b))"),
+            "((1 2 3) (4))"
+        );
+        assert_eq!(do_lisp("(list-tail '(1 2 3 4) 2)"), "(3 4)");
+        assert_eq!(do_lisp("(list-tail '(1 2 3 4) 4)"), "()");
+        assert_eq!(do_lisp("(last-pair '(1 2 3))"), "(3)");
+        assert_eq!(do_lisp("(last-pair (cons 1 (cons 2 3)))"), "(2 . 3)");
+
+        // the selector names can be bound as the variables
+        assert_eq!(
+            do_lisp("(let loop ((first 0) (l '(1 2 3))) (if (null? l) first (loop (+ first (car l)) (cdr l))))"),
+            "6"
+        );
+        do_lisp_env("(define (f first second) (- first second))", &env);
+        assert_eq!(do_lisp_env("(f 10 3)", &env), "7");
+    }
+    #[test]
+    fn selectors_effect() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3 4))", &env);
+        assert_eq!(do_lisp_env("(take! a 3)", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("a", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("(drop-right! a 1)", &env), "(1 2)");
+        assert_eq!(do_lisp_env("a", &env), "(1 2)");
+    }
+    #[test]
+    fn miscellaneous() {
+        assert_eq!(do_lisp("(length+ '(1 2 3))"), "3");
+        assert_eq!(do_lisp("(concatenate '((1) () (2 3)))"), "(1 2 3)");
+        assert_eq!(do_lisp("(concatenate '())"), "()");
+        assert_eq!(do_lisp("(append-reverse '(3 2 1) '(4 5))"), "(1 2 3 4 5)");
+        assert_eq!(do_lisp("(append-reverse '(2 1) 3)"), "(1 2 . 3)");
+        assert_eq!(do_lisp("(append-reverse! '(2 1) '())"), "(1 2)");
+        assert_eq!(do_lisp("(zip '(1 2 3))"), "((1) (2) (3))");
+        assert_eq!(
+            do_lisp("(zip '(1 2 3) '(a b c) '(#t #f))"),
+            "((1 a #t) (2 b #f))"
+        );
+        assert_eq!(do_lisp("(unzip1 '((1 a) (2 b)))"), "(1 2)");
+        assert_eq!(do_lisp("(unzip2 '((1 a) (2 b)))"), "(1 2) (a b)");
+        assert_eq!(do_lisp("(unzip3 '((1 a x) (2 b y)))"), "(1 2) (a b) (x y)");
+        assert_eq!(do_lisp("(unzip4 '((1 2 3 4)))"), "(1) (2) (3) (4)");
+        assert_eq!(do_lisp("(unzip5 '((1 2 3 4 5)))"), "(1) (2) (3) (4) (5)");
+        assert_eq!(do_lisp("(count even? '(3 1 4 1 5 9 2 5 6))"), "3");
+        assert_eq!(do_lisp("(count < '(1 2 4 8) '(2 4 6 8 10 12 14 16))"), "3");
+        assert_eq!(do_lisp("(count even? '())"), "0");
+        assert_eq!(do_lisp("(let ((count 3)) (+ count 1))"), "4");
+        assert_eq!(
+            do_lisp("(let loop ((count 0)) (if (< count 5) (loop (+ count 1)) count))"),
+            "5"
+        );
+    }
+    #[test]
+    fn map() {
+        assert_eq!(do_lisp("(map + '(1 2 3) '(10 20 30))"), "(11 22 33)");
+        assert_eq!(
+            do_lisp("(map + '(1 2 3) '(10 20) '(100 200 300))"),
+            "(111 222)"
+        );
+        assert_eq!(
+            do_lisp("(map-in-order (lambda (n) (* n 2)) '(1 2))"),
+            "(2 4)"
+        );
+        assert_eq!(
+            do_lisp("(map-in-order cons '(1 2) '(a b))"),
+            "((1 . a) (2 . b))"
+        );
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a 0)", &env);
+        do_lisp_env(
+            "(for-each (lambda (x y) (set! a (+ a (* x y)))) '(1 2 3) '(4 5 6))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("a", &env), "32");
+        do_lisp_env("(define b '())", &env);
+        do_lisp_env(
+            "(pair-for-each (lambda (p) (set! b (cons p b))) '(1 2 3))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("b", &env), "((3) (2 3) (1 2 3))");
+    }
+    #[test]
+    fn fold() {
+        assert_eq!(do_lisp("(fold + 0 '(1 2 3))"), "6");
+        assert_eq!(do_lisp("(fold cons '() '(1 2 3))"), "(3 2 1)");
+        assert_eq!(
+            do_lisp("(fold cons* '() '(a b c) '(1 2 3 4))"),
+            "(c 3 b 2 a 1)"
+        );
+        assert_eq!(do_lisp("(fold-left list '() '(1 2 3))"), "(((() 1) 2) 3)");
+        assert_eq!(
+            do_lisp("(fold-left list '() '(1 2) '(a b))"),
+            "((() 1 a) 2 b)"
+        );
+        assert_eq!(do_lisp("(fold-right cons '() '(1 2 3))"), "(1 2 3)");
+        assert_eq!(
+            do_lisp("(fold-right cons* '() '(a b c) '(1 2 3))"),
+            "(a 1 b 2 c 3)"
+        );
+        assert_eq!(
+            do_lisp("(pair-fold cons '() '(1 2 3))"),
+            "((3) (2 3) (1 2 3))"
+        );
+        assert_eq!(
+            do_lisp("(pair-fold-right cons '() '(1 2 3))"),
+            "((1 2 3) (2 3) (3))"
+        );
+        assert_eq!(do_lisp("(reduce-right + 0 '(1 2 3))"), "6");
+        assert_eq!(do_lisp("(reduce-right list 0 '(1 2 3))"), "(1 (2 3))");
+        assert_eq!(do_lisp("(reduce-right + 0 '())"), "0");
+    }
+    #[test]
+    fn fold_machine() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (deep n) (if (= n 0) 0 (fold (lambda (x a) (+ 1 (deep (- n 1)))) 0 '(1))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(deep 100000)", &env), "100000");
+
+        // the continuation is re-entered in the callback
+        do_lisp_env("(define k #f)", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(+ 1000 (fold (lambda (x a) (call/cc (lambda (c) (if (= x 3) (set! k c)) (+ x a)))) 0 '(1 2 3 4)))",
+                &env
+            ),
+            "1010"
+        );
+        assert_eq!(do_lisp_env("(k 100)", &env), "1104");
+        assert_eq!(do_lisp_env("(k 200)", &env), "1204");
+
+        assert_eq!(
+            do_lisp_env(
+                "(length (map-in-order (lambda (x) (call/cc (lambda (c) (if (= x 2) (set! k c)) x))) '(1 2 3)))",
+                &env
+            ),
+            "3"
+        );
+        assert_eq!(do_lisp_env("(k 10)", &env), "3");
+        assert_eq!(
+            do_lisp_env(
+                "(any (lambda (x) (call/cc (lambda (c) (if (= x 1) (set! k c)) #f))) '(1 2))",
+                &env
+            ),
+            "#f"
+        );
+        assert_eq!(do_lisp_env("(k 'a)", &env), "a");
+        assert_eq!(
+            do_lisp_env(
+                "(lset-adjoin (lambda (x y) (call/cc (lambda (c) (set! k c) (eq? x y)))) '(a) 'b)",
+                &env
+            ),
+            "(b a)"
+        );
+        assert_eq!(do_lisp_env("(k #t)", &env), "(a)");
+    }
+    #[test]
+    fn unfold() {
+        assert_eq!(
+            do_lisp("(unfold (lambda (x) (> x 5)) (lambda (x) (* x x)) (lambda (x) (+ x 1)) 1)"),
+            "(1 4 9 16 25)"
+        );
+        assert_eq!(
+            do_lisp("(unfold null? car cdr '(1 2 3) (lambda (x) 'end))"),
+            "(1 2 3 . end)"
+        );
+        assert_eq!(
+            do_lisp("(unfold-right zero? (lambda (x) (* x x)) (lambda (x) (- x 1)) 5)"),
+            "(1 4 9 16 25)"
+        );
+        assert_eq!(
+            do_lisp("(unfold-right null? car cdr '(1 2 3) '(4))"),
+            "(3 2 1 4)"
+        );
+    }
+    #[test]
+    fn append_map() {
+        assert_eq!(
+            do_lisp("(append-map (lambda (x) (list x (- x))) '(1 3 8))"),
+            "(1 -1 3 -3 8 -8)"
+        );
+        assert_eq!(do_lisp("(append-map list '(1 2) '(a b))"), "(1 a 2 b)");
+        assert_eq!(do_lisp("(append-map! list '(1 2))"), "(1 2)");
+        assert_eq!(
+            do_lisp("(filter-map (lambda (x) (if (number? x) (* x x) #f)) '(a 1 b 3 c 7))"),
+            "(1 9 49)"
+        );
+        assert_eq!(
+            do_lisp("(filter-map (lambda (x y) (if (< x y) y #f)) '(1 5 3) '(2 4 6))"),
+            "(2 6)"
+        );
+    }
+    #[test]
+    fn filter() {
+        assert_eq!(do_lisp("(remove even? '(0 7 8 8 43 -4))"), "(7 43)");
+        assert_eq!(
+            do_lisp("(partition symbol? '(one 2 3 four five 6))"),
+            "(one four five) (2 3 6)"
+        );
+        assert_eq!(do_lisp("(partition! even? '())"), "() ()");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3 4 5))", &env);
+        assert_eq!(do_lisp_env("(filter! odd? a)", &env), "(1 3 5)");
+        assert_eq!(do_lisp_env("a", &env), "(1 3 5)");
+        assert_eq!(
+            do_lisp_env("(remove! (lambda (n) (= n 3)) a)", &env),
+            "(1 5)"
+        );
+        assert_eq!(do_lisp_env("a", &env), "(1 5)");
+    }
+    #[test]
+    fn find() {
+        assert_eq!(do_lisp("(find even? '(3 1 4 1 5 9))"), "4");
+        assert_eq!(do_lisp("(find even? '(3 1 5))"), "#f");
+        assert_eq!(
+            do_lisp("(find-tail even? '(3 1 37 -8 -5 0 0))"),
+            "(-8 -5 0 0)"
+        );
+        assert_eq!(do_lisp("(find-tail even? '(3 1 37 -5))"), "#f");
+        assert_eq!(do_lisp("(take-while even? '(2 18 3 10 22 9))"), "(2 18)");
+        assert_eq!(
+            do_lisp("(drop-while even? '(2 18 3 10 22 9))"),
+            "(3 10 22 9)"
+        );
+        assert_eq!(do_lisp("(take-while even? '(2 4))"), "(2 4)");
+        assert_eq!(do_lisp("(drop-while even? '(2 4))"), "()");
+        assert_eq!(
+            do_lisp("(span even? '(2 18 3 10 22 9))"),
+            "(2 18) (3 10 22 9)"
+        );
+        assert_eq!(do_lisp("(span! even? '(1))"), "() (1)");
+        assert_eq!(
+            do_lisp("(break even? '(3 1 4 1 5 9 2 6))"),
+            "(3 1) (4 1 5 9 2 6)"
+        );
+        assert_eq!(do_lisp("(break! even? '(1 3))"), "(1 3) ()");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 2 4 5 6))", &env);
+        assert_eq!(do_lisp_env("(take-while! even? a)", &env), "(2 4)");
+        assert_eq!(do_lisp_env("a", &env), "(2 4)");
+    }
+    #[test]
+    fn any() {
+        assert_eq!(do_lisp("(any integer? '(a 3 b 2.7))"), "#t");
+        assert_eq!(do_lisp("(any integer? '(a 3.1 b 2.7))"), "#f");
+        assert_eq!(do_lisp("(any < '(3 1 4 1 5) '(2 7 1 8 2))"), "#t");
+        assert_eq!(
+            do_lisp("(any (lambda (n) (if (even? n) n #f)) '(1 4 6))"),
+            "4"
+        );
+        assert_eq!(do_lisp("(any even? '())"), "#f");
+    }
+    #[test]
+    fn every() {
+        assert_eq!(do_lisp("(every integer? '(1 3 2))"), "#t");
+        assert_eq!(do_lisp("(every integer? '(1 3.1 2))"), "#f");
+        assert_eq!(do_lisp("(every < '(1 2) '(2 3 0))"), "#t");
+        assert_eq!(
+            do_lisp("(every (lambda (n) (if (even? n) n #f)) '(2 4 6))"),
+            "6"
+        );
+        assert_eq!(do_lisp("(every even? '())"), "#t");
+    }
+    #[test]
+    fn list_index() {
+        assert_eq!(do_lisp("(list-index even? '(3 1 4 1 5 9))"), "2");
+        assert_eq!(
+            do_lisp("(list-index < '(3 1 4 1 5 9 2 5 6) '(2 7 1 8 2))"),
+            "1"
+        );
+        assert_eq!(
+            do_lisp("(list-index = '(3 1 4 1 5 9 2 5 6) '(2 7 1 8 2))"),
+            "#f"
+        );
+    }
+    #[test]
+    fn delete_duplicates() {
+        assert_eq!(
+            do_lisp("(delete-duplicates '(a b a c a b c z))"),
+            "(a b c z)"
+        );
+        assert_eq!(
+            do_lisp("(delete-duplicates '((a 3) (b 7) (a 9) (c 1)) (lambda (x y) (eq? (car x) (car y))))"),
+            "((a 3) (b 7) (c 1))"
+        );
+        assert_eq!(do_lisp("(delete-duplicates '((1) (1) 2))"), "((1) 2)");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 1 3 2))", &env);
+        assert_eq!(do_lisp_env("(delete-duplicates! a)", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("a", &env), "(1 2 3)");
+    }
+    #[test]
+    fn alist() {
+        assert_eq!(do_lisp("(alist-cons 'a 1 '())"), "((a . 1))");
+        assert_eq!(
+            do_lisp("(alist-cons 'a 1 (list (cons 'b 2)))"),
+            "((a . 1) (b . 2))"
+        );
+        assert_eq!(
+            do_lisp("(alist-delete 'a (list (cons 'a 1) (cons 'b 2) (cons 'a 3)))"),
+            "((b . 2))"
+        );
+        assert_eq!(
+            do_lisp("(alist-delete 2 '((1 a) (2 b) (3 c)) <)"),
+            "((1 a) (2 b))"
+        );
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list (list 'a 1) (cons 'b 2)))", &env);
+        do_lisp_env("(define b (alist-copy a))", &env);
+        do_lisp_env("(set-car! (car b) 'c)", &env);
+        assert_eq!(do_lisp_env("a", &env), "((a 1) (b . 2))");
+        assert_eq!(do_lisp_env("b", &env), "((c 1) (b . 2))");
+        assert_eq!(do_lisp_env("(alist-delete! 'b a)", &env), "((a 1))");
+        assert_eq!(do_lisp_env("a", &env), "((a 1))");
+    }
+    #[test]
+    fn lset() {
+        assert_eq!(do_lisp("(lset<= eq? '(a) '(a b a) '(a b c c))"), "#t");
+        assert_eq!(do_lisp("(lset<= eq? '(a d) '(a b a))"), "#f");
+        assert_eq!(do_lisp("(lset<= eq?)"), "#t");
+        assert_eq!(do_lisp("(lset= eq? '(b e a) '(a e b) '(e e b a))"), "#t");
+        assert_eq!(do_lisp("(lset= eq? '(b e a) '(a e b c))"), "#f");
+        assert_eq!(
+            do_lisp("(lset-adjoin eq? '(a b c d c e) 'a 'e 'i 'o 'u)"),
+            "(u o i a b c d c e)"
+        );
+        assert_eq!(
+            do_lisp("(lset-union eq? '(a b c d e) '(a e i o u))"),
+            "(u o i a b c d e)"
+        );
+        assert_eq!(do_lisp("(lset-union eq? '(a a c) '(x a x))"), "(x a a c)");
+        assert_eq!(do_lisp("(lset-union eq?)"), "()");
+        assert_eq!(do_lisp("(lset-union eq? '(a b c))"), "(a b c)");
+        assert_eq!(
+            do_lisp("(lset-intersection eq? '(a b c d e) '(a e i o u))"),
+            "(a e)"
+        );
+        assert_eq!(
+            do_lisp("(lset-intersection eq? '(a x y a) '(x a x z))"),
+            "(a x a)"
+        );
+        assert_eq!(do_lisp("(lset-intersection eq? '(a b c))"), "(a b c)");
+        assert_eq!(
+            do_lisp("(lset-difference eq? '(a b c d e) '(a e i o u))"),
+            "(b c d)"
+        );
+        assert_eq!(do_lisp("(lset-difference eq? '(a b c))"), "(a b c)");
+        assert_eq!(
+            do_lisp("(lset-xor eq? '(a b c d e) '(a e i o u))"),
+            "(u o i b c d)"
+        );
+        assert_eq!(do_lisp("(lset-xor eq?)"), "()");
+        assert_eq!(do_lisp("(lset-xor eq? '(a b c d e))"), "(a b c d e)");
+        assert_eq!(do_lisp("(lset-xor eq? '(a b) '(c d))"), "(c d a b)");
+        assert_eq!(do_lisp("(lset-xor eq? '(a b) '(a b c))"), "(c)");
+        assert_eq!(
+            do_lisp("(lset-diff+intersection eq? '(a b c d e) '(a e i o u))"),
+            "(b c d) (a e)"
+        );
+        assert_eq!(do_lisp("(lset-diff+intersection! eq? '(a b))"), "(a b) ()");
+    }
+    #[test]
+    fn lset_effect() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2 3))", &env);
+        assert_eq!(do_lisp_env("(lset-union! = a '(3 4))", &env), "(4 1 2 3)");
+        assert_eq!(do_lisp_env("a", &env), "(4 1 2 3)");
+        assert_eq!(
+            do_lisp_env("(lset-intersection! = a '(1 2 5))", &env),
+            "(1 2)"
+        );
+        assert_eq!(do_lisp_env("a", &env), "(1 2)");
+        assert_eq!(do_lisp_env("(lset-difference! = a '(1))", &env), "(2)");
+        assert_eq!(do_lisp_env("a", &env), "(2)");
+        assert_eq!(do_lisp_env("(lset-xor! = a '(2 7))", &env), "(7)");
+        assert_eq!(do_lisp_env("a", &env), "(7)");
+    }
+}
+#[cfg(test)]
+mod error_tests {
+    use crate::do_lisp;
+
+    #[test]
+    fn constructors() {
+        assert_eq!(do_lisp("(xcons 1)"), "E1007");
+        assert_eq!(do_lisp("(cons*)"), "E1007");
+        assert_eq!(do_lisp("(list-tabulate 1)"), "E1007");
+        assert_eq!(do_lisp("(list-tabulate 1.5 list)"), "E1002");
+        assert_eq!(do_lisp("(list-tabulate -1 list)"), "E1011");
+        assert_eq!(do_lisp("(list-tabulate 1 2)"), "E1006");
+        assert_eq!(do_lisp("(list-copy)"), "E1007");
+        assert_eq!(do_lisp("(list-copy a)"), "E1008");
+    }
+    #[test]
+    fn predicates() {
+        assert_eq!(do_lisp("(proper-list?)"), "E1007");
+        assert_eq!(do_lisp("(dotted-list? 1 2)"), "E1007");
+        assert_eq!(do_lisp("(not-pair?)"), "E1007");
+        assert_eq!(do_lisp("(null-list?)"), "E1007");
+        assert_eq!(do_lisp("(null-list? 1)"), "E1005");
+        assert_eq!(do_lisp("(list=)"), "E1007");
+        assert_eq!(do_lisp("(list= 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(list= = '(1) 1)"), "E1005");
+    }
+    #[test]
+    fn selectors() {
+        assert_eq!(do_lisp("(first)"), "E1007");
+        assert_eq!(do_lisp("(first '(1) '(2))"), "E1007");
+        assert_eq!(do_lisp("(first '())"), "E1011");
+        assert_eq!(do_lisp("(tenth (iota 9))"), "E1011");
+        assert_eq!(do_lisp("(second 1)"), "E1005");
+        assert_eq!(do_lisp("(car+cdr)"), "E1007");
+        assert_eq!(do_lisp("(car+cdr '())"), "E1011");
+        assert_eq!(do_lisp("(car+cdr 1)"), "E1005");
+        assert_eq!(do_lisp("(take! '(1))"), "E1007");
+        assert_eq!(do_lisp("(take! '(1) 2)"), "E1011");
+        assert_eq!(do_lisp("(take-right '(1) 2)"), "E1011");
+        assert_eq!(do_lisp("(take-right 1 1)"), "E1005");
+        assert_eq!(do_lisp("(drop-right '(1) -1)"), "E1011");
+        assert_eq!(do_lisp("(drop-right! '(1) 'a)"), "E1002");
+        assert_eq!(do_lisp("(split-at '(1) 1 2)"), "E1007");
+        assert_eq!(do_lisp("(list-tail '(1) 2)"), "E1011");
+        assert_eq!(do_lisp("(last-pair)"), "E1007");
+        assert_eq!(do_lisp("(last-pair '())"), "E1011");
+        assert_eq!(do_lisp("(last-pair 1)"), "E1005");
+    }
+    #[test]
+    fn miscellaneous() {
+        assert_eq!(do_lisp("(length+)"), "E1007");
+        assert_eq!(do_lisp("(length+ 1)"), "E1005");
+        assert_eq!(do_lisp("(concatenate)"), "E1007");
+        assert_eq!(do_lisp("(concatenate '(1))"), "E1005");
+        assert_eq!(do_lisp("(append-reverse '(1))"), "E1007");
+        assert_eq!(do_lisp("(append-reverse 1 '(1))"), "E1005");
+        assert_eq!(do_lisp("(zip)"), "E1007");
+        assert_eq!(do_lisp("(zip '(1) 2)"), "E1005");
+        assert_eq!(do_lisp("(unzip2)"), "E1007");
+        assert_eq!(do_lisp("(unzip2 '(1))"), "E1005");
+        assert_eq!(do_lisp("(unzip2 '((1)))"), "E1011");
+        assert_eq!(do_lisp("(count even?)"), "E1007");
+        assert_eq!(do_lisp("(count 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(count even? 1)"), "E1005");
+    }
+    #[test]
+    fn fold() {
+        assert_eq!(do_lisp("(fold + 0)"), "E1007");
+        assert_eq!(do_lisp("(fold 1 0 '(1))"), "E1006");
+        assert_eq!(do_lisp("(fold + 0 1)"), "E1005");
+        assert_eq!(do_lisp("(fold-left + 0 '(1) 1)"), "E1005");
+        assert_eq!(do_lisp("(fold-right + 0)"), "E1007");
+        assert_eq!(do_lisp("(pair-fold + 0 '(1))"), "E1003");
+        assert_eq!(do_lisp("(pair-fold-right cons 0 1)"), "E1005");
+        assert_eq!(do_lisp("(reduce-right + 0)"), "E1007");
+        assert_eq!(do_lisp("(reduce-right 1 0 '(1))"), "E1006");
+        assert_eq!(do_lisp("(reduce-right + 0 1)"), "E1005");
+    }
+    #[test]
+    fn unfold() {
+        assert_eq!(do_lisp("(unfold null? car cdr)"), "E1007");
+        assert_eq!(do_lisp("(unfold null? car cdr '(1) list 1)"), "E1007");
+        assert_eq!(do_lisp("(unfold 1 car cdr '(1))"), "E1006");
+        assert_eq!(do_lisp("(unfold null? car cdr '(1) 1)"), "E1006");
+        assert_eq!(do_lisp("(unfold-right null? car cdr)"), "E1007");
+        assert_eq!(do_lisp("(unfold-right null? car 1 '(1))"), "E1006");
+    }
+    #[test]
+    fn append_map() {
+        assert_eq!(do_lisp("(append-map list)"), "E1007");
+        assert_eq!(do_lisp("(append-map 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(append-map (lambda (n) n) '(1))"), "E1005");
+        assert_eq!(do_lisp("(filter-map list)"), "E1007");
+        assert_eq!(do_lisp("(filter-map list 1)"), "E1005");
+        assert_eq!(do_lisp("(pair-for-each list)"), "E1007");
+        assert_eq!(do_lisp("(pair-for-each 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(map-in-order list)"), "E1007");
+    }
+    #[test]
+    fn filter() {
+        assert_eq!(do_lisp("(remove even?)"), "E1007");
+        assert_eq!(do_lisp("(remove 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(remove even? 1)"), "E1005");
+        assert_eq!(do_lisp("(filter! even? '(1) '(2))"), "E1007");
+        assert_eq!(do_lisp("(partition even?)"), "E1007");
+        assert_eq!(do_lisp("(partition even? 1)"), "E1005");
+    }
+    #[test]
+    fn find() {
+        assert_eq!(do_lisp("(find even?)"), "E1007");
+        assert_eq!(do_lisp("(find 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(find-tail even? 1)"), "E1005");
+        assert_eq!(do_lisp("(take-while even?)"), "E1007");
+        assert_eq!(do_lisp("(drop-while 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(span even? 1)"), "E1005");
+        assert_eq!(do_lisp("(break even? '(1) '(2))"), "E1007");
+        assert_eq!(do_lisp("(any even?)"), "E1007");
+        assert_eq!(do_lisp("(any 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(every even? 1)"), "E1005");
+        assert_eq!(do_lisp("(list-index even?)"), "E1007");
+        assert_eq!(do_lisp("(list-index even? '(1) 1)"), "E1005");
+    }
+    #[test]
+    fn delete_duplicates() {
+        assert_eq!(do_lisp("(delete-duplicates)"), "E1007");
+        assert_eq!(do_lisp("(delete-duplicates '(1) eq? 1)"), "E1007");
+        assert_eq!(do_lisp("(delete-duplicates 1)"), "E1005");
+        assert_eq!(do_lisp("(delete-duplicates '(1) 1)"), "E1006");
+        assert_eq!(do_lisp("(delete-duplicates! 1)"), "E1005");
+    }
+    #[test]
+    fn alist() {
+        assert_eq!(do_lisp("(alist-cons 1 2)"), "E1007");
+        assert_eq!(do_lisp("(alist-copy)"), "E1007");
+        assert_eq!(do_lisp("(alist-copy 1)"), "E1005");
+        assert_eq!(do_lisp("(alist-copy '(1))"), "E1005");
+        assert_eq!(do_lisp("(alist-delete 1)"), "E1007");
+        assert_eq!(do_lisp("(alist-delete 1 '() eq? 1)"), "E1007");
+        assert_eq!(do_lisp("(alist-delete 1 '(1))"), "E1005");
+        assert_eq!(do_lisp("(alist-delete 1 '(()))"), "E1005");
+        assert_eq!(do_lisp("(alist-delete 1 '() 1)"), "E1006");
+        assert_eq!(do_lisp("(alist-delete! 1 1)"), "E1005");
+    }
+    #[test]
+    fn lset() {
+        assert_eq!(do_lisp("(lset<=)"), "E1007");
+        assert_eq!(do_lisp("(lset= 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(lset= eq? '(1) 1)"), "E1005");
+        assert_eq!(do_lisp("(lset-adjoin eq?)"), "E1007");
+        assert_eq!(do_lisp("(lset-adjoin eq? 1)"), "E1005");
+        assert_eq!(do_lisp("(lset-union)"), "E1007");
+        assert_eq!(do_lisp("(lset-union eq? 1)"), "E1005");
+        assert_eq!(do_lisp("(lset-intersection eq?)"), "E1007");
+        assert_eq!(do_lisp("(lset-intersection 1 '(1))"), "E1006");
+        assert_eq!(do_lisp("(lset-difference eq? '(1) 1)"), "E1005");
+        assert_eq!(do_lisp("(lset-xor)"), "E1007");
+        assert_eq!(do_lisp("(lset-xor! eq? 1)"), "E1005");
+        assert_eq!(do_lisp("(lset-diff+intersection eq?)"), "E1007");
+    }
+}
@@ -11,21 +11,21 @@ use crate::create_error;
 use crate::create_error_value;
 use crate::reference_obj;
 
+use num_traits::One;
 use std::vec::Vec;
 
 use crate::buildin::BuildInTable;
 use crate::lisp::eval;
 use crate::lisp::{Environment, Expression, Int, ResultExpression};
 use crate::lisp::{ErrCode, Error};
-use crate::number::Number;
-use crate::number::Rat;
+use crate::number::{parse_number, Number};
+use crate::pair;
+use crate::symbol::Symbol;
 
 pub fn create_function<T>(b: &mut T)
 where
     T: BuildInTable + ?Sized,
 {
-    b.regist("format", format_f);
-
     b.regist("string", string);
     b.regist("string=?", |exp, env| strcmp(exp, env, |x, y| x == y));
     b.regist("string<?", |exp, env| strcmp(exp, env, |x, y| x < y));
@@ -69,6 +69,9 @@ where
     b.regist("substring", substring);
     b.regist("symbol->string", symbol_string);
     b.regist("string->symbol", string_symbol);
+    b.regist("string->uninterned-symbol", string_uninterned_symbol);
+    b.regist("symbol-interned?", symbol_interned);
+    b.regist("gensym", gensym);
     b.regist("make-string", make_string);
 
     b.regist("string-split", string_split);
@@ -200,32 +203,6 @@ pub fn do_radix(
         func(&exp[1], env, r as u32)
     }
 }
-fn format_f(exp: &[Expression], env: &Environment) -> ResultExpression {
-    if exp.len() != 3 {
-        return Err(create_error_value!(ErrCode::E1007, exp.len()));
-    }
-    let s = if let Expression::String(s) = eval(&exp[1], env)? {
-        s
-    } else {
-        return Err(create_error!(ErrCode::E1015));
-    };
-    let i = if let Expression::Integer(i) = eval(&exp[2], env)? {
-        i
-    } else {
-        return Err(create_error!(ErrCode::E1002));
-    };
-    let s = match s.as_str() {
-        "~X" => format!("{:X}", i),
-        "~x" => format!("{:x}", i),
-        n => match n.to_lowercase().as_str() {
-            "~d" => format!("{:?}", i),
-            "~o" => format!("{:o}", i),
-            "~b" => format!("{:b}", i),
-            _ => return Err(create_error!(ErrCode::E1018)),
-        },
-    };
-    Ok(Environment::create_string(s))
-}
 fn string(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
@@ -295,6 +272,27 @@ fn number_string(exp: &Expression, env: &Environment, r: u32) -> ResultExpressio
                 Err(create_error!(ErrCode::E1021))
             }
         }
+        Number::BigInt(n) => {
+            if (2..=36).contains(&r) {
+                Ok(Environment::create_string(n.to_str_radix(r)))
+            } else {
+                Err(create_error!(ErrCode::E1021))
+            }
+        }
+        // ex. (number->string 1/3 2) => "1/11"
+        Number::Rational(n) => {
+            if !(2..=36).contains(&r) {
+                Err(create_error!(ErrCode::E1021))
+            } else if n.denom.is_one() {
+                Ok(Environment::create_string(n.numer.to_str_radix(r)))
+            } else {
+                Ok(Environment::create_string(format!(
+                    "{}/{}",
+                    n.numer.to_str_radix(r),
+                    n.denom.to_str_radix(r)
+                )))
+            }
+        }
         _ => Ok(Environment::create_string(v.to_string())),
     }
 }
@@ -303,42 +301,33 @@ fn string_number(exp: &Expression, env: &Environment, r: u32) -> ResultExpressio
         Expression::String(s) => s,
         e => return Err(create_error_value!(ErrCode::E1015, e)),
     };
-    if let Ok(n) = Int::from_str_radix(&s, r) {
-        return Ok(Expression::Integer(n));
+    match parse_number(&s, r) {
+        Ok(Some(n)) => Ok(n.to_expression()),
+        _ => Ok(Expression::Boolean(false)),
     }
-
-    let v = if let Ok(n) = s.parse::<f64>() {
-        Expression::Float(n)
-    } else {
-        match Rat::from_radix(&s, r) {
-            Ok(n) => Expression::Rational(n),
-            Err(_) => Expression::Boolean(false),
-        }
-    };
-    Ok(v)
 }
 fn list_string(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_string(exp, env, ErrCode::E1005)
+    if 2 != exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let l = pair::get_list(eval(&exp[1], env)?)?;
+    seq_string(&l)
 }
 fn vector_string(exp: &[Expression], env: &Environment) -> ResultExpression {
-    seq_string(exp, env, ErrCode::E1022)
-}
-fn seq_string(exp: &[Expression], env: &Environment, err: ErrCode) -> ResultExpression {
     if 2 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        Expression::Vector(l) => l,
-        e => return Err(create_error_value!(err, e)),
-    };
-
-    let l = &*(reference_obj!(l));
+    match eval(&exp[1], env)? {
+        Expression::Vector(l) => seq_string(&reference_obj!(l)),
+        e => Err(create_error_value!(ErrCode::E1022, e)),
+    }
+}
+fn seq_string(l: &[Expression]) -> ResultExpression {
     let mut v = String::new();
 
     for e in l.iter() {
-        v.push(match eval(e, env)? {
-            Expression::Char(c) => c,
+        v.push(match e {
+            Expression::Char(c) => *c,
             e => return Err(create_error_value!(ErrCode::E1019, e)),
         });
     }
@@ -386,17 +375,49 @@ fn symbol_string(exp: &[Expression], env: &Environment) -> ResultExpression {
         Expression::Symbol(s) => s,
         e => return Err(create_error_value!(ErrCode::E1004, e)),
     };
-    Ok(Environment::create_string(s))
+    Ok(Environment::create_string(s.to_string()))
 }
 fn string_symbol(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 2 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     match eval(&exp[1], env)? {
-        Expression::String(s) => Ok(Expression::Symbol(s.to_string())),
+        Expression::String(s) => Ok(Expression::Symbol(Symbol::from(s.as_str()))),
+        e => Err(create_error_value!(ErrCode::E1015, e)),
+    }
+}
+fn string_uninterned_symbol(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if 2 != exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::String(s) => Ok(Expression::Symbol(Symbol::uninterned(&s))),
         e => Err(create_error_value!(ErrCode::E1015, e)),
     }
 }
+fn symbol_interned(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if 2 != exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Symbol(s) => Ok(Expression::Boolean(s.is_interned())),
+        e => Err(create_error_value!(ErrCode::E1004, e)),
+    }
+}
+// (gensym) => G0, (gensym "tmp") => tmp1
+fn gensym(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if 2 < exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let prefix = match exp.get(1) {
+        Some(e) => match eval(e, env)? {
+            Expression::String(s) => s,
+            e => return Err(create_error_value!(ErrCode::E1015, e)),
+        },
+        None => return Ok(Expression::Symbol(Symbol::gensym("G"))),
+    };
+    Ok(Expression::Symbol(Symbol::gensym(&prefix)))
+}
 fn make_string(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 3 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
@@ -442,11 +463,7 @@ fn string_join(exp: &[Expression], env: &Environment) -> ResultExpression {
     if 3 != exp.len() {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    let l = match eval(&exp[1], env)? {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
-    };
-    let l = &*(reference_obj!(l));
+    let l = pair::get_list(eval(&exp[1], env)?)?;
 
     let s = match eval(&exp[2], env)? {
         Expression::String(s) => s,
@@ -454,7 +471,7 @@ fn string_join(exp: &[Expression], env: &Environment) -> ResultExpression {
     };
 
     let mut v: Vec<String> = Vec::new();
-    for e in l {
+    for e in &l {
         let s = match e {
             Expression::String(s) => s,
             e => return Err(create_error_value!(ErrCode::E1015, e)),
@@ -686,22 +703,6 @@ mod tests {
     use crate::lisp;
     use crate::{do_lisp, do_lisp_env};
 
-    #[test]
-    fn format_f() {
-        assert_eq!(do_lisp("(format \"~D\" 10)"), "\"10\"");
-        assert_eq!(do_lisp("(format \"~d\" 10)"), "\"10\"");
-        assert_eq!(do_lisp("(format \"~X\" 10)"), "\"A\"");
-        assert_eq!(do_lisp("(format \"~x\" 10)"), "\"a\"");
-        assert_eq!(do_lisp("(format \"~O\" 10)"), "\"12\"");
-        assert_eq!(do_lisp("(format \"~o\" 10)"), "\"12\"");
-        assert_eq!(do_lisp("(format \"~B\" 10)"), "\"1010\"");
-        assert_eq!(do_lisp("(format \"~b\" 10)"), "\"1010\"");
-
-        let env = lisp::Environment::new();
-        do_lisp_env("(define a \"~D\")", &env);
-        do_lisp_env("(define b 100)", &env);
-        assert_eq!(do_lisp_env("(format a b)", &env), "\"100\"");
-    }
     #[test]
     fn string() {
         assert_eq!(do_lisp("(string #\\a)"), "\"a\"");
@@ -789,6 +790,8 @@ mod tests {
         assert_eq!(do_lisp("(number->string 10)"), "\"10\"");
         assert_eq!(do_lisp("(number->string 10.5)"), "\"10.5\"");
         assert_eq!(do_lisp("(number->string 1/3)"), "\"1/3\"");
+        assert_eq!(do_lisp("(number->string 1/3 2)"), "\"1/11\"");
+        assert_eq!(do_lisp("(number->string -255/256 16)"), "\"-ff/100\"");
         assert_eq!(
             do_lisp("(number->string 3735927486 2)"),
             "\"11011110101011011011101010111110\""
@@ -839,6 +842,14 @@ mod tests {
         assert_eq!(do_lisp("(number->string 3735927486 34)"), "\"2e7m366\"");
         assert_eq!(do_lisp("(number->string 3735927486 35)"), "\"214kbpb\"");
         assert_eq!(do_lisp("(number->string 3735927486 36)"), "\"1ps9w3i\"");
+        assert_eq!(
+            do_lisp("(number->string (expt 2 100))"),
+            "\"1267650600228229401496703205376\""
+        );
+        assert_eq!(
+            do_lisp("(number->string (expt 2 100) 16)"),
+            "\"10000000000000000000000000\""
+        );
     }
     #[test]
     fn string_number() {
@@ -849,6 +860,25 @@ mod tests {
         assert_eq!(do_lisp("(string->number \"012\" 8)"), "10");
         assert_eq!(do_lisp("(string->number \"123\" 10)"), "123");
         assert_eq!(do_lisp("(string->number \"ab\" 16)"), "171");
+        assert_eq!(do_lisp("(string->number \"#xff\")"), "255");
+        assert_eq!(do_lisp("(string->number \"1e3\")"), "1000.0");
+        assert_eq!(do_lisp("(string->number \"-inf.0\")"), "-inf.0");
+        assert_eq!(do_lisp("(string->number \"1+2i\")"), "1.0+2.0i");
+        assert_eq!(do_lisp("(string->number \"1/0\")"), "#f");
+        assert_eq!(do_lisp("(string->number \"inf\")"), "#f");
+        assert_eq!(
+            do_lisp("(string->number \"1267650600228229401496703205376\")"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(
+            do_lisp("(string->number \"ffffffffffffffffffffffff\" 16)"),
+            "79228162514264337593543950335"
+        );
+        assert_eq!(
+            do_lisp("(string->number \"1/1267650600228229401496703205376\")"),
+            "1/1267650600228229401496703205376"
+        );
+        assert_eq!(do_lisp("(string->number \"6/2\")"), "3");
     }
     #[test]
     fn list_string() {
@@ -890,6 +920,47 @@ mod tests {
     #[test]
     fn string_symbol() {
         assert_eq!(do_lisp("(string->symbol \"abc\")"), "abc");
+        assert_eq!(do_lisp("(eq? (string->symbol \"abc\") 'abc)"), "#t");
+    }
+    #[test]
+    fn string_uninterned_symbol() {
+        assert_eq!(do_lisp("(string->uninterned-symbol \"abc\")"), "abc");
+        assert_eq!(
+            do_lisp("(eq? (string->uninterned-symbol \"abc\") 'abc)"),
+            "#f"
+        );
+        assert_eq!(
+            do_lisp("(symbol->string (string->uninterned-symbol \"abc\"))"),
+            "\"abc\""
+        );
+    }
+    #[test]
+    fn symbol_interned() {
+        assert_eq!(do_lisp("(symbol-interned? 'abc)"), "#t");
+        assert_eq!(
+            do_lisp("(symbol-interned? (string->uninterned-symbol \"abc\"))"),
+            "#f"
+        );
+        assert_eq!(do_lisp("(symbol-interned? (gensym))"), "#f");
+    }
+    #[test]
+    fn gensym() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (gensym))", &env);
+        assert_eq!(do_lisp_env("(symbol? a)", &env), "#t");
+        assert_eq!(do_lisp_env("(eq? a a)", &env), "#t");
+        assert_eq!(do_lisp_env("(eq? a (gensym))", &env), "#f");
+        assert_eq!(
+            do_lisp_env(
+                "(string=? (substring (symbol->string (gensym \"tmp\")) 0 3) \"tmp\")",
+                &env
+            ),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp_env("(eq? (string->symbol (symbol->string a)) a)", &env),
+            "#f"
+        );
     }
     #[test]
     fn make_string() {
@@ -1129,15 +1200,6 @@ mod tests {
 mod error_tests {
     use crate::do_lisp;
 
-    #[test]
-    fn format_f() {
-        assert_eq!(do_lisp("(format)"), "E1007");
-        assert_eq!(do_lisp("(format \"~B\")"), "E1007");
-        assert_eq!(do_lisp("(format \"~B\" 10 12)"), "E1007");
-        assert_eq!(do_lisp("(format 10 12)"), "E1015");
-        assert_eq!(do_lisp("(format \"~A\" #f)"), "E1002");
-        assert_eq!(do_lisp("(format \"~A\" 10)"), "E1018");
-    }
     #[test]
     fn string() {
         assert_eq!(do_lisp("(string)"), "E1007");
@@ -1262,6 +1324,8 @@ mod error_tests {
         assert_eq!(do_lisp("(number->string #f 10)"), "E1003");
         assert_eq!(do_lisp("(number->string 100 1)"), "E1021");
         assert_eq!(do_lisp("(number->string 100 37)"), "E1021");
+        assert_eq!(do_lisp("(number->string (expt 2 100) 37)"), "E1021");
+        assert_eq!(do_lisp("(number->string 1/3 37)"), "E1021");
         assert_eq!(do_lisp("(number->string a)"), "E1008");
         assert_eq!(do_lisp("(number->string 10 a)"), "E1008");
     }
@@ -1337,6 +1401,26 @@ mod error_tests {
         assert_eq!(do_lisp("(string->symbol #t)"), "E1015");
     }
     #[test]
+    fn string_uninterned_symbol() {
+        assert_eq!(do_lisp("(string->uninterned-symbol)"), "E1007");
+        assert_eq!(
+            do_lisp("(string->uninterned-symbol \"abc\" \"def\")"),
+            "E1007"
+        );
+        assert_eq!(do_lisp("(string->uninterned-symbol #t)"), "E1015");
+    }
+    #[test]
+    fn symbol_interned() {
+        assert_eq!(do_lisp("(symbol-interned?)"), "E1007");
+        assert_eq!(do_lisp("(symbol-interned? 'a 'b)"), "E1007");
+        assert_eq!(do_lisp("(symbol-interned? #t)"), "E1004");
+    }
+    #[test]
+    fn gensym() {
+        assert_eq!(do_lisp("(gensym \"a\" \"b\")"), "E1007");
+        assert_eq!(do_lisp("(gensym 'a)"), "E1015");
+    }
+    #[test]
     fn string_list() {
         assert_eq!(do_lisp("(string->list)"), "E1007");
         assert_eq!(do_lisp("(string->list \"a\" \"b\")"), "E1007");
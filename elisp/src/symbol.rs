@@ -0,0 +1,195 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+
+struct Name {
+    name: Box<str>,
+    interned: bool,
+    // the original symbol and the scope of the renamed identifier
+    origin: Option<(Symbol, usize)>,
+}
+// The interned symbol is never released, like as the other lisp.
+lazy_static! {
+    static ref SYMBOL_TBL: Mutex<HashMap<Box<str>, Symbol>> = Mutex::new(HashMap::new());
+}
+// suffix counter for gensym
+static GENSYM_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// The same name is always the same object, so that comparing is a pointer operation.
+#[derive(Clone)]
+pub struct Symbol(Arc<Name>);
+
+impl Symbol {
+    pub fn intern(s: &str) -> Self {
+        let mut tbl = SYMBOL_TBL.lock().unwrap();
+        if let Some(sym) = tbl.get(s) {
+            return sym.clone();
+        }
+        let sym = Symbol(Arc::new(Name {
+            name: s.into(),
+            interned: true,
+            origin: None,
+        }));
+        tbl.insert(s.into(), sym.clone());
+        sym
+    }
+    // It is not equal to any other symbol, even if the name is the same.
+    pub fn uninterned(s: &str) -> Self {
+        Symbol(Arc::new(Name {
+            name: s.into(),
+            interned: false,
+            origin: None,
+        }))
+    }
+    // The renamed identifier of the macro expansion, which refers to the origin
+    // in the environment of the scope, unless it is bound by the expansion.
+    pub fn rename(origin: &Symbol, scope: usize) -> Self {
+        Symbol(Arc::new(Name {
+            name: origin.as_str().into(),
+            interned: false,
+            origin: Some((origin.clone(), scope)),
+        }))
+    }
+    pub fn origin(&self) -> Option<(&Symbol, usize)> {
+        self.0.origin.as_ref().map(|(s, scope)| (s, *scope))
+    }
+    // The quoted identifier is the original symbol, ex. (quote tmp) in the template
+    pub fn strip(&self) -> Symbol {
+        match &self.0.origin {
+            Some((s, _)) => s.strip(),
+            None => self.clone(),
+        }
+    }
+    pub fn gensym(prefix: &str) -> Self {
+        let n = GENSYM_COUNT.fetch_add(1, AtomicOrdering::Relaxed);
+        Symbol::uninterned(&format!("{}{}", prefix, n))
+    }
+    // The symbol which is not interned yet, is never bound.
+    pub fn lookup(s: &str) -> Option<Self> {
+        SYMBOL_TBL.lock().unwrap().get(s).cloned()
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0.name
+    }
+    pub fn is_interned(&self) -> bool {
+        self.0.interned
+    }
+    pub fn ptr_eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.ptr_eq(other)
+    }
+}
+impl Eq for Symbol {}
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> Ordering {
+        match self.as_str().cmp(other.as_str()) {
+            Ordering::Equal => Arc::as_ptr(&self.0).cmp(&Arc::as_ptr(&other.0)),
+            o => o,
+        }
+    }
+}
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+impl Deref for Symbol {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::intern(s)
+    }
+}
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        Symbol::intern(&s)
+    }
+}
+impl From<&String> for Symbol {
+    fn from(s: &String) -> Self {
+        Symbol::intern(s)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern() {
+        let a = Symbol::intern("abc");
+        let b = Symbol::from(String::from("abc"));
+        assert!(a.ptr_eq(&b));
+        assert_eq!(a, b);
+        assert!(a.is_interned());
+        assert_eq!(a, "abc");
+        assert_eq!(a.to_string(), "abc");
+        assert!(Symbol::lookup("abc").is_some());
+        assert!(Symbol::lookup("#not-interned-yet#").is_none());
+    }
+    #[test]
+    fn uninterned() {
+        let a = Symbol::uninterned("abc");
+        let b = Symbol::uninterned("abc");
+        assert_ne!(a, b);
+        assert_ne!(a, Symbol::intern("abc"));
+        assert!(!a.is_interned());
+        assert_ne!(Symbol::gensym("g"), Symbol::gensym("g"));
+    }
+    #[test]
+    fn rename() {
+        let a = Symbol::intern("abc");
+        let b = Symbol::rename(&a, 1);
+        let c = Symbol::rename(&b, 2);
+        assert_ne!(a, b);
+        assert_eq!(b, "abc");
+        assert!(!b.is_interned());
+        assert!(b.origin().unwrap().0.ptr_eq(&a));
+        assert_eq!(c.origin().unwrap().1, 2);
+        assert!(c.strip().ptr_eq(&a));
+        assert!(a.strip().ptr_eq(&a));
+        assert!(a.origin().is_none());
+    }
+}
@@ -9,15 +9,17 @@ use log::{debug, error, info, warn};
 use std::vec::Vec;
 
 use crate::buildin::BuildInTable;
-use crate::create_continuation;
 use crate::create_error;
 use crate::create_error_value;
-use crate::get_ptr;
 use crate::lisp::eval;
-use crate::lisp::{Environment, Expression, ResultExpression};
-use crate::lisp::{ErrCode, Error, Function};
+use crate::lisp::{Environment, Expression, ListRc, ResultExpression};
+use crate::lisp::{ErrCode, Error, Function, Param};
 use crate::list::make_evaled_list;
+use crate::machine;
+use crate::mut_obj;
+use crate::pair;
 use crate::reference_obj;
+use crate::symbol::Symbol;
 use crate::util::eqv;
 
 pub fn create_function<T>(b: &mut T)
@@ -26,7 +28,21 @@ where
 {
     b.regist("define", define);
     b.regist("lambda", lambda);
+    b.regist("case-lambda", case_lambda);
     b.regist("let", let_f);
+    b.regist("let*", |exp, env| eval(&expand("let*", exp)?, env));
+    b.regist("letrec", |exp, env| eval(&expand("letrec", exp)?, env));
+    b.regist("letrec*", |exp, env| eval(&expand("letrec*", exp)?, env));
+    b.regist("let-values", |exp, env| {
+        eval(&expand("let-values", exp)?, env)
+    });
+    b.regist("let*-values", |exp, env| {
+        eval(&expand("let*-values", exp)?, env)
+    });
+    b.regist("define-values", |exp, env| {
+        eval(&expand("define-values", exp)?, env)
+    });
+    b.regist("receive", |exp, env| eval(&expand("receive", exp)?, env));
     b.regist("set!", set_f);
 
     b.regist("if", if_f);
@@ -37,58 +53,171 @@ where
     b.regist("begin", begin);
 
     b.regist("apply", apply);
+    b.regist("eval", eval_f);
+    b.regist("interaction-environment", interaction_environment);
+    b.regist("scheme-report-environment", |exp, env| {
+        report_environment(exp, env, false)
+    });
+    b.regist("null-environment", |exp, env| {
+        report_environment(exp, env, true)
+    });
+    b.regist("make-environment", make_environment);
     b.regist("delay", delay);
     b.regist("force", force);
     b.regist("quote", quote);
+    b.regist("quasiquote", quasiquote);
 
     b.regist("do", do_f);
     b.regist("call/cc", call_cc);
+    b.regist("call-with-current-continuation", call_cc);
+    b.regist("dynamic-wind", dynamic_wind);
+    b.regist("values", values);
+    b.regist("call-with-values", call_with_values);
 }
-#[derive(Clone)]
-pub struct Continuation {
-    cont: Expression,
-}
-impl Continuation {
-    pub fn new(cont: Expression) -> Self {
-        // This variable is not used.
-        // Because not support rerun as follows.
-        //
-        // (call/cc (lambda (c) (set! cc c)...
-        // (cc 10)
-        //
-        Continuation { cont }
-    }
-    pub fn execute(&self, exp: &[Expression], env: &Environment) -> ResultExpression {
-        if exp.len() != 2 {
-            return Err(create_error_value!(ErrCode::E1007, exp.len()));
-        }
-        if let Expression::List(l) = &self.cont {
-            debug!("Continuation = {:?}", get_ptr!(l));
-        }
-        Err(create_continuation!(eval(&exp[1], env)?, exp[0].clone()))
-    }
-}
+// The continuation is captured by the machine.
 pub fn call_cc(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    if let Expression::Function(f) = eval(&exp[1], env)? {
-        let e = env.get_cont().unwrap();
-        let c = Continuation::new(e);
-
-        let sexp: Vec<Expression> = vec![exp[0].clone(), Expression::Continuation(Box::new(c))];
-        f.execute(&sexp, env)
+    let f = eval(&exp[1], env)?;
+    if let Expression::Function(_) = f {
+        let call_cc = Expression::BuildInFunction("call/cc", call_cc);
+        machine::apply(&call_cc, vec![f], env)
     } else {
         Err(create_error!(ErrCode::E1006))
     }
 }
+fn dynamic_wind(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut args = Vec::new();
+    for e in &exp[1..] {
+        args.push(eval(e, env)?);
+    }
+    let dynamic_wind = Expression::BuildInFunction("dynamic-wind", dynamic_wind);
+    machine::apply(&dynamic_wind, args, env)
+}
+// (values) and (values 1 2) are the multiple values, (values 1) is 1.
+fn values(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let mut v = Vec::with_capacity(exp.len() - 1);
+    for e in &exp[1..] {
+        v.push(eval(e, env)?);
+    }
+    if v.len() == 1 {
+        return Ok(v.pop().unwrap());
+    }
+    Ok(Environment::create_values(v))
+}
+// The consumer is called by the machine.
+fn call_with_values(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let mut args = Vec::new();
+    for e in &exp[1..] {
+        args.push(eval(e, env)?);
+    }
+    let call_with_values = Expression::BuildInFunction("call-with-values", call_with_values);
+    machine::apply(&call_with_values, args, env)
+}
 pub fn quote(exp: &[Expression], _env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         Err(create_error_value!(ErrCode::E1007, exp.len()))
     } else {
-        Ok(exp[1].clone())
+        Ok(pair::datum(&exp[1]))
     }
 }
+// The quoted list is converted to the pairs at the first time, and the form keeps them.
+// So the literal is the same object, ex. (define (f) '(1 2)) (eq? (f) (f)) => #t
+pub(crate) fn quoted(form: &ListRc) -> Expression {
+    let e = reference_obj!(form)[1].clone();
+    if !Expression::is_form(&e) && !Expression::is_symbol(&e) {
+        return e;
+    }
+    let d = pair::datum(&e);
+    mut_obj!(form)[1] = d.clone();
+    d
+}
+fn quasiquote(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    quasiquote_expand(&exp[1], 1, env)
+}
+fn quasiquote_keyword(exp: &Expression) -> Option<&str> {
+    let s = match exp {
+        Expression::Symbol(s) => s.as_str(),
+        Expression::BuildInFunction(s, _) => s,
+        _ => return None,
+    };
+    match s {
+        "quasiquote" | "unquote" | "unquote-splicing" => Some(s),
+        _ => None,
+    }
+}
+fn quasiquote_expand(exp: &Expression, depth: usize, env: &Environment) -> ResultExpression {
+    let l = match exp {
+        Expression::List(l) => l,
+        Expression::Symbol(_) => return Ok(pair::datum(exp)),
+        // ex. `#(1 ,a)
+        Expression::Vector(l) => {
+            let l: Vec<Expression> = reference_obj!(l).iter().map(pair::form).collect();
+            let (v, _) = quasiquote_list(&l, depth, env)?;
+            return Ok(Environment::create_vector(v));
+        }
+        _ => return Ok(exp.clone()),
+    };
+    let l = &*(reference_obj!(l));
+    if l.is_empty() {
+        return Ok(Expression::Null());
+    }
+    if l.len() == 2 {
+        match quasiquote_keyword(&l[0]) {
+            Some("unquote") if depth == 1 => return eval(&l[1], env),
+            Some(k @ "unquote") | Some(k @ "unquote-splicing") => {
+                let e = quasiquote_expand(&l[1], depth - 1, env)?;
+                let k = Expression::Symbol(Symbol::from(k));
+                return Ok(Environment::create_list(vec![k, e]));
+            }
+            Some(k @ "quasiquote") => {
+                let e = quasiquote_expand(&l[1], depth + 1, env)?;
+                let k = Expression::Symbol(Symbol::from(k));
+                return Ok(Environment::create_list(vec![k, e]));
+            }
+            _ => {}
+        }
+    }
+    let (v, tail) = quasiquote_list(l, depth, env)?;
+    let tail = match tail {
+        Some(t) => quasiquote_expand(t, depth, env)?,
+        None => Expression::Null(),
+    };
+    Ok(Environment::create_dotted_list(v, tail))
+}
+// ex. `(1 . ,a)
+fn quasiquote_list<'a>(
+    l: &'a [Expression],
+    depth: usize,
+    env: &Environment,
+) -> Result<(Vec<Expression>, Option<&'a Expression>), Error> {
+    let (l, tail) = match l {
+        [x @ .., Expression::Symbol(s), t] if s == "." && !x.is_empty() => (x, Some(t)),
+        _ => (l, None),
+    };
+    let mut v = Vec::new();
+    for e in l {
+        if let Expression::List(u) = e {
+            let u = &*(reference_obj!(u));
+            if u.len() == 2 && depth == 1 && quasiquote_keyword(&u[0]) == Some("unquote-splicing") {
+                v.extend(pair::get_list(eval(&u[1], env)?)?);
+                continue;
+            }
+        }
+        v.push(quasiquote_expand(e, depth, env)?);
+    }
+    Ok((v, tail))
+}
 fn define(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() < 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
@@ -98,9 +227,9 @@ fn define(exp: &[Expression], env: &Environment) -> ResultExpression {
             return Err(create_error_value!(ErrCode::E1007, exp.len()));
         }
         let se = eval(&exp[2], env)?;
-        env.regist(v.to_string(), se);
+        env.regist(v.clone(), se);
 
-        return Ok(Expression::Symbol(v.to_string()));
+        return Ok(Expression::Symbol(v.clone()));
     }
     if let Expression::List(l) = &exp[1] {
         let l = &*(reference_obj!(l));
@@ -108,25 +237,15 @@ fn define(exp: &[Expression], env: &Environment) -> ResultExpression {
             return Err(create_error_value!(ErrCode::E1007, l.len()));
         }
         if let Expression::Symbol(s) = &l[0] {
-            let mut param: Vec<Expression> = Vec::new();
-            for n in &l[1..] {
-                match n {
-                    Expression::Symbol(_) => {
-                        param.push(n.clone());
-                    }
-                    _ => return Err(create_error!(ErrCode::E1004)),
-                }
-            }
+            let param = Environment::create_form(l[1..].to_vec());
+            Param::new(&param)?;
 
             let mut f = exp.to_vec();
-            f[1] = Environment::create_list(param);
-            let mut func = Function::new(&f, s.to_string(), env.clone());
-            if env.is_tail_recursion() {
-                func.set_tail_recurcieve();
-            }
-            env.regist(s.to_string(), Environment::create_func(func));
+            f[1] = param;
+            let func = Function::new(&f, s.to_string(), env.clone());
+            env.regist(s.clone(), Environment::create_func(func));
 
-            Ok(Expression::Symbol(s.to_string()))
+            Ok(Expression::Symbol(s.clone()))
         } else {
             Err(create_error!(ErrCode::E1004))
         }
@@ -138,24 +257,42 @@ fn lambda(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() < 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    if let Expression::List(l) = &exp[1] {
-        let l = &*(reference_obj!(l));
-        for e in l {
-            match e {
-                Expression::Symbol(_) => {}
-                _ => return Err(create_error!(ErrCode::E1004)),
-            }
-        }
-    } else {
-        return Err(create_error!(ErrCode::E1005));
-    }
+    Param::new(&exp[1])?;
     Ok(Environment::create_func(Function::new(
         exp,
         String::from("lambda"),
         env.clone(),
     )))
 }
-fn let_f(exp: &[Expression], env: &Environment) -> ResultExpression {
+fn case_lambda(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let mut funcs = Vec::new();
+    for e in &exp[1..] {
+        let l = match e {
+            Expression::List(l) => l,
+            e => return Err(create_error_value!(ErrCode::E1005, e)),
+        };
+        let l = &*(reference_obj!(l));
+        if l.len() < 2 {
+            return Err(create_error_value!(ErrCode::E1007, l.len()));
+        }
+        Param::new(&l[0])?;
+
+        let mut f = vec![exp[0].clone()];
+        f.extend_from_slice(l);
+        funcs.push(Function::new(&f, String::from("case-lambda"), env.clone()));
+    }
+    Ok(Environment::create_func_ext(move |exp, env| {
+        let n = exp.len() - 1;
+        match funcs.iter().find(|f| f.is_acceptable(n)) {
+            Some(f) => f.execute(exp, env),
+            None => Err(create_error_value!(ErrCode::E1007, n)),
+        }
+    }))
+}
+pub(crate) fn let_setup(
+    exp: &[Expression],
+    env: &Environment,
+) -> Result<(Function, Vec<Expression>, Environment), Error> {
     if exp.len() < 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
@@ -170,8 +307,7 @@ fn let_f(exp: &[Expression], env: &Environment) -> ResultExpression {
     }
     // Parameter Setup
     let mut param_list: Vec<Expression> = Vec::new();
-    let mut param_value_list: Vec<Expression> =
-        vec![Environment::create_string(String::from("dummy"))];
+    let mut param_value_list: Vec<Expression> = Vec::new();
 
     if let Expression::List(l) = &exp[idx] {
         let l = &*(reference_obj!(l));
@@ -199,36 +335,226 @@ fn let_f(exp: &[Expression], env: &Environment) -> ResultExpression {
     // Setup Function
     let mut vec = vec![
         Environment::create_string(name.to_string()),
-        Environment::create_list(param_list),
+        Environment::create_form(param_list),
     ];
     vec.extend_from_slice(&exp[idx..]);
-    let mut f = Function::new(&vec[..], name, param.clone());
+    let f = Function::new(&vec[..], name, param.clone());
 
     // Setup label name let
     if let Expression::Symbol(s) = &exp[1] {
-        if env.is_tail_recursion() {
-            f.set_tail_recurcieve();
-            if !f.get_tail_recurcieve() {
-                param.regist(s.to_string(), Environment::create_func(f.clone()));
+        param.regist(s.clone(), Environment::create_func(f.clone()));
+    }
+    Ok((f, param_value_list, param))
+}
+fn let_f(exp: &[Expression], env: &Environment) -> ResultExpression {
+    let (f, args, param) = let_setup(exp, env)?;
+
+    let mut vec = vec![Environment::create_func(f.clone())];
+    vec.extend(args);
+    f.execute(&vec, &param)
+}
+// The derived forms are rewritten into let, lambda, define and call-with-values.
+pub(crate) fn expand(name: &str, exp: &[Expression]) -> ResultExpression {
+    match name {
+        "let*" => let_star(exp),
+        "letrec" | "letrec*" => letrec(exp),
+        "let-values" => let_values(exp),
+        "let*-values" => let_star_values(exp),
+        "define-values" => define_values(exp),
+        "receive" => receive(exp),
+        _ => Err(create_error_value!(ErrCode::E9999, name)),
+    }
+}
+fn form(v: Vec<Expression>) -> Expression {
+    Environment::create_form(v)
+}
+fn let_form(bindings: Vec<Expression>, body: &[Expression]) -> Expression {
+    let mut v = vec![Expression::BuildInFunction("let", let_f), form(bindings)];
+    v.extend_from_slice(body);
+    form(v)
+}
+fn lambda_form(param: Expression, body: &[Expression]) -> Expression {
+    let mut v = vec![Expression::BuildInFunction("lambda", lambda), param];
+    v.extend_from_slice(body);
+    form(v)
+}
+// (call-with-values (lambda () producer) consumer)
+fn call_with_values_form(producer: &Expression, consumer: Expression) -> Expression {
+    form(vec![
+        Expression::BuildInFunction("call-with-values", call_with_values),
+        lambda_form(form(Vec::new()), std::slice::from_ref(producer)),
+        consumer,
+    ])
+}
+fn bindings(exp: &[Expression]) -> Result<Vec<Expression>, Error> {
+    if exp.len() < 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match &exp[1] {
+        Expression::List(l) => Ok(reference_obj!(l).to_vec()),
+        e => Err(create_error_value!(ErrCode::E1005, e)),
+    }
+}
+// ex. (v e) or (formals e)
+fn binding(exp: &Expression) -> Result<(Expression, Expression), Error> {
+    match exp {
+        Expression::List(l) => match &reference_obj!(l)[..] {
+            [v, e] => Ok((v.clone(), e.clone())),
+            v => Err(create_error_value!(ErrCode::E1007, v.len())),
+        },
+        e => Err(create_error_value!(ErrCode::E1005, e)),
+    }
+}
+// The variables of the formals, ex. (a b . c) => [a, b, c]
+fn formals(exp: &Expression) -> Result<Vec<Symbol>, Error> {
+    let param = Param::new(exp)?;
+    if !param.is_positional() {
+        return Err(create_error_value!(ErrCode::E1004, exp));
+    }
+    Ok(param
+        .required()
+        .iter()
+        .chain(param.rest())
+        .cloned()
+        .collect())
+}
+// (let* ((a 1) (b a)) body ...) => (let ((a 1)) (let* ((b a)) body ...))
+fn let_star(exp: &[Expression]) -> ResultExpression {
+    let b = bindings(exp)?;
+    match b.split_first() {
+        None => Ok(let_form(b, &exp[2..])),
+        Some((first, rest)) => {
+            let mut v = vec![exp[0].clone(), form(rest.to_vec())];
+            v.extend_from_slice(&exp[2..]);
+            Ok(let_form(vec![first.clone()], &[form(v)]))
+        }
+    }
+}
+// (letrec ((a e)) body ...) => (let () (define a e) body ...)
+fn letrec(exp: &[Expression]) -> ResultExpression {
+    let mut body = Vec::new();
+    for e in bindings(exp)? {
+        match binding(&e)? {
+            (Expression::Symbol(s), e) => body.push(form(vec![
+                Expression::BuildInFunction("define", define),
+                Expression::Symbol(s),
+                e,
+            ])),
+            (e, _) => return Err(create_error_value!(ErrCode::E1004, e)),
+        }
+    }
+    body.extend_from_slice(&exp[2..]);
+    Ok(let_form(Vec::new(), &body))
+}
+// The temporary variables for the formals, ex. (a b . c) => (t1 t2 . t3)
+fn temporaries(param: &Expression) -> Result<(Vec<(Symbol, Symbol)>, Expression), Error> {
+    let vars: Vec<(Symbol, Symbol)> = formals(param)?
+        .into_iter()
+        .map(|s| (s, Symbol::gensym("t")))
+        .collect();
+    let mut temp: Vec<Expression> = vars
+        .iter()
+        .map(|(_, t)| Expression::Symbol(t.clone()))
+        .collect();
+    let temp = match param {
+        Expression::List(l) => {
+            if reference_obj!(l).len() > temp.len() {
+                let rest = temp.pop().unwrap();
+                temp.push(Expression::Symbol(Symbol::from(".")));
+                temp.push(rest);
             }
-        } else {
-            param.regist(s.to_string(), Environment::create_func(f.clone()));
+            form(temp)
         }
+        _ => temp.pop().unwrap(),
+    };
+    Ok((vars, temp))
+}
+// All the producers are evaluated in the outer environment, so the values are
+// bound to the temporary variables at first.
+// (let-values (((a b) e)) body ...)
+//   => (call-with-values (lambda () e) (lambda (t1 t2) (let ((a t1) (b t2)) body ...)))
+fn let_values(exp: &[Expression]) -> ResultExpression {
+    let mut specs = Vec::new();
+    let mut vars = Vec::new();
+    for e in bindings(exp)? {
+        let (param, producer) = binding(&e)?;
+        let (v, temp) = temporaries(&param)?;
+        for (s, t) in v {
+            vars.push(form(vec![Expression::Symbol(s), Expression::Symbol(t)]));
+        }
+        specs.push((temp, producer));
+    }
+    let mut body = let_form(vars, &exp[2..]);
+    for (temp, producer) in specs.into_iter().rev() {
+        body = call_with_values_form(&producer, lambda_form(temp, &[body]));
+    }
+    Ok(body)
+}
+// (let*-values (((a b) e)) body ...)
+//   => (call-with-values (lambda () e) (lambda (a b) (let () body ...)))
+fn let_star_values(exp: &[Expression]) -> ResultExpression {
+    let mut specs = Vec::new();
+    for e in bindings(exp)? {
+        let (param, producer) = binding(&e)?;
+        formals(&param)?;
+        specs.push((param, producer));
+    }
+    let mut body = let_form(Vec::new(), &exp[2..]);
+    for (param, producer) in specs.into_iter().rev() {
+        body = call_with_values_form(&producer, lambda_form(param, &[body]));
+    }
+    Ok(body)
+}
+// (define-values (a b) e)
+//   => (begin (define a #f) (define b #f)
+//        (call-with-values (lambda () e) (lambda (t1 t2) (set! a t1) (set! b t2))))
+fn define_values(exp: &[Expression]) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    f.execute(&param_value_list, &param)
+    let (vars, temp) = temporaries(&exp[1])?;
+    let mut defs = vec![Expression::BuildInFunction("begin", begin)];
+    let mut sets = Vec::new();
+    for (s, t) in vars {
+        defs.push(form(vec![
+            Expression::BuildInFunction("define", define),
+            Expression::Symbol(s.clone()),
+            Expression::Boolean(false),
+        ]));
+        sets.push(form(vec![
+            Expression::BuildInFunction("set!", set_f),
+            Expression::Symbol(s),
+            Expression::Symbol(t),
+        ]));
+    }
+    // the value of define-values is nil
+    sets.push(Expression::Nil());
+    defs.push(call_with_values_form(&exp[2], lambda_form(temp, &sets)));
+    Ok(form(defs))
+}
+// (receive formals e body ...) => (call-with-values (lambda () e) (lambda formals body ...))
+fn receive(exp: &[Expression]) -> ResultExpression {
+    if exp.len() < 4 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    formals(&exp[1])?;
+    Ok(call_with_values_form(
+        &exp[2],
+        lambda_form(exp[1].clone(), &exp[3..]),
+    ))
 }
 fn set_f(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     if let Expression::Symbol(s) = &exp[1] {
-        if env.find(s).is_some() {
+        if let Some((name, e)) = machine::bound(s, env) {
             let v = eval(&exp[2], env)?;
-            env.update(s, v);
+            e.update_symbol(&name, v);
         } else {
             return Err(create_error_value!(ErrCode::E1008, s));
         }
-        Ok(Expression::Symbol(s.to_string()))
+        Ok(Expression::Symbol(s.clone()))
     } else {
         Err(create_error!(ErrCode::E1004))
     }
@@ -350,7 +676,7 @@ fn case(exp: &[Expression], env: &Environment) -> ResultExpression {
                                     if 1 < l.len() {
                                         return begin(l, env);
                                     } else {
-                                        return Ok(Expression::List(r.clone()));
+                                        return Ok(pair::datum(&l[0]));
                                     }
                                 }
                             }
@@ -379,15 +705,61 @@ fn apply(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 3 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
-    if let Expression::List(l) = eval(&exp[2], env)? {
-        let l = &*(reference_obj!(l));
-        let sexp = make_evaled_list(&exp[1], l, &None);
+    let l = pair::get_list(eval(&exp[2], env)?)?;
+    let sexp = make_evaled_list(&exp[1], &l, &None);
 
-        eval(&Environment::create_list(sexp), env)
-    } else {
-        Err(create_error!(ErrCode::E1005))
+    eval(&Environment::create_form(sexp), env)
+}
+// The expression is evaluated by the machine.
+fn eval_f(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let v = eval(&exp[1], env)?;
+    let e = eval(&exp[2], env)?;
+    if !Expression::is_environment(&e) {
+        return Err(create_error_value!(ErrCode::E1029, e));
+    }
+    let eval_f = Expression::BuildInFunction("eval", eval_f);
+    machine::apply(&eval_f, vec![v, e], env)
+}
+fn interaction_environment(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 1 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    Ok(Expression::Environment(env.root()))
+}
+// ex. (scheme-report-environment 7), (null-environment 5)
+fn report_environment(
+    exp: &[Expression],
+    env: &Environment,
+    syntax_only: bool,
+) -> ResultExpression {
+    if exp.len() != 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match eval(&exp[1], env)? {
+        Expression::Integer(5) | Expression::Integer(7) => Ok(Expression::Environment(
+            Environment::with_globals(env, syntax_only),
+        )),
+        Expression::Integer(_) => Err(create_error!(ErrCode::E1021)),
+        e => Err(create_error_value!(ErrCode::E1002, e)),
     }
 }
+// The child of the environment, ex. (make-environment) or (make-environment env)
+fn make_environment(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() > 2 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let parent = match exp.get(1) {
+        Some(e) => match eval(e, env)? {
+            Expression::Environment(e) => e,
+            e => return Err(create_error_value!(ErrCode::E1029, e)),
+        },
+        None => env.root(),
+    };
+    Ok(Expression::Environment(Environment::with_parent(&parent)))
+}
 fn delay(exp: &[Expression], env: &Environment) -> ResultExpression {
     if exp.len() != 2 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
@@ -420,7 +792,7 @@ fn do_f(exp: &[Expression], env: &Environment) -> ResultExpression {
     }
 
     let local_env = Environment::with_parent(env);
-    let mut param = Vec::<String>::new();
+    let mut param = Vec::<Symbol>::new();
     let mut update = Vec::<Expression>::new();
 
     for e in l {
@@ -434,8 +806,8 @@ fn do_f(exp: &[Expression], env: &Environment) -> ResultExpression {
             return Err(create_error_value!(ErrCode::E1007, f.len()));
         }
         if let Expression::Symbol(s) = &f[0] {
-            local_env.regist(s.to_string(), eval(&f[1], env)?);
-            param.push(s.to_string());
+            local_env.regist(s.clone(), eval(&f[1], env)?);
+            param.push(s.clone());
         } else {
             return Err(create_error!(ErrCode::E1004));
         }
@@ -487,6 +859,92 @@ mod tests {
     use crate::lisp;
     use crate::{do_lisp, do_lisp_env};
     #[test]
+    fn callcc_reentry() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define r #f)", &env);
+        assert_eq!(
+            do_lisp_env("(+ 1 (call/cc (lambda (k) (set! r k) 1)))", &env),
+            "2"
+        );
+        assert_eq!(do_lisp_env("(r 10)", &env), "11");
+        assert_eq!(do_lisp_env("(r 20)", &env), "21");
+        assert_eq!(do_lisp_env("(list 1 (r 30))", &env), "31");
+
+        do_lisp_env(
+            "(define (count-to n) (let ((i 0) (k #f)) (call/cc (lambda (c) (set! k c))) (set! i (+ i 1)) (if (< i n) (k #f) i)))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(count-to 5)", &env), "5");
+        assert_eq!(
+            do_lisp_env("(call-with-current-continuation (lambda (k) (k 1)))", &env),
+            "1"
+        );
+    }
+    #[test]
+    fn callcc_generator() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            &("(define (make-generator lst)".to_string()
+                + "(define return #f)"
+                + "(define resume #f)"
+                + "(define (start)"
+                + "  (for-each (lambda (x) (call/cc (lambda (k) (set! resume k) (return x)))) lst)"
+                + "  (return 'done))"
+                + "(lambda () (call/cc (lambda (r) (set! return r) (if (eq? resume #f) (start) (resume #f))))))"),
+            &env,
+        );
+        do_lisp_env("(define g (make-generator '(1 2 3)))", &env);
+        assert_eq!(do_lisp_env("(list (g) (g) (g) (g))", &env), "(1 2 3 done)");
+
+        do_lisp_env("(define h (make-generator '(a b)))", &env);
+        assert_eq!(do_lisp_env("(h)", &env), "a");
+        assert_eq!(do_lisp_env("(h)", &env), "b");
+        assert_eq!(do_lisp_env("(h)", &env), "done");
+    }
+    #[test]
+    fn dynamic_wind() {
+        let env = lisp::Environment::new();
+        assert_eq!(
+            do_lisp_env(
+                "(dynamic-wind (lambda () 1) (lambda () 2) (lambda () 3))",
+                &env
+            ),
+            "2"
+        );
+        assert_eq!(
+            do_lisp_env(
+                &("(let ((path '()) (c #f))".to_string()
+                    + "(let ((add (lambda (s) (set! path (cons s path)))))"
+                    + "(dynamic-wind (lambda () (add 'connect))"
+                    + "(lambda () (add (call/cc (lambda (c0) (set! c c0) 'talk1))))"
+                    + "(lambda () (add 'disconnect)))"
+                    + "(if (< (length path) 4) (c 'talk2) (reverse path))))"),
+                &env
+            ),
+            "(connect talk1 disconnect connect talk2 disconnect)"
+        );
+        do_lisp_env("(define out '())", &env);
+        do_lisp_env("(define (add s) (set! out (cons s out)))", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(call/cc (lambda (k) (dynamic-wind (lambda () (add 'in)) (lambda () (k 'x)) (lambda () (add 'out)))))",
+                &env
+            ),
+            "x"
+        );
+        assert_eq!(do_lisp_env("out", &env), "(out in)");
+
+        do_lisp_env("(set! out '())", &env);
+        assert_eq!(
+            do_lisp_env(
+                "(dynamic-wind (lambda () (add 'in)) (lambda () (car 1)) (lambda () (add 'out)))",
+                &env
+            ),
+            "E1005"
+        );
+        assert_eq!(do_lisp_env("out", &env), "(out in)");
+    }
+    #[test]
     fn callcc() {
         let env = lisp::Environment::new();
         assert_eq!(
@@ -582,6 +1040,51 @@ mod tests {
         assert_eq!(do_lisp_env("(hoge 3 4)", &env), "27");
     }
     #[test]
+    fn define_variadic() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (f . args) args)", &env);
+        assert_eq!(do_lisp_env("(f)", &env), "()");
+        assert_eq!(do_lisp_env("(f 1 2)", &env), "(1 2)");
+        do_lisp_env("(define (g a b . c) (list a b c))", &env);
+        assert_eq!(do_lisp_env("(g 1 2)", &env), "(1 2 ())");
+        assert_eq!(do_lisp_env("(g 1 2 3 4)", &env), "(1 2 (3 4))");
+        do_lisp_env("(define (h x #!optional (y 5)) (+ x y))", &env);
+        assert_eq!(do_lisp_env("(h 1)", &env), "6");
+        assert_eq!(do_lisp_env("(h 1 2)", &env), "3");
+        do_lisp_env("(define (k x #!key (y 5) (z 7)) (list x y z))", &env);
+        assert_eq!(do_lisp_env("(k 1 :z 2)", &env), "(1 5 2)");
+        assert_eq!(do_lisp_env("(apply g '(1 2 3))", &env), "(1 2 (3))");
+        assert_eq!(do_lisp_env("(map f '(1 2))", &env), "((1) (2))");
+    }
+    #[test]
+    fn case_lambda() {
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define area (case-lambda ((r) (* r r)) ((w h) (* w h)) ((a b . c) (length c))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(area 3)", &env), "9");
+        assert_eq!(do_lisp_env("(area 3 4)", &env), "12");
+        assert_eq!(do_lisp_env("(area 3 4 5 6)", &env), "2");
+        assert_eq!(do_lisp("((case-lambda (() 0) (x (length x))))"), "0");
+        assert_eq!(do_lisp("((case-lambda (() 0) (x (length x))) 1 2)"), "2");
+        assert_eq!(do_lisp("(procedure? (case-lambda ((a) a)))"), "#t");
+    }
+    #[test]
+    fn arity_message() {
+        let env = lisp::Environment::new();
+        let msg = |p: &str| match lisp::do_core_logic(p, &env) {
+            Ok(_) => String::new(),
+            Err(e) => e.get_msg(),
+        };
+        do_lisp_env("(define (f a b) a)", &env);
+        do_lisp_env("(define (g a #!optional b) a)", &env);
+        do_lisp_env("(define (h a . b) a)", &env);
+        assert!(msg("(f 1)").contains("expected 2, but got 1"));
+        assert!(msg("(g 1 2 3)").contains("expected 1 to 2, but got 3"));
+        assert!(msg("(h)").contains("expected at least 1, but got 0"));
+    }
+    #[test]
     fn lambda() {
         assert_eq!(do_lisp("(lambda (a b)(+ a b))"), "Function");
         assert_eq!(do_lisp("((lambda (a b)(+ a b)) 1 2)"), "3");
@@ -593,6 +1096,39 @@ mod tests {
         assert_eq!(do_lisp_env("(hoge 6 8)", &env), "8");
     }
     #[test]
+    fn lambda_variadic() {
+        assert_eq!(do_lisp("((lambda a a))"), "()");
+        assert_eq!(do_lisp("((lambda a a) 1 2 3)"), "(1 2 3)");
+        assert_eq!(do_lisp("((lambda (a . b) (list a b)) 1)"), "(1 ())");
+        assert_eq!(do_lisp("((lambda (a . b) (list a b)) 1 2 3)"), "(1 (2 3))");
+        assert_eq!(do_lisp("((lambda (a #!rest b) b) 1 2 3)"), "(2 3)");
+        assert_eq!(
+            do_lisp("((lambda (a #!optional b (c (+ a 1))) (list a b c)) 1)"),
+            "(1 #f 2)"
+        );
+        assert_eq!(
+            do_lisp("((lambda (a #!optional b (c (+ a 1))) (list a b c)) 1 2 3)"),
+            "(1 2 3)"
+        );
+        assert_eq!(
+            do_lisp("((lambda (a #!optional b . c) (list a b c)) 1 2 3 4)"),
+            "(1 2 (3 4))"
+        );
+        assert_eq!(
+            do_lisp("((lambda (a #!key b (c 10)) (list a b c)) 1)"),
+            "(1 #f 10)"
+        );
+        assert_eq!(
+            do_lisp("((lambda (a #!key b (c 10)) (list a b c)) 1 :c 3 :b 2)"),
+            "(1 2 3)"
+        );
+        assert_eq!(
+            do_lisp("((lambda (#!rest r #!key (b 0)) (list r b)) :b 2 :z 1)"),
+            "((:b 2 :z 1) 2)"
+        );
+        assert_eq!(do_lisp(":abc"), ":abc");
+    }
+    #[test]
     fn let_f() {
         assert_eq!(do_lisp("(let ((a 10)(b 20)) (+ a b))"), "30");
         assert_eq!(
@@ -614,6 +1150,125 @@ mod tests {
         );
     }
     #[test]
+    fn let_star() {
+        assert_eq!(do_lisp("(let* ((a 10)(b (* a 2))) (+ a b))"), "30");
+        assert_eq!(do_lisp("(let* () 10)"), "10");
+        assert_eq!(
+            do_lisp("(let ((x 2) (y 3)) (let* ((x 7) (z (+ x y))) (* z x)))"),
+            "70"
+        );
+        assert_eq!(
+            do_lisp("(let loop ((i 0)) (let* ((j (+ i 1))) (if (<= 10000 j) j (loop j))))"),
+            "10000"
+        );
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f n) (let* ((m (- n 1)) (k m)) (if (= k 0) 'done (f k))))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f 10000)", &env), "done");
+    }
+    #[test]
+    fn letrec() {
+        assert_eq!(
+            do_lisp("(letrec ((ev? (lambda (n) (if (= n 0) #t (od? (- n 1))))) (od? (lambda (n) (if (= n 0) #f (ev? (- n 1)))))) (ev? 10000))"),
+            "#t"
+        );
+        assert_eq!(do_lisp("(letrec* ((a 1) (b (+ a 1))) (list a b))"), "(1 2)");
+        assert_eq!(
+            do_lisp("(letrec* ((p (lambda (x) (+ 1 (q (- x 1))))) (q (lambda (y) (if (zero? y) 0 (+ 1 (p (- y 1)))))) (x (p 5)) (y x)) y)"),
+            "5"
+        );
+        assert_eq!(do_lisp("(letrec () 10)"), "10");
+    }
+    #[test]
+    fn values() {
+        assert_eq!(do_lisp("(values 1)"), "1");
+        assert_eq!(do_lisp("(values 1 2 3)"), "1 2 3");
+        assert_eq!(do_lisp("(values)"), "");
+        assert_eq!(
+            do_lisp("(call-with-values (lambda () (values 4 5)) -)"),
+            "-1"
+        );
+        assert_eq!(do_lisp("(call-with-values (lambda () 10) -)"), "-10");
+        assert_eq!(
+            do_lisp("(call-with-values (lambda () (values)) list)"),
+            "()"
+        );
+        assert_eq!(
+            do_lisp("(call-with-values (lambda () 1) (lambda (a) (+ a 1)))"),
+            "2"
+        );
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f n) (if (= n 0) (values 'a 'b) (f (- n 1))))",
+            &env,
+        );
+        assert_eq!(
+            do_lisp_env("(call-with-values (lambda () (f 10000)) list)", &env),
+            "(a b)"
+        );
+    }
+    #[test]
+    fn let_values() {
+        assert_eq!(
+            do_lisp("(let-values (((a b) (values 1 2)) ((c) (values 3))) (list a b c))"),
+            "(1 2 3)"
+        );
+        assert_eq!(
+            do_lisp("(let-values (((a . b) (values 1 2 3)) (c (values 4 5))) (list a b c))"),
+            "(1 (2 3) (4 5))"
+        );
+        assert_eq!(
+            do_lisp("(let ((a 'a) (b 'b) (x 'x) (y 'y)) (let-values (((a b) (values x y)) ((x y) (values a b))) (list a b x y)))"),
+            "(x y a b)"
+        );
+        assert_eq!(do_lisp("(let-values () 10)"), "10");
+    }
+    #[test]
+    fn let_star_values() {
+        assert_eq!(
+            do_lisp("(let ((a 'a) (b 'b) (x 'x) (y 'y)) (let*-values (((a b) (values x y)) ((x y) (values a b))) (list a b x y)))"),
+            "(x y x y)"
+        );
+        assert_eq!(do_lisp("(let*-values () 10)"), "10");
+    }
+    #[test]
+    fn define_values() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define-values (a b) (values 1 2))", &env);
+        assert_eq!(do_lisp_env("(list a b)", &env), "(1 2)");
+        do_lisp_env("(define-values (c . d) (values 1 2 3))", &env);
+        assert_eq!(do_lisp_env("(list c d)", &env), "(1 (2 3))");
+        do_lisp_env("(define-values e (values 1 2))", &env);
+        assert_eq!(do_lisp_env("e", &env), "(1 2)");
+        assert_eq!(do_lisp_env("(define-values () (values))", &env), "nil");
+        assert_eq!(
+            do_lisp("(let () (define-values (x y) (values 1 2)) (+ x y))"),
+            "3"
+        );
+        let env = lisp::Environment::new();
+        do_lisp_env(
+            "(define (f) (define-values (x y) (values 1 2)) (list x y))",
+            &env,
+        );
+        assert_eq!(do_lisp_env("(f)", &env), "(1 2)");
+        assert_eq!(do_lisp_env("x", &env), "E1008");
+    }
+    #[test]
+    fn receive() {
+        assert_eq!(do_lisp("(receive (a b) (values 1 2) (+ a b))"), "3");
+        assert_eq!(
+            do_lisp("(receive (a . rest) (values 1 2 3) (list a rest))"),
+            "(1 (2 3))"
+        );
+        assert_eq!(do_lisp("(receive all (values 1 2) all)"), "(1 2)");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define (f a) (receive (x y) (values a 2) (+ x y)))", &env);
+        assert_eq!(do_lisp_env("(f 1)", &env), "3");
+    }
+    #[test]
     fn set_f() {
         let env = lisp::Environment::new();
         do_lisp_env("(define c 0)", &env);
@@ -769,6 +1424,60 @@ mod tests {
         assert_eq!(do_lisp_env("(apply hoge (list 3 4))", &env), "12");
     }
     #[test]
+    fn eval_f() {
+        assert_eq!(
+            do_lisp("(eval '(* 7 3) (scheme-report-environment 7))"),
+            "21"
+        );
+        assert_eq!(
+            do_lisp("(eval (list (string->symbol \"+\") 1 2) (interaction-environment))"),
+            "3"
+        );
+        assert_eq!(
+            do_lisp("(let ((f (eval '(lambda (f x) (f x x)) (null-environment 5)))) (f + 10))"),
+            "20"
+        );
+        assert_eq!(do_lisp("(eval '(if #t 1 2) (null-environment 7))"), "1");
+        assert_eq!(do_lisp("(eval '(+ 1 2) (null-environment 7))"), "E1008");
+        assert_eq!(
+            do_lisp("(eval (list (string->symbol \"car\") ''(1)) (null-environment 7))"),
+            "E1008"
+        );
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a 10)", &env);
+        do_lisp_env("(define (f) (eval 'a (interaction-environment)))", &env);
+        assert_eq!(do_lisp_env("(let ((a 20)) (f))", &env), "10");
+        do_lisp_env("(eval '(define b 20) (interaction-environment))", &env);
+        assert_eq!(do_lisp_env("b", &env), "20");
+        assert_eq!(
+            do_lisp_env("(eval 'a (scheme-report-environment 7))", &env),
+            "E1008"
+        );
+        do_lisp_env("(define (loop n) (if (= n 0) 'done (eval (list 'loop (- n 1)) (interaction-environment))))", &env);
+        assert_eq!(do_lisp_env("(loop 10000)", &env), "done");
+    }
+    #[test]
+    fn make_environment() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a 10)", &env);
+        do_lisp_env("(define e (make-environment))", &env);
+        assert_eq!(do_lisp_env("e", &env), "Environment");
+        assert_eq!(do_lisp_env("(environment? e)", &env), "#t");
+        assert_eq!(do_lisp_env("(environment? a)", &env), "#f");
+        assert_eq!(do_lisp_env("(eval 'a e)", &env), "10");
+        do_lisp_env("(eval '(define a 20) e)", &env);
+        do_lisp_env("(eval '(define b 30) e)", &env);
+        assert_eq!(do_lisp_env("(eval '(+ a b) e)", &env), "50");
+        assert_eq!(do_lisp_env("a", &env), "10");
+        assert_eq!(do_lisp_env("b", &env), "E1008");
+
+        do_lisp_env("(define s (make-environment (null-environment 7)))", &env);
+        do_lisp_env("(eval '(define c 1) s)", &env);
+        assert_eq!(do_lisp_env("(eval 'c s)", &env), "1");
+        assert_eq!(do_lisp_env("(eval '(list c) s)", &env), "E1008");
+    }
+    #[test]
     fn delay_force() {
         assert_eq!(do_lisp("(delay (+ 1 1))"), "Promise");
         assert_eq!(do_lisp("(force (delay (+ 1 1)))"), "2");
@@ -796,6 +1505,72 @@ mod tests {
         );
     }
     #[test]
+    fn quote_nested() {
+        assert_eq!(do_lisp("(length ''a)"), "2");
+        assert_eq!(do_lisp("(cadr ''a)"), "a");
+        assert_eq!(do_lisp("(cadr (cadr '(a 'b)))"), "b");
+        assert_eq!(do_lisp("(length '(a '(b c) d))"), "3");
+    }
+    #[test]
+    fn quasiquote() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a 10)", &env);
+        do_lisp_env("(define l (list 1 2 3))", &env);
+        assert_eq!(do_lisp_env("(quasiquote (a (unquote a)))", &env), "(a 10)");
+        assert_eq!(do_lisp_env("`a", &env), "a");
+        assert_eq!(do_lisp_env("`,a", &env), "10");
+        assert_eq!(do_lisp_env("`(a ,a)", &env), "(a 10)");
+        assert_eq!(do_lisp_env("`(a ,(+ a 1) b)", &env), "(a 11 b)");
+        assert_eq!(do_lisp_env("`(a ,@l b)", &env), "(a 1 2 3 b)");
+        assert_eq!(do_lisp_env("`(,@l)", &env), "(1 2 3)");
+        assert_eq!(do_lisp_env("`(a ,@'() b)", &env), "(a b)");
+        assert_eq!(
+            do_lisp_env("`(1 (2 ,a) ((,@l)))", &env),
+            "(1 (2 10) ((1 2 3)))"
+        );
+        assert_eq!(do_lisp_env("`(\"a\" ,a)", &env), "(\"a\" 10)");
+        assert_eq!(do_lisp_env("`#(1 ,a ,@l)", &env), "#(1 10 1 2 3)");
+        assert_eq!(do_lisp_env("`(1 #(,a))", &env), "(1 #(10))");
+        assert_eq!(do_lisp_env("`()", &env), "()");
+    }
+    #[test]
+    fn quasiquote_nested() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a 10)", &env);
+        assert_eq!(do_lisp_env("(length `(1 `(2 ,(3 ,a))))", &env), "2");
+        assert_eq!(
+            do_lisp_env("(cadr (cadr (cadr (cadr `(1 `(2 ,(3 ,a)))))))", &env),
+            "(3 10)"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(cadr (cadr (cadr (cadr `(1 `(2 ,(3 ,@(list a a))))))))",
+                &env
+            ),
+            "(3 10 10)"
+        );
+        assert_eq!(do_lisp_env("(cadr ``,,a)", &env), "(unquote 10)");
+        assert_eq!(
+            do_lisp_env("`(1 `(2 ,(3 ,(+ 1 3))))", &env),
+            "(1 (quasiquote (2 (unquote (3 4)))))"
+        );
+        assert_eq!(
+            do_lisp_env("`(1 `(2 ,@(3 ,(+ 1 3))))", &env),
+            "(1 (quasiquote (2 (unquote-splicing (3 4)))))"
+        );
+        assert_eq!(
+            do_lisp_env(
+                "(map symbol? (list (car (cadr `(1 `,a))) (car ``,,a)))",
+                &env
+            ),
+            "(#t #t)"
+        );
+        assert_eq!(
+            do_lisp_env("(quasiquote (1 (quasiquote (unquote (unquote a)))))", &env),
+            "(1 (quasiquote (unquote 10)))"
+        );
+    }
+    #[test]
     fn do_f() {
         assert_eq!(do_lisp("(do ((i 0 (+ i 1)))((= i 10) i))"), "10");
         assert_eq!(
@@ -819,12 +1594,33 @@ mod error_tests {
     use crate::lisp;
     use crate::{do_lisp, do_lisp_env};
 
+    #[test]
+    fn dynamic_wind() {
+        assert_eq!(do_lisp("(dynamic-wind)"), "E1007");
+        assert_eq!(
+            do_lisp("(dynamic-wind (lambda () 1) (lambda () 2))"),
+            "E1007"
+        );
+        assert_eq!(
+            do_lisp("(dynamic-wind 1 (lambda () 2) (lambda () 3))"),
+            "E1006"
+        );
+        assert_eq!(
+            do_lisp("(dynamic-wind (lambda () 1) (lambda (a) a) (lambda () 3))"),
+            "E1007"
+        );
+        assert_eq!(
+            do_lisp("(dynamic-wind (lambda () 1) (lambda () 2) (lambda () a))"),
+            "E1008"
+        );
+    }
     #[test]
     fn callcc() {
         assert_eq!(do_lisp("(call/cc)"), "E1007");
         assert_eq!(do_lisp("(call/cc (lambda (c) 10) 10)"), "E1007");
         assert_eq!(do_lisp("(call/cc (lambda () 10))"), "E1007");
         assert_eq!(do_lisp("(call/cc 100)"), "E1006");
+        assert_eq!(do_lisp("(call/cc (lambda (k) (k 1 2)))"), "E1007");
     }
     #[test]
     fn define() {
@@ -836,6 +1632,7 @@ mod error_tests {
         assert_eq!(do_lisp_env("(define (hoge a 1) (+ 100 a))", &env), "E1004");
         assert_eq!(do_lisp_env("(define (hoge 1 a) (+ 100 a))", &env), "E1004");
         assert_eq!(do_lisp_env("(define (100 a b) (+ 100 a))", &env), "E1004");
+        assert_eq!(do_lisp_env("(define (hoge a . 1) a)", &env), "E1004");
         assert_eq!(do_lisp_env("(define () (+ 100 a))", &env), "E1007");
 
         assert_eq!(do_lisp_env("(define a ga)", &env), "E1008");
@@ -845,7 +1642,21 @@ mod error_tests {
         let env = lisp::Environment::new();
         assert_eq!(do_lisp_env("(lambda)", &env), "E1007");
         assert_eq!(do_lisp_env("(lambda (a b))", &env), "E1007");
-        assert_eq!(do_lisp_env("(lambda  a (+ a b))", &env), "E1005");
+        assert_eq!(do_lisp_env("(lambda 1 (+ a b))", &env), "E1005");
+        assert_eq!(do_lisp_env("(lambda (a .) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("(lambda (a . b c) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("(lambda (a . 1) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("(lambda (a #!foo b) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("(lambda (#!optional (a)) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("(lambda (#!optional 1) a)", &env), "E1004");
+        assert_eq!(do_lisp_env("((lambda (a . b) a))", &env), "E1007");
+        assert_eq!(
+            do_lisp_env("((lambda (a #!optional b) a) 1 2 3)", &env),
+            "E1007"
+        );
+        assert_eq!(do_lisp_env("((lambda (#!key a) a) :a)", &env), "E1007");
+        assert_eq!(do_lisp_env("((lambda (#!key a) a) 1 2)", &env), "E1004");
+        assert_eq!(do_lisp_env("((lambda (#!key a) a) :b 2)", &env), "E1004");
         assert_eq!(do_lisp_env("(lambda (a 1) (+ a 10))", &env), "E1004");
         assert_eq!(do_lisp_env("((list 1) 10)", &env), "E1006");
 
@@ -856,6 +1667,14 @@ mod error_tests {
         assert_eq!(do_lisp_env("(hoge 10 20)", &env), "E1008");
     }
     #[test]
+    fn case_lambda() {
+        assert_eq!(do_lisp("(case-lambda 1)"), "E1005");
+        assert_eq!(do_lisp("(case-lambda ((a)))"), "E1007");
+        assert_eq!(do_lisp("(case-lambda ((a 1) a))"), "E1004");
+        assert_eq!(do_lisp("((case-lambda ((a) a) ((a b) b)))"), "E1007");
+        assert_eq!(do_lisp("((case-lambda))"), "E1007");
+    }
+    #[test]
     fn let_f() {
         assert_eq!(do_lisp("(let)"), "E1007");
         assert_eq!(do_lisp("(let loop)"), "E1007");
@@ -870,6 +1689,57 @@ mod error_tests {
         );
     }
     #[test]
+    fn let_star() {
+        assert_eq!(do_lisp("(let*)"), "E1007");
+        assert_eq!(do_lisp("(let* ((a 1)))"), "E1007");
+        assert_eq!(do_lisp("(let* 10 1)"), "E1005");
+        assert_eq!(do_lisp("(let* ((a 1 2)) a)"), "E1007");
+        assert_eq!(do_lisp("(let* ((a 1) (b c)) a)"), "E1008");
+    }
+    #[test]
+    fn letrec() {
+        assert_eq!(do_lisp("(letrec)"), "E1007");
+        assert_eq!(do_lisp("(letrec 10 1)"), "E1005");
+        assert_eq!(do_lisp("(letrec (10) 1)"), "E1005");
+        assert_eq!(do_lisp("(letrec ((a)) 1)"), "E1007");
+        assert_eq!(do_lisp("(letrec ((10 1)) 1)"), "E1004");
+        assert_eq!(do_lisp("(letrec* ((a b)) 1)"), "E1008");
+    }
+    #[test]
+    fn values() {
+        assert_eq!(do_lisp("(values a)"), "E1008");
+        assert_eq!(do_lisp("(call-with-values)"), "E1007");
+        assert_eq!(do_lisp("(call-with-values list)"), "E1007");
+        assert_eq!(do_lisp("(call-with-values 10 list)"), "E1006");
+        assert_eq!(
+            do_lisp("(call-with-values (lambda () (values 1 2)) (lambda (a) a))"),
+            "E1007"
+        );
+    }
+    #[test]
+    fn let_values() {
+        assert_eq!(do_lisp("(let-values)"), "E1007");
+        assert_eq!(do_lisp("(let-values 10 1)"), "E1005");
+        assert_eq!(do_lisp("(let-values (((a) 1 2)) a)"), "E1007");
+        assert_eq!(do_lisp("(let-values ((10 1)) 1)"), "E1005");
+        assert_eq!(do_lisp("(let-values (((a b) (values 1))) a)"), "E1007");
+        assert_eq!(do_lisp("(let*-values)"), "E1007");
+        assert_eq!(do_lisp("(let*-values (((a #!optional b) 1)) a)"), "E1004");
+    }
+    #[test]
+    fn define_values() {
+        assert_eq!(do_lisp("(define-values (a b))"), "E1007");
+        assert_eq!(do_lisp("(define-values (a b) (values 1 2) 3)"), "E1007");
+        assert_eq!(do_lisp("(define-values (a 10) (values 1 2))"), "E1004");
+        assert_eq!(do_lisp("(define-values (a b) (values 1))"), "E1007");
+    }
+    #[test]
+    fn receive() {
+        assert_eq!(do_lisp("(receive (a) (values 1))"), "E1007");
+        assert_eq!(do_lisp("(receive 10 (values 1) 1)"), "E1005");
+        assert_eq!(do_lisp("(receive (a) (values 1 2) a)"), "E1007");
+    }
+    #[test]
     fn set_f() {
         let env = lisp::Environment::new();
         assert_eq!(do_lisp_env("(set!)", &env), "E1007");
@@ -929,6 +1799,25 @@ mod error_tests {
         assert_eq!(do_lisp("(apply hoge (list 1 2))"), "E1008");
     }
     #[test]
+    fn eval_f() {
+        assert_eq!(do_lisp("(eval)"), "E1007");
+        assert_eq!(do_lisp("(eval 1)"), "E1007");
+        assert_eq!(do_lisp("(eval 1 (interaction-environment) 2)"), "E1007");
+        assert_eq!(do_lisp("(eval 1 2)"), "E1029");
+        assert_eq!(do_lisp("(eval a (interaction-environment))"), "E1008");
+        assert_eq!(do_lisp("(eval 'a (interaction-environment))"), "E1008");
+        assert_eq!(do_lisp("(interaction-environment 1)"), "E1007");
+        assert_eq!(do_lisp("(scheme-report-environment)"), "E1007");
+        assert_eq!(do_lisp("(scheme-report-environment 6)"), "E1021");
+        assert_eq!(do_lisp("(null-environment \"7\")"), "E1002");
+    }
+    #[test]
+    fn make_environment() {
+        assert_eq!(do_lisp("(make-environment 1 2)"), "E1007");
+        assert_eq!(do_lisp("(make-environment 1)"), "E1029");
+        assert_eq!(do_lisp("(environment?)"), "E1007");
+    }
+    #[test]
     fn delay_force() {
         assert_eq!(do_lisp("(delay)"), "E1007");
         assert_eq!(do_lisp("(delay 1 2)"), "E1007");
@@ -942,6 +1831,15 @@ mod error_tests {
         assert_eq!(do_lisp("(quote 1 2)"), "E1007");
     }
     #[test]
+    fn quasiquote() {
+        assert_eq!(do_lisp("(quasiquote)"), "E1007");
+        assert_eq!(do_lisp("(quasiquote 1 2)"), "E1007");
+        assert_eq!(do_lisp("`(1 ,b)"), "E1008");
+        assert_eq!(do_lisp("`(1 ,@b)"), "E1008");
+        assert_eq!(do_lisp("`(1 ,@2)"), "E1005");
+        assert_eq!(do_lisp(",a"), "E1008");
+    }
+    #[test]
     fn do_f() {
         assert_eq!(do_lisp("(do)"), "E1007");
         assert_eq!(do_lisp("(do 1 2)"), "E1005");
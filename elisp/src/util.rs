@@ -17,6 +17,7 @@ use crate::lisp::eval;
 use crate::lisp::{Environment, Expression, Int, ResultExpression};
 use crate::lisp::{ErrCode, Error};
 use crate::number::Number;
+use crate::symbol::Symbol;
 
 pub fn create_function<T>(b: &mut T)
 where
@@ -39,12 +40,18 @@ where
     b.regist("vector?", |exp, env| {
         is_type(exp, env, Expression::is_vector)
     });
+    b.regist("bytevector?", |exp, env| {
+        is_type(exp, env, Expression::is_bytevector)
+    });
     b.regist("hash-table?", |exp, env| {
         is_type(exp, env, Expression::is_hashtable)
     });
     b.regist("tree-map?", |exp, env| {
         is_type(exp, env, Expression::is_tree_map)
     });
+    b.regist("environment?", |exp, env| {
+        is_type(exp, env, Expression::is_environment)
+    });
     b.regist("char?", |exp, env| is_type(exp, env, Expression::is_char));
     b.regist("string?", |exp, env| {
         is_type(exp, env, Expression::is_string)
@@ -53,11 +60,41 @@ where
         is_type(exp, env, Expression::is_procedure)
     });
     b.regist("integer?", |exp, env| {
-        is_type(exp, env, Expression::is_integer)
+        is_number_type(exp, env, Number::is_integer)
+    });
+    b.regist("rational?", |exp, env| {
+        is_number_type(exp, env, |x| x.is_real() && x.to_f64().is_finite())
+    });
+    b.regist("real?", |exp, env| {
+        is_number_type(exp, env, Number::is_real)
     });
+    b.regist("complex?", |exp, env| is_number_type(exp, env, |_| true));
     b.regist("number?", |exp, env| {
         is_type(exp, env, Expression::is_number)
     });
+    b.regist("exact-integer?", |exp, env| {
+        is_number_type(exp, env, |x| x.is_exact() && x.is_integer())
+    });
+    b.regist("exact?", |exp, env| is_sign(exp, env, Number::is_exact));
+    b.regist("inexact?", |exp, env| is_sign(exp, env, |x| !x.is_exact()));
+    b.regist("nan?", |exp, env| {
+        is_sign(exp, env, |x| {
+            let (a, b) = x.to_complex();
+            a.is_nan() || b.is_nan()
+        })
+    });
+    b.regist("infinite?", |exp, env| {
+        is_sign(exp, env, |x| {
+            let (a, b) = x.to_complex();
+            a.is_infinite() || b.is_infinite()
+        })
+    });
+    b.regist("finite?", |exp, env| {
+        is_sign(exp, env, |x| {
+            let (a, b) = x.to_complex();
+            a.is_finite() && b.is_finite()
+        })
+    });
     b.regist("boolean?", |exp, env| {
         is_type(exp, env, Expression::is_boolean)
     });
@@ -67,6 +104,7 @@ where
     b.regist("time", time_f);
     b.regist("eq?", eq);
     b.regist("eqv?", eqv);
+    b.regist("equal?", equal);
     b.regist("identity", identity);
     b.regist("get-environment-variable", get_env);
     b.regist("native-endian", native_endian);
@@ -87,6 +125,7 @@ fn odd_even(exp: &[Expression], env: &Environment, func: fn(Int) -> bool) -> Res
     }
     match eval(&exp[1], env)? {
         Expression::Integer(i) => Ok(Expression::Boolean(func(i))),
+        Expression::BigInt(i) => Ok(Expression::Boolean(func(i.bit(0) as Int))),
         e => Err(create_error_value!(ErrCode::E1002, e)),
     }
 }
@@ -98,6 +137,20 @@ fn is_sign(exp: &[Expression], env: &Environment, func: fn(&Number) -> bool) ->
 
     Ok(Expression::Boolean(func(&v)))
 }
+// The value which is not number, is false.
+fn is_number_type(
+    exp: &[Expression],
+    env: &Environment,
+    func: fn(&Number) -> bool,
+) -> ResultExpression {
+    if 2 != exp.len() {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    match Expression::to_number(&eval(&exp[1], env)?) {
+        Ok(v) => Ok(Expression::Boolean(func(&v))),
+        Err(_) => Ok(Expression::Boolean(false)),
+    }
+}
 fn is_type(
     exp: &[Expression],
     env: &Environment,
@@ -148,15 +201,22 @@ pub fn eqv(exp: &[Expression], env: &Environment) -> ResultExpression {
     let (a, b) = (eval(&exp[1], env)?, eval(&exp[2], env)?);
     Ok(Expression::Boolean(Expression::eqv(&a, &b)))
 }
+pub fn equal(exp: &[Expression], env: &Environment) -> ResultExpression {
+    if exp.len() != 3 {
+        return Err(create_error_value!(ErrCode::E1007, exp.len()));
+    }
+    let (a, b) = (eval(&exp[1], env)?, eval(&exp[2], env)?);
+    Ok(Expression::Boolean(Expression::equal(&a, &b)))
+}
 fn native_endian(exp: &[Expression], _env: &Environment) -> ResultExpression {
     if exp.len() != 1 {
         return Err(create_error_value!(ErrCode::E1007, exp.len()));
     }
     if cfg!(target_endian = "big") {
-        return Ok(Expression::Symbol("big-endian".to_string()));
+        return Ok(Expression::Symbol(Symbol::from("big-endian")));
     }
     if cfg!(target_endian = "little") {
-        return Ok(Expression::Symbol("little-endian".to_string()));
+        return Ok(Expression::Symbol(Symbol::from("little-endian")));
     }
     Err(create_error!(ErrCode::E9999))
 }
@@ -179,6 +239,8 @@ mod tests {
         assert_eq!(do_lisp("(even? 0)"), "#t");
         assert_eq!(do_lisp("(even? 1)"), "#f");
         assert_eq!(do_lisp("(even? 5)"), "#f");
+        assert_eq!(do_lisp("(even? (expt 2 100))"), "#t");
+        assert_eq!(do_lisp("(even? (+ (expt 2 100) 1))"), "#f");
     }
     #[test]
     fn odd() {
@@ -187,6 +249,7 @@ mod tests {
         assert_eq!(do_lisp("(odd? 0)"), "#f");
         assert_eq!(do_lisp("(odd? 1)"), "#t");
         assert_eq!(do_lisp("(odd? 5)"), "#t");
+        assert_eq!(do_lisp("(odd? (- 1 (expt 2 100)))"), "#t");
     }
     #[test]
     fn zero() {
@@ -226,10 +289,13 @@ mod tests {
     fn list_f() {
         assert_eq!(do_lisp("(list? (list 1 2 3))"), "#t");
         assert_eq!(do_lisp("(list? 90)"), "#f");
+        assert_eq!(do_lisp("(list? '())"), "#t");
+        assert_eq!(do_lisp("(list? (cons 1 2))"), "#f");
     }
     #[test]
     fn vector_f() {
         assert_eq!(do_lisp("(vector? #(1 2 3))"), "#t");
+        assert_eq!(do_lisp("(vector? '#(1 2))"), "#t");
         assert_eq!(do_lisp("(vector? 90)"), "#f");
     }
     #[test]
@@ -246,6 +312,8 @@ mod tests {
     fn pair_f() {
         assert_eq!(do_lisp("(pair? (cons 1 2))"), "#t");
         assert_eq!(do_lisp("(pair? 110)"), "#f");
+        assert_eq!(do_lisp("(pair? '(1 2))"), "#t");
+        assert_eq!(do_lisp("(pair? '())"), "#f");
     }
     #[test]
     fn char_f() {
@@ -260,6 +328,10 @@ mod tests {
     #[test]
     fn integer_f() {
         assert_eq!(do_lisp("(integer? 10)"), "#t");
+        assert_eq!(do_lisp("(integer? 10.0)"), "#t");
+        assert_eq!(do_lisp("(integer? 10.5)"), "#f");
+        assert_eq!(do_lisp("(integer? 1/2)"), "#f");
+        assert_eq!(do_lisp("(integer? +inf.0)"), "#f");
         assert_eq!(do_lisp("(integer? \"a\")"), "#f");
     }
     #[test]
@@ -267,9 +339,56 @@ mod tests {
         assert_eq!(do_lisp("(number? 10)"), "#t");
         assert_eq!(do_lisp("(number? 10.5)"), "#t");
         assert_eq!(do_lisp("(number? 1/3)"), "#t");
+        assert_eq!(do_lisp("(number? 1+2i)"), "#t");
         assert_eq!(do_lisp("(number? \"a\")"), "#f");
     }
     #[test]
+    fn rational_f() {
+        assert_eq!(do_lisp("(rational? 1/3)"), "#t");
+        assert_eq!(do_lisp("(rational? 0.5)"), "#t");
+        assert_eq!(do_lisp("(rational? +nan.0)"), "#f");
+        assert_eq!(do_lisp("(rational? 1+2i)"), "#f");
+        assert_eq!(do_lisp("(rational? \"a\")"), "#f");
+    }
+    #[test]
+    fn real_f() {
+        assert_eq!(do_lisp("(real? 1/3)"), "#t");
+        assert_eq!(do_lisp("(real? -inf.0)"), "#t");
+        assert_eq!(do_lisp("(real? 1+2i)"), "#f");
+        assert_eq!(do_lisp("(real? \"a\")"), "#f");
+    }
+    #[test]
+    fn complex_f() {
+        assert_eq!(do_lisp("(complex? 1)"), "#t");
+        assert_eq!(do_lisp("(complex? 1+2i)"), "#t");
+        assert_eq!(do_lisp("(complex? \"a\")"), "#f");
+    }
+    #[test]
+    fn exact_f() {
+        assert_eq!(do_lisp("(exact? 1/3)"), "#t");
+        assert_eq!(do_lisp("(exact? (expt 2 100))"), "#t");
+        assert_eq!(do_lisp("(exact? 0.5)"), "#f");
+        assert_eq!(do_lisp("(exact? +i)"), "#f");
+        assert_eq!(do_lisp("(inexact? 0.5)"), "#t");
+        assert_eq!(do_lisp("(inexact? 1)"), "#f");
+    }
+    #[test]
+    fn exact_integer_f() {
+        assert_eq!(do_lisp("(exact-integer? 10)"), "#t");
+        assert_eq!(do_lisp("(exact-integer? 10.0)"), "#f");
+        assert_eq!(do_lisp("(exact-integer? \"a\")"), "#f");
+    }
+    #[test]
+    fn nan_f() {
+        assert_eq!(do_lisp("(nan? +nan.0)"), "#t");
+        assert_eq!(do_lisp("(nan? 1.0)"), "#f");
+        assert_eq!(do_lisp("(infinite? -inf.0)"), "#t");
+        assert_eq!(do_lisp("(infinite? 1+inf.0i)"), "#t");
+        assert_eq!(do_lisp("(infinite? 1/2)"), "#f");
+        assert_eq!(do_lisp("(finite? 1/2)"), "#t");
+        assert_eq!(do_lisp("(finite? +nan.0)"), "#f");
+    }
+    #[test]
     fn symbol_f() {
         assert_eq!(do_lisp("(symbol? 'a)"), "#t");
         assert_eq!(do_lisp("(symbol? \"a\")"), "#f");
@@ -322,6 +441,57 @@ mod tests {
         assert_eq!(do_lisp_env("(eq? a b)", &env), "#t");
     }
     #[test]
+    fn equal() {
+        assert_eq!(do_lisp("(equal? 1 1)"), "#t");
+        assert_eq!(do_lisp("(equal? 1 1.0)"), "#f");
+        assert_eq!(do_lisp("(equal? 'a 'a)"), "#t");
+        assert_eq!(do_lisp("(equal? \"abc\" \"abc\")"), "#t");
+        assert_eq!(do_lisp("(equal? \"abc\" \"abd\")"), "#f");
+        assert_eq!(
+            do_lisp("(equal? (list 1 2 (list 3)) (list 1 2 (list 3)))"),
+            "#t"
+        );
+        assert_eq!(
+            do_lisp("(equal? (list 1 2 (list 3)) (list 1 2 (list 4)))"),
+            "#f"
+        );
+        assert_eq!(do_lisp("(equal? (list 1 2) (list 1 2 3))"), "#f");
+        assert_eq!(do_lisp("(equal? (cons 1 2) (cons 1 2))"), "#t");
+        assert_eq!(do_lisp("(equal? (cons 1 2) (cons 1 3))"), "#f");
+        assert_eq!(do_lisp("(equal? (vector 1 \"a\") (vector 1 \"a\"))"), "#t");
+        assert_eq!(do_lisp("(equal? (vector 1 2) (list 1 2))"), "#f");
+        assert_eq!(do_lisp("(equal? '() '())"), "#t");
+
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (make-hash-table))", &env);
+        do_lisp_env("(define b (make-hash-table))", &env);
+        do_lisp_env("(hash-table-put! a 'k (list 1 2))", &env);
+        assert_eq!(do_lisp_env("(equal? a b)", &env), "#f");
+        do_lisp_env("(hash-table-put! b 'k (list 1 2))", &env);
+        assert_eq!(do_lisp_env("(equal? a b)", &env), "#t");
+
+        do_lisp_env("(define a (make-tree-map))", &env);
+        do_lisp_env("(define b (make-tree-map))", &env);
+        do_lisp_env("(tree-map-put! a 'k \"v\")", &env);
+        do_lisp_env("(tree-map-put! b 'k \"v\")", &env);
+        assert_eq!(do_lisp_env("(equal? a b)", &env), "#t");
+        do_lisp_env("(tree-map-put! b 'j \"v\")", &env);
+        assert_eq!(do_lisp_env("(equal? a b)", &env), "#f");
+    }
+    #[test]
+    fn equal_cyclic() {
+        let env = lisp::Environment::new();
+        do_lisp_env("(define a (list 1 2))", &env);
+        do_lisp_env("(define b (list 1 2))", &env);
+        do_lisp_env("(list-set! a 1 a)", &env);
+        do_lisp_env("(list-set! b 1 b)", &env);
+        assert_eq!(do_lisp_env("(equal? a b)", &env), "#t");
+        assert_eq!(do_lisp_env("(equal? a a)", &env), "#t");
+        do_lisp_env("(define c (list 3 2))", &env);
+        do_lisp_env("(list-set! c 1 c)", &env);
+        assert_eq!(do_lisp_env("(equal? a c)", &env), "#f");
+    }
+    #[test]
     fn identity() {
         assert_eq!(do_lisp("(identity (+ 1 2 3))"), "6");
         assert_eq!(do_lisp("(identity ((lambda (a b) (+ a b)) 1 2))"), "3");
@@ -460,6 +630,26 @@ mod error_tests {
         assert_eq!(do_lisp("(number? a)"), "E1008");
     }
     #[test]
+    fn rational_f() {
+        assert_eq!(do_lisp("(rational?)"), "E1007");
+        assert_eq!(do_lisp("(real? 10 20)"), "E1007");
+        assert_eq!(do_lisp("(complex? a)"), "E1008");
+    }
+    #[test]
+    fn exact_f() {
+        assert_eq!(do_lisp("(exact?)"), "E1007");
+        assert_eq!(do_lisp("(inexact? 10 20)"), "E1007");
+        assert_eq!(do_lisp("(exact? #t)"), "E1003");
+        assert_eq!(do_lisp("(exact-integer? a)"), "E1008");
+    }
+    #[test]
+    fn nan_f() {
+        assert_eq!(do_lisp("(nan?)"), "E1007");
+        assert_eq!(do_lisp("(infinite? 10 20)"), "E1007");
+        assert_eq!(do_lisp("(finite? #t)"), "E1003");
+        assert_eq!(do_lisp("(nan? a)"), "E1008");
+    }
+    #[test]
     fn symbol_f() {
         assert_eq!(do_lisp("(symbol?)"), "E1007");
         assert_eq!(do_lisp("(symbol? 10 20)"), "E1007");
@@ -489,6 +679,12 @@ mod error_tests {
         assert_eq!(do_lisp("(eq? a 10)"), "E1008");
     }
     #[test]
+    fn equal() {
+        assert_eq!(do_lisp("(equal?)"), "E1007");
+        assert_eq!(do_lisp("(equal? 10 10 10)"), "E1007");
+        assert_eq!(do_lisp("(equal? 10 a)"), "E1008");
+    }
+    #[test]
     fn identity() {
         assert_eq!(do_lisp("(identity)"), "E1007");
         assert_eq!(do_lisp("(identity 10 20)"), "E1007");
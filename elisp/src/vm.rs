@@ -0,0 +1,332 @@
+/*
+   Rust study program.
+   This is prototype program mini scheme subset what porting from go-scheme.
+
+   hidekuno@gmail.com
+*/
+use std::vec::Vec;
+
+use crate::compile::{Code, Next, Op};
+use crate::create_error;
+use crate::create_error_value;
+use crate::lisp::{CodeRc, Environment, ErrCode, Error, Expression, FunctionRc, ListRc};
+use crate::machine::{bound, check, is_native, lookup, Active, SYNTAX};
+
+// The request to the machine.
+pub(crate) enum Yield {
+    Return(Expression),
+    Apply(Expression, Vec<Expression>, Next),
+    Eval(Expression, Environment, Next),
+}
+// The activation of the compiled function.
+#[derive(Clone)]
+struct Frame {
+    code: CodeRc,
+    pc: usize,
+    // the local variables are stack[base..base + slots]
+    base: usize,
+    env: Environment,
+    proc: Active,
+    // the returned value is checked as boolean (the last of and, or)
+    bool: bool,
+}
+// The compiled functions which call each other, they run on the one stack.
+#[derive(Clone)]
+pub(crate) struct Vm {
+    // the last one is running
+    frames: Vec<Frame>,
+    stack: Vec<Expression>,
+}
+impl Vm {
+    pub(crate) fn new(
+        f: &FunctionRc,
+        code: &CodeRc,
+        args: Vec<Expression>,
+        proc: Active,
+    ) -> Result<Self, Error> {
+        let mut vm = Vm {
+            frames: Vec::new(),
+            stack: args,
+        };
+        vm.enter(f, code.clone(), 0, proc, false)?;
+        Ok(vm)
+    }
+    // The finished vm is used for the next call, not to allocate the stacks again.
+    pub(crate) fn call(
+        &mut self,
+        f: &FunctionRc,
+        code: &CodeRc,
+        args: Vec<Expression>,
+        proc: Active,
+    ) -> Result<(), Error> {
+        self.stack.extend(args);
+        self.enter(f, code.clone(), 0, proc, false)
+    }
+    // The arguments on the stack become the local variables of the function.
+    fn enter(
+        &mut self,
+        f: &FunctionRc,
+        code: CodeRc,
+        base: usize,
+        proc: Active,
+        bool: bool,
+    ) -> Result<(), Error> {
+        let n = self.stack.len() - base;
+        if !f.is_acceptable(n) {
+            return Err(f.arity_error(n));
+        }
+        let param = f.param();
+        if param.rest().is_some() {
+            let rest = self.stack.split_off(base + param.required().len());
+            self.stack.push(Environment::create_list(rest));
+        }
+        self.stack.resize(base + code.slots, Expression::Nil());
+        self.frames.push(Frame {
+            code,
+            pc: 0,
+            base,
+            env: f.closure_env().clone(),
+            proc,
+            bool,
+        });
+        Ok(())
+    }
+    // The running function is finished, false if no caller is in the vm.
+    pub(crate) fn leave(&mut self) -> bool {
+        if let Some(f) = self.frames.pop() {
+            self.stack.truncate(f.base);
+        }
+        !self.frames.is_empty()
+    }
+    // The innermost form of the last op, used for the error report.
+    pub(crate) fn form(&self) -> Option<ListRc> {
+        Self::frame_form(self.frames.last()?)
+    }
+    fn frame_form(f: &Frame) -> Option<ListRc> {
+        f.code.forms.get(f.pc.checked_sub(1)?)?.clone()
+    }
+    // The running functions and their forms, the innermost first.
+    pub(crate) fn trace(&self) -> Vec<(Option<ListRc>, Option<&Active>)> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|f| (Self::frame_form(f), Some(&f.proc)))
+            .collect()
+    }
+    pub(crate) fn proc(&self) -> Option<Active> {
+        self.frames.last().map(|f| f.proc.clone())
+    }
+    pub(crate) fn env(&self) -> &Environment {
+        &self.frames.last().unwrap().env
+    }
+    pub(crate) fn is_bool(&self) -> bool {
+        self.frames.last().is_some_and(|f| f.bool)
+    }
+    pub(crate) fn push(&mut self, v: Expression) {
+        self.stack.push(v);
+    }
+    fn pop(&mut self) -> Expression {
+        self.stack.pop().unwrap()
+    }
+    // The environment of the syntax which is passed as the value, ex. (define (f g) (g ...)) (f if)
+    // the local variables are copied because they are not changed in the compiled function.
+    fn snapshot(&self, code: &Code, scope: usize, base: usize, env: &Environment) -> Environment {
+        let e = Environment::with_parent(env);
+        for (s, i) in &code.scopes[scope] {
+            e.regist(s.clone(), self.stack[base + i].clone());
+        }
+        e
+    }
+    pub(crate) fn run(&mut self, calls: &mut usize) -> Result<Yield, Error> {
+        loop {
+            let f = self.frames.last().unwrap();
+            let code = f.code.clone();
+            let env = f.env.clone();
+            let mut pc = f.pc;
+
+            match self.exec(&code, &env, &mut pc, calls) {
+                Ok(Some(y)) => return Ok(y),
+                Ok(None) => {}
+                Err(e) => {
+                    if let Some(f) = self.frames.last_mut() {
+                        f.pc = pc;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+    // Run the ops of the function, None is returned if the running function is changed.
+    fn exec(
+        &mut self,
+        code: &Code,
+        env: &Environment,
+        pc: &mut usize,
+        calls: &mut usize,
+    ) -> Result<Option<Yield>, Error> {
+        let base = self.frames.last().unwrap().base;
+        let values = env.evaluated(true);
+        loop {
+            let op = &code.ops[*pc];
+            *pc += 1;
+
+            match op {
+                Op::Const(e) => self.push(e.clone()),
+                Op::Local(i) => self.push(self.stack[base + i].clone()),
+                Op::Free(s) => self.push(lookup(s, env)?),
+                Op::Bound(s) => {
+                    if bound(s, env).is_none() {
+                        return Err(create_error_value!(ErrCode::E1008, s));
+                    }
+                }
+                Op::SetFree(s) => {
+                    let v = self.pop();
+                    if let Some((name, env)) = bound(s, env) {
+                        env.update_symbol(&name, v);
+                    }
+                    self.push(Expression::Symbol(s.clone()));
+                }
+                Op::Bind(i) => self.stack[base + i] = self.pop(),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Jump(t) => *pc = *t,
+                Op::If(t) => match self.pop() {
+                    Expression::Boolean(true) => {}
+                    Expression::Boolean(false) => *pc = *t,
+                    _ => return Err(create_error!(ErrCode::E1001)),
+                },
+                Op::Logic(and, t) => match self.pop() {
+                    Expression::Boolean(b) if b != *and => {
+                        self.push(Expression::Boolean(b));
+                        *pc = *t;
+                    }
+                    Expression::Boolean(_) => {}
+                    _ => return Err(create_error!(ErrCode::E1001)),
+                },
+                Op::Bool => {
+                    if !matches!(self.stack.last(), Some(Expression::Boolean(_))) {
+                        return Err(create_error!(ErrCode::E1001));
+                    }
+                }
+                Op::Lambda(l) => {
+                    let e = if l.captures.is_empty() && l.name.is_none() {
+                        env.clone()
+                    } else {
+                        Environment::with_parent(env)
+                    };
+                    for (s, i) in &l.captures {
+                        e.regist(s.clone(), self.stack[base + i].clone());
+                    }
+                    let f = Environment::create_func(l.func.with_env(e.clone()));
+                    if let Some(s) = &l.name {
+                        e.regist(s.clone(), f.clone());
+                    }
+                    self.push(f);
+                }
+                Op::Check(n, form, scope, t, next) => match self.stack.last().unwrap() {
+                    Expression::Function(f) => {
+                        if !f.is_acceptable(*n) {
+                            return Err(f.arity_error(*n));
+                        }
+                    }
+                    Expression::Continuation(_) => {
+                        if *n != 1 {
+                            return Err(create_error_value!(ErrCode::E1007, n + 1));
+                        }
+                    }
+                    Expression::BuildInFunction(name, _) if !SYNTAX.contains(name) => {}
+                    Expression::BuildInFunction(_, _) | Expression::Syntax(_) => {
+                        self.pop();
+                        *pc = *t;
+                        self.frames.last_mut().unwrap().pc = *pc;
+                        let e = self.snapshot(code, *scope, base, env);
+                        return Ok(Some(Yield::Eval(form.clone(), e, *next)));
+                    }
+                    Expression::BuildInFunctionExt(_) => {}
+                    Expression::Parameter(_) => {
+                        if *n != 0 {
+                            return Err(create_error_value!(ErrCode::E1007, n + 1));
+                        }
+                    }
+                    e => return Err(create_error_value!(ErrCode::E1006, e)),
+                },
+                Op::Builtin(f, n) => {
+                    // called with (f a b ...) on the stack, they are the values.
+                    let b = self.stack.len() - n - 1;
+                    let r = f(&self.stack[b..], &values)?;
+                    self.stack.truncate(b);
+                    self.push(r);
+                }
+                Op::Arith(a, f) => {
+                    let b = self.stack.len() - 3;
+                    let r = match (&self.stack[b + 1], &self.stack[b + 2]) {
+                        (Expression::Integer(x), Expression::Integer(y)) => a.calc(*x, *y),
+                        _ => None,
+                    };
+                    let r = match r {
+                        Some(r) => r,
+                        None => f(&self.stack[b..], &values)?,
+                    };
+                    self.stack.truncate(b);
+                    self.push(r);
+                }
+                Op::Call(n, next) => {
+                    let b = self.stack.len() - n - 1;
+                    match &self.stack[b] {
+                        Expression::Function(f) if env.is_bytecode() => {
+                            if let Some(c) = f.code() {
+                                let (f, c) = (f.clone(), c.clone());
+                                if !f.is_acceptable(*n) {
+                                    return Err(f.arity_error(*n));
+                                }
+                                check(env)?;
+                                *calls += 1;
+                                let proc = Active::new(&f, *calls);
+                                let (b, bool) = match next {
+                                    Next::Push => {
+                                        self.frames.last_mut().unwrap().pc = *pc;
+                                        self.stack.remove(b);
+                                        (b, false)
+                                    }
+                                    // tail call, this activation is replaced.
+                                    _ => {
+                                        let bool = *next == Next::ReturnBool || self.is_bool();
+                                        self.stack.drain(base..=b);
+                                        self.frames.pop();
+                                        (base, bool)
+                                    }
+                                };
+                                self.enter(&f, c, b, proc, bool)?;
+                                return Ok(None);
+                            }
+                        }
+                        Expression::BuildInFunction(name, f) if !is_native(name) => {
+                            let f = *f;
+                            let r = f(&self.stack[b..], &values)?;
+                            self.stack.truncate(b);
+                            self.push(r);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    let args = self.stack.split_off(b + 1);
+                    let func = self.pop();
+                    self.frames.last_mut().unwrap().pc = *pc;
+                    return Ok(Some(Yield::Apply(func, args, *next)));
+                }
+                Op::Return => {
+                    let v = self.pop();
+                    if self.is_bool() && !matches!(v, Expression::Boolean(_)) {
+                        return Err(create_error!(ErrCode::E1001));
+                    }
+                    if !self.leave() {
+                        return Ok(Some(Yield::Return(v)));
+                    }
+                    self.push(v);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
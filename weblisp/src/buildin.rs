@@ -12,6 +12,7 @@ use chrono::Utc;
 use elisp::create_error;
 use elisp::create_error_value;
 use elisp::lisp;
+use elisp::pair;
 use elisp::reference_obj;
 use lisp::eval;
 use lisp::Environment;
@@ -112,18 +113,17 @@ fn get_key_value(exp: &[Expression], env: &Environment, idx: usize) -> ResultExp
     if l.len() != REQUEST_COLUMNS {
         return Err(create_error!(ErrCode::E1021));
     }
-    let l = match &l[idx] {
-        Expression::List(l) => l,
-        e => return Err(create_error_value!(ErrCode::E1005, e)),
+    let l = match pair::to_vec(&l[idx]) {
+        Some(l) => l,
+        None => return Err(create_error_value!(ErrCode::E1005, l[idx].clone())),
     };
-    let l = &*(reference_obj!(l));
-    for v in l {
+    for v in &l {
         match eval(v, env)? {
-            Expression::Pair(car, cdr) => match *car {
+            p @ Expression::Pair(_) => match pair::car(&p) {
                 Expression::String(s) => {
                     if s == key {
-                        match *cdr {
-                            Expression::String(_) => return Ok(*cdr),
+                        match pair::cdr(&p) {
+                            e @ Expression::String(_) => return Ok(e),
                             e => return Err(create_error_value!(ErrCode::E1015, e)),
                         }
                     }
@@ -214,7 +214,7 @@ fn init() -> Environment {
 #[cfg(test)]
 fn create_data<'a>() -> &'a str {
     "
-#(\"GET\"
+(vector \"GET\"
   (list (cons \"Host\" \"www.mukogawa.or.jp\")(cons \"User-Agent\" \"rust\"))
   (list (cons \"Value1\" \"10\")(cons \"Value2\" \"20\"))
   \"/test.scm\"
@@ -341,21 +341,21 @@ mod error_tests {
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-header \"User-Agent\" #(1 (list 10) 10 10 10))",
+                "(web-get-header \"User-Agent\" (vector 1 (list 10) 10 10 10))",
                 &env
             ),
             "E1005"
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-header \"User-Agent\" #(1 (list (cons 10 20)) 10 10 10))",
+                "(web-get-header \"User-Agent\" (vector 1 (list (cons 10 20)) 10 10 10))",
                 &env
             ),
             "E1015"
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-header \"User-Agent\" #(1 (list (cons \"User-Agent\" 20)) 10 10 10))",
+                "(web-get-header \"User-Agent\" (vector 1 (list (cons \"User-Agent\" 20)) 10 10 10))",
                 &env
             ),
             "E1015"
@@ -378,21 +378,21 @@ mod error_tests {
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-parameter \"Value1\" #(1 10 (list 10) 10 10))",
+                "(web-get-parameter \"Value1\" (vector 1 10 (list 10) 10 10))",
                 &env
             ),
             "E1005"
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-parameter \"Value1\" #(1 10 (list (cons 10 20)) 10 10))",
+                "(web-get-parameter \"Value1\" (vector 1 10 (list (cons 10 20)) 10 10))",
                 &env
             ),
             "E1015"
         );
         assert_eq!(
             do_lisp_env(
-                "(web-get-parameter \"Value1\" #(1 10 (list (cons \"Value1\" 20)) 10 10))",
+                "(web-get-parameter \"Value1\" (vector 1 10 (list (cons \"Value1\" 20)) 10 10))",
                 &env
             ),
             "E1015"
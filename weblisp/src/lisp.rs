@@ -87,10 +87,10 @@ pub fn do_scm(r: &Request, env: lisp::Environment, id: usize) -> WebResult {
     if file.read_to_string(&mut load_file).is_err() {
         return http_error!(RESPONSE_500);
     }
-    match lisp::do_core_logic(&load_file, &env) {
+    match lisp::do_core_logic_file(&load_file, r.get_resource(), &env) {
         Ok(_) => {}
         Err(e) => {
-            error!("{}", e.get_msg());
+            error!("{}", e.get_report());
             return http_value_error!(RESPONSE_500, e.get_msg());
         }
     };
@@ -103,7 +103,7 @@ pub fn do_scm(r: &Request, env: lisp::Environment, id: usize) -> WebResult {
     let (sid, first) = get_session_id(r, id);
 
     let lisp = format!(
-        "((lambda () ({}::main #({:#?} {} {} {:#?} {:#?}) {:#?})))",
+        "((lambda () ({}::main (vector {:#?} {} {} {:#?} {:#?}) {:#?})))",
         f,
         method,
         r.get_lisp_header(),
@@ -116,7 +116,7 @@ pub fn do_scm(r: &Request, env: lisp::Environment, id: usize) -> WebResult {
     let result = match lisp::do_core_logic(&lisp, &env) {
         Ok(v) => v,
         Err(e) => {
-            error!("{}", e.get_msg());
+            error!("{}", e.get_report());
             return http_value_error!(RESPONSE_500, e.get_msg());
         }
     };